@@ -0,0 +1,104 @@
+//! Throughput of the game registry when many sessions look up games
+//! and many hosts create games at the same time
+
+use std::thread;
+
+use actix::{dev::channel, Addr};
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+use futures_util::FutureExt;
+use quizler::{game::Game, games::GameRegistry, token::TokenGenerator};
+
+/// The number of games running while joins are looked up
+const GAMES: usize = 10_000;
+/// The number of lookups or tokens each thread performs
+const OPERATIONS: usize = 1_000;
+/// The numbers of threads contending on the registry
+const THREADS: [usize; 4] = [1, 2, 4, 8];
+
+/// Creates an address for a game that isn't running, lookups only
+/// clone the address so the game never needs to start
+fn game() -> Addr<Game> {
+    let (tx, _) = channel::channel(16);
+    Addr::new(tx)
+}
+
+/// Joining players looking up running games by token
+fn find(c: &mut Criterion) {
+    let registry = GameRegistry::new();
+    let tokens = TokenGenerator::from_env();
+    let game = game();
+    let joined: Vec<String> = (0..GAMES)
+        .map(|_| {
+            let token = registry.generate_token(&tokens);
+            registry.insert(token.clone(), game.clone());
+            token
+        })
+        .collect();
+
+    let mut group = c.benchmark_group("registry/find");
+    for threads in THREADS {
+        group.throughput(Throughput::Elements((threads * OPERATIONS) as u64));
+        group.bench_with_input(
+            BenchmarkId::from_parameter(threads),
+            &threads,
+            |b, &threads| {
+                b.iter(|| {
+                    thread::scope(|scope| {
+                        for offset in 0..threads {
+                            let (registry, joined) = (&registry, &joined);
+                            scope.spawn(move || {
+                                for index in 0..OPERATIONS {
+                                    let token = &joined[(offset * OPERATIONS + index * 7) % GAMES];
+                                    // Games on this node are found without waiting
+                                    let found = registry.find(token, None).now_or_never();
+                                    assert!(matches!(found, Some(Ok(_))));
+                                }
+                            });
+                        }
+                    });
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+/// Hosts creating games at once, each token is generated against the
+/// games already running and stored in the registry
+fn create(c: &mut Criterion) {
+    let tokens = TokenGenerator::from_env();
+    let game = game();
+
+    let mut group = c.benchmark_group("registry/create");
+    for threads in THREADS {
+        group.throughput(Throughput::Elements((threads * OPERATIONS) as u64));
+        group.bench_with_input(
+            BenchmarkId::from_parameter(threads),
+            &threads,
+            |b, &threads| {
+                b.iter_batched(
+                    GameRegistry::new,
+                    |registry| {
+                        thread::scope(|scope| {
+                            for _ in 0..threads {
+                                let (registry, tokens, game) = (&registry, &tokens, &game);
+                                scope.spawn(move || {
+                                    for _ in 0..OPERATIONS {
+                                        let token = registry.generate_token(tokens);
+                                        registry.insert(token, game.clone());
+                                    }
+                                });
+                            }
+                        });
+                        registry
+                    },
+                    BatchSize::PerIteration,
+                )
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, find, create);
+criterion_main!(benches);
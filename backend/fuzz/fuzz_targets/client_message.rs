@@ -0,0 +1,8 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+// Any input must be rejected with an error rather than a panic
+fuzz_target!(|data: &[u8]| {
+    let _ = quizler::decode::decode_client_message(data);
+});
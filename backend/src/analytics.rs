@@ -0,0 +1,151 @@
+use std::{cmp::Reverse, collections::BTreeMap};
+
+use serde::Serialize;
+
+use crate::{
+    game::QuestionAnswer,
+    storage::{AnswerRecord, GameResults, PlayerResults},
+};
+
+/// Fraction of the players at the top and bottom of the scores
+/// compared when calculating the discrimination index
+const DISCRIMINATION_GROUP: f32 = 0.27;
+
+/// Item statistics for each question of a finished game
+#[derive(Serialize)]
+pub struct GameAnalytics {
+    /// The statistics for each question in the order they were asked
+    pub questions: Vec<QuestionAnalytics>,
+}
+
+/// Item statistics for a single question
+#[derive(Serialize)]
+pub struct QuestionAnalytics {
+    /// The title of the question
+    pub title: String,
+    /// The number of players that answered the question
+    pub answered: usize,
+    /// The percentage of all the players that answered correctly, none
+    /// for polls and questions nobody answered
+    pub percent_correct: Option<f32>,
+    /// The average time in milliseconds taken to answer
+    pub average_time: Option<u64>,
+    /// The number of players that gave each answer, choices are keyed
+    /// by their index in the original answer order
+    pub distribution: BTreeMap<String, u32>,
+    /// Difference between the fraction of the highest and lowest
+    /// scoring players that answered correctly, ranges from -1 to 1
+    /// where higher values separate strong and weak players better
+    pub discrimination: Option<f32>,
+}
+
+/// Calculates the item statistics for each question from the
+/// persisted results of a game
+///
+/// `results` The results of the game
+pub fn analyze(results: &GameResults) -> GameAnalytics {
+    // Players ordered from highest to lowest score for discrimination
+    let mut ranked: Vec<_> = results.players.iter().collect();
+    ranked.sort_by_key(|player| Reverse(player.score));
+    let group = (ranked.len() as f32 * DISCRIMINATION_GROUP)
+        .round()
+        .max(1.0) as usize;
+
+    let questions = results
+        .questions
+        .iter()
+        .enumerate()
+        .map(|(index, question)| {
+            let records: Vec<&AnswerRecord> = results
+                .players
+                .iter()
+                .filter_map(|player| record(player, index))
+                .collect();
+
+            let answered = records.len();
+            let percent_correct = if question.poll || answered == 0 {
+                None
+            } else {
+                let correct = records
+                    .iter()
+                    .filter(|record| record.result.is_correct())
+                    .count();
+                Some(correct as f32 / results.players.len() as f32 * 100.0)
+            };
+
+            let average_time = if answered == 0 {
+                None
+            } else {
+                let total: u64 = records.iter().map(|record| record.elapsed).sum();
+                Some(total / answered as u64)
+            };
+
+            let mut distribution = BTreeMap::new();
+            for answer in records.iter().filter_map(|record| record.answer.as_ref()) {
+                for key in distribution_keys(answer) {
+                    *distribution.entry(key).or_insert(0) += 1;
+                }
+            }
+
+            // Fraction of the group that answered the question correctly
+            let fraction = |players: &[&PlayerResults]| -> f32 {
+                let correct = players
+                    .iter()
+                    .filter(|player| {
+                        record(player, index).is_some_and(|record| record.result.is_correct())
+                    })
+                    .count();
+                correct as f32 / players.len() as f32
+            };
+            let discrimination = if question.poll || answered == 0 || ranked.len() < 2 {
+                None
+            } else {
+                let group = group.min(ranked.len() / 2);
+                let upper = &ranked[..group];
+                let lower = &ranked[ranked.len() - group..];
+                Some(fraction(upper) - fraction(lower))
+            };
+
+            QuestionAnalytics {
+                title: question.title.clone(),
+                answered,
+                percent_correct,
+                average_time,
+                distribution,
+                discrimination,
+            }
+        })
+        .collect();
+
+    GameAnalytics { questions }
+}
+
+/// Returns the answer record of a player for a question
+///
+/// `player` The results of the player
+/// `index`  The index of the question
+fn record(player: &PlayerResults, index: usize) -> Option<&AnswerRecord> {
+    player.answers.get(index)?.as_ref()
+}
+
+/// Returns the keys an answer is counted under in the answer
+/// distribution, clicked image positions aren't counted
+///
+/// `answer` The answer
+fn distribution_keys(answer: &QuestionAnswer) -> Vec<String> {
+    match answer {
+        QuestionAnswer::Single { answer } => vec![answer.to_string()],
+        QuestionAnswer::MultiSelect { answers } => {
+            answers.iter().map(|answer| answer.to_string()).collect()
+        }
+        QuestionAnswer::TrueFalse { answer } => vec![answer.to_string()],
+        QuestionAnswer::TextEntry { answer } => vec![answer.trim().to_lowercase()],
+        QuestionAnswer::Numeric { answer } => vec![answer.to_string()],
+        QuestionAnswer::Ordering { order } => vec![order
+            .iter()
+            .map(|index| index.to_string())
+            .collect::<Vec<_>>()
+            .join(",")],
+        QuestionAnswer::ClickableImage { .. } => Vec::new(),
+    }
+}
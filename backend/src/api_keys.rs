@@ -0,0 +1,102 @@
+use std::{
+    fs,
+    path::Path,
+    sync::{Mutex, OnceLock},
+    time::Duration,
+};
+
+use tracing::{info, warn};
+
+use crate::{auth::constant_time_eq, config, rate_limit::WindowCounter};
+
+/// Key allowing organizations to create quizzes programmatically
+pub struct ApiKey {
+    /// Name of the key used to identify its usage
+    pub name: String,
+    /// The secret key value
+    key: String,
+    /// The number of quizzes that can be created within the rate
+    /// limit window using the key
+    limit: u32,
+    /// Counter for the hits within the current window
+    counter: Mutex<WindowCounter>,
+}
+
+impl ApiKey {
+    /// Records a use of the key returning whether the key
+    /// is still within its rate limit
+    pub fn hit(&self) -> bool {
+        let window = Duration::from_secs(config::from_env(config::RATE_LIMIT_WINDOW));
+        match self.counter.lock() {
+            Ok(mut counter) => counter.hit(self.limit, window),
+            Err(_) => true,
+        }
+    }
+}
+
+/// The loaded API keys
+static API_KEYS: OnceLock<Vec<ApiKey>> = OnceLock::new();
+
+/// Loads the API keys from the file at the provided path. Each line
+/// contains the name of the key, the key itself and optionally the
+/// number of quizzes it can create within the rate limit window
+/// separated by whitespace, blank lines and lines starting with #
+/// are ignored. A missing file results in no keys
+///
+/// `path` The path to the API keys file
+pub fn load_api_keys(path: impl AsRef<Path>) {
+    let path = path.as_ref();
+    let keys: Vec<ApiKey> = match fs::read_to_string(path) {
+        Ok(value) => value
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .filter_map(parse_key)
+            .collect(),
+        Err(err) => {
+            warn!("Unable to load API keys from {}: {}", path.display(), err);
+            Vec::new()
+        }
+    };
+    info!("Loaded {} API keys", keys.len());
+    let _ = API_KEYS.set(keys);
+}
+
+/// Parses a line from the API keys file
+///
+/// `line` The line to parse
+fn parse_key(line: &str) -> Option<ApiKey> {
+    let mut parts = line.split_whitespace();
+    let (Some(name), Some(key)) = (parts.next(), parts.next()) else {
+        warn!("Skipping API key line missing a name or key");
+        return None;
+    };
+
+    let limit = match parts.next() {
+        Some(value) => match value.parse() {
+            Ok(value) => value,
+            Err(_) => {
+                warn!("Skipping API key {} with invalid limit", name);
+                return None;
+            }
+        },
+        None => config::from_env(config::CREATE_LIMIT),
+    };
+
+    Some(ApiKey {
+        name: name.to_string(),
+        key: key.to_string(),
+        limit,
+        counter: Mutex::new(WindowCounter::new()),
+    })
+}
+
+/// Finds the API key matching the provided value
+///
+/// `value` The key provided by the client
+pub fn find_api_key(value: &str) -> Option<&'static ApiKey> {
+    API_KEYS
+        .get()?
+        .iter()
+        .find(|key| constant_time_eq(key.key.as_bytes(), value.as_bytes()))
+}
@@ -0,0 +1,64 @@
+use serde::{Deserialize, Serialize};
+
+use crate::error::ServerError;
+
+/// Avatars players can choose from, clients map each
+/// name to the artwork they display for it
+pub const AVATARS: &[&str] = &[
+    "bear", "cat", "dog", "fox", "frog", "koala", "lion", "monkey", "octopus", "owl", "panda",
+    "penguin", "rabbit", "tiger", "unicorn", "whale",
+];
+
+/// Avatar and color chosen by a player shown alongside their name
+#[derive(Default, Clone, Serialize, Deserialize)]
+pub struct Appearance {
+    /// The name of the chosen avatar from [`AVATARS`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub avatar: Option<String>,
+    /// The chosen color as a `#rrggbb` hex string
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+}
+
+impl Appearance {
+    /// Validates the provided appearance returning the normalized
+    /// form of the appearance that should be used
+    pub fn validate(self) -> Result<Self, ServerError> {
+        if self
+            .avatar
+            .as_deref()
+            .is_some_and(|avatar| !is_valid_avatar(avatar))
+        {
+            return Err(ServerError::InvalidAppearance);
+        }
+
+        if self
+            .color
+            .as_deref()
+            .is_some_and(|color| !is_valid_color(color))
+        {
+            return Err(ServerError::InvalidAppearance);
+        }
+
+        Ok(Self {
+            avatar: self.avatar,
+            color: self.color.map(|color| color.to_ascii_lowercase()),
+        })
+    }
+}
+
+/// Checks whether the avatar is one of the avatars players can choose
+///
+/// `avatar` The name of the avatar
+pub fn is_valid_avatar(avatar: &str) -> bool {
+    AVATARS.contains(&avatar)
+}
+
+/// Checks whether the color is a `#rrggbb` hex string
+///
+/// `color` The color to check
+fn is_valid_color(color: &str) -> bool {
+    color
+        .strip_prefix('#')
+        .is_some_and(|hex| hex.len() == 6 && hex.chars().all(|c| c.is_ascii_hexdigit()))
+}
@@ -0,0 +1,121 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{grading::Grade, session::SessionId};
+
+/// Maximum number of entries recorded for a single game, entries past
+/// this are dropped so a long running game can't use unbounded memory
+const MAX_ENTRIES: usize = 10_000;
+
+/// Answers arriving sooner than this after the question reached the
+/// player are faster than a player could read the question and are
+/// recorded as suspicious when the game doesn't configure anti-cheat
+pub const RAPID_ANSWER_TIME: Duration = Duration::from_millis(500);
+
+/// Entry within the audit log of a game
+#[derive(Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    /// Unix timestamp in milliseconds that the event happened
+    pub at: u64,
+    /// The event itself
+    #[serde(flatten)]
+    pub kind: AuditEventKind,
+}
+
+/// The kinds of events recorded in the audit log
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(tag = "ty")]
+pub enum AuditEventKind {
+    /// The host started the game, `forced` when started before
+    /// all the players were ready
+    Started { forced: bool },
+    /// The host cancelled starting the game
+    StartCancelled,
+    /// The host skipped the current timer
+    Skipped,
+    /// The host paused the game
+    Paused,
+    /// The host resumed the game
+    Resumed,
+    /// The host moved on to the question at the index
+    NextQuestion { index: usize },
+    /// The host ended the game
+    Ended,
+    /// The host kicked a player optionally banning them
+    Kicked {
+        id: SessionId,
+        name: String,
+        ban: bool,
+    },
+    /// The host let a player waiting for approval into the game
+    JoinApproved { id: SessionId, name: String },
+    /// The host turned away a player waiting for approval
+    JoinDenied { id: SessionId, name: String },
+    /// The host graded an open ended answer
+    AnswerGraded { id: SessionId, grade: Grade },
+    /// The host changed the answer time multiplier of a player
+    TimeExtended { id: SessionId, multiplier: f32 },
+    /// The host muted or unmuted a player
+    Muted { id: SessionId, muted: bool },
+    /// The host lost its connection
+    HostDisconnected,
+    /// The host didn't return and the player was promoted to host
+    HostPromoted { id: SessionId },
+    /// An admin closed the game
+    Closed { reason: String },
+    /// A banned player attempted to join again
+    BannedJoin { id: SessionId },
+    /// A player that was already connected connected again, either
+    /// moving to the new connection or having it rejected
+    DuplicateConnection { id: SessionId, transferred: bool },
+    /// A player answered sooner than the minimum answer time after the
+    /// question reached them, `elapsed` is in milliseconds
+    RapidAnswer {
+        id: SessionId,
+        index: usize,
+        elapsed: u64,
+    },
+}
+
+/// Append-only record of the host actions and suspicious events of a
+/// game, kept with its results for the host and shown to admins
+#[derive(Default)]
+pub struct AuditLog {
+    /// The entries recorded so far
+    entries: Vec<AuditEntry>,
+    /// Whether entries have been dropped
+    truncated: bool,
+}
+
+impl AuditLog {
+    /// Continues the audit log of a restored game
+    ///
+    /// `entries`   The entries recorded before the game was restored
+    /// `truncated` Whether entries had been dropped
+    pub fn resume(entries: Vec<AuditEntry>, truncated: bool) -> Self {
+        Self { entries, truncated }
+    }
+
+    /// Records the provided event at the current time
+    ///
+    /// `kind` The event to record
+    pub fn record(&mut self, kind: AuditEventKind) {
+        if self.entries.len() >= MAX_ENTRIES {
+            self.truncated = true;
+            return;
+        }
+
+        let at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|value| value.as_millis() as u64)
+            .unwrap_or_default();
+        self.entries.push(AuditEntry { at, kind });
+    }
+
+    /// Returns a copy of the recorded entries along with whether any
+    /// entries were dropped
+    pub fn entries(&self) -> (Vec<AuditEntry>, bool) {
+        (self.entries.clone(), self.truncated)
+    }
+}
@@ -0,0 +1,303 @@
+use std::{
+    sync::OnceLock,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
+use rand_core::{OsRng, RngCore};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use tracing::error;
+use uuid::Uuid;
+
+use crate::{config, game::GameId};
+
+/// Claims within the signed token issued to the host of a quiz
+#[derive(Serialize, Deserialize)]
+pub struct HostClaims {
+    /// The ID of the quiz the token grants control of
+    pub quiz: GameId,
+    /// Unix timestamp in seconds the token expires at
+    pub exp: u64,
+}
+
+/// Claims within the signed token issued to hosts that logged in
+#[derive(Serialize, Deserialize)]
+pub struct UserClaims {
+    /// The ID of the user
+    pub user: Uuid,
+    /// Unix timestamp in seconds the token expires at
+    pub exp: u64,
+}
+
+/// Claims within the signed profile cookie of returning players
+#[derive(Serialize, Deserialize)]
+pub struct ProfileClaims {
+    /// The ID of the profile
+    pub profile: Uuid,
+    /// Unix timestamp in seconds the token expires at
+    pub exp: u64,
+}
+
+/// Claims within the signed cookie of instructors launched from an
+/// LMS identifying the gradebook games they create post scores to
+#[cfg(feature = "lti")]
+#[derive(Serialize, Deserialize)]
+pub struct LtiContextClaims {
+    /// The gradebook of the launch
+    pub context: crate::lti::LtiContext,
+    /// Unix timestamp in seconds the token expires at
+    pub exp: u64,
+}
+
+/// The purpose a token is issued for, every token is signed with the
+/// audience of its kind so that a token can't be used in place of a
+/// token of another kind with the same shape
+#[derive(Clone, Copy)]
+enum TokenKind {
+    Host,
+    User,
+    Profile,
+    #[cfg(feature = "lti")]
+    LtiContext,
+}
+
+impl TokenKind {
+    /// Returns the audience claim of the kind
+    fn audience(self) -> &'static str {
+        match self {
+            Self::Host => "quizler:host",
+            Self::User => "quizler:user",
+            Self::Profile => "quizler:profile",
+            #[cfg(feature = "lti")]
+            Self::LtiContext => "quizler:lti-context",
+        }
+    }
+}
+
+/// Claims of a token along with the audience of its kind
+#[derive(Serialize, Deserialize)]
+struct Audienced<C> {
+    /// The audience of the token kind
+    aud: String,
+    #[serde(flatten)]
+    claims: C,
+}
+
+/// Returns the secret host and user tokens are signed with
+fn host_secret() -> &'static [u8] {
+    static SECRET: OnceLock<Vec<u8>> = OnceLock::new();
+
+    SECRET.get_or_init(|| {
+        let secret = config::from_env_string(config::HOST_TOKEN_SECRET);
+        if !secret.is_empty() {
+            return secret.into_bytes();
+        }
+
+        let mut secret = vec![0; 32];
+        OsRng.fill_bytes(&mut secret);
+        secret
+    })
+}
+
+/// Returns the unix timestamp in seconds that a token issued now
+/// with the provided lifetime expires at
+///
+/// `ttl` The lifetime of the token in seconds
+fn expiry(ttl: u64) -> u64 {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|value| value.as_secs())
+        .unwrap_or_default();
+    now + ttl
+}
+
+/// Signs the provided claims for the kind of token
+///
+/// `kind`   The kind of token
+/// `claims` The claims to sign
+fn sign<C: Serialize>(kind: TokenKind, claims: C) -> String {
+    let claims = Audienced {
+        aud: kind.audience().to_string(),
+        claims,
+    };
+    match encode(
+        &Header::default(),
+        &claims,
+        &EncodingKey::from_secret(host_secret()),
+    ) {
+        Ok(value) => value,
+        Err(err) => {
+            // Encoding only fails for invalid keys which HMAC never has
+            error!("Failed to sign token: {:?}", err);
+            String::new()
+        }
+    }
+}
+
+/// Verifies the signature, expiry and kind of the token returning its
+/// claims, tokens issued for any other kind are rejected
+///
+/// `kind`  The kind of token expected
+/// `token` The token to verify
+fn verify<C: DeserializeOwned>(kind: TokenKind, token: &str) -> Option<C> {
+    let mut validation = Validation::default();
+    validation.set_audience(&[kind.audience()]);
+    validation.set_required_spec_claims(&["exp", "aud"]);
+
+    decode::<Audienced<C>>(token, &DecodingKey::from_secret(host_secret()), &validation)
+        .ok()
+        .map(|data| data.claims.claims)
+}
+
+/// Issues a signed host token for the provided quiz
+///
+/// `quiz` The ID of the quiz
+pub fn issue_host_token(quiz: GameId) -> String {
+    sign(
+        TokenKind::Host,
+        HostClaims {
+            quiz,
+            exp: expiry(config::from_env(config::HOST_TOKEN_TTL)),
+        },
+    )
+}
+
+/// Issues a signed token for a host that logged in
+///
+/// `user` The ID of the user
+#[cfg(feature = "oauth")]
+pub fn issue_user_token(user: Uuid) -> String {
+    sign(
+        TokenKind::User,
+        UserClaims {
+            user,
+            exp: expiry(config::from_env(config::USER_TOKEN_TTL)),
+        },
+    )
+}
+
+/// Verifies the user token returning the ID of the user
+///
+/// `token` The user token
+pub fn verify_user_token(token: &str) -> Option<Uuid> {
+    verify::<UserClaims>(TokenKind::User, token).map(|claims| claims.user)
+}
+
+/// Issues a signed token identifying the profile of a player
+///
+/// `profile` The ID of the profile
+pub fn issue_profile_token(profile: Uuid) -> String {
+    sign(
+        TokenKind::Profile,
+        ProfileClaims {
+            profile,
+            exp: expiry(config::from_env(config::PROFILE_TOKEN_TTL)),
+        },
+    )
+}
+
+/// Verifies the profile token returning the ID of the profile
+///
+/// `token` The profile token
+pub fn verify_profile_token(token: &str) -> Option<Uuid> {
+    verify::<ProfileClaims>(TokenKind::Profile, token).map(|claims| claims.profile)
+}
+
+/// Issues a signed token identifying the gradebook an instructor
+/// launched from, valid for as long as host tokens
+///
+/// `context` The gradebook of the launch
+#[cfg(feature = "lti")]
+pub fn issue_lti_context_token(context: crate::lti::LtiContext) -> String {
+    sign(
+        TokenKind::LtiContext,
+        LtiContextClaims {
+            context,
+            exp: expiry(config::from_env(config::HOST_TOKEN_TTL)),
+        },
+    )
+}
+
+/// Verifies the LTI context token returning the gradebook
+///
+/// `token` The LTI context token
+#[cfg(feature = "lti")]
+pub fn verify_lti_context_token(token: &str) -> Option<crate::lti::LtiContext> {
+    verify::<LtiContextClaims>(TokenKind::LtiContext, token).map(|claims| claims.context)
+}
+
+/// Checks whether the host token has a valid signature, hasn't
+/// expired and grants control of the provided quiz
+///
+/// `token` The host token
+/// `quiz`  The ID of the quiz
+pub fn verify_host_token(token: &str, quiz: GameId) -> bool {
+    verify::<HostClaims>(TokenKind::Host, token).is_some_and(|claims| claims.quiz == quiz)
+}
+
+/// Checks whether the provided key matches the host key from the
+/// environment. When no host key is configured nothing is accepted
+///
+/// `key` The key provided by the client
+pub fn is_host_key(key: &str) -> bool {
+    let expected = config::from_env_string(config::HOST_KEY);
+    !expected.is_empty() && constant_time_eq(expected.as_bytes(), key.as_bytes())
+}
+
+/// Compares the two values in time that only depends on their
+/// lengths so that keys can't be guessed from response times
+///
+/// `a` The first value
+/// `b` The second value
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn host_tokens_grant_their_quiz() {
+        let quiz = Uuid::new_v4();
+        let token = issue_host_token(quiz);
+        assert!(verify_host_token(&token, quiz));
+        assert!(!verify_host_token(&token, Uuid::new_v4()));
+    }
+
+    #[test]
+    fn tokens_are_rejected_as_other_kinds() {
+        let id = Uuid::new_v4();
+        let profile = issue_profile_token(id);
+        assert_eq!(verify_profile_token(&profile), Some(id));
+        assert_eq!(verify_user_token(&profile), None);
+
+        // Claims of the right shape signed for another kind
+        let user = sign(
+            TokenKind::User,
+            ProfileClaims {
+                profile: id,
+                exp: expiry(60),
+            },
+        );
+        assert_eq!(verify_profile_token(&user), None);
+    }
+
+    #[test]
+    fn tokens_without_a_kind_are_rejected() {
+        let id = Uuid::new_v4();
+        let token = encode(
+            &Header::default(),
+            &ProfileClaims {
+                profile: id,
+                exp: expiry(60),
+            },
+            &EncodingKey::from_secret(host_secret()),
+        )
+        .unwrap();
+        assert_eq!(verify_profile_token(&token), None);
+    }
+}
@@ -0,0 +1,144 @@
+use std::{
+    collections::BTreeMap,
+    io::{self, ErrorKind},
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::{
+    config,
+    error::{FieldError, FieldErrorReason},
+    game::{GameConfig, GameId, ImageRef, Question},
+    images::{game_dir, image_path, quiz_path, ImageFormat},
+};
+
+/// Portable quiz definition along with the images it uses so that
+/// quizzes can be backed up and imported on another server
+#[derive(Serialize, Deserialize)]
+pub struct QuizBundle {
+    /// The quiz definition
+    pub quiz: GameConfig,
+    /// The images used by the quiz keyed by the ID the
+    /// questions refer to them with
+    #[serde(default)]
+    pub images: BTreeMap<ImageRef, BundledImage>,
+}
+
+/// Image embedded within a quiz bundle
+#[derive(Serialize, Deserialize)]
+pub struct BundledImage {
+    /// The mime type of the image
+    pub mime: String,
+    /// The base64 encoded image bytes
+    pub data: String,
+}
+
+impl QuizBundle {
+    /// Validates the quiz definition and the bundled images returning
+    /// the decoded images or the list of invalid fields
+    pub fn validate(&self) -> Result<Vec<DecodedImage>, Vec<FieldError>> {
+        let mut errors = self.quiz.validate();
+        match decode_images(&self.images) {
+            Ok(images) if errors.is_empty() => Ok(images),
+            Ok(_) => Err(errors),
+            Err(image_errors) => {
+                errors.extend(image_errors);
+                Err(errors)
+            }
+        }
+    }
+}
+
+/// Image decoded from a bundle ready to be stored
+pub type DecodedImage = (ImageRef, Vec<u8>);
+
+/// Stores the quiz definition and the provided images in the
+/// directory for the game so that the quiz can be exported
+///
+/// `game`       The ID of the game
+/// `definition` The serialized quiz definition
+/// `images`     The images to store
+pub async fn store(game: GameId, definition: &[u8], images: &[DecodedImage]) -> io::Result<()> {
+    tokio::fs::create_dir_all(game_dir(game)).await?;
+    for (id, bytes) in images {
+        tokio::fs::write(image_path(game, id), bytes).await?;
+    }
+    tokio::fs::write(quiz_path(game), definition).await
+}
+
+/// Creates the bundle for the quiz stored for the provided game
+/// embedding each of the images used by the questions, images that
+/// are missing from storage are left out of the bundle
+///
+/// `game` The ID of the game
+pub async fn export(game: GameId) -> io::Result<Option<QuizBundle>> {
+    let definition = match tokio::fs::read(quiz_path(game)).await {
+        Ok(value) => value,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err),
+    };
+    let quiz: GameConfig = serde_json::from_slice(&definition)?;
+
+    let mut images = BTreeMap::new();
+    for id in quiz.questions.iter().flat_map(Question::images) {
+        if images.contains_key(&id) {
+            continue;
+        }
+
+        let bytes = match tokio::fs::read(image_path(game, &id)).await {
+            Ok(value) => value,
+            Err(_) => {
+                warn!("Image {} missing from export of quiz {}", id, game);
+                continue;
+            }
+        };
+        let Some(format) = ImageFormat::detect(&bytes) else {
+            continue;
+        };
+
+        images.insert(
+            id,
+            BundledImage {
+                mime: format.mime().to_string(),
+                data: STANDARD.encode(bytes),
+            },
+        );
+    }
+
+    Ok(Some(QuizBundle { quiz, images }))
+}
+
+/// Decodes the images from a bundle checking that each is a supported
+/// image format within the maximum image size
+///
+/// `images` The bundled images
+pub fn decode_images(
+    images: &BTreeMap<ImageRef, BundledImage>,
+) -> Result<Vec<DecodedImage>, Vec<FieldError>> {
+    let max_size = config::from_env(config::MAX_IMAGE_SIZE);
+
+    let mut decoded = Vec::with_capacity(images.len());
+    let mut errors = Vec::new();
+    for (id, image) in images {
+        let bytes = STANDARD
+            .decode(&image.data)
+            .ok()
+            .filter(|bytes| bytes.len() <= max_size && ImageFormat::detect(bytes).is_some());
+
+        match bytes {
+            Some(bytes) => decoded.push((*id, bytes)),
+            None => errors.push(FieldError::new(
+                format!("images.{}", id),
+                FieldErrorReason::InvalidValue,
+            )),
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(decoded)
+    } else {
+        Err(errors)
+    }
+}
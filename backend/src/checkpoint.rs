@@ -0,0 +1,301 @@
+use std::{
+    collections::{HashMap, HashSet},
+    io::{self, ErrorKind},
+    net::IpAddr,
+    path::PathBuf,
+    sync::{mpsc, OnceLock},
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+use tracing::{error, warn};
+use uuid::Uuid;
+
+use crate::{
+    appearance::Appearance,
+    audit::AuditEntry,
+    config,
+    game::{AnswerResult, GameConfig, GameId, GameState, PlayerAnswer},
+    grading::Grade,
+    replay::ReplayEvent,
+    session::SessionId,
+};
+
+/// Version of the on-disk checkpoint format, checkpoints written with
+/// a different version are discarded when the server starts
+pub const CHECKPOINT_VERSION: u32 = 2;
+
+/// The number of writes that can be waiting before new
+/// checkpoints are dropped
+const BUFFER_SIZE: usize = 256;
+
+/// State of a running game written to disk periodically so that the
+/// game can be rebuilt after the server restarts. Sessions can't
+/// survive a restart so the host and players rejoin the restored game
+/// using their resume tokens
+#[derive(Serialize, Deserialize)]
+pub struct GameCheckpoint {
+    /// The version of the checkpoint format
+    pub version: u32,
+    /// Unix timestamp in seconds the checkpoint was written
+    pub saved_at: u64,
+    /// The ID of the game
+    pub id: Uuid,
+    /// The token the game is stored behind
+    pub token: String,
+    /// The ID of the quiz the game was created from
+    pub quiz: GameId,
+    /// The config of the game with the picked and shuffled questions
+    pub config: GameConfig,
+    /// The user hosting the game, this isn't part of the config format
+    pub owner: Option<Uuid>,
+    /// The LMS gradebook the scores are posted back to
+    #[cfg(feature = "lti")]
+    #[serde(default)]
+    pub lti: Option<crate::lti::LtiContext>,
+    /// The session ID of the host
+    pub host_id: SessionId,
+    /// The key the host reclaims the game with
+    pub host_key: String,
+    /// The state of the game
+    pub state: GameState,
+    /// The index of the current question
+    pub question_index: usize,
+    /// The number of questions that have been marked
+    pub questions_marked: usize,
+    /// The delayed task that was running or paused
+    pub task: Option<TaskCheckpoint>,
+    /// The players in the game
+    pub players: Vec<PlayerCheckpoint>,
+    /// Session IDs banned from the game
+    pub banned_ids: HashSet<SessionId>,
+    /// IP addresses banned from the game
+    pub banned_ips: HashSet<IpAddr>,
+    /// The sessions that have answered the current question
+    pub answered: HashSet<SessionId>,
+    /// The number of answers that arrived for the current question
+    pub arrivals: usize,
+    /// The players that submitted an answer to the question being graded
+    pub submissions: Vec<SessionId>,
+    /// The grades the host has given
+    pub grades: HashMap<SessionId, Grade>,
+    /// Unix timestamp in seconds that the game was created
+    pub started_at: u64,
+    /// The events recorded for the replay of the game
+    pub replay: Vec<ReplayEvent>,
+    /// Whether events were dropped from the replay
+    pub replay_truncated: bool,
+    /// The entries recorded in the audit log of the game
+    #[serde(default)]
+    pub audit: Vec<AuditEntry>,
+    /// Whether entries were dropped from the audit log
+    #[serde(default)]
+    pub audit_truncated: bool,
+}
+
+/// Remaining time on the delayed task of a game, the task itself is
+/// recreated from the state of the game when restored
+#[derive(Serialize, Deserialize)]
+pub struct TaskCheckpoint {
+    /// Milliseconds remaining on the displayed timer
+    pub remaining: u64,
+    /// Milliseconds after the displayed timer before the task runs
+    pub grace: u64,
+    /// Whether the task was paused by the host and should stay paused
+    pub held: bool,
+}
+
+/// State of a player within a game checkpoint
+#[derive(Serialize, Deserialize)]
+pub struct PlayerCheckpoint {
+    pub id: SessionId,
+    pub name: String,
+    pub ip: Option<IpAddr>,
+    pub resume_key: String,
+    pub answers: Vec<Option<PlayerAnswer>>,
+    pub results: Vec<Option<AnswerResult>>,
+    pub score: u32,
+    pub streak: u32,
+    pub team: Option<usize>,
+    pub muted: bool,
+    pub time_multiplier: f32,
+    pub answer_order: Vec<usize>,
+    pub wager: Option<u32>,
+    pub eliminated: Option<usize>,
+    pub profile: Option<Uuid>,
+    pub appearance: Appearance,
+}
+
+/// Write waiting to be made by the checkpoint writer
+enum Write {
+    /// Write the checkpoint replacing the previous one
+    Save(Box<GameCheckpoint>),
+    /// Delete the checkpoint of the game
+    Remove(Uuid),
+}
+
+/// Sender for the writer channel when checkpoints are enabled
+static SENDER: OnceLock<mpsc::SyncSender<Write>> = OnceLock::new();
+
+/// Returns the interval checkpoints are written at, this is `None`
+/// when checkpoints are disabled and games aren't restored
+pub fn interval() -> Option<Duration> {
+    SENDER.get()?;
+    let secs = config::from_env(config::CHECKPOINT_INTERVAL);
+    Some(Duration::from_secs(secs))
+}
+
+/// Starts the writer thread that writes the checkpoints to disk in
+/// the order they were queued so that a slow disk never holds up the
+/// games, nothing is started when the checkpoint interval is zero
+pub fn start() {
+    if config::from_env(config::CHECKPOINT_INTERVAL) == 0 {
+        return;
+    }
+
+    let (sender, receiver) = mpsc::sync_channel(BUFFER_SIZE);
+    thread::spawn(move || {
+        for write in receiver {
+            match write {
+                Write::Save(checkpoint) => {
+                    if let Err(err) = save(&checkpoint) {
+                        error!(
+                            "Failed to save checkpoint for game {}: {}",
+                            checkpoint.id, err
+                        );
+                    }
+                }
+                Write::Remove(game) => remove(game),
+            }
+        }
+    });
+    let _ = SENDER.set(sender);
+}
+
+/// Queues the checkpoint to be written, the checkpoint is dropped
+/// when the writer has fallen behind since a newer one will follow
+///
+/// `checkpoint` The checkpoint to write
+pub fn queue_save(checkpoint: GameCheckpoint) {
+    let Some(sender) = SENDER.get() else {
+        return;
+    };
+    let id = checkpoint.id;
+    if sender.try_send(Write::Save(Box::new(checkpoint))).is_err() {
+        warn!("Dropped checkpoint for game {}", id);
+    }
+}
+
+/// Queues the checkpoint of the game to be deleted after any of its
+/// queued writes, waits for room rather than dropping the removal so
+/// that finished games are never restored
+///
+/// `game` The ID of the game
+pub fn queue_remove(game: Uuid) {
+    if let Some(sender) = SENDER.get() {
+        let _ = sender.send(Write::Remove(game));
+    }
+}
+
+/// Returns the path the checkpoint for the provided game is stored at
+///
+/// `game` The ID of the game
+fn checkpoint_path(game: Uuid) -> PathBuf {
+    PathBuf::from(config::from_env_string(config::CHECKPOINT_DIR)).join(format!("{}.json", game))
+}
+
+/// Returns the current unix timestamp in seconds
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|value| value.as_secs())
+        .unwrap_or_default()
+}
+
+/// Writes the checkpoint to disk replacing the previous checkpoint of
+/// the game, the checkpoint is written to a temporary file first so a
+/// crash while writing leaves the previous checkpoint intact. Games
+/// only write directly when they can't wait for the writer
+///
+/// `checkpoint` The checkpoint to write
+pub fn save(checkpoint: &GameCheckpoint) -> io::Result<()> {
+    let path = checkpoint_path(checkpoint.id);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let bytes = serde_json::to_vec(checkpoint)?;
+    let temp = path.with_extension("tmp");
+    std::fs::write(&temp, bytes)?;
+    std::fs::rename(temp, path)
+}
+
+/// Deletes the checkpoint for the provided game if one exists
+///
+/// `game` The ID of the game
+fn remove(game: Uuid) {
+    match std::fs::remove_file(checkpoint_path(game)) {
+        Ok(_) => {}
+        Err(err) if err.kind() == ErrorKind::NotFound => {}
+        Err(err) => error!("Failed to remove checkpoint for game {}: {}", game, err),
+    }
+}
+
+/// Only the version of a checkpoint used to check the version
+/// before parsing the rest of the checkpoint
+#[derive(Deserialize)]
+struct CheckpointHeader {
+    version: u32,
+}
+
+/// Loads every checkpoint from disk, checkpoints that can't be read,
+/// are from another version of the format or are older than the empty
+/// game TTL are deleted since the game would have been stopped anyway
+pub fn load_all() -> Vec<GameCheckpoint> {
+    let dir = PathBuf::from(config::from_env_string(config::CHECKPOINT_DIR));
+    let entries = match std::fs::read_dir(&dir) {
+        Ok(value) => value,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Vec::new(),
+        Err(err) => {
+            error!("Failed to read checkpoint directory: {}", err);
+            return Vec::new();
+        }
+    };
+
+    let empty_ttl = config::from_env(config::EMPTY_GAME_TTL);
+    let now = now_secs();
+    let mut checkpoints = Vec::new();
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().is_none_or(|ext| ext != "json") {
+            continue;
+        }
+
+        let checkpoint = std::fs::read(&path)
+            .map_err(|err| err.to_string())
+            .and_then(|bytes| {
+                let header: CheckpointHeader =
+                    serde_json::from_slice(&bytes).map_err(|err| err.to_string())?;
+                if header.version != CHECKPOINT_VERSION {
+                    return Err(format!("unsupported version {}", header.version));
+                }
+                serde_json::from_slice::<GameCheckpoint>(&bytes).map_err(|err| err.to_string())
+            });
+
+        match checkpoint {
+            Ok(checkpoint) if now.saturating_sub(checkpoint.saved_at) <= empty_ttl => {
+                checkpoints.push(checkpoint);
+                continue;
+            }
+            Ok(checkpoint) => warn!("Discarding expired checkpoint for game {}", checkpoint.id),
+            Err(err) => warn!("Discarding checkpoint {}: {}", path.display(), err),
+        }
+
+        if let Err(err) = std::fs::remove_file(&path) {
+            error!("Failed to remove checkpoint {}: {}", path.display(), err);
+        }
+    }
+
+    checkpoints
+}
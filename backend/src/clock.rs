@@ -0,0 +1,162 @@
+use std::{
+    cell::Cell,
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+/// Source of the current time for the timing of a game, the question
+/// deadlines, grace periods and timer syncs are all measured against
+/// the clock so they can be driven by a clock that only moves when told
+pub trait Clock {
+    /// Returns the current time
+    fn now(&self) -> Instant;
+}
+
+/// Clock reading the system time
+#[derive(Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// Clock that stays still until advanced so that timing edge cases such
+/// as an answer arriving exactly at the deadline or a pause between two
+/// timer syncs can be reproduced. Clones share the same time so a copy
+/// can be kept to advance the clock that timing is measured against
+#[derive(Clone)]
+pub struct ManualClock {
+    now: Rc<Cell<Instant>>,
+}
+
+impl ManualClock {
+    /// Creates a clock stopped at the current system time
+    pub fn new() -> Self {
+        Self {
+            now: Rc::new(Cell::new(Instant::now())),
+        }
+    }
+
+    /// Moves the clock forward
+    ///
+    /// `duration` The time to move forward by
+    pub fn advance(&self, duration: Duration) {
+        self.now.set(self.now.get() + duration);
+    }
+}
+
+impl Default for ManualClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Instant {
+        self.now.get()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lifecycle::{GameCore, Step};
+
+    const SECOND: Duration = Duration::from_secs(1);
+    const MILLI: Duration = Duration::from_millis(1);
+
+    #[test]
+    fn deadline_expires_exactly_at_the_end_of_the_wait() {
+        let clock = ManualClock::new();
+        let mut core = GameCore::new(clock.now());
+        core.schedule(Step::Mark, 10 * SECOND, 2 * SECOND, clock.now());
+
+        clock.advance(12 * SECOND - MILLI);
+        assert_eq!(core.due(clock.now()), None);
+
+        clock.advance(MILLI);
+        assert_eq!(core.due(clock.now()), Some(Step::Mark));
+        assert_eq!(core.due(clock.now()), None);
+    }
+
+    #[test]
+    fn pause_keeps_the_remaining_time() {
+        let clock = ManualClock::new();
+        let mut core = GameCore::new(clock.now());
+        core.schedule(Step::Mark, 10 * SECOND, 5 * SECOND, clock.now());
+
+        clock.advance(4 * SECOND);
+        assert!(core.pause(clock.now()));
+        let paused = core.paused().unwrap();
+        assert_eq!(paused.remaining, 6 * SECOND);
+        assert_eq!(paused.grace, 5 * SECOND);
+
+        // Time passing while paused doesn't count towards the wait
+        clock.advance(60 * SECOND);
+        assert_eq!(core.due(clock.now()), None);
+
+        assert!(core.resume(clock.now()));
+        assert_eq!(core.remaining(clock.now()), (6 * SECOND, 5 * SECOND));
+
+        clock.advance(11 * SECOND - MILLI);
+        assert_eq!(core.due(clock.now()), None);
+        clock.advance(MILLI);
+        assert_eq!(core.due(clock.now()), Some(Step::Mark));
+    }
+
+    #[test]
+    fn pause_within_grace_keeps_the_remaining_grace() {
+        let clock = ManualClock::new();
+        let mut core = GameCore::new(clock.now());
+        core.schedule(Step::Mark, 10 * SECOND, 5 * SECOND, clock.now());
+
+        clock.advance(12 * SECOND);
+        assert!(core.pause(clock.now()));
+        let paused = core.paused().unwrap();
+        assert_eq!(paused.remaining, Duration::ZERO);
+        assert_eq!(paused.grace, 3 * SECOND);
+    }
+
+    #[test]
+    fn answers_are_late_after_the_extended_time() {
+        let clock = ManualClock::new();
+        let mut core = GameCore::new(clock.now());
+        core.schedule(Step::Mark, 10 * SECOND, 5 * SECOND, clock.now());
+
+        // Answers exactly at the deadline are still in time
+        clock.advance(10 * SECOND);
+        assert!(!core.is_late(1.0, clock.now()));
+
+        clock.advance(MILLI);
+        assert!(core.is_late(1.0, clock.now()));
+        assert!(!core.is_late(1.5, clock.now()));
+
+        clock.advance(5 * SECOND - MILLI);
+        assert!(!core.is_late(1.5, clock.now()));
+        clock.advance(MILLI);
+        assert!(core.is_late(1.5, clock.now()));
+    }
+
+    #[test]
+    fn time_sync_stops_at_the_total() {
+        let clock = ManualClock::new();
+        let mut core = GameCore::new(clock.now());
+        core.schedule(Step::Mark, 10 * SECOND, 5 * SECOND, clock.now());
+
+        clock.advance(2500 * MILLI);
+        assert_eq!(core.time_sync(clock.now()), (10000, 2500));
+
+        clock.advance(10 * SECOND);
+        assert_eq!(core.time_sync(clock.now()), (10000, 10000));
+    }
+
+    #[test]
+    fn clones_share_the_time() {
+        let clock = ManualClock::new();
+        let copy = clock.clone();
+        copy.advance(SECOND);
+        assert_eq!(clock.now(), copy.now());
+    }
+}
@@ -0,0 +1,379 @@
+use std::sync::OnceLock;
+
+use futures_util::future::{ready, BoxFuture};
+use serde::{Deserialize, Serialize};
+
+/// Directory of the games running across every node so that multiple
+/// servers behind a load balancer don't hand out the same token and
+/// players can be sent to the node hosting their game. The running
+/// games themselves stay in the local [`crate::games::GameRegistry`].
+///
+/// Lookups are asynchronous so a slow directory never holds up the
+/// thread of the session or game waiting on it
+pub trait GameDirectory: Send + Sync {
+    /// Claims the token for a game on this node, resolves to false when
+    /// another node already has a game with the token
+    ///
+    /// `token` The game token
+    fn claim(&self, token: String) -> BoxFuture<'static, bool>;
+
+    /// Releases the claim this node has on the token
+    ///
+    /// `token` The game token
+    fn release(&self, token: String) -> BoxFuture<'static, ()>;
+
+    /// Finds the URL of the other node hosting the game with the token
+    ///
+    /// `token` The game token
+    fn locate(&self, token: String) -> BoxFuture<'static, Option<String>>;
+
+    /// Publishes the message to the other nodes
+    ///
+    /// `message` The message to publish
+    fn publish(&self, message: ClusterMessage) -> BoxFuture<'static, ()>;
+}
+
+/// Message published between the nodes
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(tag = "ty")]
+pub enum ClusterMessage {
+    /// Maintenance mode was enabled or disabled by an admin
+    Maintenance { enabled: bool },
+}
+
+/// Directory for a single node, every token is available locally
+pub struct LocalDirectory;
+
+impl GameDirectory for LocalDirectory {
+    fn claim(&self, _token: String) -> BoxFuture<'static, bool> {
+        Box::pin(ready(true))
+    }
+
+    fn release(&self, _token: String) -> BoxFuture<'static, ()> {
+        Box::pin(ready(()))
+    }
+
+    fn locate(&self, _token: String) -> BoxFuture<'static, Option<String>> {
+        Box::pin(ready(None))
+    }
+
+    fn publish(&self, _message: ClusterMessage) -> BoxFuture<'static, ()> {
+        Box::pin(ready(()))
+    }
+}
+
+static DIRECTORY: OnceLock<Box<dyn GameDirectory>> = OnceLock::new();
+
+/// Returns the game directory, games are only tracked locally
+/// unless a shared directory has been started
+pub fn directory() -> &'static dyn GameDirectory {
+    DIRECTORY.get_or_init(|| Box::new(LocalDirectory)).as_ref()
+}
+
+/// Starts the shared directory configured in the environment, games
+/// are only tracked locally when no Redis URL is set. Must be called
+/// from within the runtime of the system so the connection outlives
+/// the workers
+#[cfg(feature = "redis")]
+pub fn start() {
+    use tracing::{error, info};
+
+    use crate::config;
+
+    let url = config::from_env_string(config::REDIS_URL);
+    if url.is_empty() {
+        return;
+    }
+    let node = config::from_env_string(config::NODE_URL);
+
+    let directory = match shared::RedisDirectory::new(&url, node) {
+        Ok(value) => value,
+        Err(err) => {
+            error!("Invalid Redis URL {}: {:?}", url, err);
+            return;
+        }
+    };
+    directory.spawn_tasks();
+
+    if DIRECTORY.set(Box::new(directory)).is_err() {
+        error!("Game directory was used before the shared directory started");
+        return;
+    }
+    info!("Sharing games with other nodes through {}", url);
+}
+
+/// Shared directory backed by Redis, each token is stored as a key
+/// holding the URL of the node hosting it and messages are published
+/// on a channel every node subscribes to
+#[cfg(feature = "redis")]
+mod shared {
+    use std::{
+        sync::{Arc, OnceLock},
+        thread,
+        time::{Duration, Instant},
+    };
+
+    use ::redis::{aio::ConnectionManager, Client, Cmd, FromRedisValue, RedisResult, Script};
+    use futures_util::future::BoxFuture;
+    use serde::{Deserialize, Serialize};
+    use tracing::{error, info, warn};
+
+    use super::{ClusterMessage, GameDirectory};
+    use crate::games::registry;
+
+    /// Prefix for the keys of the game tokens
+    const KEY_PREFIX: &str = "quizler:game:";
+
+    /// Channel the nodes publish messages on
+    const CHANNEL: &str = "quizler:cluster";
+
+    /// Seconds a claim lasts without being refreshed so that the
+    /// tokens of nodes that stopped unexpectedly are freed
+    const CLAIM_TTL: u64 = 180;
+
+    /// How often the claims of this node are refreshed
+    const REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
+    /// How long requests to Redis can take before failing so that
+    /// an unreachable Redis doesn't stall the games
+    const TIMEOUT: Duration = Duration::from_millis(500);
+
+    /// How long to wait before resubscribing after losing the connection
+    const RETRY_DELAY: Duration = Duration::from_secs(5);
+
+    /// Message along with the node that published it
+    #[derive(Serialize, Deserialize)]
+    struct Envelope {
+        node: String,
+        #[serde(flatten)]
+        message: ClusterMessage,
+    }
+
+    pub struct RedisDirectory {
+        client: Client,
+        /// Connection for commands, set once the first connection is
+        /// made and reconnected by the manager after failing
+        conn: Arc<OnceLock<ConnectionManager>>,
+        /// The URL of this node
+        node: String,
+    }
+
+    impl RedisDirectory {
+        /// Creates the directory for the Redis server at the URL
+        ///
+        /// `url`  The Redis URL
+        /// `node` The URL of this node
+        pub fn new(url: &str, node: String) -> RedisResult<Self> {
+            Ok(Self {
+                client: Client::open(url)?,
+                conn: Arc::new(OnceLock::new()),
+                node,
+            })
+        }
+
+        /// Runs the provided command on the connection, failures and
+        /// commands sent before the first connection is made resolve
+        /// to `None`
+        ///
+        /// `cmd` The command to run
+        fn query<T>(&self, cmd: Cmd) -> BoxFuture<'static, Option<T>>
+        where
+            T: FromRedisValue + Send + 'static,
+        {
+            let conn = self.conn.get().cloned();
+            Box::pin(async move {
+                let mut conn = conn?;
+                finish(tokio::time::timeout(TIMEOUT, cmd.query_async(&mut conn)).await)
+            })
+        }
+
+        /// Spawns the task making the connection for commands along with
+        /// the threads refreshing the claims of this node and applying the
+        /// messages published by the other nodes
+        pub fn spawn_tasks(&self) {
+            tokio::spawn(connect(self.client.clone(), self.conn.clone()));
+
+            let client = self.client.clone();
+            let node = self.node.clone();
+            thread::spawn(move || subscribe(client, node));
+
+            let client = self.client.clone();
+            let node = self.node.clone();
+            thread::spawn(move || refresh(client, node));
+        }
+    }
+
+    impl GameDirectory for RedisDirectory {
+        fn claim(&self, token: String) -> BoxFuture<'static, bool> {
+            let mut cmd = ::redis::cmd("SET");
+            cmd.arg(format!("{}{}", KEY_PREFIX, token))
+                .arg(&self.node)
+                .arg("NX")
+                .arg("EX")
+                .arg(CLAIM_TTL);
+            let claimed = self.query::<Option<String>>(cmd);
+            Box::pin(async move {
+                match claimed.await {
+                    Some(value) => value.is_some(),
+                    // Games keep being created while Redis is unreachable
+                    None => true,
+                }
+            })
+        }
+
+        fn release(&self, token: String) -> BoxFuture<'static, ()> {
+            let conn = self.conn.get().cloned();
+            let node = self.node.clone();
+            Box::pin(async move {
+                let Some(mut conn) = conn else {
+                    return;
+                };
+
+                // Only release the claim if it still belongs to this node
+                let script = Script::new(
+                    "if redis.call('GET', KEYS[1]) == ARGV[1] then \
+                     return redis.call('DEL', KEYS[1]) else return 0 end",
+                );
+                let mut invocation = script.key(format!("{}{}", KEY_PREFIX, token));
+                invocation.arg(node);
+                let result =
+                    tokio::time::timeout(TIMEOUT, invocation.invoke_async::<_, i64>(&mut conn))
+                        .await;
+                finish(result);
+            })
+        }
+
+        fn locate(&self, token: String) -> BoxFuture<'static, Option<String>> {
+            let mut cmd = ::redis::cmd("GET");
+            cmd.arg(format!("{}{}", KEY_PREFIX, token));
+            let node = self.query::<Option<String>>(cmd);
+            let local = self.node.clone();
+            Box::pin(async move {
+                let node = node.await??;
+                (node != local).then_some(node)
+            })
+        }
+
+        fn publish(&self, message: ClusterMessage) -> BoxFuture<'static, ()> {
+            let envelope = Envelope {
+                node: self.node.clone(),
+                message,
+            };
+            let Ok(payload) = serde_json::to_string(&envelope) else {
+                return Box::pin(async {});
+            };
+            let mut cmd = ::redis::cmd("PUBLISH");
+            cmd.arg(CHANNEL).arg(payload);
+            let published = self.query::<i64>(cmd);
+            Box::pin(async move {
+                published.await;
+            })
+        }
+    }
+
+    /// Logs the failure of a command returning its value when it succeeded
+    ///
+    /// `result` The result of the command within the timeout
+    fn finish<T>(result: Result<RedisResult<T>, tokio::time::error::Elapsed>) -> Option<T> {
+        match result {
+            Ok(Ok(value)) => Some(value),
+            Ok(Err(err)) => {
+                warn!("Redis command failed: {:?}", err);
+                None
+            }
+            Err(_) => {
+                warn!("Redis command timed out");
+                None
+            }
+        }
+    }
+
+    /// Makes the connection for commands retrying until Redis can be
+    /// reached, the manager reconnects by itself once connected
+    ///
+    /// `client` The Redis client
+    /// `conn`   Where the connection is stored once made
+    async fn connect(client: Client, conn: Arc<OnceLock<ConnectionManager>>) {
+        loop {
+            let result =
+                tokio::time::timeout(TIMEOUT, ConnectionManager::new(client.clone())).await;
+            match result {
+                Ok(Ok(value)) => {
+                    let _ = conn.set(value);
+                    info!("Connected to Redis");
+                    return;
+                }
+                Ok(Err(err)) => warn!("Failed to connect to Redis: {:?}", err),
+                Err(_) => warn!("Timed out connecting to Redis"),
+            }
+            tokio::time::sleep(RETRY_DELAY).await;
+        }
+    }
+
+    /// Applies the messages published by the other nodes, the
+    /// subscription is reopened whenever the connection is lost
+    ///
+    /// `client` The Redis client
+    /// `node`   The URL of this node
+    fn subscribe(client: Client, node: String) {
+        loop {
+            let result: RedisResult<()> = client.get_connection().and_then(|mut conn| {
+                let mut pubsub = conn.as_pubsub();
+                pubsub.subscribe(CHANNEL)?;
+                loop {
+                    let payload: String = pubsub.get_message()?.get_payload()?;
+                    let Ok(envelope) = serde_json::from_str::<Envelope>(&payload) else {
+                        continue;
+                    };
+                    if envelope.node == node {
+                        continue;
+                    }
+
+                    match envelope.message {
+                        ClusterMessage::Maintenance { enabled } => {
+                            registry().apply_maintenance(enabled)
+                        }
+                    }
+                }
+            });
+            if let Err(err) = result {
+                error!("Lost Redis subscription: {:?}", err);
+            }
+            thread::sleep(RETRY_DELAY);
+        }
+    }
+
+    /// Refreshes the claims on the tokens of the games running on
+    /// this node before they expire
+    ///
+    /// `client` The Redis client
+    /// `node`   The URL of this node
+    fn refresh(client: Client, node: String) {
+        let mut last = Instant::now();
+        loop {
+            thread::sleep(REFRESH_INTERVAL.saturating_sub(last.elapsed()));
+            last = Instant::now();
+
+            let tokens = registry().tokens();
+            if tokens.is_empty() {
+                continue;
+            }
+
+            let mut pipe = ::redis::pipe();
+            for token in &tokens {
+                pipe.cmd("SET")
+                    .arg(format!("{}{}", KEY_PREFIX, token))
+                    .arg(&node)
+                    .arg("EX")
+                    .arg(CLAIM_TTL)
+                    .ignore();
+            }
+            let result = client
+                .get_connection_with_timeout(TIMEOUT)
+                .and_then(|mut conn| pipe.query::<()>(&mut conn));
+            if let Err(err) = result {
+                warn!("Failed to refresh game claims: {:?}", err);
+            }
+        }
+    }
+}
@@ -0,0 +1,529 @@
+use std::{collections::HashMap, str::FromStr, sync::OnceLock};
+
+use crate::proxy::{Cidr, UNIX_PROXY};
+
+/// Environment variable for the path of the optional config file
+const CONFIG_PATH: (&str, &str) = ("QUIZLER_CONFIG", "quizler.toml");
+
+/// Prefix of the environment variables, the keys in the config file
+/// are the lowercase variable names without the prefix
+const ENV_PREFIX: &str = "QUIZLER_";
+
+/// Values loaded from the config file keyed by environment variable name
+static FILE_VALUES: OnceLock<HashMap<String, String>> = OnceLock::new();
+
+/// Environment variable for the application port along with its default value
+pub const PORT: (&str, u16) = ("QUIZLER_PORT", 80);
+
+/// Environment variable for a comma separated list of host:port addresses
+/// to listen on, when empty the server listens on all interfaces at the port
+pub const BIND: (&str, &str) = ("QUIZLER_BIND", "");
+
+/// Environment variable for the path of a unix socket to listen on
+/// in addition to the bind addresses
+#[cfg(unix)]
+pub const UNIX_SOCKET: (&str, &str) = ("QUIZLER_UNIX_SOCKET", "");
+
+/// Environment variable for the format logs are written in, either
+/// json for structured logs or text for human readable logs
+pub const LOG_FORMAT: (&str, &str) = ("QUIZLER_LOG_FORMAT", "json");
+
+/// Environment variable for the OTLP endpoint spans are exported to,
+/// exporting is disabled when empty
+#[cfg(feature = "otel")]
+pub const OTLP_ENDPOINT: (&str, &str) = ("QUIZLER_OTLP_ENDPOINT", "");
+
+/// Environment variable for the service name spans are exported with
+#[cfg(feature = "otel")]
+pub const OTLP_SERVICE_NAME: (&str, &str) = ("QUIZLER_OTLP_SERVICE_NAME", "quizler");
+
+/// Environment variable for the path of the PEM certificate chain used
+/// to serve HTTPS, TLS is disabled unless both paths are set
+#[cfg(feature = "tls")]
+pub const TLS_CERT: (&str, &str) = ("QUIZLER_TLS_CERT", "");
+
+/// Environment variable for the path of the PEM private key used to serve HTTPS
+#[cfg(feature = "tls")]
+pub const TLS_KEY: (&str, &str) = ("QUIZLER_TLS_KEY", "");
+
+/// Environment variable for the number of seconds a disconnected player
+/// is kept in the game waiting for them to reconnect
+pub const RECONNECT_GRACE: (&str, u64) = ("QUIZLER_RECONNECT_GRACE", 60);
+
+/// Environment variable for the number of seconds a session can go without
+/// responding to a heartbeat before it is considered dead
+pub const HEARTBEAT_TIMEOUT: (&str, u64) = ("QUIZLER_HEARTBEAT_TIMEOUT", 15);
+
+/// Environment variable for the maximum number of bytes written to a
+/// session that the client hasn't received before it is disconnected
+pub const MAX_OUTBOUND_BUFFER: (&str, usize) = ("QUIZLER_MAX_OUTBOUND_BUFFER", 1024 * 1024);
+
+/// Environment variable for the number of messages each session can
+/// send per second before being limited
+pub const MESSAGE_RATE: (&str, u32) = ("QUIZLER_MESSAGE_RATE", 20);
+
+/// Environment variable for the number of messages each session can
+/// send in a burst above the message rate
+pub const MESSAGE_BURST: (&str, u32) = ("QUIZLER_MESSAGE_BURST", 40);
+
+/// Environment variable for the maximum size in bytes of a websocket
+/// frame sent by a client, larger frames close the connection
+pub const MAX_MESSAGE_SIZE: (&str, usize) = ("QUIZLER_MAX_MESSAGE_SIZE", 16 * 1024);
+
+/// Environment variable for the directory uploaded images are stored in
+pub const IMAGE_DIR: (&str, &str) = ("QUIZLER_IMAGE_DIR", "images");
+
+/// Environment variable for the directory replays of finished games are stored in
+pub const REPLAY_DIR: (&str, &str) = ("QUIZLER_REPLAY_DIR", "replays");
+
+/// Environment variable for the directory checkpoints of running games are stored in
+pub const CHECKPOINT_DIR: (&str, &str) = ("QUIZLER_CHECKPOINT_DIR", "checkpoints");
+
+/// Environment variable for the number of seconds between checkpoints of
+/// the running games that are restored after a restart, games aren't
+/// checkpointed or restored when zero
+pub const CHECKPOINT_INTERVAL: (&str, u64) = ("QUIZLER_CHECKPOINT_INTERVAL", 10);
+
+/// Environment variable for the maximum size in bytes of uploaded images
+pub const MAX_IMAGE_SIZE: (&str, usize) = ("QUIZLER_MAX_IMAGE_SIZE", 5 * 1024 * 1024);
+
+/// Environment variable for the maximum size in bytes of an imported
+/// quiz bundle including its images
+pub const MAX_BUNDLE_SIZE: (&str, usize) = ("QUIZLER_MAX_BUNDLE_SIZE", 50 * 1024 * 1024);
+
+/// Environment variable for the path to the storage database
+pub const STORAGE_PATH: (&str, &str) = ("QUIZLER_STORAGE_PATH", "quizler.db");
+
+/// Environment variable for the number of seconds running games are given
+/// to finish their current question when the server is shutting down
+pub const SHUTDOWN_GRACE: (&str, u64) = ("QUIZLER_SHUTDOWN_GRACE", 30);
+
+/// Environment variable for the number of quizzes or image uploads each IP
+/// address can create within the rate limit window
+pub const CREATE_LIMIT: (&str, u32) = ("QUIZLER_CREATE_LIMIT", 10);
+
+/// Environment variable for the path to the file of API keys that can be
+/// used to create quizzes with their own rate limits
+pub const API_KEYS: (&str, &str) = ("QUIZLER_API_KEYS", "api_keys.txt");
+
+/// Environment variable for the number of invalid game tokens each IP address
+/// can attempt to join within the rate limit window
+pub const TOKEN_GUESS_LIMIT: (&str, u32) = ("QUIZLER_TOKEN_GUESS_LIMIT", 10);
+
+/// Environment variable for the length in seconds of the rate limit window
+pub const RATE_LIMIT_WINDOW: (&str, u64) = ("QUIZLER_RATE_LIMIT_WINDOW", 60);
+
+/// Environment variable for the path to the list of words that
+/// aren't allowed within usernames
+pub const PROFANITY_LIST: (&str, &str) = ("QUIZLER_PROFANITY_LIST", "profanity.txt");
+
+/// Environment variable for the number of seconds a game can go without any
+/// connected sessions before it is stopped
+pub const EMPTY_GAME_TTL: (&str, u64) = ("QUIZLER_EMPTY_GAME_TTL", 5 * 60);
+
+/// Environment variable for the number of seconds a game can go without any
+/// activity before it is stopped
+pub const IDLE_GAME_TTL: (&str, u64) = ("QUIZLER_IDLE_GAME_TTL", 60 * 60);
+
+/// Environment variable for the length of generated game tokens
+pub const TOKEN_LENGTH: (&str, usize) = ("QUIZLER_TOKEN_LENGTH", 5);
+
+/// Environment variable for a comma separated list of the CIDR ranges of
+/// proxies trusted to report the client address through the forwarding
+/// headers, unix trusts connections over the unix socket
+pub const TRUSTED_PROXIES: (&str, &str) = ("QUIZLER_TRUSTED_PROXIES", "");
+
+/// Environment variable for a comma separated list of extra origins allowed
+/// to use the API and connect to the websocket, the origin of the public URL
+/// is always allowed and * allows every origin
+pub const ALLOWED_ORIGINS: (&str, &str) = ("QUIZLER_ALLOWED_ORIGINS", "");
+
+/// Environment variable for the public URL of the frontend that
+/// players are sent to when joining through QR codes
+pub const PUBLIC_URL: (&str, &str) = ("QUIZLER_PUBLIC_URL", "http://localhost:8080");
+
+/// Environment variable for the key hosts authenticate with to manage the
+/// quiz library, the library is unavailable when no key is set
+pub const HOST_KEY: (&str, &str) = ("QUIZLER_HOST_KEY", "");
+
+/// Environment variable for the secret host tokens are signed with, a
+/// random secret is generated on startup when not set which invalidates
+/// existing host tokens whenever the server restarts
+pub const HOST_TOKEN_SECRET: (&str, &str) = ("QUIZLER_HOST_TOKEN_SECRET", "");
+
+/// Environment variable for the number of seconds host tokens are valid for
+pub const HOST_TOKEN_TTL: (&str, u64) = ("QUIZLER_HOST_TOKEN_TTL", 24 * 60 * 60);
+
+/// Environment variable for the number of seconds the profile cookies
+/// of returning players are valid for
+pub const PROFILE_TOKEN_TTL: (&str, u64) = ("QUIZLER_PROFILE_TOKEN_TTL", 365 * 24 * 60 * 60);
+
+/// Environment variable for the number of seconds the tokens issued to
+/// hosts that logged in are valid for
+#[cfg(feature = "oauth")]
+pub const USER_TOKEN_TTL: (&str, u64) = ("QUIZLER_USER_TOKEN_TTL", 7 * 24 * 60 * 60);
+
+/// Environment variable for the public URL of this server that OAuth
+/// providers redirect back to after logging in
+#[cfg(feature = "oauth")]
+pub const SERVER_URL: (&str, &str) = ("QUIZLER_SERVER_URL", "http://localhost");
+
+/// Environment variables for the Google OAuth client
+#[cfg(feature = "oauth")]
+pub const GOOGLE_CLIENT_ID: (&str, &str) = ("QUIZLER_GOOGLE_CLIENT_ID", "");
+#[cfg(feature = "oauth")]
+pub const GOOGLE_CLIENT_SECRET: (&str, &str) = ("QUIZLER_GOOGLE_CLIENT_SECRET", "");
+
+/// Environment variables for the Microsoft OAuth client
+#[cfg(feature = "oauth")]
+pub const MICROSOFT_CLIENT_ID: (&str, &str) = ("QUIZLER_MICROSOFT_CLIENT_ID", "");
+#[cfg(feature = "oauth")]
+pub const MICROSOFT_CLIENT_SECRET: (&str, &str) = ("QUIZLER_MICROSOFT_CLIENT_SECRET", "");
+
+/// Environment variable for the URL of the sink game events are published
+/// to, either nats://host:port or kafka://broker,broker
+#[cfg(any(feature = "nats", feature = "kafka"))]
+pub const EVENTS_URL: (&str, &str) = ("QUIZLER_EVENTS_URL", "");
+
+/// Environment variable for the subject or topic game events are published to
+#[cfg(any(feature = "nats", feature = "kafka"))]
+pub const EVENTS_TOPIC: (&str, &str) = ("QUIZLER_EVENTS_TOPIC", "quizler.events");
+
+/// Environment variable for the host of the SMTP server results are
+/// emailed through, results aren't emailed when not set
+#[cfg(feature = "email")]
+pub const SMTP_HOST: (&str, &str) = ("QUIZLER_SMTP_HOST", "");
+
+/// Environment variable for the port of the SMTP server
+#[cfg(feature = "email")]
+pub const SMTP_PORT: (&str, u16) = ("QUIZLER_SMTP_PORT", 587);
+
+/// Environment variables for the credentials for the SMTP server
+#[cfg(feature = "email")]
+pub const SMTP_USERNAME: (&str, &str) = ("QUIZLER_SMTP_USERNAME", "");
+#[cfg(feature = "email")]
+pub const SMTP_PASSWORD: (&str, &str) = ("QUIZLER_SMTP_PASSWORD", "");
+
+/// Environment variable for the address results emails are sent from
+#[cfg(feature = "email")]
+pub const SMTP_FROM: (&str, &str) = ("QUIZLER_SMTP_FROM", "");
+
+/// Environment variables for the LMS platform registration, games can
+/// only be launched from the LMS when the issuer is set
+#[cfg(feature = "lti")]
+pub const LTI_ISSUER: (&str, &str) = ("QUIZLER_LTI_ISSUER", "");
+#[cfg(feature = "lti")]
+pub const LTI_CLIENT_ID: (&str, &str) = ("QUIZLER_LTI_CLIENT_ID", "");
+
+/// Environment variables for the platform OIDC authentication, key set
+/// and access token endpoints
+#[cfg(feature = "lti")]
+pub const LTI_AUTH_URL: (&str, &str) = ("QUIZLER_LTI_AUTH_URL", "");
+#[cfg(feature = "lti")]
+pub const LTI_JWKS_URL: (&str, &str) = ("QUIZLER_LTI_JWKS_URL", "");
+#[cfg(feature = "lti")]
+pub const LTI_TOKEN_URL: (&str, &str) = ("QUIZLER_LTI_TOKEN_URL", "");
+
+/// Environment variables for the path to the RSA private key in PEM
+/// format that access token requests are signed with and the ID of
+/// the key registered with the platform
+#[cfg(feature = "lti")]
+pub const LTI_PRIVATE_KEY: (&str, &str) = ("QUIZLER_LTI_PRIVATE_KEY", "");
+#[cfg(feature = "lti")]
+pub const LTI_KEY_ID: (&str, &str) = ("QUIZLER_LTI_KEY_ID", "");
+
+/// Environment variable for the URL of the Redis server shared by
+/// every node, games are only tracked locally when not set
+#[cfg(feature = "redis")]
+pub const REDIS_URL: (&str, &str) = ("QUIZLER_REDIS_URL", "");
+
+/// Environment variable for the public URL of this node that players
+/// are sent to when joining one of its games through another node
+#[cfg(feature = "redis")]
+pub const NODE_URL: (&str, &str) = ("QUIZLER_NODE_URL", "");
+
+/// Loads the config file and validates every setting returning a
+/// description of each value that is invalid so that they can all be
+/// reported at once. Settings are layered with the environment taking
+/// priority over the config file which takes priority over the defaults
+pub fn load() -> Vec<String> {
+    let mut errors = Vec::new();
+    let values = read_file(&mut errors);
+    let _ = FILE_VALUES.set(values);
+
+    check(&mut errors, PORT);
+    check(&mut errors, RECONNECT_GRACE);
+    check(&mut errors, HEARTBEAT_TIMEOUT);
+    check(&mut errors, MAX_MESSAGE_SIZE);
+    check(&mut errors, MAX_OUTBOUND_BUFFER);
+    check(&mut errors, MESSAGE_RATE);
+    check(&mut errors, MESSAGE_BURST);
+    check(&mut errors, CHECKPOINT_INTERVAL);
+    check(&mut errors, MAX_IMAGE_SIZE);
+    check(&mut errors, MAX_BUNDLE_SIZE);
+    check(&mut errors, SHUTDOWN_GRACE);
+    check(&mut errors, CREATE_LIMIT);
+    check(&mut errors, TOKEN_GUESS_LIMIT);
+    check(&mut errors, RATE_LIMIT_WINDOW);
+    check(&mut errors, EMPTY_GAME_TTL);
+    check(&mut errors, IDLE_GAME_TTL);
+    check(&mut errors, TOKEN_LENGTH);
+    check(&mut errors, HOST_TOKEN_TTL);
+    check(&mut errors, PROFILE_TOKEN_TTL);
+
+    #[cfg(feature = "tls")]
+    {
+        let cert = from_env_string(TLS_CERT);
+        let key = from_env_string(TLS_KEY);
+        if cert.is_empty() != key.is_empty() {
+            errors.push(format!(
+                "{} and {} must be set together",
+                TLS_CERT.0, TLS_KEY.0
+            ));
+        }
+    }
+
+    #[cfg(feature = "oauth")]
+    {
+        check(&mut errors, USER_TOKEN_TTL);
+
+        // Providers need both the client ID and the secret
+        for (id, secret) in [
+            (GOOGLE_CLIENT_ID, GOOGLE_CLIENT_SECRET),
+            (MICROSOFT_CLIENT_ID, MICROSOFT_CLIENT_SECRET),
+        ] {
+            if !from_env_string(id).is_empty() && from_env_string(secret).is_empty() {
+                errors.push(format!("{} is required when {} is set", secret.0, id.0));
+            }
+        }
+    }
+
+    #[cfg(feature = "email")]
+    {
+        check(&mut errors, SMTP_PORT);
+
+        if !from_env_string(SMTP_HOST).is_empty() && from_env_string(SMTP_FROM).is_empty() {
+            errors.push(format!(
+                "{} is required when {} is set",
+                SMTP_FROM.0, SMTP_HOST.0
+            ));
+        }
+    }
+
+    #[cfg(feature = "lti")]
+    {
+        let settings = [
+            LTI_ISSUER,
+            LTI_CLIENT_ID,
+            LTI_AUTH_URL,
+            LTI_JWKS_URL,
+            LTI_TOKEN_URL,
+            LTI_PRIVATE_KEY,
+            LTI_KEY_ID,
+        ];
+
+        // The whole registration is needed once the issuer is set
+        if !from_env_string(LTI_ISSUER).is_empty() {
+            for setting in &settings[1..] {
+                if from_env_string(*setting).is_empty() {
+                    errors.push(format!(
+                        "{} is required when {} is set",
+                        setting.0, LTI_ISSUER.0
+                    ));
+                }
+            }
+        }
+    }
+
+    #[cfg(feature = "redis")]
+    {
+        if !from_env_string(REDIS_URL).is_empty() && from_env_string(NODE_URL).is_empty() {
+            errors.push(format!(
+                "{} is required when {} is set",
+                NODE_URL.0, REDIS_URL.0
+            ));
+        }
+    }
+
+    if !matches!(from_env_string(LOG_FORMAT).as_str(), "json" | "text") {
+        errors.push(format!("{} must be either json or text", LOG_FORMAT.0));
+    }
+    for addr in bind_addresses() {
+        let valid = addr
+            .rsplit_once(':')
+            .is_some_and(|(host, port)| !host.is_empty() && port.parse::<u16>().is_ok());
+        if !valid {
+            errors.push(format!("{} has an invalid address {:?}", BIND.0, addr));
+        }
+    }
+    for origin in from_env_string(ALLOWED_ORIGINS).split(',').map(str::trim) {
+        if !origin.is_empty() && origin != "*" && !is_valid_origin(origin) {
+            errors.push(format!(
+                "{} has an invalid origin {:?}",
+                ALLOWED_ORIGINS.0, origin
+            ));
+        }
+    }
+    for proxy in from_env_string(TRUSTED_PROXIES).split(',').map(str::trim) {
+        if !proxy.is_empty() && proxy != UNIX_PROXY && Cidr::parse(proxy).is_none() {
+            errors.push(format!(
+                "{} has an invalid range {:?}",
+                TRUSTED_PROXIES.0, proxy
+            ));
+        }
+    }
+    if from_env(MESSAGE_RATE) == 0 || from_env(MESSAGE_BURST) == 0 {
+        errors.push(format!(
+            "{} and {} must be greater than zero",
+            MESSAGE_RATE.0, MESSAGE_BURST.0
+        ));
+    }
+    if from_env(TOKEN_LENGTH) == 0 {
+        errors.push(format!("{} must be greater than zero", TOKEN_LENGTH.0));
+    }
+
+    errors
+}
+
+/// Returns the addresses the server should listen on
+pub fn bind_addresses() -> Vec<String> {
+    let bind = from_env_string(BIND);
+    let addrs: Vec<String> = bind
+        .split(',')
+        .map(str::trim)
+        .filter(|addr| !addr.is_empty())
+        .map(str::to_string)
+        .collect();
+
+    if addrs.is_empty() {
+        vec![format!("0.0.0.0:{}", from_env(PORT))]
+    } else {
+        addrs
+    }
+}
+
+/// Returns the origins allowed to use the API and connect to the
+/// websocket, includes the origin of the public URL
+pub fn allowed_origins() -> Vec<String> {
+    let public_url = from_env_string(PUBLIC_URL);
+    let public_origin = match public_url.split_once("://") {
+        Some((scheme, rest)) => {
+            let host = rest.split('/').next().unwrap_or_default();
+            format!("{}://{}", scheme, host)
+        }
+        None => public_url,
+    };
+
+    let mut origins = vec![public_origin];
+    origins.extend(
+        from_env_string(ALLOWED_ORIGINS)
+            .split(',')
+            .map(|origin| origin.trim().trim_end_matches('/'))
+            .filter(|origin| !origin.is_empty())
+            .map(str::to_string),
+    );
+    origins
+}
+
+/// Checks that an origin is a http or https scheme followed by a host
+/// and an optional port without any path
+///
+/// `origin` The origin to check
+fn is_valid_origin(origin: &str) -> bool {
+    let Some((scheme, rest)) = origin.trim_end_matches('/').split_once("://") else {
+        return false;
+    };
+    let (host, port) = match rest.rsplit_once(':') {
+        Some((host, port)) if !port.ends_with(']') => (host, Some(port)),
+        _ => (rest, None),
+    };
+    matches!(scheme, "http" | "https")
+        && !host.is_empty()
+        && !host.contains(['/', '?', '#', '@'])
+        && port.is_none_or(|port| port.parse::<u16>().is_ok())
+}
+
+/// Reads the values from the config file, the file is optional so
+/// nothing is loaded when it doesn't exist
+///
+/// `errors` The list of errors to add to
+fn read_file(errors: &mut Vec<String>) -> HashMap<String, String> {
+    let mut values = HashMap::new();
+
+    let path = std::env::var(CONFIG_PATH.0).unwrap_or_else(|_| CONFIG_PATH.1.to_string());
+    let text = match std::fs::read_to_string(&path) {
+        Ok(value) => value,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return values,
+        Err(err) => {
+            errors.push(format!("Failed to read config file {}: {}", path, err));
+            return values;
+        }
+    };
+
+    let table = match text.parse::<toml::Table>() {
+        Ok(value) => value,
+        Err(err) => {
+            errors.push(format!("Failed to parse config file {}: {}", path, err));
+            return values;
+        }
+    };
+
+    for (key, value) in table {
+        let value = match value {
+            toml::Value::String(value) => value,
+            toml::Value::Integer(value) => value.to_string(),
+            toml::Value::Float(value) => value.to_string(),
+            toml::Value::Boolean(value) => value.to_string(),
+            _ => {
+                errors.push(format!("{} in {} must be a single value", key, path));
+                continue;
+            }
+        };
+        values.insert(format!("{}{}", ENV_PREFIX, key.to_uppercase()), value);
+    }
+
+    values
+}
+
+/// Checks that the value provided for the setting can be parsed
+///
+/// `errors` The list of errors to add to
+/// `pair`   The environment pair
+fn check<V: FromStr>(errors: &mut Vec<String>, pair: (&str, V)) {
+    if let Some(value) = lookup(pair.0) {
+        if value.parse::<V>().is_err() {
+            errors.push(format!("{} has an invalid value {:?}", pair.0, value));
+        }
+    }
+}
+
+/// Finds the value for the provided environment variable checking
+/// the environment before the config file
+///
+/// `key` The environment variable name
+fn lookup(key: &str) -> Option<String> {
+    std::env::var(key).ok().or_else(|| {
+        FILE_VALUES
+            .get()
+            .and_then(|values| values.get(key))
+            .cloned()
+    })
+}
+
+/// Retrieve and parse a setting from the provided pair returning
+/// the default value if not set
+///
+/// `pair` The environment pair
+pub fn from_env<V: FromStr>(pair: (&str, V)) -> V {
+    lookup(pair.0)
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(pair.1)
+}
+
+/// Retrieve a string setting from the provided pair returning
+/// the default value if not set
+///
+/// `pair` The environment pair
+pub fn from_env_string(pair: (&str, &str)) -> String {
+    lookup(pair.0).unwrap_or_else(|| pair.1.to_string())
+}
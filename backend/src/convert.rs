@@ -0,0 +1,670 @@
+use rand_core::OsRng;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::{FieldError, FieldErrorReason},
+    game::{shuffle, Question, QuestionType},
+    matching::TextMatchOptions,
+};
+
+/// Quiz export formats from other platforms that can be converted
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConvertFormat {
+    /// JSON quiz definition exported from Kahoot
+    Kahoot,
+    /// Kahoot quiz spreadsheet template
+    #[cfg(feature = "xlsx")]
+    KahootXlsx,
+    /// JSON quiz definition exported from Quizizz
+    Quizizz,
+}
+
+/// Quiz converted from another platform
+#[derive(Serialize)]
+pub struct ConvertedQuiz {
+    /// The name of the quiz
+    pub name: String,
+    /// The description of the quiz
+    pub text: String,
+    /// The converted questions
+    pub questions: Vec<Question>,
+    /// The indexes of the questions that were skipped because
+    /// they have no equivalent question type
+    pub skipped: Vec<usize>,
+}
+
+/// Converts the provided quiz export into a quiz, the converted
+/// questions are validated using the question indexes from the export
+///
+/// `format` The format of the export
+/// `data`   The export contents
+pub fn convert(format: ConvertFormat, data: &[u8]) -> Result<ConvertedQuiz, Vec<FieldError>> {
+    let quiz = match format {
+        ConvertFormat::Kahoot => convert_kahoot(data),
+        #[cfg(feature = "xlsx")]
+        ConvertFormat::KahootXlsx => convert_kahoot_xlsx(data),
+        ConvertFormat::Quizizz => convert_quizizz(data),
+    }?;
+
+    let mut errors = Vec::new();
+    if quiz.questions.is_empty() {
+        errors.push(FieldError::new("questions", FieldErrorReason::Empty));
+    }
+    for (index, question) in quiz.questions.iter().enumerate() {
+        question.validate(&format!("questions[{}]", index), &mut errors);
+    }
+
+    if errors.is_empty() {
+        Ok(quiz)
+    } else {
+        Err(errors)
+    }
+}
+
+/// Parses the provided JSON export
+///
+/// `data` The export contents
+fn parse_json<'de, T: Deserialize<'de>>(data: &'de [u8]) -> Result<T, Vec<FieldError>> {
+    serde_json::from_slice(data)
+        .map_err(|_| vec![FieldError::new("quiz", FieldErrorReason::InvalidValue)])
+}
+
+/// Creates the values and correct answer indexes from a list of
+/// choices skipping any empty choices
+///
+/// `choices` The choice text along with whether its correct
+fn choice_values<'a>(choices: impl Iterator<Item = (&'a str, bool)>) -> (Vec<String>, Vec<usize>) {
+    let mut values = Vec::new();
+    let mut answers = Vec::new();
+    for (text, correct) in choices {
+        let text = text.trim();
+        if text.is_empty() {
+            continue;
+        }
+        if correct {
+            answers.push(values.len());
+        }
+        values.push(text.to_string());
+    }
+    (values, answers)
+}
+
+/// Creates an ordering question from values that are already in the
+/// correct order, the shown order is shuffled so it doesn't give away
+/// the answer
+///
+/// `values` The values in the correct order
+fn ordering(values: Vec<String>) -> QuestionType {
+    let mut shown: Vec<usize> = (0..values.len()).collect();
+    shuffle(&mut OsRng, &mut shown);
+
+    let order = (0..values.len())
+        .map(|index| {
+            shown
+                .iter()
+                .position(|value| *value == index)
+                .unwrap_or(index)
+        })
+        .collect();
+    let values = shown.iter().map(|index| values[*index].clone()).collect();
+    QuestionType::Ordering {
+        order,
+        values,
+        partial_credit: false,
+    }
+}
+
+/// Kahoot JSON export
+#[derive(Deserialize)]
+struct KahootQuiz {
+    title: String,
+    #[serde(default)]
+    description: String,
+    questions: Vec<KahootQuestion>,
+}
+
+#[derive(Deserialize)]
+struct KahootQuestion {
+    #[serde(rename = "type")]
+    ty: String,
+    #[serde(default)]
+    question: String,
+    /// The answer time in milliseconds
+    #[serde(default)]
+    time: u64,
+    #[serde(default)]
+    choices: Vec<KahootChoice>,
+}
+
+#[derive(Deserialize)]
+struct KahootChoice {
+    #[serde(default)]
+    answer: String,
+    #[serde(default)]
+    correct: bool,
+}
+
+/// Converts a Kahoot JSON export
+///
+/// `data` The export contents
+fn convert_kahoot(data: &[u8]) -> Result<ConvertedQuiz, Vec<FieldError>> {
+    let quiz: KahootQuiz = parse_json(data)?;
+
+    let mut questions = Vec::new();
+    let mut skipped = Vec::new();
+    for (index, question) in quiz.questions.into_iter().enumerate() {
+        let choices = question
+            .choices
+            .iter()
+            .map(|choice| (choice.answer.as_str(), choice.correct));
+        let (values, answers) = choice_values(choices);
+
+        let ty = match question.ty.as_str() {
+            "quiz" if is_true_false(&values) => QuestionType::TrueFalse {
+                answer: answers
+                    .first()
+                    .is_some_and(|answer| values[*answer].eq_ignore_ascii_case("true")),
+            },
+            "quiz" => QuestionType::Single { answers, values },
+            "multiple_select_quiz" => QuestionType::MultiSelect { answers, values },
+            "open_ended" => QuestionType::TextEntry {
+                answers: values,
+                options: TextMatchOptions::default(),
+            },
+            "jumble" => ordering(values),
+            "survey" => QuestionType::Poll {
+                values,
+                participation: 0,
+            },
+            _ => {
+                skipped.push(index);
+                continue;
+            }
+        };
+
+        questions.push(Question::new(
+            question.question,
+            String::new(),
+            ty,
+            question.time,
+            None,
+        ));
+    }
+
+    Ok(ConvertedQuiz {
+        name: quiz.title,
+        text: quiz.description,
+        questions,
+        skipped,
+    })
+}
+
+/// Checks whether the values are the true and false values
+/// of a true or false question
+///
+/// `values` The values to check
+fn is_true_false(values: &[String]) -> bool {
+    let [first, second] = values else {
+        return false;
+    };
+    (first.eq_ignore_ascii_case("true") && second.eq_ignore_ascii_case("false"))
+        || (first.eq_ignore_ascii_case("false") && second.eq_ignore_ascii_case("true"))
+}
+
+/// Converts the Kahoot spreadsheet template. Question rows have the
+/// question number in the first column followed by the question, up
+/// to four answers, the time limit in seconds and the 1-based indexes
+/// of the correct answers separated by commas
+///
+/// `data` The spreadsheet contents
+#[cfg(feature = "xlsx")]
+fn convert_kahoot_xlsx(data: &[u8]) -> Result<ConvertedQuiz, Vec<FieldError>> {
+    let rows = crate::import::read_xlsx(data)?;
+
+    let mut questions = Vec::new();
+    let mut errors = Vec::new();
+    for (row, cells) in rows {
+        let cell = |index: usize| cells.get(index).map_or("", |value| value.trim());
+
+        // Rows that don't start with a question number are template text
+        if cell(0).parse::<f64>().is_err() || cell(1).is_empty() {
+            continue;
+        }
+
+        let Ok(time) = cell(6).parse::<f64>() else {
+            errors.push(FieldError::new(
+                format!("rows[{}].time", row),
+                FieldErrorReason::InvalidValue,
+            ));
+            continue;
+        };
+
+        let correct: Vec<usize> = cell(7)
+            .split(',')
+            .filter_map(|value| value.trim().parse::<usize>().ok())
+            .collect();
+        let choices = (2..=5).map(|index| (cell(index), correct.contains(&(index - 1))));
+        let (values, answers) = choice_values(choices);
+
+        questions.push(Question::new(
+            cell(1).to_string(),
+            String::new(),
+            QuestionType::Single { answers, values },
+            (time * 1000.0) as u64,
+            None,
+        ));
+    }
+
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    Ok(ConvertedQuiz {
+        name: String::new(),
+        text: String::new(),
+        questions,
+        skipped: Vec::new(),
+    })
+}
+
+/// Quizizz JSON export
+#[derive(Deserialize)]
+struct QuizizzExport {
+    data: QuizizzData,
+}
+
+#[derive(Deserialize)]
+struct QuizizzData {
+    quiz: QuizizzQuiz,
+}
+
+#[derive(Deserialize)]
+struct QuizizzQuiz {
+    info: QuizizzInfo,
+}
+
+#[derive(Deserialize)]
+struct QuizizzInfo {
+    name: String,
+    questions: Vec<QuizizzQuestion>,
+}
+
+#[derive(Deserialize)]
+struct QuizizzQuestion {
+    #[serde(rename = "type")]
+    ty: String,
+    /// The answer time in milliseconds
+    #[serde(default)]
+    time: u64,
+    structure: QuizizzStructure,
+}
+
+#[derive(Deserialize)]
+struct QuizizzStructure {
+    query: QuizizzText,
+    #[serde(default)]
+    options: Vec<QuizizzText>,
+    #[serde(default)]
+    answer: Option<QuizizzAnswer>,
+}
+
+#[derive(Deserialize)]
+struct QuizizzText {
+    /// The text as HTML
+    #[serde(default)]
+    text: String,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum QuizizzAnswer {
+    Single(usize),
+    Multiple(Vec<usize>),
+}
+
+/// Converts a Quizizz JSON export
+///
+/// `data` The export contents
+fn convert_quizizz(data: &[u8]) -> Result<ConvertedQuiz, Vec<FieldError>> {
+    let export: QuizizzExport = parse_json(data)?;
+    let info = export.data.quiz.info;
+
+    let mut questions = Vec::new();
+    let mut skipped = Vec::new();
+    for (index, question) in info.questions.into_iter().enumerate() {
+        let structure = question.structure;
+        let correct = match structure.answer {
+            Some(QuizizzAnswer::Single(answer)) => vec![answer],
+            Some(QuizizzAnswer::Multiple(answers)) => answers,
+            None => Vec::new(),
+        };
+        let texts: Vec<String> = structure
+            .options
+            .iter()
+            .map(|option| strip_html(&option.text))
+            .collect();
+        let choices = texts
+            .iter()
+            .enumerate()
+            .map(|(index, text)| (text.as_str(), correct.contains(&index)));
+        let (values, answers) = choice_values(choices);
+
+        let ty = match question.ty.as_str() {
+            "MCQ" => QuestionType::Single { answers, values },
+            "MSQ" => QuestionType::MultiSelect { answers, values },
+            "BLANK" => QuestionType::TextEntry {
+                answers: values,
+                options: TextMatchOptions::default(),
+            },
+            "POLL" => QuestionType::Poll {
+                values,
+                participation: 0,
+            },
+            "OPEN" => QuestionType::OpenEnded {},
+            _ => {
+                skipped.push(index);
+                continue;
+            }
+        };
+
+        questions.push(Question::new(
+            strip_html(&structure.query.text),
+            String::new(),
+            ty,
+            question.time,
+            None,
+        ));
+    }
+
+    Ok(ConvertedQuiz {
+        name: info.name,
+        text: String::new(),
+        questions,
+        skipped,
+    })
+}
+
+/// Removes the HTML tags from the provided text and decodes the
+/// common HTML entities
+///
+/// `value` The HTML text
+fn strip_html(value: &str) -> String {
+    let mut output = String::with_capacity(value.len());
+    let mut in_tag = false;
+    for char in value.chars() {
+        match char {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            char if !in_tag => output.push(char),
+            _ => {}
+        }
+    }
+
+    output
+        .replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+        .trim()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::*;
+
+    /// Converts the export expecting it to succeed
+    fn converted(format: ConvertFormat, data: &[u8]) -> ConvertedQuiz {
+        match convert(format, data) {
+            Ok(quiz) => quiz,
+            Err(errors) => panic!(
+                "conversion failed: {}",
+                serde_json::to_string(&errors).unwrap_or_default()
+            ),
+        }
+    }
+
+    /// Converts the export expecting it to fail returning the
+    /// fields and reasons of the errors
+    fn errors(format: ConvertFormat, data: &[u8]) -> Vec<(String, Value)> {
+        let Err(errors) = convert(format, data) else {
+            panic!("conversion unexpectedly succeeded");
+        };
+        errors
+            .iter()
+            .map(|error| {
+                let reason = serde_json::to_value(&error.reason).unwrap_or_default();
+                (error.field.clone(), reason)
+            })
+            .collect()
+    }
+
+    /// Serializes the question at the index for comparison
+    fn question(quiz: &ConvertedQuiz, index: usize) -> Value {
+        serde_json::to_value(&quiz.questions[index]).unwrap_or_default()
+    }
+
+    #[test]
+    fn kahoot_export_is_converted() {
+        let data = json!({
+            "title": "Capitals",
+            "description": "World capitals",
+            "questions": [
+                {
+                    "type": "quiz",
+                    "question": "Capital of France?",
+                    "time": 20000,
+                    "choices": [
+                        { "answer": "Paris", "correct": true },
+                        { "answer": "Lyon" },
+                        { "answer": " " }
+                    ]
+                },
+                {
+                    "type": "quiz",
+                    "question": "The sky is green",
+                    "time": 10000,
+                    "choices": [
+                        { "answer": "True" },
+                        { "answer": "False", "correct": true }
+                    ]
+                },
+                {
+                    "type": "multiple_select_quiz",
+                    "question": "Prime numbers",
+                    "time": 30000,
+                    "choices": [
+                        { "answer": "2", "correct": true },
+                        { "answer": "4" },
+                        { "answer": "5", "correct": true }
+                    ]
+                },
+                {
+                    "type": "jumble",
+                    "question": "Smallest to largest",
+                    "time": 30000,
+                    "choices": [
+                        { "answer": "1" },
+                        { "answer": "2" },
+                        { "answer": "3" }
+                    ]
+                },
+                { "type": "content", "question": "Intermission" }
+            ]
+        });
+        let quiz = converted(ConvertFormat::Kahoot, data.to_string().as_bytes());
+
+        assert_eq!(quiz.name, "Capitals");
+        assert_eq!(quiz.text, "World capitals");
+        assert_eq!(quiz.questions.len(), 4);
+        assert_eq!(quiz.skipped, vec![4]);
+
+        let first = question(&quiz, 0);
+        assert_eq!(first["title"], "Capital of France?");
+        assert_eq!(first["answer_time"], 20000);
+        assert_eq!(
+            first["ty"],
+            json!({ "Single": { "answers": [0], "values": ["Paris", "Lyon"] } })
+        );
+
+        assert_eq!(
+            question(&quiz, 1)["ty"],
+            json!({ "TrueFalse": { "answer": false } })
+        );
+        assert_eq!(
+            question(&quiz, 2)["ty"],
+            json!({ "MultiSelect": { "answers": [0, 2], "values": ["2", "4", "5"] } })
+        );
+
+        // The shown values are shuffled with the order leading back
+        let ordering = &question(&quiz, 3)["ty"]["Ordering"];
+        let values = ordering["values"].as_array().cloned().unwrap_or_default();
+        let order = ordering["order"].as_array().cloned().unwrap_or_default();
+        let sorted: Vec<&Value> = order
+            .iter()
+            .filter_map(|index| values.get(index.as_u64()? as usize))
+            .collect();
+        assert_eq!(sorted, vec!["1", "2", "3"]);
+    }
+
+    #[test]
+    fn malformed_kahoot_export_is_rejected() {
+        assert_eq!(
+            errors(ConvertFormat::Kahoot, b"{\"title\": 5}"),
+            vec![("quiz".to_string(), json!("InvalidValue"))]
+        );
+
+        // Questions are validated after converting
+        let data = json!({
+            "title": "Untimed",
+            "questions": [{
+                "type": "quiz",
+                "question": "No time limit",
+                "choices": [{ "answer": "Yes" }]
+            }]
+        });
+        let errors = errors(ConvertFormat::Kahoot, data.to_string().as_bytes());
+        assert!(errors.contains(&(
+            "questions[0].answer_time".to_string(),
+            json!("InvalidTiming")
+        )));
+        assert!(errors.contains(&(
+            "questions[0].ty.answers".to_string(),
+            json!("NoCorrectAnswer")
+        )));
+    }
+
+    #[test]
+    fn quizizz_export_is_converted() {
+        let data = json!({
+            "data": { "quiz": { "info": {
+                "name": "Science",
+                "questions": [
+                    {
+                        "type": "MCQ",
+                        "time": 30000,
+                        "structure": {
+                            "query": { "text": "<p>Is H<sub>2</sub>O water &amp; ice?</p>" },
+                            "options": [{ "text": "<p>Yes</p>" }, { "text": "<p>No</p>" }],
+                            "answer": 0
+                        }
+                    },
+                    {
+                        "type": "MSQ",
+                        "time": 30000,
+                        "structure": {
+                            "query": { "text": "Noble gases" },
+                            "options": [{ "text": "Neon" }, { "text": "Iron" }, { "text": "Argon" }],
+                            "answer": [0, 2]
+                        }
+                    },
+                    {
+                        "type": "OPEN",
+                        "time": 60000,
+                        "structure": { "query": { "text": "Explain why" } }
+                    },
+                    {
+                        "type": "DRAW",
+                        "time": 60000,
+                        "structure": { "query": { "text": "Draw a cell" } }
+                    }
+                ]
+            } } }
+        });
+        let quiz = converted(ConvertFormat::Quizizz, data.to_string().as_bytes());
+
+        assert_eq!(quiz.name, "Science");
+        assert_eq!(quiz.questions.len(), 3);
+        assert_eq!(quiz.skipped, vec![3]);
+
+        let first = question(&quiz, 0);
+        assert_eq!(first["title"], "Is H2O water & ice?");
+        assert_eq!(first["answer_time"], 30000);
+        assert_eq!(
+            first["ty"],
+            json!({ "Single": { "answers": [0], "values": ["Yes", "No"] } })
+        );
+        assert_eq!(
+            question(&quiz, 1)["ty"],
+            json!({ "MultiSelect": { "answers": [0, 2], "values": ["Neon", "Iron", "Argon"] } })
+        );
+        assert_eq!(question(&quiz, 2)["ty"], json!({ "OpenEnded": {} }));
+    }
+
+    #[test]
+    fn malformed_quizizz_export_is_rejected() {
+        assert_eq!(
+            errors(ConvertFormat::Quizizz, b"{\"data\": {}}"),
+            vec![("quiz".to_string(), json!("InvalidValue"))]
+        );
+
+        let data = json!({
+            "data": { "quiz": { "info": { "name": "Empty", "questions": [] } } }
+        });
+        assert_eq!(
+            errors(ConvertFormat::Quizizz, data.to_string().as_bytes()),
+            vec![("questions".to_string(), json!("Empty"))]
+        );
+    }
+
+    #[cfg(feature = "xlsx")]
+    #[test]
+    fn kahoot_spreadsheet_is_converted() {
+        let data = include_bytes!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/kahoot.xlsx"
+        ));
+        let quiz = converted(ConvertFormat::KahootXlsx, data);
+
+        assert_eq!(quiz.questions.len(), 2);
+
+        let first = question(&quiz, 0);
+        assert_eq!(first["title"], "Capital of France?");
+        assert_eq!(first["answer_time"], 20000);
+        assert_eq!(
+            first["ty"],
+            json!({ "Single": { "answers": [0], "values": ["Paris", "Lyon", "Nice", "Marseille"] } })
+        );
+
+        let second = question(&quiz, 1);
+        assert_eq!(second["answer_time"], 30000);
+        assert_eq!(
+            second["ty"],
+            json!({ "Single": { "answers": [0, 2], "values": ["2", "4", "5"] } })
+        );
+    }
+
+    #[cfg(feature = "xlsx")]
+    #[test]
+    fn malformed_kahoot_spreadsheet_is_rejected() {
+        assert_eq!(
+            errors(ConvertFormat::KahootXlsx, b"not a spreadsheet"),
+            vec![("rows".to_string(), json!("InvalidValue"))]
+        );
+    }
+}
@@ -0,0 +1,352 @@
+use std::{fmt, str::Utf8Error};
+
+use crate::session::ClientMessage;
+
+/// The maximum depth of nested objects and arrays in a message
+const MAX_DEPTH: usize = 16;
+/// The maximum length in bytes of a single string in a message
+const MAX_STRING: usize = 4096;
+
+/// Errors that can occur while decoding a client message
+#[derive(Debug)]
+pub enum DecodeError {
+    /// The message nested deeper or held longer strings than any
+    /// valid client message would
+    Limits,
+    /// The JSON text wasn't valid UTF-8
+    Utf8(Utf8Error),
+    /// The JSON message was malformed
+    Json(serde_json::Error),
+    /// The MessagePack message was malformed
+    MessagePack(rmp_serde::decode::Error),
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Limits => f.write_str("Message exceeded the size limits"),
+            Self::Utf8(err) => write!(f, "Invalid UTF-8: {}", err),
+            Self::Json(err) => write!(f, "Invalid JSON: {}", err),
+            Self::MessagePack(err) => write!(f, "Invalid MessagePack: {}", err),
+        }
+    }
+}
+
+/// Decodes a client message from the payload of a websocket frame. JSON
+/// messages always start with an ASCII byte while MessagePack messages
+/// always start with a map or array marker so the encoding is detected
+/// from the first byte. Both encodings are checked against the limits
+/// before decoding so hostile messages are rejected without allocating
+/// for them, any input is rejected with an error rather than a panic
+///
+/// `bytes` The frame payload
+pub fn decode_client_message(bytes: &[u8]) -> Result<ClientMessage, DecodeError> {
+    match bytes.first() {
+        Some(byte) if byte.is_ascii() => {
+            let text = std::str::from_utf8(bytes).map_err(DecodeError::Utf8)?;
+            if !is_within_json_limits(text) {
+                return Err(DecodeError::Limits);
+            }
+            serde_json::from_str(text).map_err(DecodeError::Json)
+        }
+        _ => {
+            if !is_within_msgpack_limits(bytes) {
+                return Err(DecodeError::Limits);
+            }
+            rmp_serde::from_slice(bytes).map_err(DecodeError::MessagePack)
+        }
+    }
+}
+
+/// Checks the JSON text doesn't nest deeper or contain longer strings
+/// than any valid client message would
+///
+/// `text` The JSON text
+fn is_within_json_limits(text: &str) -> bool {
+    let mut depth = 0;
+    let mut in_string = false;
+    let mut escaped = false;
+    let mut string_length = 0;
+
+    for byte in text.bytes() {
+        if in_string {
+            if escaped {
+                escaped = false;
+            } else if byte == b'\\' {
+                escaped = true;
+            } else if byte == b'"' {
+                in_string = false;
+                continue;
+            }
+            string_length += 1;
+            if string_length > MAX_STRING {
+                return false;
+            }
+            continue;
+        }
+
+        match byte {
+            b'"' => {
+                in_string = true;
+                string_length = 0;
+            }
+            b'{' | b'[' => {
+                depth += 1;
+                if depth > MAX_DEPTH {
+                    return false;
+                }
+            }
+            b'}' | b']' => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+    }
+
+    true
+}
+
+/// Checks the MessagePack message doesn't nest deeper or contain longer
+/// strings, binary or extension values than any valid client message
+/// would. Truncated or invalid messages are left for the decoder to
+/// reject, every value takes at least a byte so the walk is bounded by
+/// the length of the message even when the declared lengths are absurd
+///
+/// `bytes` The MessagePack message
+fn is_within_msgpack_limits(bytes: &[u8]) -> bool {
+    // The number of values left in each open array or map along
+    // with the single value making up the whole message
+    let mut open: Vec<u64> = vec![1];
+    let mut pos = 0;
+
+    loop {
+        while open.last() == Some(&0) {
+            open.pop();
+        }
+        let Some(remaining) = open.last_mut() else {
+            return true;
+        };
+        let Some(&marker) = bytes.get(pos) else {
+            return true;
+        };
+        *remaining -= 1;
+        pos += 1;
+
+        // Length of the marker payload along with whether the payload
+        // is a string, binary or extension value
+        let (size, is_data) = match marker {
+            0x00..=0x7f | 0xc0..=0xc3 | 0xe0..=0xff => (0, false),
+            0x80..=0x8f => {
+                open.push(u64::from(marker & 0x0f) * 2);
+                (0, false)
+            }
+            0x90..=0x9f => {
+                open.push(u64::from(marker & 0x0f));
+                (0, false)
+            }
+            0xa0..=0xbf => (u64::from(marker & 0x1f), true),
+            0xc4 | 0xd9 => (read_length(bytes, &mut pos, 1), true),
+            0xc5 | 0xda => (read_length(bytes, &mut pos, 2), true),
+            0xc6 | 0xdb => (read_length(bytes, &mut pos, 4), true),
+            // Extensions are followed by their type byte
+            0xc7 => (read_length(bytes, &mut pos, 1).saturating_add(1), true),
+            0xc8 => (read_length(bytes, &mut pos, 2).saturating_add(1), true),
+            0xc9 => (read_length(bytes, &mut pos, 4).saturating_add(1), true),
+            0xca | 0xce | 0xd2 => (4, false),
+            0xcb | 0xcf | 0xd3 => (8, false),
+            0xcc | 0xd0 => (1, false),
+            0xcd | 0xd1 => (2, false),
+            0xd4 => (2, false),
+            0xd5 => (3, false),
+            0xd6 => (5, false),
+            0xd7 => (9, false),
+            0xd8 => (17, false),
+            0xdc => {
+                open.push(read_length(bytes, &mut pos, 2));
+                (0, false)
+            }
+            0xdd => {
+                open.push(read_length(bytes, &mut pos, 4));
+                (0, false)
+            }
+            0xde => {
+                open.push(read_length(bytes, &mut pos, 2) * 2);
+                (0, false)
+            }
+            0xdf => {
+                open.push(read_length(bytes, &mut pos, 4) * 2);
+                (0, false)
+            }
+        };
+
+        // The message itself sits at the bottom of the stack
+        if open.len() > MAX_DEPTH + 1 || (is_data && size > MAX_STRING as u64) {
+            return false;
+        }
+        pos = pos.saturating_add(usize::try_from(size).unwrap_or(usize::MAX));
+    }
+}
+
+/// Reads a big endian length of the provided number of bytes, missing
+/// bytes are read as zero and left for the decoder to reject
+///
+/// `bytes` The MessagePack message
+/// `pos`   The position of the length, moved past the length
+/// `size`  The number of bytes in the length
+fn read_length(bytes: &[u8], pos: &mut usize, size: usize) -> u64 {
+    let mut length = 0;
+    for _ in 0..size {
+        length = (length << 8) | u64::from(bytes.get(*pos).copied().unwrap_or(0));
+        *pos = pos.saturating_add(1);
+    }
+    length
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const JSON: &str = r#"{"ty":"Spectate","token":"ABCDE"}"#;
+
+    /// The spectate message encoded as MessagePack
+    fn msgpack() -> Vec<u8> {
+        let mut bytes = vec![0x82];
+        for value in ["ty", "Spectate", "token", "ABCDE"] {
+            bytes.push(0xa0 | value.len() as u8);
+            bytes.extend_from_slice(value.as_bytes());
+        }
+        bytes
+    }
+
+    /// MessagePack string of the provided length
+    fn msgpack_string(length: usize) -> Vec<u8> {
+        let mut bytes = vec![0xda];
+        bytes.extend_from_slice(&(length as u16).to_be_bytes());
+        bytes.resize(bytes.len() + length, b'a');
+        bytes
+    }
+
+    /// JSON array holding a string of the provided length
+    fn json_string(length: usize) -> String {
+        format!("[\"{}\"]", "a".repeat(length))
+    }
+
+    #[test]
+    fn encoding_is_detected_from_the_first_byte() {
+        assert!(matches!(
+            decode_client_message(JSON.as_bytes()),
+            Ok(ClientMessage::Spectate { token }) if token == "ABCDE"
+        ));
+        assert!(matches!(
+            decode_client_message(&msgpack()),
+            Ok(ClientMessage::Spectate { token }) if token == "ABCDE"
+        ));
+
+        // Leading whitespace is ASCII so still read as JSON
+        let padded = format!(" {}", JSON);
+        assert!(decode_client_message(padded.as_bytes()).is_ok());
+
+        assert!(matches!(
+            decode_client_message(b"not json"),
+            Err(DecodeError::Json(_))
+        ));
+        assert!(matches!(
+            decode_client_message(b"{\"ty\":\"\xff\"}"),
+            Err(DecodeError::Utf8(_))
+        ));
+        assert!(matches!(
+            decode_client_message(&[0xc1]),
+            Err(DecodeError::MessagePack(_))
+        ));
+        assert!(decode_client_message(&[]).is_err());
+    }
+
+    #[test]
+    fn huge_declared_lengths_are_rejected() {
+        // Strings, binary and extension values are rejected by length
+        for marker in [0xdb, 0xc6, 0xc9] {
+            let bytes = [marker, 0xff, 0xff, 0xff, 0xff];
+            assert!(!is_within_msgpack_limits(&bytes));
+            assert!(matches!(
+                decode_client_message(&bytes),
+                Err(DecodeError::Limits)
+            ));
+        }
+    }
+
+    #[test]
+    fn huge_declared_collections_fail_without_allocating() {
+        // Arrays and maps are walked one value at a time so the declared
+        // length is left for the decoder to reject once the bytes run out
+        for marker in [0xdd, 0xdf] {
+            let bytes = [marker, 0xff, 0xff, 0xff, 0xff];
+            assert!(is_within_msgpack_limits(&bytes));
+            assert!(matches!(
+                decode_client_message(&bytes),
+                Err(DecodeError::MessagePack(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn nesting_is_limited() {
+        let nested = |depth: usize| {
+            let mut bytes = vec![0x91; depth];
+            bytes.push(0xc0);
+            bytes
+        };
+        assert!(is_within_msgpack_limits(&nested(MAX_DEPTH)));
+        assert!(!is_within_msgpack_limits(&nested(MAX_DEPTH + 1)));
+        assert!(matches!(
+            decode_client_message(&nested(1000)),
+            Err(DecodeError::Limits)
+        ));
+
+        let nested = |depth: usize| format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+        assert!(is_within_json_limits(&nested(MAX_DEPTH)));
+        assert!(!is_within_json_limits(&nested(MAX_DEPTH + 1)));
+        assert!(matches!(
+            decode_client_message(nested(1000).as_bytes()),
+            Err(DecodeError::Limits)
+        ));
+
+        // Brackets within strings aren't nesting
+        let quoted = format!("[\"{}\"]", "[".repeat(100));
+        assert!(is_within_json_limits(&quoted));
+    }
+
+    #[test]
+    fn long_strings_are_rejected() {
+        assert!(is_within_msgpack_limits(&msgpack_string(MAX_STRING)));
+        assert!(!is_within_msgpack_limits(&msgpack_string(MAX_STRING + 1)));
+
+        assert!(is_within_json_limits(&json_string(MAX_STRING)));
+        assert!(!is_within_json_limits(&json_string(MAX_STRING + 1)));
+        assert!(matches!(
+            decode_client_message(json_string(MAX_STRING + 1).as_bytes()),
+            Err(DecodeError::Limits)
+        ));
+
+        // Escaped quotes don't end the string
+        let escaped = format!("[\"{}\"]", "\\\"".repeat(MAX_STRING));
+        assert!(!is_within_json_limits(&escaped));
+    }
+
+    #[test]
+    fn truncated_messages_are_errors() {
+        let bytes = msgpack();
+        for length in 1..bytes.len() {
+            let truncated = &bytes[..length];
+            assert!(is_within_msgpack_limits(truncated));
+            assert!(matches!(
+                decode_client_message(truncated),
+                Err(DecodeError::MessagePack(_))
+            ));
+        }
+
+        for length in 1..JSON.len() {
+            assert!(matches!(
+                decode_client_message(&JSON.as_bytes()[..length]),
+                Err(DecodeError::Json(_))
+            ));
+        }
+    }
+}
@@ -13,6 +13,62 @@ pub enum ServerError {
     NotJoinable,
     /// An action was attempting on a player that wasnt found
     UnknownPlayer,
+    /// The provided answer was not valid for the current question
+    InvalidAnswer,
+    /// An action that only the host can perform was attempted by a player
+    NotHost,
+    /// The player has been banned from the game
+    Banned,
+    /// The game can't be started until all the players are ready
+    NotAllReady,
+    /// The action isn't allowed in the current state
+    InvalidStateForAction,
+    /// The server is shutting down and not accepting new games
+    ShuttingDown,
+    /// Too many attempts have been made in a short period of time
+    RateLimited,
+    /// The provided username wasn't allowed
+    InvalidUsername(UsernameError),
+    /// The requested team doesn't exist
+    InvalidTeam,
+    /// The game has reached its maximum number of players
+    GameFull,
+    /// The requested vanity token is already in use
+    TokenTaken,
+    /// Chat has been disabled for the game
+    ChatDisabled,
+    /// The chat message was empty, too long or not allowed
+    InvalidChat,
+    /// The player has been muted by the host
+    Muted,
+    /// The time multiplier was outside of the allowed range
+    InvalidTimeMultiplier,
+    /// The player has already answered the current question
+    AlreadyAnswered,
+    /// The wager was larger than the player score
+    InvalidWager,
+    /// The player has been eliminated from the game
+    Eliminated,
+    /// The graded submission doesn't exist or the grade was invalid
+    InvalidGrade,
+    /// The host token was missing, expired or for another quiz
+    InvalidHostToken,
+    /// The join PIN was missing or incorrect
+    InvalidPin,
+    /// The server is in maintenance mode and not accepting
+    /// new games or players
+    Maintenance,
+    /// The client protocol version isn't supported by the server
+    UnsupportedProtocol,
+    /// The avatar or color chosen by the player wasn't allowed
+    InvalidAppearance,
+    /// The game is hosted by another node, the client should
+    /// connect to the node at the provided URL instead
+    WrongNode(String),
+    /// The game stopped unexpectedly while handling the request
+    GameCrashed,
+    /// The player is already connected to the game from another session
+    AlreadyConnected,
 }
 
 impl ServerError {
@@ -23,20 +79,138 @@ impl ServerError {
             Self::UsernameTaken => 0x2,
             Self::NotJoinable => 0x3,
             Self::UnknownPlayer => 0x4,
+            Self::InvalidAnswer => 0x5,
+            Self::NotHost => 0x6,
+            Self::Banned => 0x7,
+            Self::NotAllReady => 0x8,
+            Self::InvalidStateForAction => 0x9,
+            Self::ShuttingDown => 0xA,
+            Self::RateLimited => 0xB,
+            Self::InvalidUsername(_) => 0xC,
+            Self::InvalidTeam => 0xD,
+            Self::GameFull => 0xE,
+            Self::TokenTaken => 0xF,
+            Self::ChatDisabled => 0x10,
+            Self::InvalidChat => 0x11,
+            Self::Muted => 0x12,
+            Self::InvalidTimeMultiplier => 0x13,
+            Self::AlreadyAnswered => 0x14,
+            Self::InvalidWager => 0x15,
+            Self::Eliminated => 0x16,
+            Self::InvalidGrade => 0x17,
+            Self::InvalidHostToken => 0x18,
+            Self::InvalidPin => 0x19,
+            Self::Maintenance => 0x1A,
+            Self::UnsupportedProtocol => 0x1B,
+            Self::InvalidAppearance => 0x1C,
+            Self::WrongNode(_) => 0x1D,
+            Self::GameCrashed => 0x1E,
+            Self::AlreadyConnected => 0x1F,
         }
     }
 }
 
+/// Reasons that a username can be rejected
+#[derive(Clone, Serialize)]
+pub enum UsernameError {
+    /// The username was empty or too long
+    Length,
+    /// The username contained chars that aren't allowed
+    Charset,
+    /// The username contained a word from the profanity list
+    Profanity,
+}
+
+/// Error describing why a specific field of a provided quiz
+/// definition was invalid
+#[derive(Serialize)]
+pub struct FieldError {
+    /// Path to the invalid field (e.g. questions[2].answer_time)
+    pub field: String,
+    /// The reason the field was invalid
+    pub reason: FieldErrorReason,
+}
+
+impl FieldError {
+    pub fn new(field: impl Into<String>, reason: FieldErrorReason) -> Self {
+        Self {
+            field: field.into(),
+            reason,
+        }
+    }
+}
+
+/// Reasons that a quiz definition field can be invalid
+#[derive(Serialize)]
+pub enum FieldErrorReason {
+    /// The field was empty when it requires a value
+    Empty,
+    /// The question doesn't have any correct answers
+    NoCorrectAnswer,
+    /// A correct answer refers to a value that doesn't exist
+    InvalidAnswerIndex,
+    /// The timing value was zero or too large
+    InvalidTiming,
+    /// The minimum score is larger than the maximum score
+    InvalidScoring,
+    /// The clickable area is not a valid box
+    InvalidArea,
+    /// The team count is out of range or doesn't match the team names
+    InvalidTeams,
+    /// The limit must be greater than zero
+    InvalidLimit,
+    /// The token must be 4 to 12 letters or digits
+    InvalidToken,
+    /// The tolerance must be a finite non negative number
+    InvalidTolerance,
+    /// The imported value couldn't be parsed
+    InvalidValue,
+    /// The imported question type isn't a known type
+    UnknownType,
+}
+
+/// Errors that can occur while uploading an image
+#[derive(Serialize)]
+pub enum ImageError {
+    /// The upload stream was malformed or interrupted
+    Upload,
+    /// The image exceeded the maximum allowed size
+    TooLarge,
+    /// The image wasn't a supported image format
+    UnsupportedFormat,
+    /// The image couldn't be written to storage
+    Storage,
+}
+
+/// Response body for a quiz definition that failed validation
+#[derive(Serialize)]
+pub struct ValidationErrors {
+    /// The list of invalid fields
+    pub errors: Vec<FieldError>,
+}
+
 impl Serialize for ServerError {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: serde::Serializer,
     {
-        let mut stru = serializer.serialize_struct("ServerError", 2)?;
+        let mut stru = serializer.serialize_struct("ServerError", 4)?;
         // Message type field to match up with server messages
         stru.serialize_field("ty", "Error")?;
         // The error code field
         stru.serialize_field("error", &self.code())?;
+        // The reason for errors that provide more detail
+        if let Self::InvalidUsername(reason) = self {
+            stru.serialize_field("reason", reason)?;
+        } else {
+            stru.skip_field("reason")?;
+        }
+        // The node hosting the game for games on other nodes
+        if let Self::WrongNode(node) = self {
+            stru.serialize_field("node", node)?;
+        } else {
+            stru.skip_field("node")?;
+        }
 
         stru.end()
     }
@@ -0,0 +1,217 @@
+use std::{
+    sync::OnceLock,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::Serialize;
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+use crate::{
+    game::{AnswerResult, GameId},
+    metrics::metrics,
+    session::SessionId,
+};
+
+/// The number of events that can be waiting to be published before
+/// new events are dropped
+#[cfg(any(feature = "nats", feature = "kafka"))]
+const BUFFER_SIZE: usize = 4096;
+
+/// Structured event describing something that happened in a game
+#[derive(Serialize)]
+#[serde(tag = "ty")]
+pub enum GameEvent {
+    /// A host connected and the game was created
+    GameStarted {
+        game: Uuid,
+        token: String,
+        quiz: GameId,
+        name: String,
+    },
+    /// A player joined the game
+    PlayerJoined {
+        game: Uuid,
+        player: SessionId,
+        name: String,
+    },
+    /// A question was shown to the players
+    QuestionStarted { game: Uuid, index: usize },
+    /// The answer of a player was marked
+    AnswerMarked {
+        game: Uuid,
+        player: SessionId,
+        question: usize,
+        result: AnswerResult,
+        /// The time in milliseconds taken to answer
+        elapsed: u64,
+    },
+    /// The game finished
+    GameFinished {
+        game: Uuid,
+        players: usize,
+        questions: usize,
+    },
+}
+
+/// Event along with the time it happened
+#[derive(Serialize)]
+pub struct EventEnvelope {
+    /// Unix timestamp in milliseconds the event happened at
+    pub timestamp: u64,
+    #[serde(flatten)]
+    pub event: GameEvent,
+}
+
+/// Sender for the events channel when a sink is running
+static SENDER: OnceLock<mpsc::Sender<EventEnvelope>> = OnceLock::new();
+
+/// Publishes the event created by the provided function to the
+/// running sink, the event is only created when a sink is running
+/// and is dropped when the sink has fallen behind so that games are
+/// never slowed down by the sink
+///
+/// `event` Function creating the event
+pub fn publish(event: impl FnOnce() -> GameEvent) {
+    let Some(sender) = SENDER.get() else {
+        return;
+    };
+
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|value| value.as_millis() as u64)
+        .unwrap_or_default();
+
+    let envelope = EventEnvelope {
+        timestamp,
+        event: event(),
+    };
+    if sender.try_send(envelope).is_err() {
+        metrics().events_dropped.inc();
+    }
+}
+
+/// Starts the event sink configured in the environment, nothing is
+/// started when no events URL is set
+#[cfg(any(feature = "nats", feature = "kafka"))]
+pub fn start() {
+    use tracing::{error, info};
+
+    use crate::config;
+
+    let url = config::from_env_string(config::EVENTS_URL);
+    if url.is_empty() {
+        return;
+    }
+    let topic = config::from_env_string(config::EVENTS_TOPIC);
+
+    let (sender, receiver) = mpsc::channel(BUFFER_SIZE);
+
+    let task = match url.split_once("://") {
+        #[cfg(feature = "nats")]
+        Some(("nats", _)) => tokio::spawn(sink::nats(url.clone(), topic, receiver)),
+        #[cfg(feature = "kafka")]
+        Some(("kafka", brokers)) => tokio::spawn(sink::kafka(brokers.to_string(), topic, receiver)),
+        _ => {
+            error!("Unsupported events URL {}", url);
+            return;
+        }
+    };
+    drop(task);
+
+    info!("Publishing game events to {}", url);
+    let _ = SENDER.set(sender);
+}
+
+/// Sinks that publish the events to external systems
+#[cfg(any(feature = "nats", feature = "kafka"))]
+mod sink {
+    use tokio::sync::mpsc;
+    use tracing::error;
+
+    use super::EventEnvelope;
+
+    /// Publishes the events to the NATS subject
+    ///
+    /// `url`      The NATS server URL
+    /// `subject`  The subject to publish to
+    /// `receiver` The events channel
+    #[cfg(feature = "nats")]
+    pub async fn nats(url: String, subject: String, mut receiver: mpsc::Receiver<EventEnvelope>) {
+        let client = match async_nats::connect(url).await {
+            Ok(value) => value,
+            Err(err) => {
+                error!("Failed to connect to NATS: {:?}", err);
+                return;
+            }
+        };
+
+        while let Some(event) = receiver.recv().await {
+            let Ok(payload) = serde_json::to_vec(&event) else {
+                continue;
+            };
+            if let Err(err) = client.publish(subject.clone(), payload.into()).await {
+                error!("Failed to publish event: {:?}", err);
+            }
+        }
+    }
+
+    /// Publishes the events to the Kafka topic
+    ///
+    /// `brokers`  The comma separated Kafka brokers
+    /// `topic`    The topic to publish to
+    /// `receiver` The events channel
+    #[cfg(feature = "kafka")]
+    pub async fn kafka(
+        brokers: String,
+        topic: String,
+        mut receiver: mpsc::Receiver<EventEnvelope>,
+    ) {
+        use std::collections::BTreeMap;
+
+        use rskafka::{
+            client::{
+                partition::{Compression, UnknownTopicHandling},
+                ClientBuilder,
+            },
+            record::Record,
+        };
+
+        let brokers = brokers.split(',').map(String::from).collect();
+        let client = match ClientBuilder::new(brokers).build().await {
+            Ok(value) => value,
+            Err(err) => {
+                error!("Failed to connect to Kafka: {:?}", err);
+                return;
+            }
+        };
+        let partition = match client
+            .partition_client(topic, 0, UnknownTopicHandling::Retry)
+            .await
+        {
+            Ok(value) => value,
+            Err(err) => {
+                error!("Failed to connect to Kafka topic: {:?}", err);
+                return;
+            }
+        };
+
+        while let Some(event) = receiver.recv().await {
+            let Ok(value) = serde_json::to_vec(&event) else {
+                continue;
+            };
+            let record = Record {
+                key: None,
+                value: Some(value),
+                headers: BTreeMap::new(),
+                timestamp: chrono::Utc::now(),
+            };
+            if let Err(err) = partition
+                .produce(vec![record], Compression::NoCompression)
+                .await
+            {
+                error!("Failed to publish event: {:?}", err);
+            }
+        }
+    }
+}
@@ -1,96 +1,154 @@
 use std::{
-    collections::HashMap,
-    time::{Duration, Instant},
+    collections::{BTreeMap, HashMap, HashSet},
+    net::IpAddr,
+    panic::{self, AssertUnwindSafe},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 
 use actix::{
-    dev::MessageResponse, Actor, Addr, AsyncContext, Context, Handler, Message, SpawnHandle,
+    dev::MessageResponse, Actor, ActorContext, Addr, AsyncContext, Context, Handler, Message,
+    SpawnHandle,
 };
+use rand_chacha::ChaCha8Rng;
+use rand_core::{OsRng, RngCore, SeedableRng};
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
 
 use crate::{
-    error::ServerError,
-    session::{ServerMessage, Session, SessionId, SessionRequest},
+    appearance::Appearance,
+    audit::{AuditEntry, AuditEventKind, AuditLog, RAPID_ANSWER_TIME},
+    auth::{constant_time_eq, issue_host_token},
+    checkpoint::{self, GameCheckpoint, PlayerCheckpoint, TaskCheckpoint, CHECKPOINT_VERSION},
+    clock::Clock,
+    config,
+    error::{FieldError, FieldErrorReason, ServerError},
+    events::{self, GameEvent},
+    games::registry,
+    grading::{self, Grade, Region, Tolerance},
+    lifecycle::{GameCore, Marking, Next, Progress, Step},
+    matching::{normalize, TextMatchOptions},
+    metrics::metrics,
+    rate_limit::{RateLimiter, WindowCounter},
+    replay::{self, Replay, ReplayEventKind, ReplayRecorder, REPLAY_VERSION},
+    scoring::ScoringModel,
+    session::{DisconnectReason, ServerMessage, Session, SessionId, SessionRequest, SharedMessage},
+    storage::{
+        AnswerRecord, GameResults, PlayerResults, ProfileStats, QuestionResults,
+        RecordProfileStats, SaveResults, Storage,
+    },
+    telemetry::Traced,
+    token::normalize_vanity,
+    username,
 };
-use log::error;
+use tracing::{error, field, info, info_span};
 
 pub struct Game {
+    /// Unique ID of the game used to store its results
+    id: Uuid,
     /// The token this game is stored behind
     token: String,
+    /// The ID of the quiz the game was created from
+    quiz: GameId,
     /// The host session
     host: HostSession,
     /// Map of session IDs mapped to the session address
     players: Vec<PlayerSession>,
+    /// Sessions watching the game without playing
+    spectators: Vec<SpectatorSession>,
     /// Configuration for the game
     config: GameConfig,
-    /// The state of the game
-    state: GameState,
+    /// The question lifecycle of the game
+    core: GameCore,
+    /// The clock the timing of the game is measured with
+    clock: Box<dyn Clock>,
 
-    /// Spawn handle for the tick task
-    task: Option<DelayedTask>,
+    /// Spawn handles for the wait before the next step
+    wait: Option<WaitHandles>,
 
-    /// The index of the current question
-    question_index: usize,
+    /// Whether the step was paused because the host disconnected
+    paused_for_host: bool,
 
-    /// Game timer
-    timer: GameTimer,
-}
+    /// Duration disconnected players are kept for before being removed
+    reconnect_grace: Duration,
 
-pub struct GameTimer {
-    last: Instant,
-    want: Duration,
-}
+    /// Session IDs banned from the game
+    banned_ids: HashSet<SessionId>,
+    /// IP addresses banned from the game
+    banned_ips: HashSet<IpAddr>,
+    /// Players waiting for the host to approve their join request
+    pending: Vec<PendingPlayer>,
+    /// Limiter for the incorrect join PINs from each IP address
+    pin_attempts: RateLimiter,
 
-/// Task that is delayed
-pub struct DelayedTask {
-    // Spawn handle for the timer update task
-    timer_handle: SpawnHandle,
-    /// Spawn handle for the delayed task call
-    task_handle: SpawnHandle,
-    /// Underlying task to execute
-    task: Box<dyn FnOnce(&mut Game, &mut Context<Game>)>,
-}
+    /// The time the game was created
+    started_at: SystemTime,
 
-impl DelayedTask {
-    pub fn finish(self, game: &mut Game, ctx: &mut Context<Game>) {
-        // Cancel the timer and task handlers
-        ctx.cancel_future(self.timer_handle);
-        ctx.cancel_future(self.task_handle);
+    /// Whether the server is shutting down and the game should end
+    /// after the current question
+    shutting_down: bool,
 
-        // Call the task fn
-        (self.task)(game, ctx);
-    }
+    /// The last time a request was recieved by the game
+    last_activity: Instant,
 
-    pub fn cancel(self, ctx: &mut Context<Game>) {
-        ctx.cancel_future(self.timer_handle);
-        ctx.cancel_future(self.task_handle);
-    }
-}
+    /// Reactions recieved since they were last broadcast
+    reactions: HashMap<Reaction, u32>,
 
-impl GameTimer {
-    pub fn new() -> Self {
-        Self {
-            last: Instant::now(),
-            want: Duration::from_millis(0),
-        }
-    }
+    /// The sessions that have answered the current question
+    answered: HashSet<SessionId>,
 
-    pub fn elapsed(&self) -> Duration {
-        self.last.elapsed()
-    }
+    /// The last answer progress sent to the host
+    last_progress: Option<(usize, usize)>,
 
-    pub fn has_elapsed(&self) -> bool {
-        let elapsed = self.elapsed();
-        elapsed >= self.want
-    }
+    /// The score and streak of each player as of the last score update
+    sent_scores: HashMap<SessionId, (u32, u32)>,
+
+    /// The team scores as of the last score update
+    sent_teams: Vec<u32>,
+
+    /// Whether scores have changed since the last score update
+    scores_dirty: bool,
+
+    /// The number of answers that have arrived for the current question
+    arrivals: usize,
+
+    /// Random number generator used for shuffling, seeded from the
+    /// config when provided so that games can be reproduced
+    rng: ChaCha8Rng,
+
+    /// The players that submitted an answer to the open ended question
+    /// being graded in the anonymized order shown to the host
+    submissions: Vec<SessionId>,
+
+    /// The grades the host has given to each player
+    grades: HashMap<SessionId, Grade>,
+
+    /// Recorder for the timeline of the game saved as its replay
+    replay: ReplayRecorder,
 
-    pub fn set(&mut self, want: Duration) {
-        self.last = Instant::now();
-        self.want = want;
+    /// Record of the host actions and suspicious events
+    audit: AuditLog,
+
+    /// The last activity as of the last checkpoint, used to skip
+    /// checkpoints when nothing has changed
+    checkpoint_activity: Option<Instant>,
+}
+
+/// Spawn handles for the wait before the next step of the game
+pub struct WaitHandles {
+    /// Spawn handle for the timer update task
+    timer_handle: SpawnHandle,
+    /// Spawn handle for running the step once the wait ends
+    step_handle: SpawnHandle,
+}
+
+impl WaitHandles {
+    pub fn cancel(self, ctx: &mut Context<Game>) {
+        ctx.cancel_future(self.timer_handle);
+        ctx.cancel_future(self.step_handle);
     }
 }
 
-#[derive(Serialize, Clone, Copy)]
+#[derive(Serialize, Deserialize, Clone, Copy)]
 #[repr(u8)]
 pub enum GameState {
     /// The game is in the lobby
@@ -106,586 +164,4645 @@ pub enum GameState {
 
     /// The game has finished
     Finished = 0x4,
+
+    /// The question has been marked and the results are being shown
+    Marked = 0x5,
+
+    /// The game is waiting for players to wager on the next question
+    AwaitingWagers = 0x6,
+
+    /// The host is grading the submissions to an open ended question
+    Grading = 0x7,
+
+    /// The game is counting down before showing the next question
+    Countdown = 0x8,
+
+    /// The question is shown for reading but answers aren't open yet
+    Reading = 0x9,
 }
 
 const TIMER_INTERVAL: Duration = Duration::from_millis(500);
 
 impl Game {
-    pub fn new(token: String, host_id: u32, host_addr: Addr<Session>, config: GameConfig) -> Self {
+    /// Creates a new game in the lobby for the host
+    ///
+    /// `token`     The token the game is stored behind
+    /// `quiz`      The ID of the quiz the game was created from
+    /// `host_id`   The session ID of the host
+    /// `host_addr` The address of the host session
+    /// `config`    The config of the game
+    /// `clock`     The clock the timing of the game is measured with
+    pub fn new(
+        token: String,
+        quiz: GameId,
+        host_id: u32,
+        host_addr: Addr<Session>,
+        mut config: GameConfig,
+        clock: Box<dyn Clock>,
+    ) -> Self {
+        let mut rng = ChaCha8Rng::seed_from_u64(config.seed.unwrap_or_else(|| OsRng.next_u64()));
+
+        // Sample the questions from the bank
+        if let Some(pick) = &config.pick {
+            let bank = std::mem::take(&mut config.questions);
+            config.questions = pick_questions(&mut rng, bank, pick);
+        }
+
+        if config.shuffle_questions {
+            shuffle(&mut rng, &mut config.questions);
+        }
+
+        let host = HostSession {
+            id: host_id,
+            addr: Some(host_addr),
+            key: random_key(),
+            remove_handle: None,
+        };
+        Self::with_host(token, quiz, host, config, rng, clock)
+    }
+
+    /// Creates the game in the lobby with the provided host
+    ///
+    /// `token`  The token the game is stored behind
+    /// `quiz`   The ID of the quiz the game was created from
+    /// `host`   The host session
+    /// `config` The config with the questions already picked
+    /// `rng`    The random number generator for the game
+    /// `clock`  The clock the timing of the game is measured with
+    fn with_host(
+        token: String,
+        quiz: GameId,
+        host: HostSession,
+        config: GameConfig,
+        rng: ChaCha8Rng,
+        clock: Box<dyn Clock>,
+    ) -> Self {
+        let now = clock.now();
         Self {
+            id: Uuid::new_v4(),
             token,
-            host: HostSession {
-                id: host_id,
-                addr: host_addr,
-            },
+            quiz,
+            host,
             players: Default::default(),
+            spectators: Vec::new(),
             config,
-            state: GameState::Lobby,
-            task: None,
-            timer: GameTimer::new(),
-            question_index: 0,
+            core: GameCore::new(now),
+            clock,
+            wait: None,
+            paused_for_host: false,
+            reconnect_grace: Duration::from_secs(config::from_env(config::RECONNECT_GRACE)),
+            banned_ids: HashSet::new(),
+            banned_ips: HashSet::new(),
+            pending: Vec::new(),
+            pin_attempts: RateLimiter::new(
+                config::from_env(config::TOKEN_GUESS_LIMIT),
+                Duration::from_secs(config::from_env(config::RATE_LIMIT_WINDOW)),
+            ),
+            started_at: SystemTime::now(),
+            shutting_down: false,
+            last_activity: now,
+            reactions: HashMap::new(),
+            answered: HashSet::new(),
+            last_progress: None,
+            sent_scores: HashMap::new(),
+            sent_teams: Vec::new(),
+            scores_dirty: false,
+            arrivals: 0,
+            rng,
+            submissions: Vec::new(),
+            grades: HashMap::new(),
+            replay: ReplayRecorder::default(),
+            audit: AuditLog::default(),
+            checkpoint_activity: None,
         }
     }
 
-    /// Spawns the game starting time which will handle when the game
-    /// moves from the starting state to the started state.
+    /// Rebuilds a game from the checkpoint written before the server
+    /// restarted. The host and players are treated as disconnected and
+    /// have the reconnect grace period to rejoin with their resume
+    /// tokens, the delayed task is paused until the host returns
     ///
-    /// `ctx` The game context
-    fn starting_task(&mut self, ctx: &mut Context<Self>) {
-        // Game starts after 5 seconds unless skipped
-        const START_DURATION: Duration = Duration::from_secs(5);
+    /// `checkpoint` The checkpoint of the game
+    /// `clock`      The clock the timing of the game is measured with
+    /// `ctx`        The game context
+    pub fn restore(
+        checkpoint: GameCheckpoint,
+        clock: Box<dyn Clock>,
+        ctx: &mut Context<Self>,
+    ) -> Self {
+        let mut config = checkpoint.config;
+        config.owner = checkpoint.owner;
+        #[cfg(feature = "lti")]
+        {
+            config.lti = checkpoint.lti;
+        }
 
-        self.delayed_task(ctx, START_DURATION, |actor, ctx| {
-            actor.set_state(GameState::Starting);
-            // TODO: Started game logic
-        })
-    }
+        let host = HostSession {
+            id: checkpoint.host_id,
+            addr: None,
+            key: checkpoint.host_key,
+            remove_handle: None,
+        };
+        let rng = ChaCha8Rng::seed_from_u64(OsRng.next_u64());
+        let mut game = Self::with_host(checkpoint.token, checkpoint.quiz, host, config, rng, clock);
 
-    /// Spawns a delayed task to execute after duration where all the clients
-    /// have their times updated until said duration
-    fn delayed_task<F>(&mut self, ctx: &mut Context<Self>, duration: Duration, f: F)
-    where
-        F: Fn(&mut Self, &mut Context<Self>) + 'static,
-    {
-        // Set the timer start point and end duration
-        self.timer.set(duration);
+        let started_at = UNIX_EPOCH + Duration::from_secs(checkpoint.started_at);
+        game.id = checkpoint.id;
+        game.started_at = started_at;
+        game.core = GameCore::restore(
+            checkpoint.state,
+            checkpoint.question_index,
+            checkpoint.questions_marked,
+            game.clock.now(),
+        );
+        game.banned_ids = checkpoint.banned_ids;
+        game.banned_ips = checkpoint.banned_ips;
+        game.answered = checkpoint.answered;
+        game.arrivals = checkpoint.arrivals;
+        game.submissions = checkpoint.submissions;
+        game.grades = checkpoint.grades;
+        game.scores_dirty = true;
+        game.replay = ReplayRecorder::resume(
+            checkpoint.replay,
+            checkpoint.replay_truncated,
+            started_at.elapsed().unwrap_or_default(),
+        );
+        game.audit = AuditLog::resume(checkpoint.audit, checkpoint.audit_truncated);
+        game.players = checkpoint
+            .players
+            .into_iter()
+            .map(|player| PlayerSession {
+                id: player.id,
+                name: player.name,
+                addr: None,
+                ip: player.ip,
+                resume_key: player.resume_key,
+                remove_handle: None,
+                ready: false,
+                answers: player.answers,
+                results: player.results,
+                score: player.score,
+                streak: player.streak,
+                team: player.team,
+                muted: player.muted,
+                chat_limit: WindowCounter::new(),
+                time_multiplier: player.time_multiplier,
+                answer_order: player.answer_order,
+                wager: player.wager,
+                eliminated: player.eliminated,
+                rtt: None,
+                profile: player.profile,
+                appearance: player.appearance,
+            })
+            .collect();
 
-        // Intital time update
-        let total = self.timer.want.as_millis() as u64;
-        self.send_all(ServerMessage::TimeSync { total, elapsed: 0 });
+        if let Some(paused) = checkpoint.task {
+            let restored = game.core.restore_paused(
+                &game.config,
+                Duration::from_millis(paused.remaining),
+                Duration::from_millis(paused.grace),
+            );
+            game.paused_for_host = restored && !paused.held;
+        }
 
-        // Interval handle for updating the timers for all the clients to ensure
-        // they are up to date with the server time
-        let timer_handle = ctx.run_interval(TIMER_INTERVAL, |actor, ctx| {
-            let timer = &actor.timer;
-            let (total, elapsed) = if timer.has_elapsed() {
-                let total = timer.want.as_millis() as u64;
-                (total, total)
-            } else {
-                // Size down casted to u64 which is probbably even larger than nessicary
-                let total = timer.want.as_millis() as u64;
-                let elapsed = timer.elapsed().as_millis() as u64;
-                (total, elapsed)
-            };
-            actor.send_all(ServerMessage::TimeSync { total, elapsed })
-        });
+        let ids: Vec<SessionId> = game.players.iter().map(|player| player.id).collect();
+        for id in ids {
+            game.disconnect_player(ctx, id);
+        }
+        game.disconnect_host(ctx);
+        game
+    }
+
+    /// Moves the player at the index to a new connection and catches
+    /// it up on the game. When the player is still connected the old
+    /// connection is closed so a player never has two connections at once
+    ///
+    /// `ctx`   The game context
+    /// `index` The index of the player
+    /// `addr`  The address of the new session
+    fn transfer_player(
+        &mut self,
+        ctx: &mut Context<Self>,
+        index: usize,
+        addr: Addr<Session>,
+    ) -> Result<GameResponse, ServerError> {
+        let player = &mut self.players[index];
+        if let Some(previous) = &player.addr {
+            self.audit.record(AuditEventKind::DuplicateConnection {
+                id: player.id,
+                transferred: true,
+            });
+            previous.do_send(SessionRequest::Removed {
+                reason: DisconnectReason::ConnectionReplaced,
+            });
+        }
+
+        if let Some(handle) = player.remove_handle.take() {
+            ctx.cancel_future(handle);
+        }
+        player.addr = Some(addr);
 
-        // Task handle for finish the task after the desired duration
-        let task_handle = ctx.run_later(duration, |actor, ctx| {
-            if let Some(task) = actor.task.take() {
-                task.finish(actor, ctx);
+        // Catch the player up on the other players and state
+        let player = &self.players[index];
+        for other in &self.players {
+            if other.id != player.id {
+                player.send(other.describe(self.config.anonymous));
             }
-        });
+        }
+        player.send(ServerMessage::GameState(self.core.state()));
+        player.send(self.scores_for(player.id));
 
-        // Delayed task for storing the task
-        let task = DelayedTask {
-            task: Box::new(f),
-            task_handle,
-            timer_handle,
-        };
-        self.task = Some(task)
+        let config = &self.config;
+        Ok(GameResponse::Connected {
+            id: player.id,
+            token: self.token.clone(),
+            quiz: self.quiz,
+            basic: config.basic.clone(),
+            timing: config.timing.clone(),
+            resume: ResumeToken {
+                game: self.token.clone(),
+                key: player.resume_key.clone(),
+            },
+            name: Some(player.name.clone()),
+            snapshot: None,
+        })
     }
 
-    /// Immediately completes the current delayed task
-    fn immediate_task(&mut self, ctx: &mut Context<Self>) {
-        if let Some(task) = self.task.take() {
-            task.finish(self, ctx);
-        }
+    /// Checks whether the host or player with the provided ID has moved
+    /// on to another connection, the disconnect of the replaced connection
+    /// can arrive after the session has moved
+    ///
+    /// `id`   The ID of the host or player
+    /// `addr` The address of the session that lost its connection
+    fn is_replaced(&self, id: SessionId, addr: &Addr<Session>) -> bool {
+        let current = if id == self.host.id {
+            self.host.addr.as_ref()
+        } else {
+            self.players
+                .iter()
+                .find(|player| player.id == id)
+                .and_then(|player| player.addr.as_ref())
+        };
+        current.is_some_and(|current| current != addr)
     }
 
-    /// Begins the question at the provided index
+    /// Handles a player losing its connection by keeping its slot for
+    /// the reconnect grace period before removing it
     ///
-    /// `ctx`   The game context
-    /// `index` The question index
-    fn begin_question(&mut self, ctx: &mut Context<Self>, index: usize) {
-        self.reset_ready();
-        let question = match self.config.questions.get(index) {
+    /// `ctx` The game context
+    /// `id`  The ID of the disconnected session
+    fn disconnect_player(&mut self, ctx: &mut Context<Self>, id: SessionId) {
+        let player = match self.players.iter_mut().find(|player| player.id == id) {
             Some(value) => value,
-            None => {
-                error!("Attempted to begin a question at an index which doesn't exist");
-                return;
-            }
+            None => return,
         };
-        self.question_index = index;
-        self.send_all(ServerMessage::Question(question.clone()));
-    }
+        player.addr = None;
 
-    /// Called after all the ready messages have been recieved from all the
-    /// clients
-    fn ready_question(&mut self, ctx: &mut Context<Self>) {
-        self.send_all(ServerMessage::BeginQuestion);
-        let question = self.question();
-        self.delayed_task(
-            ctx,
-            Duration::from_millis(question.answer_time),
-            Self::mark_answers,
-        )
+        let handle = ctx.run_later(self.reconnect_grace, move |actor, _ctx| {
+            actor.remove_player(id);
+        });
+        player.remove_handle = Some(handle);
     }
 
-    fn question(&self) -> &Question {
-        match self.config.questions.get(self.question_index) {
-            Some(value) => value,
-            None => {
-                panic!("Attempted to access a question at an index that does not exist");
-            }
-        }
+    /// Removes the player with the provided ID from the game and
+    /// informs all the remaining sessions
+    ///
+    /// `id` The ID of the player to remove
+    fn remove_player(&mut self, id: SessionId) -> Option<PlayerSession> {
+        let index = self.players.iter().position(|player| player.id == id)?;
+        let player = self.players.remove(index);
+        self.send_all(ServerMessage::PlayerRemoved { id });
+        self.replay.record(ReplayEventKind::PlayerLeft { id });
+        Some(player)
     }
 
-    /// Task for marking the answers
-    fn mark_answers(&mut self, ctx: &mut Context<Self>) {
-        let question = self.question().clone();
+    /// Kicks the player with the provided ID from the game optionally
+    /// banning them from joining again
+    ///
+    /// `ctx` The game context
+    /// `id`  The ID of the player to kick
+    /// `ban` Whether to ban the player
+    fn kick_player(
+        &mut self,
+        ctx: &mut Context<Self>,
+        id: SessionId,
+        ban: bool,
+    ) -> Result<(), ServerError> {
+        let mut player = self.remove_player(id).ok_or(ServerError::UnknownPlayer)?;
+        self.audit.record(AuditEventKind::Kicked {
+            id,
+            name: player.name.clone(),
+            ban,
+        });
+
+        if let Some(handle) = player.remove_handle.take() {
+            ctx.cancel_future(handle);
+        }
 
-        let scoring = &question.scoring;
+        if ban {
+            self.banned_ids.insert(player.id);
+            if let Some(ip) = player.ip {
+                self.banned_ips.insert(ip);
+            }
+        }
 
-        for player in &mut self.players {
-            let answer = match player.answers.get(self.question_index) {
-                Some(answer) => answer,
-                None => {
-                    // Player did not answer the question
-                    continue;
-                }
+        if let Some(addr) = &player.addr {
+            let reason = if ban {
+                DisconnectReason::Banned
+            } else {
+                DisconnectReason::Kicked
             };
+            addr.do_send(SessionRequest::Removed { reason });
+        }
 
-            let elapsed = self.timer.elapsed();
-            let is_bonus = elapsed.as_micros() as u64 <= self.config.timing.bonus_score_time;
+        Ok(())
+    }
 
-            let percent =
-                1.0 - ((elapsed.as_millis() as f32) / (question.answer_time as f32)).max(1.0);
+    /// Adds a player to the game informing the other players of the
+    /// join, returns the response for the connected player
+    ///
+    /// `id`   The session ID of the player
+    /// `name` The validated username of the player
+    /// `addr` The address of the player session
+    /// `ip`   The IP address of the player
+    fn add_player(
+        &mut self,
+        id: SessionId,
+        name: String,
+        addr: Addr<Session>,
+        ip: Option<IpAddr>,
+        profile: Option<Uuid>,
+        appearance: Appearance,
+    ) -> GameResponse {
+        let resume = self.resume_token();
+        let team = match &self.config.basic.teams {
+            Some(teams) if teams.selection == TeamSelection::Auto => self.smallest_team(),
+            _ => None,
+        };
+        let game_player = PlayerSession {
+            id,
+            name: name.clone(),
+            addr: Some(addr),
+            ip,
+            resume_key: resume.key.clone(),
+            remove_handle: None,
+            ready: false,
+            answers: Vec::new(),
+            results: Vec::new(),
+            score: 0,
+            streak: 0,
+            team,
+            muted: false,
+            chat_limit: WindowCounter::new(),
+            time_multiplier: 1.0,
+            answer_order: Vec::new(),
+            wager: None,
+            eliminated: None,
+            rtt: None,
+            profile,
+            appearance,
+        };
 
-            let mut base_score = scoring.min_score
-                + ((scoring.max_score - scoring.min_score) as f32 * percent) as u32;
+        let anonymous = self.config.anonymous;
 
-            if is_bonus {
-                base_score += scoring.bonus_score;
-            }
+        // Message sent to existing players for this player
+        let joiner_message = game_player.describe(anonymous);
 
-            let result = match (&question.ty, &answer) {
-                (QuestionType::Single { answers, .. }, QuestionAnswer::Single { answer }) => {
-                    let valid = answers.contains(answer);
+        // Notify all players of the existence of eachother
+        for player in &self.players {
+            player.send(joiner_message.clone());
 
-                    if valid {
-                        AnswerResult::Correct(base_score)
-                    } else {
-                        AnswerResult::Incorrect
-                    }
-                }
-                (
-                    QuestionType::Multiple {
-                        answers: qu_answers,
-                        ..
-                    },
-                    QuestionAnswer::Multiple { answers },
-                ) => {
-                    let mut correct = 0usize;
-                    let mut incorrect = 0usize;
-                    for answer in answers {
-                        if qu_answers.contains(answer) {
-                            correct += 1;
-                        } else {
-                            incorrect += 1;
-                        }
-                    }
+            // Message describing the other player
+            game_player.send(player.describe(anonymous));
+        }
 
-                    // The percent completion
-                    let percent = (correct as f32) / ((correct + incorrect) as f32);
+        // Notify the host of the join, the host always sees real names
+        self.host.send(game_player.describe(false));
 
-                    let valid = correct == qu_answers.len();
+        events::publish(|| GameEvent::PlayerJoined {
+            game: self.id,
+            player: game_player.id,
+            name: game_player.name.clone(),
+        });
+        self.replay.record(ReplayEventKind::PlayerJoined {
+            id,
+            name: game_player.name.clone(),
+        });
 
-                    if valid {
-                        AnswerResult::Correct(base_score)
-                    } else {
-                        let score = ((base_score as f32) * percent).round() as u32;
-                        AnswerResult::Partial(score)
-                    }
-                }
-                (
-                    QuestionType::ClickableImage { top, bottom, .. },
-                    QuestionAnswer::ClickableImage { answer },
-                ) => {
-                    // Clicked position is within top and bottom box position
-                    let valid = answer.0 >= top.0
-                        && answer.0 <= bottom.0
-                        && answer.1 >= top.1
-                        && answer.1 <= bottom.1;
-                    if valid {
-                        AnswerResult::Correct(base_score)
-                    } else {
-                        AnswerResult::Incorrect
-                    }
-                }
-                _ => {
-                    error!("Mis matched question and answer types don't know how to mark.");
-                    continue;
-                }
-            };
+        self.players.push(game_player);
+        if let Some(player) = self.players.last() {
+            player.send(self.scores_for(id));
+        }
 
-            player.score += result.score();
-            player.results.push(result.clone());
+        // Players joining a game in progress need to catch up
+        let snapshot = if matches!(self.core.state(), GameState::Lobby | GameState::Starting) {
+            None
+        } else {
+            Some(Box::new(self.snapshot(id)))
+        };
 
-            // Send the result to the player
-            player.send(ServerMessage::AnswerResult(result));
+        let config = &self.config;
+        GameResponse::Connected {
+            id,
+            token: self.token.clone(),
+            quiz: self.quiz,
+            basic: config.basic.clone(),
+            timing: config.timing.clone(),
+            resume,
+            name: Some(name),
+            snapshot,
         }
-        // Update everyones scores
-        self.update_scores();
     }
 
-    /// Resets the plaeyr ready states of all the players
-    fn reset_ready(&mut self) {
-        for player in &mut self.players {
-            player.ready = false;
+    /// Checks whether a player or a player waiting for approval
+    /// already has the provided name
+    ///
+    /// `name` The name to check
+    fn is_name_taken(&self, name: &str) -> bool {
+        self.players
+            .iter()
+            .map(|player| &player.name)
+            .chain(self.pending.iter().map(|pending| &pending.name))
+            .any(|other| username::is_same(other, name))
+    }
+
+    /// Generates a random name that no other player has, names are
+    /// numbered if the random names keep colliding
+    fn generate_name(&self) -> String {
+        // The game RNG is left alone so seeded shuffles stay the same
+        let mut rng = OsRng;
+        for _ in 0..NAME_ATTEMPTS {
+            let name = username::generate(&mut rng);
+            if !self.is_name_taken(&name) {
+                return name;
+            }
         }
+
+        let name = username::generate(&mut rng);
+        (2..)
+            .map(|number| format!("{} {}", name, number))
+            .find(|numbered| !self.is_name_taken(numbered))
+            .unwrap_or(name)
     }
 
-    fn cancel_task(&mut self, ctx: &mut Context<Self>) {
-        if let Some(task) = self.task.take() {
-            task.cancel(ctx);
+    /// Checks the provided join PIN against the PIN for the game, the
+    /// incorrect attempts from each address are limited
+    ///
+    /// `ip`  The IP address of the player
+    /// `pin` The PIN provided by the player
+    fn check_pin(&mut self, ip: Option<IpAddr>, pin: Option<&str>) -> Result<(), ServerError> {
+        let Some(expected) = &self.config.pin else {
+            return Ok(());
+        };
+
+        if ip.is_some_and(|ip| self.pin_attempts.is_limited(ip)) {
+            return Err(ServerError::RateLimited);
+        }
+
+        if pin.is_some_and(|pin| constant_time_eq(pin.as_bytes(), expected.as_bytes())) {
+            return Ok(());
+        }
+
+        if let Some(ip) = ip {
+            self.pin_attempts.hit(ip);
         }
+        Err(ServerError::InvalidPin)
     }
 
-    fn set_state(&mut self, state: GameState) {
-        self.state = state;
-        self.send_all(ServerMessage::GameState(state));
+    /// Approves the join request of a pending player adding them to
+    /// the game and sending them the connection details
+    ///
+    /// `id` The session ID of the pending player
+    fn approve_join(&mut self, id: SessionId) -> Result<(), ServerError> {
+        let index = self
+            .pending
+            .iter()
+            .position(|pending| pending.id == id)
+            .ok_or(ServerError::UnknownPlayer)?;
+        let PendingPlayer {
+            id,
+            name,
+            addr,
+            ip,
+            profile,
+            appearance,
+        } = self.pending.remove(index);
+        self.audit.record(AuditEventKind::JoinApproved {
+            id,
+            name: name.clone(),
+        });
+
+        if self
+            .config
+            .basic
+            .max_players
+            .is_some_and(|max| self.players.len() >= max)
+        {
+            addr.do_send(SessionRequest::Removed {
+                reason: DisconnectReason::GameFull,
+            });
+            return Err(ServerError::GameFull);
+        }
+
+        if let GameResponse::Connected {
+            id,
+            token,
+            basic,
+            timing,
+            resume,
+            name,
+            snapshot,
+            ..
+        } = self.add_player(id, name, addr.clone(), ip, profile, appearance)
+        {
+            addr.do_send(SessionRequest::Message(ServerMessage::Connected {
+                id,
+                token,
+                basic: Box::new(basic),
+                timing,
+                resume,
+                name,
+            }));
+            if let Some(snapshot) = snapshot {
+                addr.do_send(SessionRequest::Message(ServerMessage::StateSnapshot(
+                    snapshot,
+                )));
+            }
+        }
+        Ok(())
     }
 
-    /// Send a message to all clients
-    fn send_all(&self, message: ServerMessage) {
-        for player in &self.players {
-            player.send(message.clone());
+    /// Creates a new resume token for a player in this game
+    fn resume_token(&self) -> ResumeToken {
+        ResumeToken {
+            game: self.token.clone(),
+            key: random_key(),
         }
-        self.host.send(message);
     }
 
-    fn update_scores(&self) {
-        let mut scores = HashMap::new();
-        for player in &self.players {
-            scores.insert(player.id, player.score);
+    /// Returns the token the host can use to reclaim control of the
+    /// game if it loses its connection
+    pub fn host_resume_token(&self) -> ResumeToken {
+        ResumeToken {
+            game: self.token.clone(),
+            key: self.host.key.clone(),
         }
-        self.send_all(ServerMessage::ScoreUpdate { scores })
     }
-}
 
-#[derive(Message)]
-#[rtype(result = "Result<GameResponse, ServerError>")]
-pub enum GameRequest {
-    /// Message to attempt to connect a new client
-    TryConnect {
-        id: SessionId,
-        name: String,
-        addr: Addr<Session>,
-    },
+    /// Handles the host losing its connection by pausing the game
+    /// until the host returns or the grace period ends
+    ///
+    /// `ctx` The game context
+    fn disconnect_host(&mut self, ctx: &mut Context<Self>) {
+        self.host.addr = None;
+        self.audit.record(AuditEventKind::HostDisconnected);
 
-    /// Message from the host to start the game
-    Start,
+        if self.core.is_waiting() {
+            self.pause_task(ctx);
+            self.paused_for_host = true;
+        }
 
-    /// Message to cancel starting the game
-    Cancel,
+        let handle = ctx.run_later(self.reconnect_grace, |actor, ctx| {
+            actor.host.remove_handle = None;
+            actor.guarded(ctx, Self::host_lost);
+        });
+        self.host.remove_handle = Some(handle);
+    }
 
-    /// Request to inform that a player is ready
-    Ready { id: SessionId },
+    /// Handles the host not returning within the grace period by either
+    /// promoting the earliest joined player to host or ending the game
+    ///
+    /// `ctx` The game context
+    fn host_lost(&mut self, ctx: &mut Context<Self>) {
+        // Only players that are still connected can be promoted
+        let index = self.players.iter().position(|player| player.addr.is_some());
 
-    /// Message to skip the current timer
-    SkipTimer,
-}
+        let index = match index {
+            Some(index) if self.config.host_migration => index,
+            _ => {
+                self.cancel_task(ctx);
+                self.set_state(GameState::Finished);
+                return;
+            }
+        };
 
-pub enum GameResponse {
-    Connected {
-        /// The game token
-        token: String,
-        /// The session ID
-        id: u32,
-        /// Basic game config information
-        basic: BasicConfig,
-        /// Timing data for different game events
-        timing: GameTiming,
-    },
-    None,
-}
+        let player = self.players.remove(index);
+        self.send_all(ServerMessage::PlayerRemoved { id: player.id });
+        self.audit
+            .record(AuditEventKind::HostPromoted { id: player.id });
 
-pub type GameId = u32;
+        self.host = HostSession {
+            id: player.id,
+            addr: player.addr,
+            key: random_key(),
+            remove_handle: None,
+        };
+        self.host.send(ServerMessage::HostPromoted {
+            resume: self.host_resume_token(),
+            host_token: issue_host_token(self.quiz),
+        });
+        self.send_all(ServerMessage::HostChanged { id: self.host.id });
 
-impl Actor for Game {
-    type Context = Context<Self>;
+        self.resume_host_pause(ctx);
+    }
 
-    fn started(&mut self, ctx: &mut Self::Context) {}
-}
+    /// Resumes the task that was paused when the host was lost
+    fn resume_host_pause(&mut self, ctx: &mut Context<Self>) {
+        if self.paused_for_host {
+            self.paused_for_host = false;
+            self.resume_task(ctx);
+        }
+    }
 
-impl Handler<GameRequest> for Game {
-    type Result = Result<GameResponse, ServerError>;
+    /// Starts the game, the first question begins after the start
+    /// time unless skipped
+    ///
+    /// `ctx` The game context
+    fn start_game(&mut self, ctx: &mut Context<Self>) {
+        self.core.start(self.clock.now());
+        self.start_wait(ctx);
+    }
 
-    fn handle(&mut self, msg: GameRequest, ctx: &mut Self::Context) -> Self::Result {
-        match msg {
-            GameRequest::TryConnect { id, name, addr } => {
-                match self.state {
-                    GameState::Lobby | GameState::Starting => {}
-                    _ => return Err(ServerError::NotJoinable),
-                }
+    /// Spawns the wait for the step scheduled in the core where all the
+    /// clients have their times updated until the step runs. Clients are
+    /// only shown the timer without the grace time that allows players
+    /// with extended time to finish after the shown timer
+    ///
+    /// `ctx` The game context
+    fn start_wait(&mut self, ctx: &mut Context<Self>) {
+        self.cancel_wait(ctx);
 
-                // Error if username is already taken
-                if self
-                    .players
-                    .iter()
-                    .find(|player| player.name.eq(&name))
-                    .is_some()
-                {
-                    return Err(ServerError::UsernameTaken);
-                }
+        // Intital time update
+        let (total, _) = self.core.time_sync(self.clock.now());
+        self.send_all(ServerMessage::TimeSync { total, elapsed: 0 });
 
-                let game_player = PlayerSession {
-                    id,
-                    name,
-                    addr,
-                    ready: false,
-                    answers: Vec::new(),
-                    results: Vec::new(),
-                    score: 0,
-                };
+        // Interval handle for updating the timers for all the clients to ensure
+        // they are up to date with the server time
+        let timer_handle = ctx.run_interval(TIMER_INTERVAL, |actor, _ctx| {
+            let (total, elapsed) = actor.core.time_sync(actor.clock.now());
+            actor.send_all(ServerMessage::TimeSync { total, elapsed })
+        });
 
-                // Message sent to existing players for this player
-                let joiner_message = ServerMessage::OtherPlayer {
-                    id: game_player.id,
-                    name: game_player.name.clone(),
-                };
+        // Handle for running the step once the wait ends
+        let step_handle = ctx.run_later(self.core.wait_time(), Self::wait_elapsed);
 
-                // Notify all players of the existence of eachother
-                for player in &self.players {
-                    player.send(joiner_message.clone());
+        self.wait = Some(WaitHandles {
+            timer_handle,
+            step_handle,
+        });
+    }
 
-                    // Message describing the other player
-                    game_player.send(ServerMessage::OtherPlayer {
-                        id: player.id,
-                        name: player.name.clone(),
-                    });
-                }
+    /// Runs the step that is waiting once the game clock reaches the end
+    /// of the wait, when the clock is behind the wait continues for the
+    /// time that is left
+    ///
+    /// `ctx` The game context
+    fn wait_elapsed(&mut self, ctx: &mut Context<Self>) {
+        let now = self.clock.now();
+        if let Some(step) = self.core.due(now) {
+            self.cancel_wait(ctx);
+            self.guarded(ctx, |actor, ctx| actor.run_step(ctx, step));
+            return;
+        }
 
-                // Notify the host of the join
-                self.host.send(joiner_message);
+        let (remaining, grace) = self.core.remaining(now);
+        let left = remaining + grace;
+        if let Some(wait) = self.wait.as_mut().filter(|_| !left.is_zero()) {
+            wait.step_handle = ctx.run_later(left, Self::wait_elapsed);
+        }
+    }
 
-                self.players.push(game_player);
+    /// Cancels the spawned handles for the current wait
+    ///
+    /// `ctx` The game context
+    fn cancel_wait(&mut self, ctx: &mut Context<Self>) {
+        if let Some(handles) = self.wait.take() {
+            handles.cancel(ctx);
+        }
+    }
 
-                let config = &self.config;
-                Ok(GameResponse::Connected {
+    /// Immediately runs the step that is waiting to run
+    fn immediate_task(&mut self, ctx: &mut Context<Self>) {
+        if let Some(step) = self.core.skip() {
+            self.cancel_wait(ctx);
+            self.run_step(ctx, step);
+        }
+    }
+
+    /// Pauses the step that is waiting to run
+    fn pause_task(&mut self, ctx: &mut Context<Self>) {
+        if self.core.pause(self.clock.now()) {
+            self.cancel_wait(ctx);
+            self.send_all(ServerMessage::Paused);
+        }
+    }
+
+    /// Resumes the paused step with its remaining time
+    fn resume_task(&mut self, ctx: &mut Context<Self>) {
+        if self.core.resume(self.clock.now()) {
+            self.send_all(ServerMessage::Resumed);
+            self.start_wait(ctx);
+        }
+    }
+
+    /// Handles an action from the host
+    ///
+    /// `ctx`    The game context
+    /// `action` The action to handle
+    fn host_action(
+        &mut self,
+        ctx: &mut Context<Self>,
+        action: HostAction,
+    ) -> Result<(), ServerError> {
+        match action {
+            HostAction::Skip => {
+                self.audit.record(AuditEventKind::Skipped);
+                self.immediate_task(ctx);
+            }
+            HostAction::Pause => {
+                self.audit.record(AuditEventKind::Paused);
+                self.pause_task(ctx);
+            }
+            HostAction::Resume => {
+                self.audit.record(AuditEventKind::Resumed);
+                self.resume_task(ctx);
+            }
+            HostAction::End => {
+                self.audit.record(AuditEventKind::Ended);
+                self.cancel_task(ctx);
+                self.set_state(GameState::Finished);
+            }
+            HostAction::NextQuestion => {
+                self.cancel_task(ctx);
+                let index = self.core.question_index() + 1;
+                self.audit.record(AuditEventKind::NextQuestion { index });
+                if index < self.config.questions.len() {
+                    self.run_step(ctx, Step::Begin(index));
+                } else {
+                    self.set_state(GameState::Finished);
+                }
+            }
+            HostAction::Kick { id, ban } => return self.kick_player(ctx, id, ban),
+            HostAction::ApproveJoin { id } => return self.approve_join(id),
+            HostAction::DenyJoin { id } => {
+                let index = self
+                    .pending
+                    .iter()
+                    .position(|pending| pending.id == id)
+                    .ok_or(ServerError::UnknownPlayer)?;
+                let pending = self.pending.remove(index);
+                self.audit.record(AuditEventKind::JoinDenied {
                     id,
-                    token: self.token.clone(),
-                    basic: config.basic.clone(),
-                    timing: config.timing.clone(),
+                    name: pending.name,
+                });
+                pending.addr.do_send(SessionRequest::Removed {
+                    reason: DisconnectReason::JoinDenied,
+                });
+            }
+            HostAction::GradeAnswer { submission, grade } => {
+                return self.grade_answer(ctx, submission, grade)
+            }
+            HostAction::ExtendTime { id, multiplier } => {
+                if !(1.0..=MAX_TIME_MULTIPLIER).contains(&multiplier) {
+                    return Err(ServerError::InvalidTimeMultiplier);
+                }
+                let player = self
+                    .players
+                    .iter_mut()
+                    .find(|player| player.id == id)
+                    .ok_or(ServerError::UnknownPlayer)?;
+                player.time_multiplier = multiplier;
+                player.send(ServerMessage::TimeExtended { multiplier });
+                self.audit
+                    .record(AuditEventKind::TimeExtended { id, multiplier });
+            }
+            HostAction::Mute { id, muted } => {
+                let player = self
+                    .players
+                    .iter_mut()
+                    .find(|player| player.id == id)
+                    .ok_or(ServerError::UnknownPlayer)?;
+                player.muted = muted;
+                player.send(ServerMessage::Muted { muted });
+                self.audit.record(AuditEventKind::Muted { id, muted });
+            }
+            HostAction::ListPlayers => {
+                self.host.send(ServerMessage::PlayerList {
+                    players: self.player_details(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Runs the step of the question lifecycle along with the steps
+    /// that follow straight after it, the core decides the flow while
+    /// the game sends the messages for each step
+    ///
+    /// `ctx`  The game context
+    /// `step` The step to run
+    fn run_step(&mut self, ctx: &mut Context<Self>, mut step: Step) {
+        loop {
+            if let Step::Begin(index) = step {
+                if index >= self.config.questions.len() {
+                    error!("Attempted to begin a question at an index which doesn't exist");
+                    return;
+                }
+            }
+
+            let state = self.core.enter(&self.config, step);
+            match step {
+                Step::Begin(_) => {
+                    self.begin_question(matches!(state, Some(GameState::AwaitingWagers)))
+                }
+                Step::Show => self.show_question(),
+                Step::Open => self.send_all(ServerMessage::BeginQuestion),
+                _ => {}
+            }
+            if let Some(state) = state {
+                self.announce_state(state);
+            }
+            match step {
+                Step::Grade => self.begin_grading(),
+                Step::Mark => self.mark_answers(),
+                _ => {}
+            }
+
+            let progress = self.progress();
+            match self
+                .core
+                .next(&self.config, step, &progress, self.clock.now())
+            {
+                Next::Now(next) => step = next,
+                Next::Wait => {
+                    self.start_wait(ctx);
+                    return;
+                }
+                Next::Finish => {
+                    self.set_state(GameState::Finished);
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Collects the progress of the players that decides how the
+    /// question lifecycle continues
+    fn progress(&self) -> Progress {
+        Progress {
+            multiplier: self
+                .players
+                .iter()
+                .map(|player| player.time_multiplier)
+                .fold(1.0, f32::max),
+            submissions: self.submissions.len(),
+            stop: self.shutting_down || self.is_decided(),
+        }
+    }
+
+    /// Resets the answers and wagers for the question that is beginning,
+    /// players are asked how much of their score they want to wager
+    /// when the question takes wagers
+    ///
+    /// `wagers` Whether the question takes wagers
+    fn begin_question(&mut self, wagers: bool) {
+        self.reset_ready();
+        self.answered.clear();
+        self.last_progress = None;
+        self.arrivals = 0;
+        for player in &mut self.players {
+            player.wager = None;
+        }
+
+        if wagers {
+            for player in &self.players {
+                player.send(ServerMessage::WagerOpen { max: player.score });
+            }
+        }
+    }
+
+    /// Sends the current question to the clients
+    fn show_question(&mut self) {
+        let index = self.core.question_index();
+        events::publish(|| GameEvent::QuestionStarted {
+            game: self.id,
+            index,
+        });
+        self.replay.record(ReplayEventKind::Question {
+            index,
+            title: self.question().title.clone(),
+        });
+        if self.config.shuffle_answers {
+            self.send_shuffled_question();
+        } else {
+            self.send_all(ServerMessage::Question(self.question().project()));
+        }
+    }
+
+    /// Sends the current question to each player with the answers in an
+    /// order unique to the player, the order is stored on the player so
+    /// that submitted answers can be mapped back to the original order.
+    /// The host and spectators recieve the original order
+    fn send_shuffled_question(&mut self) {
+        let question = &self.config.questions[self.core.question_index()];
+        let count = question.ty.option_count();
+
+        for player in &mut self.players {
+            match count {
+                Some(count) => {
+                    let mut order: Vec<usize> = (0..count).collect();
+                    shuffle(&mut self.rng, &mut order);
+                    player.send(ServerMessage::Question(question.with_order(&order)));
+                    player.answer_order = order;
+                }
+                None => {
+                    player.answer_order.clear();
+                    player.send(ServerMessage::Question(question.project()));
+                }
+            }
+        }
+
+        let message = SharedMessage::new(ServerMessage::Question(question.project()));
+        for spectator in &self.spectators {
+            spectator.send_shared(&message);
+        }
+        self.host.send_shared(&message);
+    }
+
+    /// Sends the host the submissions to the current open ended question
+    /// in a random order without the names of the players, the answers
+    /// are marked once every submission is graded or the grading time ends
+    fn begin_grading(&mut self) {
+        let index = self.core.question_index();
+        let mut submissions: Vec<(SessionId, String)> = self
+            .players
+            .iter()
+            .filter_map(|player| match player.answers.get(index)?.as_ref()? {
+                PlayerAnswer {
+                    answer: QuestionAnswer::TextEntry { answer },
+                    ..
+                } => Some((player.id, answer.clone())),
+                _ => None,
+            })
+            .collect();
+        shuffle(&mut self.rng, &mut submissions);
+
+        let (ids, texts): (Vec<SessionId>, Vec<String>) = submissions.into_iter().unzip();
+        self.submissions = ids;
+        self.grades.clear();
+        self.host
+            .send(ServerMessage::Submissions { submissions: texts });
+    }
+
+    /// Grades the submission at the provided index marking the answers
+    /// once every submission has been graded
+    ///
+    /// `ctx`        The game context
+    /// `submission` The index of the submission
+    /// `grade`      The grade for the submission
+    fn grade_answer(
+        &mut self,
+        ctx: &mut Context<Self>,
+        submission: usize,
+        grade: Grade,
+    ) -> Result<(), ServerError> {
+        if !grade.is_valid() {
+            return Err(ServerError::InvalidGrade);
+        }
+        let id = *self
+            .submissions
+            .get(submission)
+            .ok_or(ServerError::InvalidGrade)?;
+        self.grades.insert(id, grade);
+        self.audit
+            .record(AuditEventKind::AnswerGraded { id, grade });
+
+        if self.grades.len() == self.submissions.len() {
+            self.immediate_task(ctx);
+        }
+        Ok(())
+    }
+
+    fn question(&self) -> &Question {
+        self.core.question(&self.config)
+    }
+
+    /// Marks the answers to the current question sending each player
+    /// its result and revealing the answer
+    fn mark_answers(&mut self) {
+        let index = self.core.question_index();
+        let question = self.question().clone();
+
+        let marking = Marking::new(
+            &self.config,
+            index,
+            &self.grades,
+            self.players.iter().map(|player| {
+                (
+                    player.id,
+                    player.answers.get(index).and_then(Option::as_ref),
+                )
+            }),
+        );
+
+        for player in &mut self.players {
+            let answer = player.answers.get(index).and_then(Option::as_ref);
+            let Some(result) = marking.mark(
+                player.id,
+                answer,
+                player.time_multiplier,
+                player.wager,
+                &mut player.score,
+                &mut player.streak,
+            ) else {
+                continue;
+            };
+
+            if player.results.len() <= index {
+                player.results.resize_with(index + 1, || None);
+            }
+            player.results[index] = Some(result.clone());
+
+            events::publish(|| GameEvent::AnswerMarked {
+                game: self.id,
+                player: player.id,
+                question: index,
+                result: result.clone(),
+                elapsed: answer.map_or(0, |answer| answer.elapsed.as_millis() as u64),
+            });
+
+            // Send the result to the player
+            player.send(ServerMessage::AnswerResult {
+                result,
+                streak: player.streak,
+            });
+        }
+        // Scores are sent on the next score tick
+        self.scores_dirty = true;
+
+        let stats = self.question_stats();
+
+        if question.ty.is_poll() {
+            // Polls reveal the distribution of responses instead of an answer
+            self.send_all(ServerMessage::PollResults {
+                counts: stats.counts.clone(),
+            });
+        } else {
+            if let GameMode::Elimination { rule } = self.config.basic.mode {
+                self.eliminate_players(rule);
+            }
+
+            self.send_reveal(&question);
+        }
+
+        // Give the host the answer breakdown to discuss
+        self.host.send(ServerMessage::QuestionStats(stats));
+    }
+
+    /// Eliminates the players that failed the current question based on
+    /// the provided rule. Players are never all eliminated at once, if
+    /// everyone would be eliminated then nobody is
+    ///
+    /// `rule` The rule deciding which players are eliminated
+    fn eliminate_players(&mut self, rule: EliminationRule) {
+        let index = self.core.question_index();
+        let mut remaining: Vec<&PlayerSession> = self
+            .players
+            .iter()
+            .filter(|player| player.eliminated.is_none())
+            .collect();
+
+        let eliminated: Vec<SessionId> = match rule {
+            EliminationRule::WrongAnswer => remaining
+                .iter()
+                .filter(|player| {
+                    !player
+                        .results
+                        .get(index)
+                        .and_then(Option::as_ref)
+                        .is_some_and(AnswerResult::is_correct)
                 })
+                .map(|player| player.id)
+                .collect(),
+            EliminationRule::Bottom { count } => {
+                remaining.sort_by_key(|player| player.score);
+                remaining
+                    .iter()
+                    .take(count.min(remaining.len().saturating_sub(1)))
+                    .map(|player| player.id)
+                    .collect()
+            }
+        };
+
+        if eliminated.len() >= remaining.len() {
+            return;
+        }
+
+        for player in &mut self.players {
+            if eliminated.contains(&player.id) {
+                player.eliminated = Some(index);
+            }
+        }
+        for id in eliminated {
+            self.send_all(ServerMessage::Eliminated { id });
+        }
+    }
+
+    /// Whether the game has been decided by all but one player being
+    /// eliminated when playing in elimination mode
+    fn is_decided(&self) -> bool {
+        matches!(self.config.basic.mode, GameMode::Elimination { .. })
+            && self
+                .players
+                .iter()
+                .filter(|player| player.eliminated.is_none())
+                .count()
+                <= 1
+    }
+
+    /// Reveals the correct answer and explanation of the question to
+    /// everyone, players that were shown the answers in a shuffled order
+    /// receive the answer in their order
+    ///
+    /// `question` The question that was just marked
+    fn send_reveal(&self, question: &Question) {
+        let correct = question.ty.reveal();
+        let explanation = question.explanation.clone();
+
+        for player in &self.players {
+            if player.answer_order.is_empty() {
+                continue;
+            }
+            player.send(ServerMessage::QuestionReveal {
+                correct: correct.with_order(&player.answer_order),
+                explanation: explanation.clone(),
+            });
+        }
+
+        let message = SharedMessage::new(ServerMessage::QuestionReveal {
+            correct,
+            explanation,
+        });
+        for player in &self.players {
+            if player.answer_order.is_empty() {
+                player.send_shared(&message);
+            }
+        }
+        for spectator in &self.spectators {
+            spectator.send_shared(&message);
+        }
+        self.host.send_shared(&message);
+    }
+
+    /// Adds a new spectator to the game catching it up on the
+    /// current players and state
+    ///
+    /// `id`   The ID of the spectator session
+    /// `addr` The address of the spectator session
+    fn add_spectator(&mut self, id: SessionId, addr: Addr<Session>) -> GameResponse {
+        let spectator = SpectatorSession { id, addr };
+
+        // Catch the spectator up on the players and state
+        for player in &self.players {
+            spectator.send(player.describe(self.config.anonymous));
+        }
+        spectator.send(ServerMessage::GameState(self.core.state()));
+        spectator.send(self.scores_for(id));
+
+        self.spectators.push(spectator);
+
+        let config = &self.config;
+        GameResponse::Spectating {
+            id,
+            token: self.token.clone(),
+            quiz: self.quiz,
+            basic: config.basic.clone(),
+            timing: config.timing.clone(),
+        }
+    }
+
+    /// Ends the answer timer early when auto advance is enabled and
+    /// every connected player has answered the current question.
+    /// Disconnected players and spectators aren't waited for
+    ///
+    /// `ctx` The game context
+    fn check_auto_advance(&mut self, ctx: &mut Context<Self>) {
+        if !self.config.auto_advance || !matches!(self.core.state(), GameState::AwaitingAnswers) {
+            return;
+        }
+
+        let index = self.core.question_index();
+        let mut connected = self
+            .players
+            .iter()
+            .filter(|player| player.is_active())
+            .peekable();
+
+        // Nobody being connected isn't a reason to skip the question
+        if connected.peek().is_none() {
+            return;
+        }
+
+        let all_answered = connected.all(|player| {
+            player
+                .answers
+                .get(index)
+                .is_some_and(|answer| answer.is_some())
+        });
+        if all_answered {
+            self.immediate_task(ctx);
+        }
+    }
+
+    /// Broadcasts the reactions collected since the last flush
+    fn flush_reactions(&mut self) {
+        if self.reactions.is_empty() {
+            return;
+        }
+        let counts = std::mem::take(&mut self.reactions);
+        self.send_all(ServerMessage::Reactions { counts });
+    }
+
+    /// Sends the host the number of active players that have answered
+    /// the current question if it changed since the last update
+    fn send_answer_progress(&mut self) {
+        if !matches!(self.core.state(), GameState::AwaitingAnswers) {
+            return;
+        }
+
+        let (answered, total) = self
+            .players
+            .iter()
+            .filter(|player| player.is_active())
+            .fold((0, 0), |(answered, total), player| {
+                let has_answered = self.answered.contains(&player.id);
+                (answered + has_answered as usize, total + 1)
+            });
+
+        let progress = Some((answered, total));
+        if self.last_progress == progress {
+            return;
+        }
+        self.last_progress = progress;
+        self.host
+            .send(ServerMessage::AnswerProgress { answered, total });
+    }
+
+    /// Checks whether the game has been abandoned either by having no
+    /// connected sessions or no activity for longer than allowed
+    ///
+    /// `empty_ttl` The time allowed without any connected sessions
+    /// `idle_ttl`  The time allowed without any activity
+    fn is_abandoned(&self, empty_ttl: Duration, idle_ttl: Duration) -> bool {
+        let idle = self
+            .clock
+            .now()
+            .saturating_duration_since(self.last_activity);
+        let connected = self.host.addr.is_some()
+            || !self.spectators.is_empty()
+            || self.players.iter().any(|player| player.addr.is_some());
+
+        // Running questions count as activity while anyone is watching
+        if connected && self.core.is_waiting() {
+            return false;
+        }
+
+        idle >= idle_ttl || (!connected && idle >= empty_ttl)
+    }
+
+    /// Stops the abandoned game removing any sessions that
+    /// are still connected
+    ///
+    /// `ctx` The game context
+    fn reap(&mut self, ctx: &mut Context<Self>) {
+        info!("Stopping abandoned game {} ({})", self.token, self.id);
+        metrics().games_reaped.inc();
+        let reason = if matches!(self.core.state(), GameState::Finished) {
+            DisconnectReason::GameEnded
+        } else {
+            DisconnectReason::GameAbandoned
+        };
+        self.close(ctx, reason);
+    }
+
+    /// Runs the provided game logic catching any panic so that a bug
+    /// in one game can't take down the other games running on the same
+    /// arbiter, a game that panics is stopped since its state can no
+    /// longer be trusted
+    ///
+    /// `ctx` The game context
+    /// `f`   The game logic to run
+    fn guarded<R>(
+        &mut self,
+        ctx: &mut Context<Self>,
+        f: impl FnOnce(&mut Self, &mut Context<Self>) -> R,
+    ) -> Option<R> {
+        match panic::catch_unwind(AssertUnwindSafe(|| f(self, ctx))) {
+            Ok(value) => Some(value),
+            Err(payload) => {
+                let reason = payload
+                    .downcast_ref::<&str>()
+                    .copied()
+                    .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+                    .unwrap_or("unknown panic");
+                self.crashed(ctx, reason);
+                None
+            }
+        }
+    }
+
+    /// Stops the game after it panicked informing every session of the
+    /// crash and removing the game from the registry straight away so
+    /// that no new sessions are sent to the stopped game
+    ///
+    /// `ctx`    The game context
+    /// `reason` The panic message
+    fn crashed(&mut self, ctx: &mut Context<Self>, reason: &str) {
+        error!(
+            game_id = %self.id,
+            token = %self.token,
+            quiz = %self.quiz,
+            state = self.core.state() as u8,
+            question = self.core.question_index(),
+            players = self.players.len(),
+            spectators = self.spectators.len(),
+            "Game panicked: {}",
+            reason
+        );
+        metrics().games_crashed.inc();
+        registry().remove(&self.token, &ctx.address());
+
+        let addrs = self
+            .players
+            .iter()
+            .filter_map(|player| player.addr.as_ref())
+            .chain(self.spectators.iter().map(|spectator| &spectator.addr))
+            .chain(self.pending.iter().map(|pending| &pending.addr))
+            .chain(self.host.addr.as_ref());
+        for addr in addrs {
+            addr.do_send(SessionRequest::Error(ServerError::GameCrashed));
+        }
+        self.close(ctx, DisconnectReason::GameCrashed);
+    }
+
+    /// Stops the game removing any sessions that are still
+    /// connected with the provided reason
+    ///
+    /// `ctx`    The game context
+    /// `reason` The reason given to the removed sessions
+    fn close(&mut self, ctx: &mut Context<Self>, reason: DisconnectReason) {
+        let addrs = self
+            .players
+            .iter()
+            .filter_map(|player| player.addr.as_ref())
+            .chain(self.spectators.iter().map(|spectator| &spectator.addr))
+            .chain(self.pending.iter().map(|pending| &pending.addr))
+            .chain(self.host.addr.as_ref());
+        for addr in addrs {
+            addr.do_send(SessionRequest::Removed {
+                reason: reason.clone(),
+            });
+        }
+
+        self.cancel_task(ctx);
+        checkpoint::queue_remove(self.id);
+        ctx.stop();
+    }
+
+    /// Creates a snapshot of the current game for clients that need to
+    /// catch up with a game that is already in progress
+    ///
+    /// `viewer` The ID of the session the snapshot is for
+    fn snapshot(&self, viewer: SessionId) -> StateSnapshot {
+        let anonymous = self.is_anonymous_to(viewer);
+        let question = match self.core.state() {
+            GameState::AwaitingReady
+            | GameState::Reading
+            | GameState::AwaitingAnswers
+            | GameState::Grading
+            | GameState::Marked => Some(self.question().project()),
+            _ => None,
+        };
+
+        let players = self
+            .players
+            .iter()
+            .map(|player| {
+                // Anonymous games hide everything about the other players
+                if anonymous && player.id != viewer {
+                    return PlayerSnapshot {
+                        id: player.id,
+                        name: ANONYMOUS_NAME.to_string(),
+                        team: player.team,
+                        score: None,
+                        appearance: Appearance::default(),
+                    };
+                }
+                PlayerSnapshot {
+                    id: player.id,
+                    name: player.name.clone(),
+                    team: player.team,
+                    score: Some(player.score),
+                    appearance: player.appearance.clone(),
+                }
+            })
+            .collect();
+
+        StateSnapshot {
+            state: self.core.state(),
+            question_index: self.core.question_index(),
+            question,
+            elapsed: self.core.elapsed(self.clock.now()).as_millis() as u64,
+            total: self.core.total().as_millis() as u64,
+            players,
+        }
+    }
+
+    /// Collects statistics about the answers given to the current question
+    fn question_stats(&self) -> QuestionStats {
+        let index = self.core.question_index();
+        let question = self.question();
+
+        let mut counts = match &question.ty {
+            QuestionType::Single { values, .. }
+            | QuestionType::MultiSelect { values, .. }
+            | QuestionType::Poll { values, .. } => vec![0; values.len()],
+            QuestionType::TrueFalse { .. } => vec![0; 2],
+            QuestionType::TextEntry { .. }
+            | QuestionType::ClickableImage { .. }
+            | QuestionType::Ordering { .. }
+            | QuestionType::Numeric { .. }
+            | QuestionType::OpenEnded {}
+            | QuestionType::Hotspot { .. } => Vec::new(),
+        };
+        let mut texts: HashMap<String, usize> = HashMap::new();
+        let mut answered = 0;
+        let mut correct = 0;
+        let mut total_time = Duration::ZERO;
+
+        for player in &self.players {
+            let answer = match player.answers.get(index).and_then(Option::as_ref) {
+                Some(value) => value,
+                None => continue,
+            };
+            answered += 1;
+            total_time += answer.elapsed;
+
+            let is_correct = player
+                .results
+                .get(index)
+                .and_then(Option::as_ref)
+                .is_some_and(AnswerResult::is_correct);
+            if is_correct {
+                correct += 1;
+            }
+
+            match (&answer.answer, &question.ty) {
+                (QuestionAnswer::Single { answer }, _) => {
+                    if let Some(count) = counts.get_mut(*answer) {
+                        *count += 1;
+                    }
+                }
+                (QuestionAnswer::MultiSelect { answers }, _) => {
+                    for answer in answers {
+                        if let Some(count) = counts.get_mut(*answer) {
+                            *count += 1;
+                        }
+                    }
+                }
+                (QuestionAnswer::TrueFalse { answer }, _) => {
+                    if let Some(count) = counts.get_mut(*answer as usize) {
+                        *count += 1;
+                    }
+                }
+                (QuestionAnswer::TextEntry { answer }, QuestionType::TextEntry { options, .. }) => {
+                    *texts.entry(normalize(answer, options)).or_default() += 1;
+                }
+                _ => {}
+            }
+        }
+
+        let average_time = if answered > 0 {
+            (total_time / answered as u32).as_millis() as u64
+        } else {
+            0
+        };
+
+        QuestionStats {
+            index,
+            answered,
+            total: self.players.len(),
+            correct,
+            average_time,
+            counts,
+            texts,
+        }
+    }
+
+    /// Checks the provided request against the permission and state
+    /// matrix ensuring the sender is allowed to perform the request
+    /// in the current game state
+    ///
+    /// `msg` The request to check
+    fn check_request(&self, msg: &GameRequest) -> Result<(), ServerError> {
+        use GameState::*;
+
+        let state = self.core.state();
+        let (host_only, allowed) = match msg {
+            GameRequest::TryConnect { .. } => {
+                let joinable = match state {
+                    Lobby | Starting => true,
+                    Finished => false,
+                    _ => self.config.late_join,
+                };
+                if !joinable {
+                    return Err(ServerError::NotJoinable);
+                }
+                (None, true)
+            }
+            GameRequest::Spectate { .. } => (None, !matches!(state, Finished)),
+            GameRequest::Start { id, .. } => (Some(*id), matches!(state, Lobby)),
+            GameRequest::Cancel { id } => (Some(*id), matches!(state, Starting)),
+            GameRequest::HostAction { id, action } => {
+                let allowed = match action {
+                    HostAction::Skip | HostAction::Pause | HostAction::Resume => {
+                        !matches!(state, Lobby | Finished)
+                    }
+                    HostAction::NextQuestion => {
+                        matches!(
+                            state,
+                            AwaitingWagers
+                                | Countdown
+                                | AwaitingReady
+                                | Reading
+                                | AwaitingAnswers
+                                | Grading
+                                | Marked
+                        )
+                    }
+                    HostAction::GradeAnswer { .. } => matches!(state, Grading),
+                    HostAction::End => !matches!(state, Finished),
+                    HostAction::Kick { .. }
+                    | HostAction::Mute { .. }
+                    | HostAction::ExtendTime { .. }
+                    | HostAction::ListPlayers => true,
+                    HostAction::ApproveJoin { .. } | HostAction::DenyJoin { .. } => {
+                        !matches!(state, Finished)
+                    }
+                };
+                (Some(*id), allowed)
             }
+            GameRequest::Ready { .. } => (None, matches!(state, Lobby | AwaitingReady)),
+            GameRequest::Wager { .. } => (None, matches!(state, AwaitingWagers)),
+            GameRequest::JoinTeam { .. } => (None, matches!(state, Lobby)),
+            GameRequest::Chat { .. } => (None, matches!(state, Lobby | Starting | Finished)),
+            GameRequest::SetAppearance { .. } => (None, !matches!(state, Finished)),
+            GameRequest::React { .. } => (None, !matches!(state, Finished)),
+            GameRequest::Answer { .. } => (None, matches!(state, AwaitingAnswers)),
+            GameRequest::SkipTimer
+            | GameRequest::Shutdown { .. }
+            | GameRequest::Disconnect { .. }
+            | GameRequest::Reconnect { .. }
+            | GameRequest::Resync { .. }
+            | GameRequest::Latency { .. }
+            | GameRequest::Inspect
+            | GameRequest::Close { .. } => (None, true),
+        };
+
+        if host_only.is_some_and(|id| id != self.host.id) {
+            return Err(ServerError::NotHost);
+        }
+
+        if !allowed {
+            return Err(ServerError::InvalidStateForAction);
+        }
+
+        Ok(())
+    }
+
+    /// Counts the number of active players that are ready returning
+    /// the ready count along with the total active players
+    fn ready_count(&self) -> (usize, usize) {
+        let connected = self.players.iter().filter(|player| player.is_active());
+        let (ready, total) = connected.fold((0, 0), |(ready, total), player| {
+            (ready + player.ready as usize, total + 1)
+        });
+        (ready, total)
+    }
+
+    /// Sends the current ready state to all the clients returning
+    /// the ready count along with the total connected players
+    fn send_ready_state(&self) -> (usize, usize) {
+        let (ready, total) = self.ready_count();
+        self.send_all(ServerMessage::ReadyState { ready, total });
+        (ready, total)
+    }
+
+    /// Resets the plaeyr ready states of all the players
+    fn reset_ready(&mut self) {
+        for player in &mut self.players {
+            player.ready = false;
+        }
+    }
+
+    fn cancel_task(&mut self, ctx: &mut Context<Self>) {
+        self.core.cancel();
+        self.cancel_wait(ctx);
+    }
+
+    fn set_state(&mut self, state: GameState) {
+        self.core.set_state(state);
+        self.announce_state(state);
+    }
+
+    /// Tells everyone about the state the game moved to
+    ///
+    /// `state` The new state of the game
+    fn announce_state(&mut self, state: GameState) {
+        self.send_all(ServerMessage::GameState(state));
+        self.replay.record(ReplayEventKind::StateChanged { state });
+
+        if let GameState::Finished = state {
+            if let Ok(duration) = self.started_at.elapsed() {
+                metrics().game_duration.observe(duration.as_secs_f64());
+            }
+            events::publish(|| GameEvent::GameFinished {
+                game: self.id,
+                players: self.players.len(),
+                questions: self.core.questions_marked(),
+            });
+            // Final scores shouldn't wait for the next score tick
+            self.flush_scores();
+            let summary = self.summary();
+            self.send_all_named(ServerMessage::GameSummary(summary.clone()), || {
+                ServerMessage::GameSummary(summary.anonymized())
+            });
+            self.save_results();
+        }
+    }
+
+    /// Creates the final summary of the game for the podium screen
+    fn summary(&self) -> GameSummary {
+        let asked = self.core.questions_marked();
+        let half = asked / 2;
+
+        let mut podium: Vec<PodiumEntry> = self
+            .players
+            .iter()
+            .map(|player| PodiumEntry {
+                id: player.id,
+                name: player.name.clone(),
+                score: player.score,
+                appearance: player.appearance.clone(),
+            })
+            .collect();
+        podium.sort_by_key(|entry| std::cmp::Reverse(entry.score));
+        if matches!(self.config.basic.mode, GameMode::Elimination { .. }) {
+            // Players that lasted longer place higher regardless of score
+            let survived = |id: SessionId| {
+                self.players
+                    .iter()
+                    .find(|player| player.id == id)
+                    .and_then(|player| player.eliminated)
+                    .unwrap_or(usize::MAX)
+            };
+            podium.sort_by_key(|entry| std::cmp::Reverse(survived(entry.id)));
+        }
+        podium.truncate(PODIUM_SIZE);
+
+        let mut accuracy = HashMap::new();
+        let mut average_times = HashMap::new();
+        let mut most_improved: Option<(SessionId, i64)> = None;
+
+        for player in &self.players {
+            let results = &player.results[..player.results.len().min(asked)];
+            let correct = results
+                .iter()
+                .flatten()
+                .filter(|result| result.is_correct())
+                .count();
+            let value = if asked > 0 {
+                correct as f32 / asked as f32
+            } else {
+                0.0
+            };
+            accuracy.insert(player.id, value);
+
+            let times: Vec<Duration> = player
+                .answers
+                .iter()
+                .flatten()
+                .map(|answer| answer.elapsed)
+                .collect();
+            if !times.is_empty() {
+                let total: Duration = times.iter().sum();
+                let average = total / times.len() as u32;
+                average_times.insert(player.id, average.as_millis() as u64);
+            }
+
+            // Improvement is how much more was scored in the second
+            // half of the game than the first half
+            let half_score = |results: &[Option<AnswerResult>]| -> i64 {
+                results
+                    .iter()
+                    .flatten()
+                    .map(|result| result.score() as i64)
+                    .sum()
+            };
+            let (first, second) = results.split_at(half.min(results.len()));
+            let improvement = half_score(second) - half_score(first);
+            if improvement > 0 && most_improved.is_none_or(|(_, best)| improvement > best) {
+                most_improved = Some((player.id, improvement));
+            }
+        }
+
+        let fastest = average_times
+            .iter()
+            .min_by_key(|(_, time)| **time)
+            .map(|(id, _)| *id);
+
+        GameSummary {
+            podium,
+            most_improved: most_improved.map(|(id, _)| id),
+            fastest,
+            accuracy,
+            average_times,
+        }
+    }
+
+    /// Sends the results of the game to storage and informs the
+    /// host of the ID to retrieve them with, the replay of the
+    /// game is written to disk in the background
+    fn save_results(&mut self) {
+        self.save_replay();
+        checkpoint::queue_remove(self.id);
+
+        let storage = match Storage::addr() {
+            Some(value) => value,
+            None => return,
+        };
+
+        storage.do_send(SaveResults(self.results()));
+        #[cfg(feature = "email")]
+        if let Some(owner) = self.config.owner.filter(|_| self.config.email_results) {
+            crate::notify::results_finished(owner, || self.results());
+        }
+        self.host.send(ServerMessage::ResultsSaved { id: self.id });
+
+        let stats = self.profile_stats();
+
+        // Players launched from the LMS are graded on their correct answers
+        #[cfg(feature = "lti")]
+        if let Some(context) = &self.config.lti {
+            let gradable = self.config.questions[..self.core.questions_marked()]
+                .iter()
+                .filter(|question| !question.ty.is_poll())
+                .count() as u32;
+            crate::lti::submit_scores(
+                context.clone(),
+                self.config.basic.name.clone(),
+                gradable,
+                stats
+                    .iter()
+                    .map(|stats| (stats.id, stats.correct))
+                    .collect(),
+            );
+        }
+
+        if !stats.is_empty() {
+            storage.do_send(RecordProfileStats(stats));
+        }
+    }
+
+    /// Writes the recorded timeline of the game to disk
+    fn save_replay(&mut self) {
+        let (events, truncated) = self.replay.take();
+        let replay = Replay {
+            version: REPLAY_VERSION,
+            game: self.id,
+            token: self.token.clone(),
+            quiz: Some(self.quiz),
+            owner: self.config.owner,
+            name: self.config.basic.name.clone(),
+            started_at: unix_secs(self.started_at),
+            truncated,
+            events,
+        };
+
+        tokio::spawn(async move {
+            if let Err(err) = replay::save(&replay).await {
+                error!("Failed to save replay for game {}: {}", replay.game, err);
+            }
+        });
+    }
+
+    /// Creates a checkpoint of the current state of the game that it
+    /// can be restored from after the server restarts
+    fn checkpoint(&self) -> GameCheckpoint {
+        let task = match (self.core.is_waiting(), self.core.paused()) {
+            (true, _) => {
+                let (remaining, grace) = self.core.remaining(self.clock.now());
+                Some(TaskCheckpoint {
+                    remaining: remaining.as_millis() as u64,
+                    grace: grace.as_millis() as u64,
+                    held: false,
+                })
+            }
+            (false, Some(paused)) => Some(TaskCheckpoint {
+                remaining: paused.remaining.as_millis() as u64,
+                grace: paused.grace.as_millis() as u64,
+                held: !self.paused_for_host,
+            }),
+            (false, None) => None,
+        };
+        let (replay, replay_truncated) = self.replay.events();
+        let (audit, audit_truncated) = self.audit.entries();
+
+        GameCheckpoint {
+            version: CHECKPOINT_VERSION,
+            saved_at: unix_secs(SystemTime::now()),
+            id: self.id,
+            token: self.token.clone(),
+            quiz: self.quiz,
+            config: self.config.clone(),
+            owner: self.config.owner,
+            #[cfg(feature = "lti")]
+            lti: self.config.lti.clone(),
+            host_id: self.host.id,
+            host_key: self.host.key.clone(),
+            state: self.core.state(),
+            question_index: self.core.question_index(),
+            questions_marked: self.core.questions_marked(),
+            task,
+            players: self
+                .players
+                .iter()
+                .map(|player| PlayerCheckpoint {
+                    id: player.id,
+                    name: player.name.clone(),
+                    ip: player.ip,
+                    resume_key: player.resume_key.clone(),
+                    answers: player.answers.clone(),
+                    results: player.results.clone(),
+                    score: player.score,
+                    streak: player.streak,
+                    team: player.team,
+                    muted: player.muted,
+                    time_multiplier: player.time_multiplier,
+                    answer_order: player.answer_order.clone(),
+                    wager: player.wager,
+                    eliminated: player.eliminated,
+                    profile: player.profile,
+                    appearance: player.appearance.clone(),
+                })
+                .collect(),
+            banned_ids: self.banned_ids.clone(),
+            banned_ips: self.banned_ips.clone(),
+            answered: self.answered.clone(),
+            arrivals: self.arrivals,
+            submissions: self.submissions.clone(),
+            grades: self.grades.clone(),
+            started_at: unix_secs(self.started_at),
+            replay,
+            replay_truncated,
+            audit,
+            audit_truncated,
+        }
+    }
+
+    /// Queues a checkpoint of the game to be written, the checkpoint
+    /// is skipped when nothing has happened since the last one
+    fn save_checkpoint(&mut self) {
+        if matches!(self.core.state(), GameState::Finished) {
+            return;
+        }
+        if !self.core.is_waiting() && self.checkpoint_activity == Some(self.last_activity) {
+            return;
+        }
+        self.checkpoint_activity = Some(self.last_activity);
+        checkpoint::queue_save(self.checkpoint());
+    }
+
+    /// Collects the stats of the players with profiles, players are
+    /// ranked by score with tied players sharing a rank
+    fn profile_stats(&self) -> Vec<ProfileStats> {
+        let asked = self.core.questions_marked();
+        self.players
+            .iter()
+            .filter_map(|player| {
+                let id = player.profile?;
+                let results = player.results[..player.results.len().min(asked)]
+                    .iter()
+                    .flatten()
+                    // Polls have no correct answer so don't count
+                    .filter(|result| !matches!(result, AnswerResult::Participated(_)));
+                let (answered, correct) = results.fold((0, 0), |(answered, correct), result| {
+                    (answered + 1, correct + result.is_correct() as u32)
+                });
+                let rank = self.rank(player.score);
+                Some(ProfileStats {
+                    id,
+                    answered,
+                    correct,
+                    rank,
+                })
+            })
+            .collect()
+    }
+
+    /// Sends a single session the authoritative state of the game so
+    /// that clients that have fallen out of sync can recover without
+    /// reconnecting
+    ///
+    /// `id` The ID of the session to resync
+    fn resync(&self, id: SessionId) -> Result<(), ServerError> {
+        let mut snapshot = self.snapshot(id);
+
+        let session: &dyn GameSession = if id == self.host.id {
+            &self.host
+        } else if let Some(spectator) = self.spectators.iter().find(|s| s.id == id) {
+            spectator
+        } else {
+            let player = self
+                .players
+                .iter()
+                .find(|player| player.id == id)
+                .ok_or(ServerError::UnknownPlayer)?;
+
+            // Players are shown their own answer order
+            if snapshot.question.is_some() && !player.answer_order.is_empty() {
+                snapshot.question = Some(self.question().with_order(&player.answer_order));
+            }
+            player
+        };
+
+        session.send(ServerMessage::StateSnapshot(Box::new(snapshot)));
+        session.send(self.scores_for(id));
+        Ok(())
+    }
+
+    /// Creates the details of each player shown to admins and the host
+    fn player_details(&self) -> Vec<PlayerDetails> {
+        self.players
+            .iter()
+            .map(|player| PlayerDetails {
+                id: player.id,
+                name: player.name.clone(),
+                score: player.score,
+                connected: player.addr.is_some(),
+                rtt: player.rtt.map(|rtt| rtt.as_millis() as u64),
+                flagged: player
+                    .answers
+                    .iter()
+                    .flatten()
+                    .filter(|answer| answer.flagged)
+                    .count(),
+            })
+            .collect()
+    }
+
+    /// Creates the details of the game shown to admins
+    fn details(&self) -> GameDetails {
+        let players = self.player_details();
+        let (audit, audit_truncated) = self.audit.entries();
+
+        GameDetails {
+            game: GameInfo {
+                id: self.id,
+                token: self.token.clone(),
+                quiz: self.quiz,
+                name: self.config.basic.name.clone(),
+                state: self.core.state(),
+                players: self.players.len(),
+                connected: self
+                    .players
+                    .iter()
+                    .filter(|player| player.addr.is_some())
+                    .count(),
+                spectators: self.spectators.len(),
+                pending: self.pending.len(),
+                question_index: self.core.question_index(),
+                questions: self.config.questions.len(),
+                host_connected: self.host.addr.is_some(),
+                started_at: unix_secs(self.started_at),
+            },
+            players,
+            audit,
+            audit_truncated,
+        }
+    }
+
+    /// Creates a summary of the results of the game
+    fn results(&self) -> GameResults {
+        let questions = self
+            .config
+            .questions
+            .iter()
+            .map(|question| QuestionResults {
+                title: question.title.clone(),
+                answer_time: question.answer_time,
+                poll: question.ty.is_poll(),
+            })
+            .collect();
+
+        let players = self
+            .players
+            .iter()
+            .map(|player| PlayerResults {
+                id: player.id,
+                name: player.name.clone(),
+                score: player.score,
+                team: player.team,
+                answers: (0..self.config.questions.len())
+                    .map(|index| {
+                        let answer = player.answers.get(index)?.as_ref()?;
+                        let result = player.results.get(index)?.clone()?;
+                        Some(AnswerRecord {
+                            result,
+                            elapsed: answer.elapsed.as_millis() as u64,
+                            answer: Some(answer.answer.clone()),
+                        })
+                    })
+                    .collect(),
+            })
+            .collect();
+        let (audit, audit_truncated) = self.audit.entries();
+
+        GameResults {
+            id: self.id,
+            token: self.token.clone(),
+            quiz: Some(self.quiz),
+            owner: self.config.owner,
+            name: self.config.basic.name.clone(),
+            started_at: unix_secs(self.started_at),
+            finished_at: unix_secs(SystemTime::now()),
+            questions,
+            players,
+            audit,
+            audit_truncated,
+        }
+    }
+
+    /// Send a message to all clients, the message is only encoded
+    /// once and shared between all the sessions
+    fn send_all(&self, message: ServerMessage) {
+        let recipients = self.players.len() + self.spectators.len() + 1;
+        let _span = info_span!("broadcast", recipients).entered();
+        let message = SharedMessage::new(message);
+        for player in &self.players {
+            player.send_shared(&message);
+        }
+        for spectator in &self.spectators {
+            spectator.send_shared(&message);
+        }
+        self.host.send_shared(&message);
+    }
+
+    /// Sends a message containing player names to everyone, in anonymous
+    /// games the players and spectators are sent the anonymized message
+    /// instead while the host still sees the real names
+    ///
+    /// `message`    The message with the real names
+    /// `anonymized` Creates the message with the names hidden
+    fn send_all_named<F>(&self, message: ServerMessage, anonymized: F)
+    where
+        F: FnOnce() -> ServerMessage,
+    {
+        if !self.config.anonymous {
+            self.send_all(message);
+            return;
+        }
+
+        let hidden = SharedMessage::new(anonymized());
+        for player in &self.players {
+            player.send_shared(&hidden);
+        }
+        for spectator in &self.spectators {
+            spectator.send_shared(&hidden);
+        }
+        self.host.send(message);
+    }
+
+    /// Checks whether the names of the players are hidden from the session
+    ///
+    /// `id` The ID of the session
+    fn is_anonymous_to(&self, id: SessionId) -> bool {
+        self.config.anonymous && id != self.host.id
+    }
+
+    /// Returns the rank a player with the provided score would have,
+    /// players with the same score share a rank
+    ///
+    /// `score` The score of the player
+    fn rank(&self, score: u32) -> u32 {
+        let higher = self
+            .players
+            .iter()
+            .filter(|player| player.score > score)
+            .count();
+        higher as u32 + 1
+    }
+
+    /// Creates the scores message for the session, in anonymous games
+    /// players only see their own score along with their rank and
+    /// spectators only see the team scores
+    ///
+    /// `id` The ID of the session
+    fn scores_for(&self, id: SessionId) -> ServerMessage {
+        if !self.is_anonymous_to(id) {
+            return self.full_scores();
+        }
+
+        let mut scores = HashMap::new();
+        let mut streaks = HashMap::new();
+        let mut rank = None;
+        if let Some(player) = self.players.iter().find(|player| player.id == id) {
+            scores.insert(id, player.score);
+            streaks.insert(id, player.streak);
+            rank = Some(self.rank(player.score));
+        }
+        ServerMessage::ScoreUpdate {
+            scores,
+            streaks,
+            teams: self.team_scores(),
+            rank,
+        }
+    }
+
+    /// Creates a message containing the full scores of every player,
+    /// sent to sessions joining or resynchronizing with the game
+    fn full_scores(&self) -> ServerMessage {
+        let mut scores = HashMap::new();
+        let mut streaks = HashMap::new();
+        for player in &self.players {
+            scores.insert(player.id, player.score);
+            streaks.insert(player.id, player.streak);
+        }
+        ServerMessage::ScoreUpdate {
+            scores,
+            streaks,
+            teams: self.team_scores(),
+            rank: None,
+        }
+    }
+
+    /// Sends everyone the scores and streaks that changed since the
+    /// last score update. Players missing from the last update are
+    /// treated as having no score
+    fn flush_scores(&mut self) {
+        if !self.scores_dirty {
+            return;
+        }
+        self.scores_dirty = false;
+
+        let mut scores = HashMap::new();
+        let mut streaks = HashMap::new();
+        let mut sent = HashMap::with_capacity(self.players.len());
+        for player in &self.players {
+            let (score, streak) = self
+                .sent_scores
+                .get(&player.id)
+                .copied()
+                .unwrap_or_default();
+            if score != player.score {
+                scores.insert(player.id, player.score);
+            }
+            if streak != player.streak {
+                streaks.insert(player.id, player.streak);
+            }
+            sent.insert(player.id, (player.score, player.streak));
+        }
+        // Removed players are dropped from the sent scores
+        self.sent_scores = sent;
+
+        let teams = self.team_scores();
+        let teams = if teams == self.sent_teams {
+            Vec::new()
+        } else {
+            self.sent_teams = teams.clone();
+            teams
+        };
+
+        if scores.is_empty() && streaks.is_empty() && teams.is_empty() {
+            return;
+        }
+
+        if !scores.is_empty() {
+            self.replay.record(ReplayEventKind::Scores {
+                scores: scores.clone(),
+            });
+        }
+
+        if self.config.anonymous {
+            // Any score change can move the rank of every player
+            for player in &self.players {
+                player.send(self.scores_for(player.id));
+            }
+            if !teams.is_empty() {
+                let message = SharedMessage::new(ServerMessage::ScoreDelta {
+                    scores: HashMap::new(),
+                    streaks: HashMap::new(),
+                    teams: teams.clone(),
+                });
+                for spectator in &self.spectators {
+                    spectator.send_shared(&message);
+                }
+            }
+            self.host.send(ServerMessage::ScoreDelta {
+                scores,
+                streaks,
+                teams,
+            });
+            return;
+        }
+
+        self.send_all(ServerMessage::ScoreDelta {
+            scores,
+            streaks,
+            teams,
+        });
+    }
+
+    /// Calculates the combined score of each team, empty when
+    /// the game isn't in team mode
+    fn team_scores(&self) -> Vec<u32> {
+        let count = match &self.config.basic.teams {
+            Some(teams) => teams.count,
+            None => return Vec::new(),
+        };
+        let mut scores = vec![0u32; count];
+        for player in &self.players {
+            if let Some(score) = player.team.and_then(|team| scores.get_mut(team)) {
+                *score = score.saturating_add(player.score);
+            }
+        }
+        scores
+    }
+
+    /// Finds the team with the fewest members, None when the
+    /// game isn't in team mode
+    fn smallest_team(&self) -> Option<usize> {
+        let teams = self.config.basic.teams.as_ref()?;
+        let mut counts = vec![0usize; teams.count];
+        for player in &self.players {
+            if let Some(count) = player.team.and_then(|team| counts.get_mut(team)) {
+                *count += 1;
+            }
+        }
+        counts
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, count)| **count)
+            .map(|(team, _)| team)
+    }
+
+    /// Places any players that haven't chosen a team into the
+    /// smallest team
+    fn assign_teams(&mut self) {
+        for index in 0..self.players.len() {
+            if self.players[index].team.is_some() {
+                continue;
+            }
+            let team = self.smallest_team();
+            if team.is_none() {
+                return;
+            }
+            let player = &mut self.players[index];
+            player.team = team;
+            let id = player.id;
+            self.send_all(ServerMessage::TeamChanged { id, team });
+        }
+    }
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<GameResponse, ServerError>")]
+pub enum GameRequest {
+    /// Message to attempt to connect a new client
+    TryConnect {
+        id: SessionId,
+        name: String,
+        addr: Addr<Session>,
+        ip: Option<IpAddr>,
+        /// The join PIN provided by the player
+        pin: Option<String>,
+        /// The profile of the player if they opted in to one
+        profile: Option<Uuid>,
+        /// The avatar and color chosen by the player
+        appearance: Appearance,
+    },
+
+    /// Message to connect a new client as a spectator
+    Spectate { id: SessionId, addr: Addr<Session> },
+
+    /// Message from the host to start the game, when forced the
+    /// game will start even if not all players are ready
+    Start { id: SessionId, force: bool },
+
+    /// Message from the host to cancel starting the game
+    Cancel { id: SessionId },
+
+    /// Request to inform that a player is ready
+    Ready { id: SessionId },
+
+    /// Request from a player to wager on the next question
+    Wager { id: SessionId, amount: u32 },
+
+    /// Request from a player to join the team with the provided index
+    JoinTeam { id: SessionId, team: usize },
+
+    /// Request from a player or the host to send a chat message
+    Chat { id: SessionId, text: String },
+
+    /// Request to add a reaction to the next reaction batch
+    React { reaction: Reaction },
+
+    /// Request from a session to perform a host action
+    HostAction { id: SessionId, action: HostAction },
+
+    /// Request to answer the current question
+    Answer {
+        id: SessionId,
+        answer: QuestionAnswer,
+    },
+
+    /// Message to skip the current timer
+    SkipTimer,
+
+    /// Message indicating a session has lost its connection
+    Disconnect {
+        id: SessionId,
+        /// The address of the session that lost its connection
+        addr: Addr<Session>,
+    },
+
+    /// Message informing the game that the server is shutting down
+    Shutdown { deadline: u64 },
+
+    /// Message to reconnect a new session to an existing player
+    Reconnect {
+        /// The key portion of the resume token
+        key: String,
+        /// The address of the new session
+        addr: Addr<Session>,
+    },
+
+    /// Request from a player to change their avatar and color
+    SetAppearance {
+        id: SessionId,
+        appearance: Appearance,
+    },
+
+    /// Request from a session for the current state of the game
+    Resync { id: SessionId },
+
+    /// Message from a session with its latest round trip time
+    Latency { id: SessionId, rtt: Duration },
+
+    /// Request from an admin for the details of the game
+    Inspect,
+
+    /// Request from an admin to stop the game removing all
+    /// of its sessions
+    Close { reason: String },
+}
+
+impl GameRequest {
+    /// Returns the name of the request type used in logs
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::TryConnect { .. } => "TryConnect",
+            Self::Spectate { .. } => "Spectate",
+            Self::Start { .. } => "Start",
+            Self::Cancel { .. } => "Cancel",
+            Self::Ready { .. } => "Ready",
+            Self::Wager { .. } => "Wager",
+            Self::JoinTeam { .. } => "JoinTeam",
+            Self::Chat { .. } => "Chat",
+            Self::React { .. } => "React",
+            Self::HostAction { .. } => "HostAction",
+            Self::Answer { .. } => "Answer",
+            Self::SkipTimer => "SkipTimer",
+            Self::Disconnect { .. } => "Disconnect",
+            Self::Shutdown { .. } => "Shutdown",
+            Self::Reconnect { .. } => "Reconnect",
+            Self::SetAppearance { .. } => "SetAppearance",
+            Self::Resync { .. } => "Resync",
+            Self::Latency { .. } => "Latency",
+            Self::Inspect => "Inspect",
+            Self::Close { .. } => "Close",
+        }
+    }
+
+    /// Returns the ID of the session that sent the request
+    /// if the request was sent by a session
+    pub fn session(&self) -> Option<SessionId> {
+        match self {
+            Self::TryConnect { id, .. }
+            | Self::Spectate { id, .. }
+            | Self::Start { id, .. }
+            | Self::Cancel { id }
+            | Self::Ready { id }
+            | Self::Wager { id, .. }
+            | Self::JoinTeam { id, .. }
+            | Self::Chat { id, .. }
+            | Self::HostAction { id, .. }
+            | Self::Answer { id, .. }
+            | Self::SetAppearance { id, .. }
+            | Self::Resync { id }
+            | Self::Latency { id, .. }
+            | Self::Disconnect { id, .. } => Some(*id),
+            Self::React { .. }
+            | Self::SkipTimer
+            | Self::Shutdown { .. }
+            | Self::Reconnect { .. }
+            | Self::Inspect
+            | Self::Close { .. } => None,
+        }
+    }
+}
+
+pub enum GameResponse {
+    Connected {
+        /// The game token
+        token: String,
+        /// The ID of the quiz the game was created from
+        quiz: GameId,
+        /// The session ID
+        id: u32,
+        /// Basic game config information
+        basic: BasicConfig,
+        /// Timing data for different game events
+        timing: GameTiming,
+        /// Token used to resume the session after losing the connection
+        resume: ResumeToken,
+        /// The name of the player, None for the host
+        name: Option<String>,
+        /// Snapshot of the game for players joining a game in progress
+        snapshot: Option<Box<StateSnapshot>>,
+    },
+    /// The player is waiting for the host to approve their join request
+    Pending {
+        /// The game token
+        token: String,
+        /// The ID of the quiz the game was created from
+        quiz: GameId,
+        /// The session ID
+        id: u32,
+    },
+    Spectating {
+        /// The game token
+        token: String,
+        /// The ID of the quiz the game was created from
+        quiz: GameId,
+        /// The session ID
+        id: u32,
+        /// Basic game config information
+        basic: BasicConfig,
+        /// Timing data for different game events
+        timing: GameTiming,
+    },
+    /// Details of the game for admins
+    Details(GameDetails),
+    None,
+}
+
+/// ID of a created quiz, host tokens, results and stored bundles are
+/// bound to it so it is random rather than counted and never repeats
+/// across restarts or between nodes
+pub type GameId = Uuid;
+
+/// Actions that can only be performed by the host of the game
+#[derive(Deserialize)]
+pub enum HostAction {
+    /// Skip the current timer
+    Skip,
+    /// Pause the current timer
+    Pause,
+    /// Resume the paused timer
+    Resume,
+    /// End the game
+    End,
+    /// Move on to the next question
+    NextQuestion,
+    /// Remove a player from the game optionally banning them
+    Kick { id: SessionId, ban: bool },
+    /// Prevent or allow a player sending chat messages
+    Mute { id: SessionId, muted: bool },
+    /// Give a player extra time to answer questions by multiplying
+    /// their answer time, used as an accessibility accommodation
+    ExtendTime { id: SessionId, multiplier: f32 },
+    /// Grade a submission to an open ended question
+    GradeAnswer { submission: usize, grade: Grade },
+    /// Let a player waiting for approval into the game
+    ApproveJoin { id: SessionId },
+    /// Reject a player waiting for approval
+    DenyJoin { id: SessionId },
+    /// Request the details of every player including their latency
+    ListPlayers,
+}
+
+impl Actor for Game {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        metrics().active_games.inc();
+        events::publish(|| GameEvent::GameStarted {
+            game: self.id,
+            token: self.token.clone(),
+            quiz: self.quiz,
+            name: self.config.basic.name.clone(),
+        });
+
+        let empty_ttl = Duration::from_secs(config::from_env(config::EMPTY_GAME_TTL));
+        let idle_ttl = Duration::from_secs(config::from_env(config::IDLE_GAME_TTL));
+        ctx.run_interval(REAPER_INTERVAL, move |actor, ctx| {
+            if actor.is_abandoned(empty_ttl, idle_ttl) {
+                actor.reap(ctx);
+            }
+        });
+
+        // Reactions are batched rather than broadcast individually
+        ctx.run_interval(REACTION_INTERVAL, |actor, ctx| {
+            actor.guarded(ctx, |actor, _ctx| actor.flush_reactions());
+        });
+
+        ctx.run_interval(SCORE_INTERVAL, |actor, ctx| {
+            actor.guarded(ctx, |actor, _ctx| actor.flush_scores());
+        });
+
+        ctx.run_interval(PROGRESS_INTERVAL, |actor, ctx| {
+            actor.guarded(ctx, |actor, _ctx| actor.send_answer_progress());
+        });
+
+        if let Some(interval) = checkpoint::interval() {
+            ctx.run_interval(interval, |actor, ctx| {
+                actor.guarded(ctx, |actor, _ctx| actor.save_checkpoint());
+            });
+        }
+    }
+
+    fn stopped(&mut self, ctx: &mut Self::Context) {
+        metrics().active_games.dec();
+
+        // Free the token for use by other games
+        registry().remove(&self.token, &ctx.address());
+    }
+}
+
+impl Handler<Traced<GameRequest>> for Game {
+    type Result = Result<GameResponse, ServerError>;
+
+    fn handle(&mut self, msg: Traced<GameRequest>, ctx: &mut Self::Context) -> Self::Result {
+        // The game span continues the trace of the sender
+        let _enter = msg.span.enter();
+        Handler::<GameRequest>::handle(self, msg.msg, ctx)
+    }
+}
+
+impl Handler<GameRequest> for Game {
+    type Result = Result<GameResponse, ServerError>;
+
+    fn handle(&mut self, msg: GameRequest, ctx: &mut Self::Context) -> Self::Result {
+        let span = info_span!(
+            "game",
+            game_id = %self.id,
+            token = %self.token,
+            session_id = field::Empty,
+            message = msg.name(),
+        );
+        if let Some(id) = msg.session() {
+            span.record("session_id", id);
+        }
+        let _enter = span.enter();
+
+        self.guarded(ctx, |actor, ctx| actor.handle_request(msg, ctx))
+            .unwrap_or(Err(ServerError::GameCrashed))
+    }
+}
+
+impl Game {
+    /// Handles a request to the game
+    ///
+    /// `msg` The request
+    /// `ctx` The game context
+    fn handle_request(
+        &mut self,
+        msg: GameRequest,
+        ctx: &mut Context<Self>,
+    ) -> Result<GameResponse, ServerError> {
+        // Admins looking at the game shouldn't keep it alive
+        if !matches!(msg, GameRequest::Inspect) {
+            self.last_activity = self.clock.now();
+        }
+        self.check_request(&msg)?;
+
+        match msg {
+            GameRequest::Inspect => Ok(GameResponse::Details(self.details())),
+
+            GameRequest::SetAppearance { id, appearance } => {
+                let appearance = appearance.validate()?;
+                let player = self
+                    .players
+                    .iter_mut()
+                    .find(|player| player.id == id)
+                    .ok_or(ServerError::UnknownPlayer)?;
+                player.appearance = appearance.clone();
+
+                // Appearances would identify players in anonymous games
+                let message = ServerMessage::PlayerAppearance { id, appearance };
+                if self.config.anonymous {
+                    self.host.send(message);
+                } else {
+                    self.send_all(message);
+                }
+                Ok(GameResponse::None)
+            }
+
+            GameRequest::Resync { id } => {
+                self.resync(id)?;
+                Ok(GameResponse::None)
+            }
+
+            GameRequest::Latency { id, rtt } => {
+                // The host and spectators aren't scored so their latency is ignored
+                if let Some(player) = self.players.iter_mut().find(|player| player.id == id) {
+                    player.rtt = Some(rtt);
+                }
+                Ok(GameResponse::None)
+            }
+
+            GameRequest::Close { reason } => {
+                info!("Closing game {} ({}): {}", self.token, self.id, reason);
+                self.audit.record(AuditEventKind::Closed {
+                    reason: reason.clone(),
+                });
+                self.close(ctx, DisconnectReason::GameClosed(reason));
+                Ok(GameResponse::None)
+            }
+
+            GameRequest::TryConnect {
+                id,
+                name,
+                addr,
+                ip,
+                pin,
+                profile,
+                appearance,
+            } => {
+                let ip_banned = ip.is_some_and(|ip| self.banned_ips.contains(&ip));
+                if ip_banned || self.banned_ids.contains(&id) {
+                    self.audit.record(AuditEventKind::BannedJoin { id });
+                    return Err(ServerError::Banned);
+                }
+
+                self.check_pin(ip, pin.as_deref())?;
+
+                // The same name from the same address is the player
+                // returning without its resume token rather than a new
+                // player. Only the resume token can take over a slot that
+                // is still connected
+                if !self.config.assigned_names && ip.is_some() {
+                    let index = self.players.iter().position(|player| {
+                        player.ip == ip && username::is_same(&player.name, &name)
+                    });
+                    if let Some(index) = index {
+                        let player = &self.players[index];
+                        if player.addr.is_some() {
+                            self.audit.record(AuditEventKind::DuplicateConnection {
+                                id: player.id,
+                                transferred: false,
+                            });
+                            return Err(ServerError::AlreadyConnected);
+                        }
+                        return self.transfer_player(ctx, index, addr);
+                    }
+                }
+
+                let basic = &self.config.basic;
+                if basic
+                    .max_players
+                    .is_some_and(|max| self.players.len() >= max)
+                {
+                    // Overflowing players can still watch when allowed
+                    if basic.overflow_spectate {
+                        return Ok(self.add_spectator(id, addr));
+                    }
+                    return Err(ServerError::GameFull);
+                }
+
+                // Players without a name or that can't choose are given one
+                let name = if self.config.assigned_names || name.trim().is_empty() {
+                    self.generate_name()
+                } else {
+                    let name = username::validate(&name)?;
+                    if self.is_name_taken(&name) {
+                        return Err(ServerError::UsernameTaken);
+                    }
+                    name
+                };
+                let appearance = appearance.validate()?;
+
+                if self.config.require_approval {
+                    self.host.send(ServerMessage::JoinRequest {
+                        id,
+                        name: name.clone(),
+                    });
+                    self.pending.push(PendingPlayer {
+                        id,
+                        name,
+                        addr,
+                        ip,
+                        profile,
+                        appearance,
+                    });
+                    return Ok(GameResponse::Pending {
+                        token: self.token.clone(),
+                        quiz: self.quiz,
+                        id,
+                    });
+                }
+
+                Ok(self.add_player(id, name, addr, ip, profile, appearance))
+            }
+
+            GameRequest::Shutdown { .. }
+                if checkpoint::interval().is_some()
+                    && !matches!(self.core.state(), GameState::Finished) =>
+            {
+                // The game is restored once the server is back so it's
+                // paused and written out rather than wound down
+                if self.core.is_waiting() {
+                    self.pause_task(ctx);
+                    self.paused_for_host = true;
+                }
+                if let Err(err) = checkpoint::save(&self.checkpoint()) {
+                    error!("Failed to save checkpoint for game {}: {}", self.id, err);
+                }
+                Ok(GameResponse::None)
+            }
+
+            GameRequest::Shutdown { deadline } => {
+                self.shutting_down = true;
+                self.send_all(ServerMessage::ServerShutdown { deadline });
+
+                // Games that haven't started yet have nothing to finish
+                if matches!(self.core.state(), GameState::Lobby | GameState::Starting) {
+                    self.cancel_task(ctx);
+                    self.set_state(GameState::Finished);
+                }
+                Ok(GameResponse::None)
+            }
+
+            GameRequest::Spectate { id, addr } => Ok(self.add_spectator(id, addr)),
+
+            GameRequest::Disconnect { id, addr } => {
+                if self.is_replaced(id, &addr) {
+                    // The session has already moved to a new connection
+                } else if id == self.host.id {
+                    self.disconnect_host(ctx);
+                } else if let Some(index) = self.spectators.iter().position(|s| s.id == id) {
+                    // Spectators have nothing to resume so are removed immediately
+                    self.spectators.remove(index);
+                } else if let Some(index) = self.pending.iter().position(|p| p.id == id) {
+                    self.pending.remove(index);
+                    self.host.send(ServerMessage::JoinRequestCancelled { id });
+                } else {
+                    self.disconnect_player(ctx, id);
+                    // The remaining players may have all answered
+                    self.check_auto_advance(ctx);
+                }
+                Ok(GameResponse::None)
+            }
+
+            GameRequest::Reconnect { key, addr } if key == self.host.key => {
+                if let Some(handle) = self.host.remove_handle.take() {
+                    ctx.cancel_future(handle);
+                }
+
+                // The host only ever has one connection
+                if let Some(previous) = self.host.addr.replace(addr) {
+                    self.audit.record(AuditEventKind::DuplicateConnection {
+                        id: self.host.id,
+                        transferred: true,
+                    });
+                    previous.do_send(SessionRequest::Removed {
+                        reason: DisconnectReason::ConnectionReplaced,
+                    });
+                }
+                self.resume_host_pause(ctx);
+
+                // The host needs to be reminded of the waiting players
+                for pending in &self.pending {
+                    self.host.send(ServerMessage::JoinRequest {
+                        id: pending.id,
+                        name: pending.name.clone(),
+                    });
+                }
+                self.host.send(self.full_scores());
+
+                let config = &self.config;
+                Ok(GameResponse::Connected {
+                    id: self.host.id,
+                    token: self.token.clone(),
+                    quiz: self.quiz,
+                    basic: config.basic.clone(),
+                    timing: config.timing.clone(),
+                    resume: self.host_resume_token(),
+                    name: None,
+                    snapshot: None,
+                })
+            }
+
+            GameRequest::Reconnect { key, addr } => {
+                let index = self
+                    .players
+                    .iter()
+                    .position(|player| player.resume_key == key)
+                    .ok_or(ServerError::InvalidToken)?;
+                self.transfer_player(ctx, index, addr)
+            }
+
+            GameRequest::Start { force, .. } => {
+                if self.config.require_ready && !force {
+                    let (ready, total) = self.ready_count();
+                    if ready < total {
+                        return Err(ServerError::NotAllReady);
+                    }
+                }
+
+                self.audit.record(AuditEventKind::Started { forced: force });
+                self.assign_teams();
+                self.set_state(GameState::Starting);
+                // Begin the start time
+                self.start_game(ctx);
+                Ok(GameResponse::None)
+            }
+
+            GameRequest::JoinTeam { id, team } => {
+                let teams = self
+                    .config
+                    .basic
+                    .teams
+                    .as_ref()
+                    .filter(|teams| teams.selection == TeamSelection::SelfSelect)
+                    .ok_or(ServerError::InvalidStateForAction)?;
+                if team >= teams.count {
+                    return Err(ServerError::InvalidTeam);
+                }
+
+                let player = self
+                    .players
+                    .iter_mut()
+                    .find(|player| player.id == id)
+                    .ok_or(ServerError::UnknownPlayer)?;
+                player.team = Some(team);
+
+                self.send_all(ServerMessage::TeamChanged {
+                    id,
+                    team: Some(team),
+                });
+                Ok(GameResponse::None)
+            }
+
+            GameRequest::Chat { id, text } => {
+                if self.config.basic.disable_chat {
+                    return Err(ServerError::ChatDisabled);
+                }
+
+                let text = text.trim();
+                let valid = !text.is_empty()
+                    && text.chars().count() <= MAX_CHAT_LENGTH
+                    && !username::contains_profanity(text);
+                if !valid {
+                    return Err(ServerError::InvalidChat);
+                }
+
+                let name = if id == self.host.id {
+                    HOST_CHAT_NAME.to_string()
+                } else {
+                    let player = self
+                        .players
+                        .iter_mut()
+                        .find(|player| player.id == id)
+                        .ok_or(ServerError::UnknownPlayer)?;
+                    if player.muted {
+                        return Err(ServerError::Muted);
+                    }
+                    if !player.chat_limit.hit(CHAT_LIMIT, CHAT_WINDOW) {
+                        return Err(ServerError::RateLimited);
+                    }
+                    player.name.clone()
+                };
+
+                let text = text.to_string();
+                let is_host = id == self.host.id;
+                self.send_all_named(
+                    ServerMessage::Chat {
+                        id,
+                        name,
+                        text: text.clone(),
+                    },
+                    || ServerMessage::Chat {
+                        id,
+                        name: if is_host {
+                            HOST_CHAT_NAME.to_string()
+                        } else {
+                            ANONYMOUS_NAME.to_string()
+                        },
+                        text,
+                    },
+                );
+                Ok(GameResponse::None)
+            }
+
+            GameRequest::React { reaction } => {
+                *self.reactions.entry(reaction).or_default() += 1;
+                Ok(GameResponse::None)
+            }
+
+            GameRequest::Cancel { .. } => {
+                self.audit.record(AuditEventKind::StartCancelled);
+                self.cancel_task(ctx);
+                self.set_state(GameState::Lobby);
+                self.reset_ready();
+                self.send_ready_state();
+                Ok(GameResponse::None)
+            }
+
+            GameRequest::SkipTimer => {
+                self.immediate_task(ctx);
+
+                // Reset the timer future
+                Ok(GameResponse::None)
+            }
+            GameRequest::HostAction { action, .. } => {
+                self.host_action(ctx, action)?;
+                self.check_auto_advance(ctx);
+                Ok(GameResponse::None)
+            }
+            GameRequest::Answer { id, answer } => {
+                if !self.question().ty.is_valid_answer(&answer) {
+                    return Err(ServerError::InvalidAnswer);
+                }
+
+                let index = self.core.question_index();
+                let player = self
+                    .players
+                    .iter_mut()
+                    .find(|player| player.id == id)
+                    .ok_or(ServerError::UnknownPlayer)?;
+                if player.eliminated.is_some() {
+                    return Err(ServerError::Eliminated);
+                }
+
+                // Answers that arrive after the players time has elapsed are rejected
+                let now = self.clock.now();
+                if self.core.is_late(player.time_multiplier, now) {
+                    return Err(ServerError::InvalidAnswer);
+                }
+
+                // Only the last submission is kept when answers can be changed
+                let is_first = !self.answered.contains(&id);
+                if !is_first && !self.config.change_answers {
+                    return Err(ServerError::AlreadyAnswered);
+                }
+
+                // Map shuffled answer positions back to the original order
+                let answer = player.unshuffle_answer(answer);
+
+                if player.answers.len() <= index {
+                    player.answers.resize_with(index + 1, || None);
+                }
+                let mut elapsed = self.core.elapsed(now);
+                if is_first {
+                    metrics().answer_latency.observe(elapsed.as_secs_f64());
+                }
+
+                // The question and the answer each spent half the round trip
+                // travelling so the player had less time than has elapsed
+                let answer_time = elapsed.saturating_sub(player.rtt.unwrap_or_default());
+                let min_answer_time = self
+                    .config
+                    .anti_cheat
+                    .as_ref()
+                    .map_or(RAPID_ANSWER_TIME, |anti_cheat| {
+                        Duration::from_millis(anti_cheat.min_answer_time)
+                    });
+                let is_rapid = answer_time < min_answer_time;
+                let flagged = is_rapid && self.config.anti_cheat.is_some();
+                if is_rapid {
+                    self.audit.record(AuditEventKind::RapidAnswer {
+                        id,
+                        index,
+                        elapsed: answer_time.as_millis() as u64,
+                    });
+                }
+                if flagged {
+                    self.host.send(ServerMessage::AnswerFlagged {
+                        id,
+                        index,
+                        elapsed: answer_time.as_millis() as u64,
+                    });
+                }
+
+                // The answer spent half the round trip travelling to the game
+                if self.config.latency_compensation {
+                    if let Some(rtt) = player.rtt {
+                        elapsed = elapsed.saturating_sub((rtt / 2).min(MAX_LATENCY_COMPENSATION));
+                    }
+                }
+                let arrival = self.arrivals;
+                self.arrivals += 1;
+                self.replay.record(ReplayEventKind::Answer {
+                    id,
+                    index,
+                    answer: answer.clone(),
+                    elapsed: elapsed.as_millis() as u64,
+                });
+                player.answers[index] = Some(PlayerAnswer {
+                    answer,
+                    elapsed,
+                    arrival,
+                    flagged,
+                });
+                self.answered.insert(id);
+
+                // Everyone is told who buzzed in first
+                if arrival == 0 && matches!(self.config.basic.mode, GameMode::Buzzer { .. }) {
+                    self.send_all(ServerMessage::Buzzed { id });
+                }
+
+                self.check_auto_advance(ctx);
+                Ok(GameResponse::None)
+            }
+            GameRequest::Ready { id } => {
+                let player = self
+                    .players
+                    .iter_mut()
+                    .find(|player| player.id == id)
+                    .ok_or(ServerError::UnknownPlayer)?;
+                player.ready = true;
+
+                let (ready, total) = self.send_ready_state();
+
+                // Skip the remaining wait time when everyone is ready
+                if ready == total && matches!(self.core.state(), GameState::AwaitingReady) {
+                    self.immediate_task(ctx);
+                }
+
+                Ok(GameResponse::None)
+            }
+            GameRequest::Wager { id, amount } => {
+                let player = self
+                    .players
+                    .iter_mut()
+                    .find(|player| player.id == id)
+                    .ok_or(ServerError::UnknownPlayer)?;
+                if player.eliminated.is_some() {
+                    return Err(ServerError::Eliminated);
+                }
+                if amount > player.score {
+                    return Err(ServerError::InvalidWager);
+                }
+                player.wager = Some(amount);
+
+                // Show the question once everyone has wagered
+                let all_wagered = self
+                    .players
+                    .iter()
+                    .filter(|player| player.is_active())
+                    .all(|player| player.wager.is_some());
+                if all_wagered {
+                    self.immediate_task(ctx);
+                }
+
+                Ok(GameResponse::None)
+            }
+        }
+    }
+}
+
+/// Trait implemented by the sessions that are connected to
+/// the game for logic to share between both
+pub trait GameSession {
+    fn id(&self) -> SessionId;
+
+    fn addr(&self) -> Option<&Addr<Session>>;
+
+    fn send(&self, message: ServerMessage) {
+        if let Some(addr) = self.addr() {
+            addr.do_send(SessionRequest::Message(message));
+        }
+    }
+
+    fn send_shared(&self, message: &SharedMessage) {
+        if let Some(addr) = self.addr() {
+            addr.do_send(SessionRequest::Shared(message.clone()));
+        }
+    }
+}
+
+pub struct HostSession {
+    /// The ID of the session
+    id: SessionId,
+    /// Address to the session if the host is connected
+    addr: Option<Addr<Session>>,
+    /// Secret key used by the host to reclaim the game
+    key: String,
+    /// Handle for the task handling the host not returning
+    remove_handle: Option<SpawnHandle>,
+}
+
+impl GameSession for HostSession {
+    fn id(&self) -> SessionId {
+        self.id
+    }
+
+    fn addr(&self) -> Option<&Addr<Session>> {
+        self.addr.as_ref()
+    }
+}
+
+pub struct PlayerSession {
+    /// The ID of the session
+    id: SessionId,
+    /// The player name
+    name: String,
+    /// Address to the session if the player is connected
+    addr: Option<Addr<Session>>,
+    /// The IP address the player connected from
+    ip: Option<IpAddr>,
+    /// Key used by the player to resume after disconnecting
+    resume_key: String,
+    /// Handle for the task removing the player if it doesn't reconnect
+    remove_handle: Option<SpawnHandle>,
+    /// The player ready state
+    ready: bool,
+    /// The players answers for each question index
+    answers: Vec<Option<PlayerAnswer>>,
+    /// Marked version of each question answer
+    results: Vec<Option<AnswerResult>>,
+    /// The player total score
+    score: u32,
+    /// The number of consecutive correct answers
+    streak: u32,
+    /// The index of the team the player is in
+    team: Option<usize>,
+    /// Whether the host has prevented the player from chatting
+    muted: bool,
+    /// Counter limiting how often the player can chat
+    chat_limit: WindowCounter,
+    /// Multiplier applied to the answer time for the player
+    time_multiplier: f32,
+    /// The order the answers to the current question were shown to the
+    /// player in, mapping each shown position to the original index
+    answer_order: Vec<usize>,
+    /// The amount of score wagered on the current question
+    wager: Option<u32>,
+    /// The index of the question the player was eliminated on
+    eliminated: Option<usize>,
+    /// The round trip time to the player measured by the session
+    rtt: Option<Duration>,
+    /// The profile the stats of the player are recorded to
+    profile: Option<Uuid>,
+    /// The avatar and color chosen by the player
+    appearance: Appearance,
+}
+
+impl PlayerSession {
+    /// Creates the message describing this player to other sessions
+    ///
+    /// `anonymous` Whether the name and appearance are hidden
+    fn describe(&self, anonymous: bool) -> ServerMessage {
+        if anonymous {
+            return ServerMessage::OtherPlayer {
+                id: self.id,
+                name: ANONYMOUS_NAME.to_string(),
+                team: self.team,
+                appearance: Appearance::default(),
+            };
+        }
+        ServerMessage::OtherPlayer {
+            id: self.id,
+            name: self.name.clone(),
+            team: self.team,
+            appearance: self.appearance.clone(),
+        }
+    }
+
+    /// Whether the player is connected and still playing
+    fn is_active(&self) -> bool {
+        self.addr.is_some() && self.eliminated.is_none()
+    }
+
+    /// Maps the positions of a submitted answer from the shuffled order
+    /// shown to the player back to the original answer indexes
+    ///
+    /// `answer` The submitted answer
+    fn unshuffle_answer(&self, answer: QuestionAnswer) -> QuestionAnswer {
+        if self.answer_order.is_empty() {
+            return answer;
+        }
+        let order = &self.answer_order;
+        let map = |index: usize| order.get(index).copied().unwrap_or(index);
+        match answer {
+            QuestionAnswer::Single { answer } => QuestionAnswer::Single {
+                answer: map(answer),
+            },
+            QuestionAnswer::MultiSelect { answers } => QuestionAnswer::MultiSelect {
+                answers: answers.into_iter().map(map).collect(),
+            },
+            QuestionAnswer::Ordering { order } => QuestionAnswer::Ordering {
+                order: order.into_iter().map(map).collect(),
+            },
+            answer => answer,
+        }
+    }
+}
+
+impl GameSession for PlayerSession {
+    fn id(&self) -> SessionId {
+        self.id
+    }
+
+    fn addr(&self) -> Option<&Addr<Session>> {
+        self.addr.as_ref()
+    }
+}
+
+/// Player waiting for the host to approve their join request
+struct PendingPlayer {
+    /// The ID of the session
+    id: SessionId,
+    /// The validated username of the player
+    name: String,
+    /// Address to the session
+    addr: Addr<Session>,
+    /// The IP address of the player
+    ip: Option<IpAddr>,
+    /// The profile of the player if they opted in to one
+    profile: Option<Uuid>,
+    /// The validated avatar and color chosen by the player
+    appearance: Appearance,
+}
+
+/// Session watching the game in read-only mode, spectators receive
+/// the game broadcasts but can't answer and aren't scored
+pub struct SpectatorSession {
+    /// The ID of the session
+    id: SessionId,
+    /// Address to the session
+    addr: Addr<Session>,
+}
+
+impl GameSession for SpectatorSession {
+    fn id(&self) -> SessionId {
+        self.id
+    }
+
+    fn addr(&self) -> Option<&Addr<Session>> {
+        Some(&self.addr)
+    }
+}
+
+/// Shuffles the provided values in place using a Fisher-Yates shuffle
+///
+/// `rng`    The random number generator to use
+/// `values` The values to shuffle
+pub fn shuffle<T>(rng: &mut impl RngCore, values: &mut [T]) {
+    for index in (1..values.len()).rev() {
+        let other = (rng.next_u64() % (index as u64 + 1)) as usize;
+        values.swap(index, other);
+    }
+}
+
+/// Picks a random subset of the question bank keeping the questions
+/// in the order they appear in the bank
+///
+/// `rng`  The random number generator to use
+/// `bank` The question bank
+/// `pick` How many questions to pick
+fn pick_questions(rng: &mut impl RngCore, bank: Vec<Question>, pick: &Pick) -> Vec<Question> {
+    let mut picked = Vec::new();
+    let mut sample = |indexes: Vec<usize>, count: usize| {
+        let mut indexes = indexes;
+        shuffle(rng, &mut indexes);
+        indexes.truncate(count);
+        picked.extend(indexes);
+    };
+
+    match pick {
+        Pick::Total(count) => sample((0..bank.len()).collect(), *count),
+        Pick::Categories(counts) => {
+            for (category, count) in counts {
+                let indexes = bank
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, question)| question.category.as_ref() == Some(category))
+                    .map(|(index, _)| index)
+                    .collect();
+                sample(indexes, *count);
+            }
+        }
+    }
+
+    picked.sort_unstable();
+    bank.into_iter()
+        .enumerate()
+        .filter(|(index, _)| picked.binary_search(index).is_ok())
+        .map(|(_, question)| question)
+        .collect()
+}
+
+/// Creates a new random key for resume tokens
+fn random_key() -> String {
+    format!("{:016x}{:016x}", OsRng.next_u64(), OsRng.next_u64())
+}
+
+/// Token issued to players allowing them to resume their place in
+/// a game after losing their connection
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ResumeToken {
+    /// The token of the game
+    pub game: String,
+    /// Key unique to the player
+    pub key: String,
+}
+
+/// Configuration data for a game
+#[derive(Clone, Serialize, Deserialize)]
+pub struct GameConfig {
+    /// Host supplied token to use for the game instead of
+    /// a randomly generated one
+    #[serde(default, skip_serializing)]
+    pub token: Option<String>,
+    /// Basic configuration such as name and subtext
+    pub basic: BasicConfig,
+    /// Timing data for different game events
+    pub timing: GameTiming,
+    /// The game questions
+    pub questions: Vec<Question>,
+    /// Whether the earliest joined player is promoted to host if the
+    /// host doesn't return, otherwise the game ends
+    #[serde(default)]
+    pub host_migration: bool,
+    /// Whether all players must be ready before the host can
+    /// start the game without forcing it
+    #[serde(default)]
+    pub require_ready: bool,
+    /// Whether players can join after the game has started
+    #[serde(default)]
+    pub late_join: bool,
+    /// Whether questions end early once every connected
+    /// player has answered
+    #[serde(default)]
+    pub auto_advance: bool,
+    /// Whether the order of the questions is shuffled
+    #[serde(default)]
+    pub shuffle_questions: bool,
+    /// Whether the order of the answers is shuffled for each player
+    #[serde(default)]
+    pub shuffle_answers: bool,
+    /// Whether players can change their answer until the question
+    /// timer ends, only the last answer is scored
+    #[serde(default)]
+    pub change_answers: bool,
+    /// Number of questions to pick from the questions when they
+    /// are a larger question bank
+    #[serde(default)]
+    pub pick: Option<Pick>,
+    /// Seed for the random number generator used for picking and
+    /// shuffling, a random seed is used when not provided
+    #[serde(default)]
+    pub seed: Option<u64>,
+    /// PIN players must provide to join the game
+    #[serde(default)]
+    pub pin: Option<String>,
+    /// Whether joining players wait for the host to approve them
+    /// before entering the game
+    #[serde(default)]
+    pub require_approval: bool,
+    /// Whether players are given generated names instead of choosing
+    /// their own, prevents inappropriate names in classrooms
+    #[serde(default)]
+    pub assigned_names: bool,
+    /// Whether player names are hidden from the other players and
+    /// spectators, players only see their own score and rank while
+    /// the host still sees everything
+    #[serde(default)]
+    pub anonymous: bool,
+    /// Whether half of the round trip time to each player is removed
+    /// from their answer times so slow connections aren't penalized
+    #[serde(default)]
+    pub latency_compensation: bool,
+    /// Whether the results are emailed to the logged in host once
+    /// the game finishes
+    #[serde(default)]
+    pub email_results: bool,
+    /// Flagging of answers that arrive sooner than a person could
+    /// have answered, disabled when not provided
+    #[serde(default)]
+    pub anti_cheat: Option<AntiCheat>,
+    /// The LMS gradebook scores are posted back to when the game was
+    /// created by an instructor launched from an LMS, this is set by
+    /// the server and never read from the definition
+    #[cfg(feature = "lti")]
+    #[serde(skip)]
+    pub lti: Option<crate::lti::LtiContext>,
+    /// The user hosting the game when launched by a logged in host,
+    /// this is set by the server and never read from the definition
+    #[serde(skip)]
+    pub owner: Option<Uuid>,
+}
+
+/// Configuration for flagging answers that arrive sooner than a person
+/// could have read the question and answered
+#[derive(Clone, Serialize, Deserialize)]
+pub struct AntiCheat {
+    /// The least time in milliseconds a person needs to answer, measured
+    /// from when the question reached the player
+    pub min_answer_time: u64,
+    /// Whether flagged answers lose their speed bonus and are scored
+    /// as if they arrived at the last moment
+    #[serde(default)]
+    pub nullify_speed_bonus: bool,
+}
+
+/// How many questions are picked from a question bank
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Pick {
+    /// Pick the provided number of questions from the whole bank
+    Total(usize),
+    /// Pick the provided number of questions from each category, the
+    /// categories are ordered so that seeded picks are reproducible
+    Categories(BTreeMap<String, usize>),
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct BasicConfig {
+    pub name: String,
+    pub text: String,
+    /// The model used to score answers
+    #[serde(default)]
+    pub scoring: ScoringModel,
+    /// Team configuration when playing in team mode
+    #[serde(default)]
+    pub teams: Option<TeamConfig>,
+    /// The maximum number of players that can join the game
+    #[serde(default)]
+    pub max_players: Option<usize>,
+    /// Whether the chat is disabled for the game
+    #[serde(default)]
+    pub disable_chat: bool,
+    /// Whether players joining a full game are added as spectators
+    /// instead of being rejected
+    #[serde(default)]
+    pub overflow_spectate: bool,
+    /// The mode the game is played in
+    #[serde(default)]
+    pub mode: GameMode,
+}
+
+/// Modes that change how a game is played
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
+pub enum GameMode {
+    /// Every player plays every question
+    #[default]
+    Classic,
+    /// Players are eliminated as they fail questions and the
+    /// last player standing wins
+    Elimination { rule: EliminationRule },
+    /// Only the first correct answer scores full points, each later
+    /// correct answer scores the decay fraction of the one before it
+    Buzzer {
+        #[serde(default = "default_buzzer_decay")]
+        decay: f32,
+    },
+}
+
+fn default_buzzer_decay() -> f32 {
+    0.5
+}
+
+/// Rules deciding which players are eliminated after each question
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub enum EliminationRule {
+    /// Players that don't answer correctly are eliminated
+    WrongAnswer,
+    /// The lowest scoring players are eliminated
+    Bottom { count: usize },
+}
+
+/// Configuration for playing a game in teams where the scores
+/// of each team member are combined
+#[derive(Clone, Serialize, Deserialize)]
+pub struct TeamConfig {
+    /// The number of teams
+    pub count: usize,
+    /// Display names for each team, teams are numbered when empty
+    #[serde(default)]
+    pub names: Vec<String>,
+    /// How players are placed into teams
+    #[serde(default)]
+    pub selection: TeamSelection,
+}
+
+/// Methods for placing players into teams
+#[derive(Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TeamSelection {
+    /// Players are placed into the smallest team when joining
+    #[default]
+    Auto,
+    /// Players choose their own team in the lobby, any players
+    /// without a team are placed when the game starts
+    SelfSelect,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct GameTiming {
+    /// The time to wait before displaying each question
+    pub wait_time: u64,
+    /// The time that a bonus score will be granted within
+    /// bonus score is disabled if none
+    pub bonus_score_time: u64,
+    /// The time to count down before displaying each question,
+    /// there is no countdown when zero
+    #[serde(default)]
+    pub countdown_time: u64,
+    /// The time the question is shown before answers are opened,
+    /// answers open immediately when zero
+    #[serde(default)]
+    pub reading_time: u64,
+}
+
+impl GameTiming {
+    /// Creates a copy of this timing with the provided overrides applied
+    ///
+    /// `value` The overrides to apply
+    pub fn with_override(&self, value: &TimingOverride) -> GameTiming {
+        GameTiming {
+            wait_time: value.wait_time.unwrap_or(self.wait_time),
+            bonus_score_time: value.bonus_score_time.unwrap_or(self.bonus_score_time),
+            countdown_time: value.countdown_time.unwrap_or(self.countdown_time),
+            reading_time: value.reading_time.unwrap_or(self.reading_time),
+        }
+    }
+}
+
+/// Type for the ID of an image uploaded for the game
+pub type ImageRef = Uuid;
+
+/// Question from the game config including the answer key, questions
+/// are only sent to clients through the [`PlayerQuestion`] projection
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Question {
+    /// The title of the question
+    title: String,
+    /// The text of the question
+    text: String,
+
+    /// Optional image
+    image: Option<ImageRef>,
+
+    /// The content of the question
+    ty: QuestionType,
+    /// The time given to answer the question
+    answer_time: u64,
+    /// Multiplier applied to the score for this question
+    #[serde(default = "default_multiplier")]
+    multiplier: f32,
+    /// Overrides for the game timing that apply to this question
+    #[serde(default, rename = "timing")]
+    timing_override: TimingOverride,
+    /// Explanation of the answer revealed after the question is marked
+    #[serde(default)]
+    explanation: Option<String>,
+    /// Modifier changing how the question is scored
+    #[serde(default)]
+    point_modifier: PointModifier,
+    /// Category used when picking questions from a question bank
+    #[serde(default)]
+    category: Option<String>,
+}
+
+/// Modifiers changing how a question is scored
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PointModifier {
+    /// The question is scored normally
+    #[default]
+    Normal,
+    /// The score for the question is doubled
+    Double,
+    /// Players wager a portion of their score before the question is
+    /// shown, correct answers win the wager and anything else loses it
+    Wager,
+}
+
+/// The parts of a question that are safe to send to clients before
+/// the question is marked, the answer key is kept within the game
+#[derive(Serialize, Clone)]
+pub struct PlayerQuestion {
+    /// The title of the question
+    title: String,
+    /// The text of the question
+    text: String,
+    /// Optional image
+    image: Option<ImageRef>,
+    /// The content of the question
+    ty: PlayerQuestionType,
+    /// The time given to answer the question
+    answer_time: u64,
+    /// Multiplier applied to the score for this question
+    multiplier: f32,
+    /// Overrides for the game timing that apply to this question
+    #[serde(rename = "timing")]
+    timing_override: TimingOverride,
+    /// Modifier changing how the question is scored
+    point_modifier: PointModifier,
+}
+
+/// The content of a question without the answer key
+#[derive(Serialize, Clone)]
+pub enum PlayerQuestionType {
+    Single { values: Vec<String> },
+    MultiSelect { values: Vec<String> },
+    TextEntry {},
+    TrueFalse {},
+    ClickableImage { image: ImageRef },
+    Ordering { values: Vec<String> },
+    Numeric {},
+    Poll { values: Vec<String> },
+    OpenEnded {},
+    Hotspot { image: ImageRef },
+}
+
+fn default_multiplier() -> f32 {
+    1.0
+}
+
+/// Optional overrides for the game timing for a specific question
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct TimingOverride {
+    /// Override for the time to wait before displaying the question
+    pub wait_time: Option<u64>,
+    /// Override for the time that a bonus score will be granted within
+    pub bonus_score_time: Option<u64>,
+    /// Override for the time to count down before displaying the question
+    pub countdown_time: Option<u64>,
+    /// Override for the time the question is shown before answers open
+    pub reading_time: Option<u64>,
+}
+
+/// Answer from a player along with the time it arrived at the game
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PlayerAnswer {
+    /// The answer itself
+    pub answer: QuestionAnswer,
+    /// Time since answering opened that the answer arrived
+    pub elapsed: Duration,
+    /// The order the answer arrived at the game in
+    pub arrival: usize,
+    /// Whether the answer arrived sooner than a person could answer
+    #[serde(default)]
+    pub flagged: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub enum QuestionAnswer {
+    Single { answer: usize },
+    MultiSelect { answers: Vec<usize> },
+    ClickableImage { answer: (f32, f32) },
+    TrueFalse { answer: bool },
+    TextEntry { answer: String },
+    Ordering { order: Vec<usize> },
+    Numeric { answer: f64 },
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub enum AnswerResult {
+    // Answer was 100% correct
+    Correct(u32),
+    // Answer was incorrect
+    Incorrect,
+    // Multiple choice has some asnwers right
+    Partial(u32),
+    // Text answer was accepted but didn't exactly match
+    Close(u32),
+    // Answer to a poll which has no correct answer
+    Participated(u32),
+}
+impl AnswerResult {
+    pub fn score(&self) -> u32 {
+        match self {
+            Self::Correct(value) => *value,
+            Self::Incorrect => 0,
+            Self::Partial(value) => *value,
+            Self::Close(value) => *value,
+            Self::Participated(value) => *value,
+        }
+    }
+
+    /// Whether the answer was accepted as correct
+    pub fn is_correct(&self) -> bool {
+        matches!(self, Self::Correct(_) | Self::Close(_))
+    }
+
+    /// Maps the score of the result using the provided function
+    ///
+    /// `f` The mapping function
+    pub fn map_score(self, f: impl FnOnce(u32) -> u32) -> Self {
+        match self {
+            Self::Correct(value) => Self::Correct(f(value)),
+            Self::Incorrect => Self::Incorrect,
+            Self::Partial(value) => Self::Partial(f(value)),
+            Self::Close(value) => Self::Close(f(value)),
+            Self::Participated(value) => Self::Participated(f(value)),
+        }
+    }
+}
+
+/// The names of the question types supported by the server
+pub const QUESTION_TYPES: &[&str] = &[
+    "Single",
+    "MultiSelect",
+    "TextEntry",
+    "TrueFalse",
+    "ClickableImage",
+    "Ordering",
+    "Poll",
+    "OpenEnded",
+    "Hotspot",
+    "Numeric",
+];
+
+#[derive(Serialize, Deserialize, Clone)]
+pub enum QuestionType {
+    /// Single choice question
+    Single {
+        /// Vec of indexes of correct answers
+        answers: Vec<usize>,
+        /// Vec of the possible answers
+        values: Vec<String>,
+    },
+    /// Question where any number of the answers can be selected with
+    /// partial credit awarded for partially correct selections
+    MultiSelect {
+        /// Vec of indexes of correct answers
+        answers: Vec<usize>,
+        /// Vec of the possible answers
+        values: Vec<String>,
+    },
+    /// Question where the answer is typed in and compared against
+    /// the accepted answers
+    TextEntry {
+        /// The accepted answers
+        answers: Vec<String>,
+        /// Options for how answers are compared
+        options: TextMatchOptions,
+    },
+    /// Question where the answer is either true or false
+    TrueFalse {
+        /// The correct answer
+        answer: bool,
+    },
+    /// Image where you must click an area
+    ClickableImage {
+        /// The image url to take clicking on
+        image: ImageRef,
+        /// Top left box coordinate
+        top: (f32, f32),
+        /// Bottom right box coordinate
+        bottom: (f32, f32),
+    },
+    /// Question where the values must be arranged into the correct order
+    Ordering {
+        /// Vec of indexes of the values in the correct order
+        order: Vec<usize>,
+        /// Vec of the values to order
+        values: Vec<String>,
+        /// Whether partially correct orders earn partial credit
+        #[serde(default)]
+        partial_credit: bool,
+    },
+    /// Question without a correct answer that collects opinions, the
+    /// distribution of responses is shown once the question ends
+    Poll {
+        /// Vec of the possible responses
+        values: Vec<String>,
+        /// Points awarded to everyone that responds
+        #[serde(default)]
+        participation: u32,
+    },
+    /// Question where players submit free text that is graded by the host
+    OpenEnded {},
+    /// Image where the click must land within any of the accepted regions
+    Hotspot {
+        /// The image to click on
+        image: ImageRef,
+        /// The accepted regions
+        regions: Vec<Region>,
+    },
+    /// Question where the answer is a number compared against the
+    /// expected value within a tolerance
+    Numeric {
+        /// The expected value
+        value: f64,
+        /// The tolerance allowed for the full score
+        #[serde(default)]
+        tolerance: Tolerance,
+        /// Whether answers outside the tolerance earn points by closeness
+        #[serde(default)]
+        proportional: bool,
+    },
+}
+
+/// Correct answer to a question revealed after the question is marked
+#[derive(Serialize, Clone)]
+#[serde(tag = "ty")]
+pub enum RevealedAnswer {
+    /// Indexes of the correct values
+    Values { answers: Vec<usize> },
+    /// The accepted text answers
+    Text { answers: Vec<String> },
+    /// The correct true or false answer
+    TrueFalse { answer: bool },
+    /// The correct area of the image
+    Area { top: (f32, f32), bottom: (f32, f32) },
+    /// Indexes of the values in the correct order
+    Order { order: Vec<usize> },
+    /// The expected number and the tolerance allowed
+    Number { value: f64, tolerance: Tolerance },
+    /// The answers were graded by the host
+    Graded,
+    /// The accepted regions of the image
+    Regions { regions: Vec<Region> },
+}
+
+impl RevealedAnswer {
+    /// Maps the original value indexes to their positions in the
+    /// shuffled order a player was shown
+    ///
+    /// `order` The original index of the value for each position
+    fn with_order(&self, order: &[usize]) -> Self {
+        match self {
+            Self::Values { answers } => Self::Values {
+                answers: answers
+                    .iter()
+                    .filter_map(|answer| order.iter().position(|index| index == answer))
+                    .collect(),
+            },
+            Self::Order { order: answers } => Self::Order {
+                order: answers
+                    .iter()
+                    .filter_map(|answer| order.iter().position(|index| index == answer))
+                    .collect(),
+            },
+            value => value.clone(),
+        }
+    }
+}
+
+/// Final summary of a game sent to all clients when the game finishes
+#[derive(Serialize, Clone)]
+pub struct GameSummary {
+    /// The top scoring players from highest to lowest
+    pub podium: Vec<PodiumEntry>,
+    /// The player that scored the most more in the second half
+    /// of the game than the first half
+    pub most_improved: Option<SessionId>,
+    /// The player with the fastest average answer time
+    pub fastest: Option<SessionId>,
+    /// The fraction of questions each player answered correctly
+    pub accuracy: HashMap<SessionId, f32>,
+    /// The average time in milliseconds each player took to answer
+    pub average_times: HashMap<SessionId, u64>,
+}
+
+impl GameSummary {
+    /// Creates a copy of the summary with the player names hidden
+    fn anonymized(&self) -> Self {
+        let mut summary = self.clone();
+        for entry in &mut summary.podium {
+            entry.name = ANONYMOUS_NAME.to_string();
+            entry.appearance = Appearance::default();
+        }
+        summary
+    }
+}
+
+/// Player placement on the final podium
+#[derive(Serialize, Clone)]
+pub struct PodiumEntry {
+    pub id: SessionId,
+    pub name: String,
+    pub score: u32,
+    /// The avatar and color chosen by the player
+    pub appearance: Appearance,
+}
+
+/// Reactions players can send live during the game
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Reaction {
+    ThumbsUp,
+    Laugh,
+    Surprised,
+    Sad,
+    Fire,
+    Clap,
+}
+
+/// Overview of a running game shown to admins
+#[derive(Serialize)]
+pub struct GameInfo {
+    /// Unique ID of the game
+    pub id: Uuid,
+    /// The token of the game
+    pub token: String,
+    /// The ID of the quiz the game was created from
+    pub quiz: GameId,
+    /// The name of the quiz
+    pub name: String,
+    /// The current game state
+    pub state: GameState,
+    /// The number of players in the game
+    pub players: usize,
+    /// The number of players that are connected
+    pub connected: usize,
+    /// The number of spectators watching the game
+    pub spectators: usize,
+    /// The number of players waiting for approval
+    pub pending: usize,
+    /// The index of the current question
+    pub question_index: usize,
+    /// The total number of questions
+    pub questions: usize,
+    /// Whether the host is connected
+    pub host_connected: bool,
+    /// Unix timestamp in seconds the game was created at
+    pub started_at: u64,
+}
+
+/// Details of a running game shown to admins
+#[derive(Serialize)]
+pub struct GameDetails {
+    /// Overview of the game
+    pub game: GameInfo,
+    /// The players in the game
+    pub players: Vec<PlayerDetails>,
+    /// The host actions and suspicious events recorded so far
+    pub audit: Vec<AuditEntry>,
+    /// Whether entries were dropped from the audit log
+    pub audit_truncated: bool,
+}
+
+/// Details about a player shown to admins and the host
+#[derive(Serialize, Clone)]
+pub struct PlayerDetails {
+    pub id: SessionId,
+    pub name: String,
+    /// The player total score
+    pub score: u32,
+    /// Whether the player is connected
+    pub connected: bool,
+    /// The round trip time to the player in milliseconds if measured
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rtt: Option<u64>,
+    /// The number of answers flagged as arriving sooner than
+    /// a person could answer
+    pub flagged: usize,
+}
+
+/// Converts the system time to unix seconds
+///
+/// `time` The time to convert
+fn unix_secs(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|value| value.as_secs())
+        .unwrap_or_default()
+}
+
+/// Snapshot of a game in progress sent to players that join late
+/// and sessions that request a resync
+#[derive(Serialize, Clone)]
+pub struct StateSnapshot {
+    /// The current game state
+    pub state: GameState,
+    /// The index of the current question
+    pub question_index: usize,
+    /// The current question if one is being asked
+    pub question: Option<PlayerQuestion>,
+    /// The time in milliseconds that has passed on the current timer
+    pub elapsed: u64,
+    /// The total time in milliseconds of the current timer
+    pub total: u64,
+    /// The players in the game
+    pub players: Vec<PlayerSnapshot>,
+}
+
+/// Details about a player within a state snapshot
+#[derive(Serialize, Clone)]
+pub struct PlayerSnapshot {
+    pub id: SessionId,
+    pub name: String,
+    /// The index of the team the player is in
+    pub team: Option<usize>,
+    /// The player total score, hidden from other players
+    /// in anonymous games
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub score: Option<u32>,
+    /// The avatar and color chosen by the player
+    pub appearance: Appearance,
+}
+
+/// Breakdown of the answers given to a question sent to the host
+/// after the question is marked
+#[derive(Serialize, Clone)]
+pub struct QuestionStats {
+    /// The index of the question
+    pub index: usize,
+    /// The number of players that answered
+    pub answered: usize,
+    /// The total number of players
+    pub total: usize,
+    /// The number of players that answered correctly
+    pub correct: usize,
+    /// The average time in milliseconds players took to answer
+    pub average_time: u64,
+    /// The number of times each option was selected, for true or
+    /// false questions this is the false count then the true count
+    pub counts: Vec<usize>,
+    /// The number of times each normalized text answer was given
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    pub texts: HashMap<String, usize>,
+}
+
+/// The number of players shown on the final podium
+const PODIUM_SIZE: usize = 3;
+
+/// The largest answer time multiplier the host can grant a player
+const MAX_TIME_MULTIPLIER: f32 = 4.0;
+
+/// The maximum number of chars in a chat message
+const MAX_CHAT_LENGTH: usize = 200;
+/// The number of chat messages a player can send within the chat window
+const CHAT_LIMIT: u32 = 5;
+/// The window chat messages are limited within
+const CHAT_WINDOW: Duration = Duration::from_secs(10);
+/// The name chat messages from the host are sent with
+const HOST_CHAT_NAME: &str = "Host";
+/// The name shown in place of player names in anonymous games
+const ANONYMOUS_NAME: &str = "Anonymous";
+
+/// Interval between each broadcast of the collected reactions
+const REACTION_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The number of random names tried before numbering the name
+const NAME_ATTEMPTS: usize = 8;
+
+/// Interval between each broadcast of the changed scores
+const SCORE_INTERVAL: Duration = Duration::from_millis(250);
+
+/// The most time removed from an answer time to compensate for latency,
+/// stops inflated round trip times from giving players a head start
+const MAX_LATENCY_COMPENSATION: Duration = Duration::from_millis(250);
+
+/// Interval between each answer progress update sent to the host
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Interval between each check for whether the game was abandoned
+const REAPER_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The maximum time in milliseconds allowed for timing values
+const MAX_TIME: u64 = 10 * 60 * 1000;
+
+/// The maximum number of teams allowed in team mode
+const MAX_TEAMS: usize = 16;
+
+/// The maximum length of a join PIN
+const MAX_PIN_LENGTH: usize = 12;
+
+impl GameConfig {
+    /// Validates the game configuration collecting a list of all the
+    /// invalid fields
+    pub fn validate(&self) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+
+        if self.basic.name.trim().is_empty() {
+            errors.push(FieldError::new("basic.name", FieldErrorReason::Empty));
+        }
+
+        if !self.basic.scoring.is_valid() {
+            errors.push(FieldError::new(
+                "basic.scoring",
+                FieldErrorReason::InvalidScoring,
+            ));
+        }
 
-            GameRequest::Start => {
-                self.set_state(GameState::Starting);
-                // Begin the start time
-                self.starting_task(ctx);
-                Ok(GameResponse::None)
+        if let Some(teams) = &self.basic.teams {
+            let valid_names = teams.names.is_empty() || teams.names.len() == teams.count;
+            if !(2..=MAX_TEAMS).contains(&teams.count) || !valid_names {
+                errors.push(FieldError::new(
+                    "basic.teams",
+                    FieldErrorReason::InvalidTeams,
+                ));
             }
+        }
 
-            GameRequest::Cancel => {
-                self.cancel_task(ctx);
-                self.set_state(GameState::Lobby);
-                // TODO: Reset all other state
-                Ok(GameResponse::None)
-            }
+        if self
+            .token
+            .as_ref()
+            .is_some_and(|token| normalize_vanity(token).is_none())
+        {
+            errors.push(FieldError::new("token", FieldErrorReason::InvalidToken));
+        }
 
-            // Not yet implemented
-            GameRequest::SkipTimer => {
-                self.immediate_task(ctx);
+        if self
+            .anti_cheat
+            .as_ref()
+            .is_some_and(|anti_cheat| anti_cheat.min_answer_time > MAX_TIME)
+        {
+            errors.push(FieldError::new(
+                "anti_cheat.min_answer_time",
+                FieldErrorReason::InvalidValue,
+            ));
+        }
 
-                // Reset the timer future
-                Ok(GameResponse::None)
+        if self
+            .pin
+            .as_ref()
+            .is_some_and(|pin| pin.is_empty() || pin.len() > MAX_PIN_LENGTH)
+        {
+            errors.push(FieldError::new("pin", FieldErrorReason::InvalidValue));
+        }
+
+        if let GameMode::Elimination {
+            rule: EliminationRule::Bottom { count: 0 },
+        } = self.basic.mode
+        {
+            errors.push(FieldError::new(
+                "basic.mode",
+                FieldErrorReason::InvalidLimit,
+            ));
+        }
+
+        if let GameMode::Buzzer { decay } = self.basic.mode {
+            if !(0.0..=1.0).contains(&decay) {
+                errors.push(FieldError::new(
+                    "basic.mode",
+                    FieldErrorReason::InvalidScoring,
+                ));
             }
-            GameRequest::Ready { id } => {
-                // Whether all players are ready
-                let mut all_ready = true;
-                let mut found_player = false;
-                for player in &mut self.players {
-                    if player.id == id {
-                        player.ready = true;
-                        found_player = true;
-                    } else if !player.ready {
-                        all_ready = false;
-                    }
-                }
+        }
 
-                if !found_player {
-                    return Err(ServerError::UnknownPlayer);
-                }
+        if self.basic.max_players == Some(0) {
+            errors.push(FieldError::new(
+                "basic.max_players",
+                FieldErrorReason::InvalidLimit,
+            ));
+        }
 
-                if all_ready {
-                    self.ready_question(ctx);
-                }
+        if self.timing.wait_time > MAX_TIME {
+            errors.push(FieldError::new(
+                "timing.wait_time",
+                FieldErrorReason::InvalidTiming,
+            ));
+        }
 
-                Ok(GameResponse::None)
+        if self.timing.bonus_score_time > MAX_TIME {
+            errors.push(FieldError::new(
+                "timing.bonus_score_time",
+                FieldErrorReason::InvalidTiming,
+            ));
+        }
+
+        if self.timing.countdown_time > MAX_TIME {
+            errors.push(FieldError::new(
+                "timing.countdown_time",
+                FieldErrorReason::InvalidTiming,
+            ));
+        }
+
+        if self.timing.reading_time > MAX_TIME {
+            errors.push(FieldError::new(
+                "timing.reading_time",
+                FieldErrorReason::InvalidTiming,
+            ));
+        }
+
+        if self.questions.is_empty() {
+            errors.push(FieldError::new("questions", FieldErrorReason::Empty));
+        }
+
+        let valid_pick = match &self.pick {
+            None => true,
+            Some(Pick::Total(count)) => (1..=self.questions.len()).contains(count),
+            Some(Pick::Categories(counts)) => {
+                !counts.is_empty()
+                    && counts.iter().all(|(category, count)| {
+                        let available = self
+                            .questions
+                            .iter()
+                            .filter(|question| question.category.as_ref() == Some(category))
+                            .count();
+                        (1..=available).contains(count)
+                    })
             }
+        };
+        if !valid_pick {
+            errors.push(FieldError::new("pick", FieldErrorReason::InvalidLimit));
         }
+
+        for (index, question) in self.questions.iter().enumerate() {
+            question.validate(&format!("questions[{}]", index), &mut errors);
+        }
+
+        errors
     }
 }
 
-/// Trait implemented by the sessions that are connected to
-/// the game for logic to share between both
-pub trait GameSession {
-    fn id(&self) -> SessionId;
+impl Question {
+    /// Creates a new question with the default scoring and timing
+    ///
+    /// `title`       The title of the question
+    /// `text`        The text of the question
+    /// `ty`          The content of the question
+    /// `answer_time` The time given to answer the question
+    /// `explanation` Explanation revealed after the question is marked
+    pub fn new(
+        title: String,
+        text: String,
+        ty: QuestionType,
+        answer_time: u64,
+        explanation: Option<String>,
+    ) -> Self {
+        Self {
+            title,
+            text,
+            image: None,
+            ty,
+            answer_time,
+            multiplier: default_multiplier(),
+            timing_override: TimingOverride::default(),
+            explanation,
+            point_modifier: PointModifier::default(),
+            category: None,
+        }
+    }
+
+    /// Validates the question appending any errors to the provided
+    /// list of errors
+    ///
+    /// `path`   The path to this question for error field names
+    /// `errors` The list of errors to append to
+    pub fn validate(&self, path: &str, errors: &mut Vec<FieldError>) {
+        let field = |name: &str| format!("{}.{}", path, name);
 
-    fn addr(&self) -> &Addr<Session>;
+        if self.title.trim().is_empty() {
+            errors.push(FieldError::new(field("title"), FieldErrorReason::Empty));
+        }
 
-    fn send(&self, message: ServerMessage) {
-        self.addr().do_send(SessionRequest::Message(message));
+        if self.answer_time == 0 || self.answer_time > MAX_TIME {
+            errors.push(FieldError::new(
+                field("answer_time"),
+                FieldErrorReason::InvalidTiming,
+            ));
+        }
+
+        let overrides = [
+            ("timing.wait_time", self.timing_override.wait_time),
+            (
+                "timing.bonus_score_time",
+                self.timing_override.bonus_score_time,
+            ),
+            ("timing.countdown_time", self.timing_override.countdown_time),
+            ("timing.reading_time", self.timing_override.reading_time),
+        ];
+        for (name, value) in overrides {
+            if value.is_some_and(|value| value > MAX_TIME) {
+                errors.push(FieldError::new(
+                    field(name),
+                    FieldErrorReason::InvalidTiming,
+                ));
+            }
+        }
+
+        if !self.multiplier.is_finite() || self.multiplier < 0.0 {
+            errors.push(FieldError::new(
+                field("multiplier"),
+                FieldErrorReason::InvalidScoring,
+            ));
+        }
+
+        match &self.ty {
+            QuestionType::Single { answers, values }
+            | QuestionType::MultiSelect { answers, values } => {
+                if values.is_empty() {
+                    errors.push(FieldError::new(field("ty.values"), FieldErrorReason::Empty));
+                }
+                if answers.is_empty() {
+                    errors.push(FieldError::new(
+                        field("ty.answers"),
+                        FieldErrorReason::NoCorrectAnswer,
+                    ));
+                }
+                if answers.iter().any(|answer| *answer >= values.len()) {
+                    errors.push(FieldError::new(
+                        field("ty.answers"),
+                        FieldErrorReason::InvalidAnswerIndex,
+                    ));
+                }
+            }
+            QuestionType::TextEntry { answers, .. } => {
+                if answers.iter().all(|answer| answer.trim().is_empty()) {
+                    errors.push(FieldError::new(
+                        field("ty.answers"),
+                        FieldErrorReason::NoCorrectAnswer,
+                    ));
+                }
+            }
+            QuestionType::ClickableImage { top, bottom, .. } => {
+                if top.0 > bottom.0 || top.1 > bottom.1 {
+                    errors.push(FieldError::new(
+                        field("ty.bottom"),
+                        FieldErrorReason::InvalidArea,
+                    ));
+                }
+            }
+            QuestionType::Ordering { order, values, .. } => {
+                if values.is_empty() {
+                    errors.push(FieldError::new(field("ty.values"), FieldErrorReason::Empty));
+                }
+                if !is_permutation(order, values.len()) {
+                    errors.push(FieldError::new(
+                        field("ty.order"),
+                        FieldErrorReason::InvalidAnswerIndex,
+                    ));
+                }
+            }
+            QuestionType::Numeric {
+                value, tolerance, ..
+            } => {
+                if !value.is_finite() || !tolerance.is_valid() {
+                    errors.push(FieldError::new(
+                        field("ty.tolerance"),
+                        FieldErrorReason::InvalidTolerance,
+                    ));
+                }
+            }
+            QuestionType::Poll { values, .. } => {
+                if values.is_empty() {
+                    errors.push(FieldError::new(field("ty.values"), FieldErrorReason::Empty));
+                }
+                // Polls have no correct answer to win a wager with
+                if self.point_modifier == PointModifier::Wager {
+                    errors.push(FieldError::new(
+                        field("point_modifier"),
+                        FieldErrorReason::InvalidScoring,
+                    ));
+                }
+            }
+            QuestionType::Hotspot { regions, .. } => {
+                if regions.is_empty() {
+                    errors.push(FieldError::new(
+                        field("ty.regions"),
+                        FieldErrorReason::NoCorrectAnswer,
+                    ));
+                }
+                if !regions.iter().all(Region::is_valid) {
+                    errors.push(FieldError::new(
+                        field("ty.regions"),
+                        FieldErrorReason::InvalidArea,
+                    ));
+                }
+            }
+            QuestionType::TrueFalse { .. } | QuestionType::OpenEnded {} => {}
+        }
     }
-}
 
-pub struct HostSession {
-    /// The ID of the session
-    id: SessionId,
-    /// Address to the session
-    addr: Addr<Session>,
-}
+    pub fn ty(&self) -> &QuestionType {
+        &self.ty
+    }
 
-impl GameSession for HostSession {
-    fn id(&self) -> SessionId {
-        self.id
+    pub fn multiplier(&self) -> f32 {
+        self.multiplier
     }
 
-    fn addr(&self) -> &Addr<Session> {
-        &self.addr
+    pub fn point_modifier(&self) -> PointModifier {
+        self.point_modifier
     }
-}
 
-pub struct PlayerSession {
-    /// The ID of the session
-    id: SessionId,
-    /// The player name
-    name: String,
-    /// Address to the session
-    addr: Addr<Session>,
-    /// The player ready state
-    ready: bool,
-    /// The players answers and the score they got for them
-    answers: Vec<QuestionAnswer>,
-    /// Marked version of each question answer
-    results: Vec<AnswerResult>,
-    /// The player total score
-    score: u32,
-}
+    /// Returns the time given to answer the question
+    pub fn answer_time(&self) -> Duration {
+        Duration::from_millis(self.answer_time)
+    }
 
-impl GameSession for PlayerSession {
-    fn id(&self) -> SessionId {
-        self.id
+    /// Whether the answers to the question are graded by the host
+    pub fn is_open_ended(&self) -> bool {
+        matches!(self.ty, QuestionType::OpenEnded {})
     }
 
-    fn addr(&self) -> &Addr<Session> {
-        &self.addr
+    /// Resolves the timing for this question by applying its
+    /// overrides to the game timing
+    ///
+    /// `timing` The timing of the game
+    pub fn timing(&self, timing: &GameTiming) -> GameTiming {
+        timing.with_override(&self.timing_override)
     }
-}
 
-/// Configuration data for a game
-pub struct GameConfig {
-    /// Basic configuration such as name and subtext
-    pub basic: BasicConfig,
-    /// Timing data for different game events
-    pub timing: GameTiming,
-    /// The game questions
-    pub questions: Vec<Question>,
-}
+    /// Returns the IDs of the images the question uses
+    pub fn images(&self) -> Vec<ImageRef> {
+        let mut images: Vec<ImageRef> = self.image.into_iter().collect();
+        match &self.ty {
+            QuestionType::ClickableImage { image, .. } | QuestionType::Hotspot { image, .. } => {
+                images.push(*image)
+            }
+            _ => {}
+        }
+        images
+    }
 
-#[derive(Clone, Serialize, Deserialize)]
-pub struct BasicConfig {
-    pub name: String,
-    pub text: String,
-}
+    /// Creates the projection of the question that is sent to clients
+    pub fn project(&self) -> PlayerQuestion {
+        let ty = match &self.ty {
+            QuestionType::Single { values, .. } => PlayerQuestionType::Single {
+                values: values.clone(),
+            },
+            QuestionType::MultiSelect { values, .. } => PlayerQuestionType::MultiSelect {
+                values: values.clone(),
+            },
+            QuestionType::TextEntry { .. } => PlayerQuestionType::TextEntry {},
+            QuestionType::TrueFalse { .. } => PlayerQuestionType::TrueFalse {},
+            QuestionType::ClickableImage { image, .. } => {
+                PlayerQuestionType::ClickableImage { image: *image }
+            }
+            QuestionType::Ordering { values, .. } => PlayerQuestionType::Ordering {
+                values: values.clone(),
+            },
+            QuestionType::Numeric { .. } => PlayerQuestionType::Numeric {},
+            QuestionType::OpenEnded {} => PlayerQuestionType::OpenEnded {},
+            QuestionType::Hotspot { image, .. } => PlayerQuestionType::Hotspot { image: *image },
+            QuestionType::Poll { values, .. } => PlayerQuestionType::Poll {
+                values: values.clone(),
+            },
+        };
 
-#[derive(Clone, Serialize)]
-pub struct Scoring {
-    /// Minimum score awarded for the longest time taken
-    pub min_score: u32,
-    /// Maximum score awarded for the shortest time taken
-    pub max_score: u32,
-    /// The amount awarded if scored within the bonus time
-    pub bonus_score: u32,
-}
+        PlayerQuestion {
+            title: self.title.clone(),
+            text: self.text.clone(),
+            image: self.image,
+            ty,
+            answer_time: self.answer_time,
+            multiplier: self.multiplier,
+            timing_override: self.timing_override.clone(),
+            point_modifier: self.point_modifier,
+        }
+    }
 
-#[derive(Clone, Serialize, Deserialize)]
-pub struct GameTiming {
-    /// The time to wait before displaying each question
-    pub wait_time: u64,
-    /// The time that a bonus score will be granted within
-    /// bonus score is disabled if none
-    pub bonus_score_time: u64,
+    /// Creates the projection of the question with the answer values in
+    /// the provided order
+    ///
+    /// `order` The original index of the value for each position
+    fn with_order(&self, order: &[usize]) -> PlayerQuestion {
+        let mut question = self.project();
+        if let PlayerQuestionType::Single { values }
+        | PlayerQuestionType::MultiSelect { values }
+        | PlayerQuestionType::Ordering { values } = &mut question.ty
+        {
+            *values = order
+                .iter()
+                .filter_map(|index| self.ty.values()?.get(*index).cloned())
+                .collect();
+        }
+        question
+    }
 }
 
-/// Type for a string which represents a reference to a tmp stored image
-pub type ImageRef = String;
+/// The maximum number of chars allowed in a text answer
+const MAX_TEXT_ANSWER_LENGTH: usize = 256;
 
-#[derive(Clone, Serialize)]
-pub struct Question {
-    /// The title of the question
-    title: String,
-    /// The text of the question
-    text: String,
+impl QuestionType {
+    /// Returns the answer values for question types that choose from a
+    /// list of values
+    fn values(&self) -> Option<&Vec<String>> {
+        match self {
+            QuestionType::Single { values, .. }
+            | QuestionType::MultiSelect { values, .. }
+            | QuestionType::Ordering { values, .. } => Some(values),
+            _ => None,
+        }
+    }
 
-    /// Optional image
-    image: Option<ImageRef>,
+    /// Returns the number of options for question types that choose
+    /// from a list of values
+    fn option_count(&self) -> Option<usize> {
+        self.values().map(Vec::len)
+    }
 
-    /// The content of the question
-    ty: QuestionType,
-    /// The time given to answer the question
-    answer_time: u64,
-    /// The point scoring for the question
-    scoring: Scoring,
-}
+    /// Marks the provided answer against this question returning
+    /// None if the answer is for a different type of question
+    ///
+    /// `answer`     The answer to mark
+    /// `base_score` The score for a correct answer
+    pub fn mark(&self, answer: &QuestionAnswer, base_score: u32) -> Option<AnswerResult> {
+        grading::mark(self, answer, base_score)
+    }
 
-#[derive(Deserialize)]
-pub enum QuestionAnswer {
-    Single { answer: usize },
-    Multiple { answers: Vec<usize> },
-    ClickableImage { answer: (f32, f32) },
-}
+    /// Whether the question is a poll without a correct answer
+    pub fn is_poll(&self) -> bool {
+        matches!(self, Self::Poll { .. })
+    }
 
-#[derive(Serialize, Clone)]
-pub enum AnswerResult {
-    // Answer was 100% correct
-    Correct(u32),
-    // Answer was incorrect
-    Incorrect,
-    // Multiple choice has some asnwers right
-    Partial(u32),
-}
-impl AnswerResult {
-    pub fn score(&self) -> u32 {
+    /// Returns the correct answer to reveal once the question is marked
+    fn reveal(&self) -> RevealedAnswer {
         match self {
-            Self::Correct(value) => *value,
-            Self::Incorrect => 0,
-            Self::Partial(value) => *value,
+            Self::Single { answers, .. } | Self::MultiSelect { answers, .. } => {
+                RevealedAnswer::Values {
+                    answers: answers.clone(),
+                }
+            }
+            // Polls have no correct values
+            Self::Poll { .. } => RevealedAnswer::Values {
+                answers: Vec::new(),
+            },
+            Self::OpenEnded {} => RevealedAnswer::Graded,
+            Self::Hotspot { regions, .. } => RevealedAnswer::Regions {
+                regions: regions.clone(),
+            },
+            Self::TextEntry { answers, .. } => RevealedAnswer::Text {
+                answers: answers.clone(),
+            },
+            Self::TrueFalse { answer } => RevealedAnswer::TrueFalse { answer: *answer },
+            Self::ClickableImage { top, bottom, .. } => RevealedAnswer::Area {
+                top: *top,
+                bottom: *bottom,
+            },
+            Self::Ordering { order, .. } => RevealedAnswer::Order {
+                order: order.clone(),
+            },
+            Self::Numeric {
+                value, tolerance, ..
+            } => RevealedAnswer::Number {
+                value: *value,
+                tolerance: *tolerance,
+            },
+        }
+    }
+
+    /// Checks whether the provided answer is a valid answer for this
+    /// type of question
+    ///
+    /// `answer` The answer to check
+    pub fn is_valid_answer(&self, answer: &QuestionAnswer) -> bool {
+        match (self, answer) {
+            (Self::Single { values, .. }, QuestionAnswer::Single { answer }) => {
+                *answer < values.len()
+            }
+            (Self::MultiSelect { values, .. }, QuestionAnswer::MultiSelect { answers }) => {
+                answers.iter().all(|answer| *answer < values.len())
+                    && answers
+                        .iter()
+                        .enumerate()
+                        .all(|(index, answer)| !answers[..index].contains(answer))
+            }
+            (Self::ClickableImage { .. }, QuestionAnswer::ClickableImage { answer }) => {
+                answer.0.is_finite() && answer.1.is_finite()
+            }
+            (Self::TrueFalse { .. }, QuestionAnswer::TrueFalse { .. }) => true,
+            (Self::TextEntry { .. }, QuestionAnswer::TextEntry { answer }) => {
+                answer.chars().count() <= MAX_TEXT_ANSWER_LENGTH
+            }
+            (Self::Ordering { values, .. }, QuestionAnswer::Ordering { order }) => {
+                is_permutation(order, values.len())
+            }
+            (Self::Numeric { .. }, QuestionAnswer::Numeric { answer }) => answer.is_finite(),
+            (Self::Poll { values, .. }, QuestionAnswer::Single { answer }) => {
+                *answer < values.len()
+            }
+            (Self::Hotspot { .. }, QuestionAnswer::ClickableImage { answer }) => {
+                (0.0..=1.0).contains(&answer.0) && (0.0..=1.0).contains(&answer.1)
+            }
+            (Self::OpenEnded {}, QuestionAnswer::TextEntry { answer }) => {
+                answer.chars().count() <= MAX_TEXT_ANSWER_LENGTH
+            }
+            _ => false,
         }
     }
 }
 
-#[derive(Serialize, Clone)]
-pub enum QuestionType {
-    /// Single choice question
-    Single {
-        /// Vec of indexes of correct answers
-        #[serde(skip)]
-        answers: Vec<usize>,
-        /// Vec of the possible answers
-        values: Vec<String>,
-    },
-    /// Multiple choice question
-    Multiple {
-        /// Vec of indexes of correct answers
-        #[serde(skip)]
-        answers: Vec<usize>,
-        /// Vec of the possible answers
-        values: Vec<String>,
-    },
-    /// Image where you must click an area
-    ClickableImage {
-        /// The image url to take clicking on
-        image: ImageRef,
-        /// Top left box coordinate
-        #[serde(skip)]
-        top: (f32, f32),
-        /// Bottom right box coordinate
-        #[serde(skip)]
-        bottom: (f32, f32),
-    },
+/// Checks whether the provided indexes contain every index
+/// below the length exactly once
+///
+/// `order`  The indexes to check
+/// `length` The number of indexes expected
+fn is_permutation(order: &[usize], length: usize) -> bool {
+    order.len() == length
+        && order.iter().all(|index| *index < length)
+        && order
+            .iter()
+            .enumerate()
+            .all(|(position, index)| !order[..position].contains(index))
 }
 
 impl<A, M> MessageResponse<A, M> for GameResponse
@@ -705,3 +4822,128 @@ where
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use actix::dev::channel;
+    use serde_json::json;
+
+    use super::*;
+    use crate::clock::ManualClock;
+
+    const MILLI: Duration = Duration::from_millis(1);
+
+    /// Creates a game with two ten second questions whose timing is
+    /// measured against the provided clock, the receiver for the host
+    /// messages is returned so the host address stays connected
+    fn game(clock: &ManualClock) -> (Game, channel::AddressReceiver<Session>) {
+        let question = json!({
+            "title": "Question",
+            "text": "",
+            "ty": { "Single": { "answers": [0], "values": ["A", "B"] } },
+            "answer_time": 10000,
+        });
+        let config: GameConfig = serde_json::from_value(json!({
+            "basic": { "name": "Quiz", "text": "" },
+            "timing": {
+                "wait_time": 2000,
+                "bonus_score_time": 1000,
+                "countdown_time": 3000,
+                "reading_time": 1000,
+            },
+            "questions": [question.clone(), question],
+        }))
+        .unwrap();
+        let (tx, rx) = channel::channel(16);
+        let game = Game::new(
+            "ABCDE".to_string(),
+            Uuid::new_v4(),
+            0,
+            Addr::new(tx),
+            config,
+            Box::new(clock.clone()),
+        );
+        (game, rx)
+    }
+
+    /// Moves the clock forward and runs the wait the way the timer
+    /// does once it fires
+    fn elapse(game: &mut Game, ctx: &mut Context<Game>, clock: &ManualClock, duration: Duration) {
+        clock.advance(duration);
+        game.wait_elapsed(ctx);
+    }
+
+    /// Starts the game and waits until the question is accepting answers
+    fn open_question(game: &mut Game, ctx: &mut Context<Game>, clock: &ManualClock) {
+        game.set_state(GameState::Starting);
+        game.start_game(ctx);
+        elapse(game, ctx, clock, Duration::from_secs(5));
+        elapse(game, ctx, clock, Duration::from_secs(3));
+        elapse(game, ctx, clock, Duration::from_secs(2));
+        elapse(game, ctx, clock, Duration::from_secs(1));
+        assert!(matches!(game.core.state(), GameState::AwaitingAnswers));
+    }
+
+    #[actix::test]
+    async fn start_waits_for_the_clock() {
+        let clock = ManualClock::new();
+        let (mut game, _rx) = game(&clock);
+        let mut ctx = Context::new();
+
+        game.set_state(GameState::Starting);
+        game.start_game(&mut ctx);
+
+        elapse(&mut game, &mut ctx, &clock, Duration::from_secs(5) - MILLI);
+        assert!(matches!(game.core.state(), GameState::Starting));
+
+        elapse(&mut game, &mut ctx, &clock, MILLI);
+        assert!(matches!(game.core.state(), GameState::Countdown));
+    }
+
+    #[actix::test]
+    async fn answers_close_when_the_clock_reaches_the_answer_time() {
+        let clock = ManualClock::new();
+        let (mut game, _rx) = game(&clock);
+        let mut ctx = Context::new();
+        open_question(&mut game, &mut ctx, &clock);
+
+        elapse(&mut game, &mut ctx, &clock, Duration::from_secs(10) - MILLI);
+        assert!(matches!(game.core.state(), GameState::AwaitingAnswers));
+
+        elapse(&mut game, &mut ctx, &clock, MILLI);
+        assert!(matches!(game.core.state(), GameState::Marked));
+    }
+
+    #[actix::test]
+    async fn paused_time_isnt_counted_towards_the_answer_time() {
+        let clock = ManualClock::new();
+        let (mut game, _rx) = game(&clock);
+        let mut ctx = Context::new();
+        open_question(&mut game, &mut ctx, &clock);
+
+        clock.advance(Duration::from_secs(4));
+        game.pause_task(&mut ctx);
+        elapse(&mut game, &mut ctx, &clock, Duration::from_secs(60));
+        assert!(matches!(game.core.state(), GameState::AwaitingAnswers));
+
+        game.resume_task(&mut ctx);
+        elapse(&mut game, &mut ctx, &clock, Duration::from_secs(6) - MILLI);
+        assert!(matches!(game.core.state(), GameState::AwaitingAnswers));
+
+        elapse(&mut game, &mut ctx, &clock, MILLI);
+        assert!(matches!(game.core.state(), GameState::Marked));
+    }
+
+    #[actix::test]
+    async fn time_sync_follows_the_clock() {
+        let clock = ManualClock::new();
+        let (mut game, _rx) = game(&clock);
+        let mut ctx = Context::new();
+        open_question(&mut game, &mut ctx, &clock);
+
+        clock.advance(Duration::from_millis(2500));
+        let snapshot = game.snapshot(0);
+        assert_eq!(snapshot.total, 10000);
+        assert_eq!(snapshot.elapsed, 2500);
+    }
+}
@@ -1,79 +1,355 @@
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    future::Future,
+    net::IpAddr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex, OnceLock,
+    },
+    time::Duration,
+};
 
-use actix::{dev::MessageResponse, Actor, Addr, Context, Handler, Message};
-use rand_core::{OsRng, RngCore};
+use actix::{
+    dev::MessageResponse, fut, Actor, ActorFutureExt, Addr, AtomicResponse, Context, Handler,
+    Message, Supervised, SystemService, WrapFuture,
+};
+use dashmap::DashMap;
+use futures_util::future::join_all;
 
 use crate::{
+    checkpoint::{self, GameCheckpoint},
+    clock::SystemClock,
+    cluster::{directory, ClusterMessage},
+    config,
     error::ServerError,
-    game::{BasicConfig, Game, GameConfig, GameRequest, GameResponse, GameTiming},
-    session::{ServerMessage, Session, SessionId, SessionRequest},
+    game::{BasicConfig, Game, GameConfig, GameId, GameRequest, GameResponse, GameTiming},
+    rate_limit::RateLimiter,
+    session::{self, ServerMessage, Session, SessionGame, SessionId, SessionRequest},
+    telemetry::traced,
+    token::{normalize_vanity, TokenGenerator},
 };
-use log::error;
+use tracing::{error, field, info, info_span, warn};
+use uuid::Uuid;
 
-/// Central store for storing all the references to the individual
-/// games that are currently running
-pub struct Games {
+/// Concurrent registry of the running games. Sessions look up games
+/// directly through the registry so that join storms don't queue up
+/// behind the mailbox of the games service
+pub struct GameRegistry {
     /// Map of the game tokens to the actual game itself
-    games: HashMap<String, Addr<Game>>,
+    games: DashMap<String, Addr<Game>>,
 
-    /// The next ID for pre init values
-    pre_init_id: u32,
+    /// Limiter for invalid game tokens attempted by each address
+    token_guesses: Mutex<RateLimiter>,
 
-    /// Uninitialized games
-    pre_init: HashMap<u32, GameConfig>,
+    /// Whether the server is shutting down and no longer
+    /// accepting new games or players
+    shutting_down: AtomicBool,
+
+    /// Whether the server is in maintenance mode, running games
+    /// continue but new games and players aren't accepted
+    maintenance: AtomicBool,
 }
 
-impl Games {
-    /// The length of game tokens
-    const TOKEN_LENGTH: usize = 5;
-
-    /// Generates a unique token not used by any other games stored
-    /// in the games map
-    fn unique_token(&self) -> String {
-        loop {
-            let token = Self::random_token();
-            if !self.games.contains_key(&token) {
-                return token;
+static REGISTRY: OnceLock<GameRegistry> = OnceLock::new();
+
+/// Returns the global game registry creating it if it
+/// hasn't been created yet
+pub fn registry() -> &'static GameRegistry {
+    REGISTRY.get_or_init(GameRegistry::new)
+}
+
+impl Default for GameRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GameRegistry {
+    /// Creates an empty registry, the server uses the global registry
+    /// from [`registry`]
+    pub fn new() -> Self {
+        let limit = config::from_env(config::TOKEN_GUESS_LIMIT);
+        let window = Duration::from_secs(config::from_env(config::RATE_LIMIT_WINDOW));
+        Self {
+            games: DashMap::new(),
+            token_guesses: Mutex::new(RateLimiter::new(limit, window)),
+            shutting_down: AtomicBool::new(false),
+            maintenance: AtomicBool::new(false),
+        }
+    }
+
+    /// Finds a game with the provided token for a new player or spectator
+    /// to join, failed lookups are counted towards the token guess limit
+    /// of the provided address unless the game is hosted by another node
+    ///
+    /// `token` The game token
+    /// `ip`    The address the lookup is from
+    pub async fn find(&self, token: &str, ip: Option<IpAddr>) -> Result<Addr<Game>, ServerError> {
+        if self.is_shutting_down() {
+            return Err(ServerError::ShuttingDown);
+        }
+
+        if self.is_maintenance() {
+            return Err(ServerError::Maintenance);
+        }
+
+        if let Some(ip) = ip {
+            let limited = self
+                .token_guesses
+                .lock()
+                .is_ok_and(|limiter| limiter.is_limited(ip));
+            if limited {
+                return Err(ServerError::RateLimited);
+            }
+        }
+
+        match self.get(token) {
+            Some(value) => Ok(value),
+            None => {
+                if let Some(node) = directory().locate(token.to_string()).await {
+                    return Err(ServerError::WrongNode(node));
+                }
+
+                if let Some(ip) = ip {
+                    if let Ok(mut limiter) = self.token_guesses.lock() {
+                        limiter.hit(ip);
+                    }
+                }
+                Err(ServerError::InvalidToken)
             }
         }
     }
 
-    /// Generates a random token from the charset
-    fn random_token() -> String {
-        /// Available chars to create the token from
-        const CHARSET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
-        /// The number of chars in the charset
-        const RANGE: u32 = 36;
+    /// Gets the game with the provided token
+    ///
+    /// `token` The game token
+    pub fn get(&self, token: &str) -> Option<Addr<Game>> {
+        self.games.get(token).map(|value| value.clone())
+    }
 
-        let mut rand = OsRng;
-        let mut out = String::with_capacity(Self::TOKEN_LENGTH);
+    /// Checks whether a game on this node has the provided token
+    ///
+    /// `token` The game token
+    pub fn contains(&self, token: &str) -> bool {
+        self.games.contains_key(token)
+    }
 
-        // Loop until the string length is finished
-        for _ in 0..Self::TOKEN_LENGTH {
-            // Loop until a valid random is found
-            loop {
-                let var = rand.next_u32() >> (32 - 6);
-                if var < RANGE {
-                    out.push(char::from(CHARSET[var as usize]));
-                    break;
+    /// Generates a token that isn't used by any game on this node
+    ///
+    /// `tokens` The generator for the token
+    pub fn generate_token(&self, tokens: &TokenGenerator) -> String {
+        tokens.generate(|token| self.contains(token))
+    }
+
+    /// Stores the game behind the provided token
+    ///
+    /// `token` The game token
+    /// `game`  The address of the game
+    pub fn insert(&self, token: String, game: Addr<Game>) {
+        self.games.insert(token, game);
+    }
+
+    /// Returns all of the running games
+    pub fn all(&self) -> Vec<Addr<Game>> {
+        self.games.iter().map(|value| value.clone()).collect()
+    }
+
+    /// Returns the tokens of all the running games
+    pub fn tokens(&self) -> Vec<String> {
+        self.games.iter().map(|value| value.key().clone()).collect()
+    }
+
+    /// Removes the game with the provided token freeing the token to
+    /// be used by new games, nothing is removed when a different game
+    /// has since taken the token
+    ///
+    /// `token` The game token
+    /// `game`  The address of the game being removed
+    pub fn remove(&self, token: &str, game: &Addr<Game>) {
+        if self
+            .games
+            .remove_if(token, |_, value| value == game)
+            .is_some()
+        {
+            tokio::spawn(directory().release(token.to_string()));
+        }
+    }
+
+    /// Checks whether the server is shutting down
+    pub fn is_shutting_down(&self) -> bool {
+        self.shutting_down.load(Ordering::Acquire)
+    }
+
+    /// Checks whether the server is in maintenance mode
+    pub fn is_maintenance(&self) -> bool {
+        self.maintenance.load(Ordering::Acquire)
+    }
+
+    /// Enables or disables maintenance mode on this node and
+    /// every other node sharing the game directory
+    ///
+    /// `enabled` Whether maintenance mode should be enabled
+    pub fn set_maintenance(&self, enabled: bool) {
+        self.apply_maintenance(enabled);
+        tokio::spawn(directory().publish(ClusterMessage::Maintenance { enabled }));
+    }
+
+    /// Enables or disables maintenance mode on only this node
+    ///
+    /// `enabled` Whether maintenance mode should be enabled
+    pub fn apply_maintenance(&self, enabled: bool) {
+        self.maintenance.store(enabled, Ordering::Release);
+    }
+
+    /// Returns the number of running games
+    pub fn count(&self) -> usize {
+        self.games.len()
+    }
+}
+
+/// Service creating new games and storing the configurations of
+/// games that haven't been initialized yet
+pub struct Games {
+    /// Uninitialized games
+    pre_init: HashMap<GameId, GameConfig>,
+
+    /// Generator for new game tokens
+    tokens: TokenGenerator,
+}
+
+impl Default for Games {
+    fn default() -> Self {
+        Self {
+            pre_init: HashMap::new(),
+            tokens: TokenGenerator::from_env(),
+        }
+    }
+}
+
+impl Games {
+    /// Finds the token for a new game using the vanity token from the
+    /// config if one was provided. Games are only inserted by this
+    /// service which finishes claiming before handling anything else so
+    /// the token can't be taken locally before it's inserted, the token
+    /// is claimed in the directory so other nodes can't take it
+    ///
+    /// `config` The config of the game
+    fn game_token(&self, config: &GameConfig) -> impl Future<Output = Result<String, ServerError>> {
+        let tokens = self.tokens.clone();
+        let vanity = config.token.clone();
+        async move {
+            match vanity {
+                Some(token) => {
+                    let token = normalize_vanity(&token).ok_or(ServerError::TokenTaken)?;
+                    if registry().contains(&token) || !directory().claim(token.clone()).await {
+                        return Err(ServerError::TokenTaken);
+                    }
+                    Ok(token)
                 }
+                // Tokens used by other nodes are only found when claiming
+                // so new tokens are generated until a claim succeeds
+                None => loop {
+                    let token = registry().generate_token(&tokens);
+                    if directory().claim(token.clone()).await {
+                        return Ok(token);
+                    }
+                },
             }
         }
+    }
+
+    /// Creates and stores the game for a host connecting to its quiz,
+    /// the host session is informed that it has joined the game
+    ///
+    /// `token`   The claimed token for the game
+    /// `id`      The ID of the quiz
+    /// `sess_id` The session ID of the host
+    /// `addr`    The address of the host session
+    /// `config`  The config of the game
+    fn create_game(
+        token: String,
+        id: GameId,
+        sess_id: SessionId,
+        addr: Addr<Session>,
+        config: GameConfig,
+    ) -> GamesResponse {
+        // Clone config data for response
+        let timing = config.timing.clone();
+        let basic = config.basic.clone();
+
+        // Initialize and store the game
+        let game = Game::new(
+            token.clone(),
+            id,
+            sess_id,
+            addr.clone(),
+            config,
+            Box::new(SystemClock),
+        );
+        let resume = game.host_resume_token();
+        let game = game.start();
+        registry().insert(token.clone(), game.clone());
+        info!(token = %token, quiz = %id, "Created game");
+
+        addr.do_send(SessionRequest::Joined {
+            id: sess_id,
+            game: SessionGame {
+                token: token.clone(),
+                quiz: id,
+                addr: game,
+            },
+        });
+        addr.do_send(SessionRequest::Message(ServerMessage::Connected {
+            id: sess_id,
+            token: token.clone(),
+            basic: Box::new(basic.clone()),
+            timing: timing.clone(),
+            resume,
+            name: None,
+        }));
+
+        GamesResponse::Connected {
+            token,
+            basic,
+            timing,
+        }
+    }
+
+    /// Restores the game from its checkpoint unless its token has been
+    /// taken since the server stopped
+    ///
+    /// `checkpoint` The checkpoint of the game
+    /// `claimed`    Whether the token was claimed in the directory
+    fn restore_game(checkpoint: GameCheckpoint, claimed: bool) {
+        let token = checkpoint.token.clone();
+        let quiz = checkpoint.quiz;
+        if registry().contains(&token) || !claimed {
+            warn!(token = %token, "Token of restored game {} was taken", checkpoint.id);
+            checkpoint::queue_remove(checkpoint.id);
+            return;
+        }
 
-        out
+        // New sessions can't reuse the restored IDs
+        let ids = checkpoint.players.iter().map(|player| player.id);
+        if let Some(id) = ids.chain([checkpoint.host_id]).max() {
+            session::reserve_ids(id);
+        }
+
+        let game = Game::create(move |ctx| Game::restore(checkpoint, Box::new(SystemClock), ctx));
+        registry().insert(token.clone(), game);
+        info!(token = %token, quiz = %quiz, "Restored game");
     }
 
-    fn try_connect(game: Addr<Game>, id: u32, name: String, addr: Addr<Session>) {
+    /// Sends the provided connection request to the game and informs
+    /// the session of the result
+    ///
+    /// `game` The game to connect to
+    /// `msg`  The connection request
+    /// `addr` The address of the connecting session
+    pub fn connect(game: Addr<Game>, msg: GameRequest, addr: Addr<Session>) {
+        let msg = traced(msg);
         tokio::spawn(async move {
-            let game = game;
-            let res = match game
-                .send(GameRequest::TryConnect {
-                    id,
-                    name,
-                    addr: addr.clone(),
-                })
-                .await
-            {
+            let res = match game.send(msg).await {
                 Ok(value) => value,
                 Err(err) => {
                     error!("Failed to send join attempt: {:?}", err);
@@ -84,14 +360,68 @@ impl Games {
             match res {
                 Ok(GameResponse::Connected {
                     token,
+                    quiz,
                     id,
                     basic,
                     timing,
+                    resume,
+                    name,
+                    snapshot,
                 }) => {
+                    addr.do_send(SessionRequest::Joined {
+                        id,
+                        game: SessionGame {
+                            token: token.clone(),
+                            quiz,
+                            addr: game,
+                        },
+                    });
                     addr.do_send(SessionRequest::Message(ServerMessage::Connected {
                         token,
                         id,
-                        basic,
+                        basic: Box::new(basic),
+                        timing,
+                        resume,
+                        name,
+                    }));
+                    if let Some(snapshot) = snapshot {
+                        addr.do_send(SessionRequest::Message(ServerMessage::StateSnapshot(
+                            snapshot,
+                        )));
+                    }
+                }
+                Ok(GameResponse::Pending { token, quiz, id }) => {
+                    addr.do_send(SessionRequest::Joined {
+                        id,
+                        game: SessionGame {
+                            token: token.clone(),
+                            quiz,
+                            addr: game,
+                        },
+                    });
+                    addr.do_send(SessionRequest::Message(ServerMessage::JoinPending {
+                        token,
+                    }));
+                }
+                Ok(GameResponse::Spectating {
+                    token,
+                    quiz,
+                    id,
+                    basic,
+                    timing,
+                }) => {
+                    addr.do_send(SessionRequest::Joined {
+                        id,
+                        game: SessionGame {
+                            token: token.clone(),
+                            quiz,
+                            addr: game,
+                        },
+                    });
+                    addr.do_send(SessionRequest::Message(ServerMessage::Spectating {
+                        token,
+                        id,
+                        basic: Box::new(basic),
                         timing,
                     }));
                 }
@@ -110,28 +440,46 @@ impl Games {
 #[rtype(result = "Result<GamesResponse, ServerError>")]
 pub enum GamesRequest {
     /// Request from the HTTP API to initialize a new game
-    PreInitGame { config: GameConfig },
+    PreInitGame { config: Box<GameConfig> },
 
     /// Message for the host to connect to an un-initialized game
     HostConnect {
-        id: u32,
+        id: GameId,
         sess_id: SessionId,
         addr: Addr<Session>,
     },
 
-    /// Message to attempt to connect to a game
-    TryConnect {
-        token: String,
-        id: SessionId,
-        name: String,
-        addr: Addr<Session>,
+    /// Message checking that the service is responsive
+    Ping,
+
+    /// Message rebuilding the games that were running when the
+    /// server last stopped from their checkpoints
+    Restore { checkpoints: Vec<GameCheckpoint> },
+
+    /// Message informing the games that the server is shutting down
+    Shutdown {
+        /// Unix timestamp in milliseconds the server will stop at
+        deadline: u64,
     },
 }
 
+impl GamesRequest {
+    /// Returns the name of the request type used in logs
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::PreInitGame { .. } => "PreInitGame",
+            Self::HostConnect { .. } => "HostConnect",
+            Self::Ping => "Ping",
+            Self::Restore { .. } => "Restore",
+            Self::Shutdown { .. } => "Shutdown",
+        }
+    }
+}
+
 pub enum GamesResponse {
     /// Pre initialization complete
     PreInitComplete {
-        id: u32,
+        id: GameId,
     },
 
     /// The game was connected to successfully
@@ -144,51 +492,108 @@ pub enum GamesResponse {
         timing: GameTiming,
     },
 
+    /// The games have been informed of the shutdown
+    ShuttingDown {
+        /// The number of games that are still running
+        games: usize,
+    },
+
     None,
 }
 
+/// Response to a games request, requests waiting on the game directory
+/// finish before the next request is handled so that a token can't be
+/// given to two games while its claim is pending
+type GamesResult = AtomicResponse<Games, Result<GamesResponse, ServerError>>;
+
+/// Responds to a games request with a result that is already known
+///
+/// `result` The result of the request
+fn respond(result: Result<GamesResponse, ServerError>) -> GamesResult {
+    AtomicResponse::new(Box::pin(fut::ready(result)))
+}
+
 impl Handler<GamesRequest> for Games {
-    type Result = Result<GamesResponse, ServerError>;
+    type Result = GamesResult;
     fn handle(&mut self, msg: GamesRequest, _ctx: &mut Self::Context) -> Self::Result {
+        let span = info_span!("games", session_id = field::Empty, message = msg.name());
+        if let GamesRequest::HostConnect { sess_id, .. } = &msg {
+            span.record("session_id", sess_id);
+        }
+        let _enter = span.enter();
+
+        // New games aren't accepted while shutting down
+        let is_new = matches!(
+            msg,
+            GamesRequest::PreInitGame { .. } | GamesRequest::HostConnect { .. }
+        );
+        if is_new && registry().is_shutting_down() {
+            return respond(Err(ServerError::ShuttingDown));
+        }
+
+        // Quizzes created before maintenance began can still be hosted
+        if matches!(msg, GamesRequest::PreInitGame { .. }) && registry().is_maintenance() {
+            return respond(Err(ServerError::Maintenance));
+        }
+
         match msg {
             GamesRequest::PreInitGame { config } => {
-                let id = self.pre_init_id;
-                self.pre_init_id += 1;
-                self.pre_init.insert(id, config);
-                Ok(GamesResponse::PreInitComplete { id })
+                let id = Uuid::new_v4();
+                self.pre_init.insert(id, *config);
+                respond(Ok(GamesResponse::PreInitComplete { id }))
             }
             GamesRequest::HostConnect { id, sess_id, addr } => {
                 // Find the config data from the pre init list
-                let config = self.pre_init.remove(&id).ok_or(ServerError::InvalidToken)?;
+                let Some(config) = self.pre_init.get(&id) else {
+                    return respond(Err(ServerError::InvalidToken));
+                };
+                let token = self.game_token(config);
 
-                // Clone config data for response
-                let timing = config.timing.clone();
-                let basic = config.basic.clone();
-
-                // Initialize and store the game
-                let token = self.unique_token();
-                let game = Game::new(token.clone(), sess_id, addr, config).start();
-                self.games.insert(token.clone(), game);
+                let span = span.clone();
+                let created = token.into_actor(self).map(move |token, actor, _ctx| {
+                    let _enter = span.enter();
+                    let token = token?;
+                    let config = actor
+                        .pre_init
+                        .remove(&id)
+                        .ok_or(ServerError::InvalidToken)?;
+                    Ok(Self::create_game(token, id, sess_id, addr, config))
+                });
+                AtomicResponse::new(Box::pin(created))
+            }
+            GamesRequest::Ping => respond(Ok(GamesResponse::None)),
+            GamesRequest::Restore { checkpoints } => {
+                let claims = join_all(
+                    checkpoints
+                        .iter()
+                        .map(|checkpoint| directory().claim(checkpoint.token.clone())),
+                );
 
-                Ok(GamesResponse::Connected {
-                    token,
-                    basic,
-                    timing,
-                })
+                let span = span.clone();
+                let restored = claims.into_actor(self).map(move |claims, _actor, _ctx| {
+                    let _enter = span.enter();
+                    for (checkpoint, claimed) in checkpoints.into_iter().zip(claims) {
+                        Self::restore_game(checkpoint, claimed);
+                    }
+                    Ok(GamesResponse::None)
+                });
+                AtomicResponse::new(Box::pin(restored))
             }
-            GamesRequest::TryConnect {
-                token,
-                id,
-                name,
-                addr,
-            } => {
-                let game = self
-                    .games
-                    .get(&token)
-                    .ok_or(ServerError::InvalidToken)?
-                    .clone();
-                Self::try_connect(game, id, name, addr);
-                Ok(GamesResponse::None)
+            GamesRequest::Shutdown { deadline } => {
+                let registry = registry();
+                registry.shutting_down.store(true, Ordering::Release);
+                for game in registry.games.iter() {
+                    game.do_send(GameRequest::Shutdown { deadline });
+                }
+
+                // Checkpointed games are restored rather than finished
+                // so there is nothing to wait for
+                let games = if checkpoint::interval().is_some() {
+                    0
+                } else {
+                    registry.games.len()
+                };
+                respond(Ok(GamesResponse::ShuttingDown { games }))
             }
         }
     }
@@ -198,6 +603,10 @@ impl Actor for Games {
     type Context = Context<Self>;
 }
 
+impl Supervised for Games {}
+
+impl SystemService for Games {}
+
 impl<A, M> MessageResponse<A, M> for GamesResponse
 where
     A: Actor,
@@ -0,0 +1,504 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    game::{AnswerResult, QuestionAnswer, QuestionType},
+    matching::{match_text, TextMatch},
+};
+
+/// Marks the provided answer against the question returning None if
+/// the answer is for a different type of question
+///
+/// `question`   The question being answered
+/// `answer`     The answer to mark
+/// `base_score` The score for a correct answer
+pub fn mark(
+    question: &QuestionType,
+    answer: &QuestionAnswer,
+    base_score: u32,
+) -> Option<AnswerResult> {
+    let result = match (question, answer) {
+        (QuestionType::Single { answers, .. }, QuestionAnswer::Single { answer }) => {
+            correct_if(answers.contains(answer), base_score)
+        }
+        (
+            QuestionType::MultiSelect {
+                answers: expected, ..
+            },
+            QuestionAnswer::MultiSelect { answers },
+        ) => grade_selection(expected, answers, base_score),
+        (QuestionType::TextEntry { answers, options }, QuestionAnswer::TextEntry { answer }) => {
+            match match_text(answer, answers, options) {
+                TextMatch::Exact => AnswerResult::Correct(base_score),
+                TextMatch::Close(_) => AnswerResult::Close(base_score),
+                TextMatch::None => AnswerResult::Incorrect,
+            }
+        }
+        (QuestionType::TrueFalse { answer: expected }, QuestionAnswer::TrueFalse { answer }) => {
+            correct_if(expected == answer, base_score)
+        }
+        (
+            QuestionType::ClickableImage { top, bottom, .. },
+            QuestionAnswer::ClickableImage { answer },
+        ) => {
+            // Clicked position is within top and bottom box position
+            let valid = answer.0 >= top.0
+                && answer.0 <= bottom.0
+                && answer.1 >= top.1
+                && answer.1 <= bottom.1;
+            correct_if(valid, base_score)
+        }
+        (
+            QuestionType::Ordering {
+                order: expected,
+                partial_credit,
+                ..
+            },
+            QuestionAnswer::Ordering { order },
+        ) => {
+            if order == expected {
+                AnswerResult::Correct(base_score)
+            } else if *partial_credit {
+                partial_result(base_score, order_agreement(expected, order))
+            } else {
+                AnswerResult::Incorrect
+            }
+        }
+        (
+            QuestionType::Numeric {
+                value,
+                tolerance,
+                proportional,
+            },
+            QuestionAnswer::Numeric { answer },
+        ) => partial_result(
+            base_score,
+            grade_numeric(*value, *tolerance, *proportional, *answer),
+        ),
+        (QuestionType::Poll { participation, .. }, QuestionAnswer::Single { .. }) => {
+            AnswerResult::Participated(*participation)
+        }
+        (QuestionType::Hotspot { regions, .. }, QuestionAnswer::ClickableImage { answer }) => {
+            correct_if(
+                regions.iter().any(|region| region.contains(*answer)),
+                base_score,
+            )
+        }
+        _ => return None,
+    };
+    Some(result)
+}
+
+/// Creates the result for an answer that is either right or wrong
+///
+/// `valid`      Whether the answer is correct
+/// `base_score` The score for a correct answer
+fn correct_if(valid: bool, base_score: u32) -> AnswerResult {
+    if valid {
+        AnswerResult::Correct(base_score)
+    } else {
+        AnswerResult::Incorrect
+    }
+}
+
+/// Grades the selected values of a multiple choice question, each
+/// incorrect selection cancels out a correct one
+///
+/// `expected`   The correct values
+/// `answers`    The selected values
+/// `base_score` The score for a correct answer
+fn grade_selection(expected: &[usize], answers: &[usize], base_score: u32) -> AnswerResult {
+    let correct = answers
+        .iter()
+        .filter(|answer| expected.contains(answer))
+        .count();
+    let incorrect = answers.len() - correct;
+
+    if correct == expected.len() && incorrect == 0 {
+        return AnswerResult::Correct(base_score);
+    }
+
+    let earned = correct.saturating_sub(incorrect);
+    let percent = (earned as f32) / (expected.len() as f32);
+    let score = ((base_score as f32) * percent).round() as u32;
+    if score == 0 {
+        AnswerResult::Incorrect
+    } else {
+        AnswerResult::Partial(score)
+    }
+}
+
+/// Tolerance allowed between a numeric answer and the expected value
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum Tolerance {
+    /// The answer can differ by at most the provided amount
+    Absolute(f64),
+    /// The answer can differ by at most the provided fraction
+    /// of the expected value
+    Relative(f64),
+}
+
+impl Default for Tolerance {
+    fn default() -> Self {
+        Self::Absolute(0.0)
+    }
+}
+
+impl Tolerance {
+    /// Returns the largest difference allowed from the expected value
+    ///
+    /// `expected` The expected value
+    pub fn allowed(&self, expected: f64) -> f64 {
+        match self {
+            Self::Absolute(value) => *value,
+            Self::Relative(value) => expected.abs() * value,
+        }
+    }
+
+    /// Checks whether the tolerance is a finite non negative value
+    pub fn is_valid(&self) -> bool {
+        let (Self::Absolute(value) | Self::Relative(value)) = self;
+        value.is_finite() && *value >= 0.0
+    }
+}
+
+/// Grades a numeric answer returning the fraction of the score earned.
+/// Answers within the tolerance earn the full score, when proportional
+/// the score falls off linearly outside the tolerance reaching nothing
+/// once the answer is off by the magnitude of the expected value
+///
+/// `expected`     The expected value
+/// `tolerance`    The tolerance allowed for full score
+/// `proportional` Whether answers outside the tolerance earn partial score
+/// `answer`       The submitted answer
+pub fn grade_numeric(expected: f64, tolerance: Tolerance, proportional: bool, answer: f64) -> f32 {
+    let allowed = tolerance.allowed(expected);
+    let distance = (answer - expected).abs();
+    if distance <= allowed {
+        return 1.0;
+    }
+    if !proportional {
+        return 0.0;
+    }
+
+    let scale = expected.abs().max(allowed);
+    if scale <= 0.0 {
+        return 0.0;
+    }
+    (1.0 - (distance - allowed) / scale).clamp(0.0, 1.0) as f32
+}
+
+/// Kendall tau style agreement between the expected order and the
+/// provided order. Each pair of values placed in the same relative
+/// order counts for the agreement and each pair placed in the wrong
+/// relative order counts against it, negative agreement is clamped
+/// to zero so random orders score nothing
+///
+/// `expected` The correct order
+/// `order`    The submitted order
+pub fn order_agreement(expected: &[usize], order: &[usize]) -> f32 {
+    let mut rank = vec![0; expected.len()];
+    for (position, index) in expected.iter().enumerate() {
+        rank[*index] = position;
+    }
+
+    let mut concordant = 0i64;
+    let mut discordant = 0i64;
+    for (position, first) in order.iter().enumerate() {
+        for second in &order[position + 1..] {
+            if rank[*first] < rank[*second] {
+                concordant += 1;
+            } else {
+                discordant += 1;
+            }
+        }
+    }
+
+    let pairs = concordant + discordant;
+    if pairs == 0 {
+        return 0.0;
+    }
+    ((concordant - discordant) as f32 / pairs as f32).max(0.0)
+}
+
+/// Accepted area of an image, coordinates are normalized to the image
+/// dimensions so (0, 0) is the top left and (1, 1) the bottom right
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Region {
+    /// Box between the top left and bottom right points
+    Rect { top: (f32, f32), bottom: (f32, f32) },
+    /// Circle around the center point
+    Circle { center: (f32, f32), radius: f32 },
+    /// Polygon with the provided points in order
+    Polygon { points: Vec<(f32, f32)> },
+}
+
+impl Region {
+    /// Checks whether the region is within the image and has an area
+    pub fn is_valid(&self) -> bool {
+        let in_image = |(x, y): (f32, f32)| (0.0..=1.0).contains(&x) && (0.0..=1.0).contains(&y);
+        match self {
+            Self::Rect { top, bottom } => {
+                in_image(*top) && in_image(*bottom) && top.0 <= bottom.0 && top.1 <= bottom.1
+            }
+            Self::Circle { center, radius } => in_image(*center) && *radius > 0.0,
+            Self::Polygon { points } => points.len() >= 3 && points.iter().all(|p| in_image(*p)),
+        }
+    }
+
+    /// Checks whether the provided point is within the region
+    ///
+    /// `point` The normalized point
+    pub fn contains(&self, (x, y): (f32, f32)) -> bool {
+        match self {
+            Self::Rect { top, bottom } => {
+                x >= top.0 && x <= bottom.0 && y >= top.1 && y <= bottom.1
+            }
+            Self::Circle { center, radius } => {
+                let (dx, dy) = (x - center.0, y - center.1);
+                dx * dx + dy * dy <= radius * radius
+            }
+            Self::Polygon { points } => {
+                // Even-odd rule, count the edges a ray to the right crosses
+                let mut inside = false;
+                let mut previous = points[points.len() - 1];
+                for current in points {
+                    let crosses = (current.1 > y) != (previous.1 > y)
+                        && x < (previous.0 - current.0) * (y - current.1)
+                            / (previous.1 - current.1)
+                            + current.0;
+                    if crosses {
+                        inside = !inside;
+                    }
+                    previous = *current;
+                }
+                inside
+            }
+        }
+    }
+}
+
+/// Grade given by the host to a submission for an open ended question
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum Grade {
+    Correct,
+    Incorrect,
+    /// The submission earns the provided fraction of the score
+    Partial(f32),
+}
+
+impl Grade {
+    /// Checks whether a partial grade fraction is between zero and one
+    pub fn is_valid(&self) -> bool {
+        match self {
+            Self::Partial(fraction) => (0.0..=1.0).contains(fraction),
+            _ => true,
+        }
+    }
+
+    /// Creates the result for a submission with this grade
+    ///
+    /// `base_score` The score for a correct answer
+    pub fn result(&self, base_score: u32) -> AnswerResult {
+        match self {
+            Self::Correct => AnswerResult::Correct(base_score),
+            Self::Incorrect => AnswerResult::Incorrect,
+            Self::Partial(fraction) => partial_result(base_score, *fraction),
+        }
+    }
+}
+
+/// Creates the result for an answer that earned the provided fraction
+/// of the score, answers earning the full score are correct and
+/// answers rounding to no score are incorrect
+///
+/// `base_score` The score for a correct answer
+/// `fraction`   The fraction of the score earned
+pub fn partial_result(base_score: u32, fraction: f32) -> AnswerResult {
+    if fraction >= 1.0 {
+        return AnswerResult::Correct(base_score);
+    }
+    let score = ((base_score as f32) * fraction).round() as u32;
+    if score == 0 {
+        AnswerResult::Incorrect
+    } else {
+        AnswerResult::Partial(score)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Creates a question of the provided type
+    fn question(ty: serde_json::Value) -> QuestionType {
+        serde_json::from_value(ty).unwrap()
+    }
+
+    #[test]
+    fn single_answers_are_marked() {
+        let question = question(serde_json::json!({
+            "Single": { "answers": [1], "values": ["A", "B"] }
+        }));
+        assert!(matches!(
+            mark(&question, &QuestionAnswer::Single { answer: 1 }, 800),
+            Some(AnswerResult::Correct(800))
+        ));
+        assert!(matches!(
+            mark(&question, &QuestionAnswer::Single { answer: 0 }, 800),
+            Some(AnswerResult::Incorrect)
+        ));
+    }
+
+    #[test]
+    fn incorrect_selections_cancel_correct_ones() {
+        let question = question(serde_json::json!({
+            "MultiSelect": { "answers": [0, 1], "values": ["A", "B", "C"] }
+        }));
+        let selected =
+            |answers: Vec<usize>| mark(&question, &QuestionAnswer::MultiSelect { answers }, 800);
+        assert!(matches!(
+            selected(vec![0, 1]),
+            Some(AnswerResult::Correct(800))
+        ));
+        assert!(matches!(
+            selected(vec![0]),
+            Some(AnswerResult::Partial(400))
+        ));
+        assert!(matches!(
+            selected(vec![0, 2]),
+            Some(AnswerResult::Incorrect)
+        ));
+        assert!(matches!(
+            selected(vec![0, 1, 2]),
+            Some(AnswerResult::Partial(400))
+        ));
+    }
+
+    #[test]
+    fn answers_for_other_question_types_are_not_marked() {
+        let question = question(serde_json::json!({ "TrueFalse": { "answer": true } }));
+        assert!(mark(&question, &QuestionAnswer::Single { answer: 0 }, 800).is_none());
+        assert!(matches!(
+            mark(&question, &QuestionAnswer::TrueFalse { answer: true }, 800),
+            Some(AnswerResult::Correct(800))
+        ));
+    }
+
+    #[test]
+    fn correct_grade_earns_the_base_score() {
+        assert!(matches!(
+            Grade::Correct.result(800),
+            AnswerResult::Correct(800)
+        ));
+    }
+
+    #[test]
+    fn incorrect_grade_earns_nothing() {
+        assert!(matches!(
+            Grade::Incorrect.result(800),
+            AnswerResult::Incorrect
+        ));
+    }
+
+    #[test]
+    fn partial_grade_scales_the_score() {
+        assert!(matches!(
+            Grade::Partial(0.5).result(800),
+            AnswerResult::Partial(400)
+        ));
+        assert!(matches!(
+            Grade::Partial(0.25).result(1001),
+            AnswerResult::Partial(250)
+        ));
+    }
+
+    #[test]
+    fn partial_grade_bounds() {
+        // A full fraction is a correct answer
+        assert!(matches!(
+            Grade::Partial(1.0).result(800),
+            AnswerResult::Correct(800)
+        ));
+        // Fractions rounding to no score are incorrect
+        assert!(matches!(
+            Grade::Partial(0.0).result(800),
+            AnswerResult::Incorrect
+        ));
+        assert!(matches!(
+            Grade::Partial(0.0004).result(1000),
+            AnswerResult::Incorrect
+        ));
+    }
+
+    #[test]
+    fn grade_validity() {
+        assert!(Grade::Correct.is_valid());
+        assert!(Grade::Incorrect.is_valid());
+        assert!(Grade::Partial(0.0).is_valid());
+        assert!(Grade::Partial(0.5).is_valid());
+        assert!(Grade::Partial(1.0).is_valid());
+        assert!(!Grade::Partial(-0.01).is_valid());
+        assert!(!Grade::Partial(1.01).is_valid());
+        assert!(!Grade::Partial(f32::NAN).is_valid());
+        assert!(!Grade::Partial(f32::INFINITY).is_valid());
+    }
+
+    #[test]
+    fn numeric_answers_within_tolerance_earn_full_score() {
+        assert_eq!(
+            grade_numeric(10.0, Tolerance::Absolute(1.0), false, 11.0),
+            1.0
+        );
+        assert_eq!(
+            grade_numeric(100.0, Tolerance::Relative(0.1), false, 91.0),
+            1.0
+        );
+        assert_eq!(
+            grade_numeric(10.0, Tolerance::Absolute(1.0), false, 11.5),
+            0.0
+        );
+    }
+
+    #[test]
+    fn numeric_answers_fall_off_when_proportional() {
+        let score = grade_numeric(100.0, Tolerance::Absolute(0.0), true, 150.0);
+        assert!((score - 0.5).abs() < 1e-6);
+        assert_eq!(
+            grade_numeric(100.0, Tolerance::Absolute(0.0), true, 300.0),
+            0.0
+        );
+        assert_eq!(grade_numeric(0.0, Tolerance::Absolute(0.0), true, 1.0), 0.0);
+    }
+
+    #[test]
+    fn order_agreement_scores_pairs() {
+        assert_eq!(order_agreement(&[0, 1, 2], &[0, 1, 2]), 1.0);
+        assert_eq!(order_agreement(&[0, 1, 2], &[2, 1, 0]), 0.0);
+        // Two of the three pairs are in order
+        let agreement = order_agreement(&[0, 1, 2], &[1, 0, 2]);
+        assert!((agreement - 1.0 / 3.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn regions_contain_points() {
+        let rect = Region::Rect {
+            top: (0.1, 0.1),
+            bottom: (0.5, 0.5),
+        };
+        assert!(rect.contains((0.3, 0.3)));
+        assert!(!rect.contains((0.6, 0.3)));
+
+        let circle = Region::Circle {
+            center: (0.5, 0.5),
+            radius: 0.1,
+        };
+        assert!(circle.contains((0.55, 0.5)));
+        assert!(!circle.contains((0.7, 0.5)));
+
+        let triangle = Region::Polygon {
+            points: vec![(0.0, 0.0), (1.0, 0.0), (0.0, 1.0)],
+        };
+        assert!(triangle.contains((0.2, 0.2)));
+        assert!(!triangle.contains((0.8, 0.8)));
+    }
+}
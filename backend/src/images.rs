@@ -0,0 +1,74 @@
+use std::path::PathBuf;
+
+use uuid::Uuid;
+
+use crate::{config, game::GameId};
+
+/// Image formats that are accepted for uploads
+#[derive(Clone, Copy)]
+pub enum ImageFormat {
+    Png,
+    Jpeg,
+    Gif,
+    Webp,
+}
+
+impl ImageFormat {
+    /// The number of bytes needed to detect any of the formats
+    pub const MAGIC_LENGTH: usize = 12;
+
+    /// Detects the image format from the magic bytes at the start
+    /// of the provided image bytes
+    ///
+    /// `bytes` The image bytes
+    pub fn detect(bytes: &[u8]) -> Option<Self> {
+        if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+            Some(Self::Png)
+        } else if bytes.starts_with(&[0xFF, 0xD8, 0xFF]) {
+            Some(Self::Jpeg)
+        } else if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+            Some(Self::Gif)
+        } else if bytes.len() >= 12 && &bytes[0..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+            Some(Self::Webp)
+        } else {
+            None
+        }
+    }
+
+    /// The mime type for the format
+    pub fn mime(&self) -> &'static str {
+        match self {
+            Self::Png => "image/png",
+            Self::Jpeg => "image/jpeg",
+            Self::Gif => "image/gif",
+            Self::Webp => "image/webp",
+        }
+    }
+}
+
+/// Name of the file the quiz definition is stored in within the game
+/// directory, this is never a valid image ID so it can't be served
+const QUIZ_FILE: &str = "quiz.json";
+
+/// Returns the directory images for the provided game are stored in
+///
+/// `game` The ID of the game
+pub fn game_dir(game: GameId) -> PathBuf {
+    PathBuf::from(config::from_env_string(config::IMAGE_DIR)).join(game.to_string())
+}
+
+/// Returns the path the quiz definition for the provided game is
+/// stored at alongside its images so that it can be exported
+///
+/// `game` The ID of the game
+pub fn quiz_path(game: GameId) -> PathBuf {
+    game_dir(game).join(QUIZ_FILE)
+}
+
+/// Returns the path to the image with the provided ID
+///
+/// `game` The ID of the game
+/// `id`   The ID of the image
+pub fn image_path(game: GameId, id: &Uuid) -> PathBuf {
+    game_dir(game).join(id.to_string())
+}
@@ -0,0 +1,252 @@
+use serde::Deserialize;
+
+use crate::{
+    error::{FieldError, FieldErrorReason},
+    game::{Question, QuestionType},
+    grading::Tolerance,
+    matching::TextMatchOptions,
+};
+
+/// Formats quizzes can be imported from
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportFormat {
+    #[default]
+    Csv,
+    #[cfg(feature = "xlsx")]
+    Xlsx,
+}
+
+/// Separator between multiple values within a single cell
+const LIST_SEPARATOR: char = '|';
+
+/// Imports the questions from the provided file. The first row is a
+/// header row and is skipped, each following row is a question with
+/// the columns in the order:
+///
+/// | Column      | Content                                                  |
+/// |-------------|----------------------------------------------------------|
+/// | type        | single, multi, truefalse, text, numeric, ordering, poll  |
+/// |             | or open                                                  |
+/// | title       | The question title                                       |
+/// | text        | The question text                                        |
+/// | answer_time | The time given to answer in milliseconds                 |
+/// | values      | The values to choose from separated by `|`               |
+/// | answers     | single and multi: the correct values separated by `|`    |
+/// |             | truefalse: true or false                                 |
+/// |             | text: the accepted answers separated by `|`              |
+/// |             | numeric: the expected number                             |
+/// |             | ordering: the values in the correct order separated `|`  |
+/// | explanation | Optional explanation revealed after the question         |
+/// | tolerance   | Optional absolute tolerance for numeric questions        |
+///
+/// Errors are reported for each invalid row using the row number from
+/// the file (e.g. rows[2].answers for the first question)
+///
+/// `format` The format of the file
+/// `data`   The file contents
+pub fn import(format: ImportFormat, data: &[u8]) -> Result<Vec<Question>, Vec<FieldError>> {
+    let rows = match format {
+        ImportFormat::Csv => read_csv(data)?,
+        #[cfg(feature = "xlsx")]
+        ImportFormat::Xlsx => read_xlsx(data)?,
+    };
+
+    if rows.is_empty() {
+        return Err(vec![FieldError::new("rows", FieldErrorReason::Empty)]);
+    }
+
+    let mut errors = Vec::new();
+    let mut questions = Vec::with_capacity(rows.len());
+    for (row, cells) in rows {
+        if let Some(question) = convert_row(row, &cells, &mut errors) {
+            question.validate(&format!("rows[{}]", row), &mut errors);
+            questions.push(question);
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(questions)
+    } else {
+        Err(errors)
+    }
+}
+
+/// Reads the rows from a CSV file along with their row numbers
+///
+/// `data` The file contents
+fn read_csv(data: &[u8]) -> Result<Vec<(usize, Vec<String>)>, Vec<FieldError>> {
+    let mut reader = csv::ReaderBuilder::new().flexible(true).from_reader(data);
+
+    let mut rows = Vec::new();
+    let mut errors = Vec::new();
+    for (index, record) in reader.records().enumerate() {
+        // Row numbers start at one and include the header row
+        let row = index + 2;
+        match record {
+            Ok(record) => rows.push((row, record.iter().map(String::from).collect())),
+            Err(_) => errors.push(FieldError::new(
+                format!("rows[{}]", row),
+                FieldErrorReason::InvalidValue,
+            )),
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(rows)
+    } else {
+        Err(errors)
+    }
+}
+
+/// Reads the rows from the first sheet of a XLSX file along
+/// with their row numbers
+///
+/// `data` The file contents
+#[cfg(feature = "xlsx")]
+pub fn read_xlsx(data: &[u8]) -> Result<Vec<(usize, Vec<String>)>, Vec<FieldError>> {
+    use calamine::{Reader, Xlsx};
+    use std::io::Cursor;
+
+    let invalid = || vec![FieldError::new("rows", FieldErrorReason::InvalidValue)];
+
+    let mut workbook = Xlsx::new(Cursor::new(data)).map_err(|_| invalid())?;
+    let range = workbook
+        .worksheet_range_at(0)
+        .ok_or_else(invalid)?
+        .map_err(|_| invalid())?;
+
+    // Row numbers start at one and the header row is skipped
+    let rows = range
+        .rows()
+        .enumerate()
+        .skip(1)
+        .map(|(index, cells)| {
+            (
+                index + 1,
+                cells.iter().map(|cell| cell.to_string()).collect(),
+            )
+        })
+        .collect();
+    Ok(rows)
+}
+
+/// Converts the cells of a row into a question, any errors are
+/// appended to the provided errors
+///
+/// `row`    The row number
+/// `cells`  The cells of the row
+/// `errors` The list of errors to append to
+fn convert_row(row: usize, cells: &[String], errors: &mut Vec<FieldError>) -> Option<Question> {
+    let field = |name: &str| format!("rows[{}].{}", row, name);
+    let cell = |index: usize| cells.get(index).map_or("", |value| value.trim());
+    let list = |index: usize| -> Vec<String> {
+        cell(index)
+            .split(LIST_SEPARATOR)
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .map(String::from)
+            .collect()
+    };
+
+    let Ok(answer_time) = cell(3).parse::<u64>() else {
+        errors.push(FieldError::new(
+            field("answer_time"),
+            FieldErrorReason::InvalidValue,
+        ));
+        return None;
+    };
+
+    let values = list(4);
+    let answers = list(5);
+
+    // Finds the index of each of the answers within the values
+    let indexes = |errors: &mut Vec<FieldError>| -> Option<Vec<usize>> {
+        let indexes: Option<Vec<usize>> = answers
+            .iter()
+            .map(|answer| values.iter().position(|value| value == answer))
+            .collect();
+        if indexes.is_none() {
+            errors.push(FieldError::new(
+                field("answers"),
+                FieldErrorReason::InvalidAnswerIndex,
+            ));
+        }
+        indexes
+    };
+
+    // Parses the cell at the provided index
+    let parse = |errors: &mut Vec<FieldError>, index: usize, name: &str| -> Option<f64> {
+        let value = cell(index).parse::<f64>().ok();
+        if value.is_none() {
+            errors.push(FieldError::new(field(name), FieldErrorReason::InvalidValue));
+        }
+        value
+    };
+
+    let ty = match cell(0).to_lowercase().as_str() {
+        "single" => QuestionType::Single {
+            answers: indexes(errors)?,
+            values,
+        },
+        "multi" => QuestionType::MultiSelect {
+            answers: indexes(errors)?,
+            values,
+        },
+        "ordering" => QuestionType::Ordering {
+            order: indexes(errors)?,
+            values,
+            partial_credit: false,
+        },
+        "truefalse" => match cell(5).to_lowercase().parse::<bool>() {
+            Ok(answer) => QuestionType::TrueFalse { answer },
+            Err(_) => {
+                errors.push(FieldError::new(
+                    field("answers"),
+                    FieldErrorReason::InvalidValue,
+                ));
+                return None;
+            }
+        },
+        "text" => QuestionType::TextEntry {
+            answers,
+            options: TextMatchOptions::default(),
+        },
+        "numeric" => {
+            let value = parse(errors, 5, "answers")?;
+            let tolerance = match cell(7) {
+                "" => Tolerance::default(),
+                _ => Tolerance::Absolute(parse(errors, 7, "tolerance")?),
+            };
+            QuestionType::Numeric {
+                value,
+                tolerance,
+                proportional: false,
+            }
+        }
+        "poll" => QuestionType::Poll {
+            values,
+            participation: 0,
+        },
+        "open" => QuestionType::OpenEnded {},
+        _ => {
+            errors.push(FieldError::new(
+                field("type"),
+                FieldErrorReason::UnknownType,
+            ));
+            return None;
+        }
+    };
+
+    let explanation = Some(cell(6))
+        .filter(|value| !value.is_empty())
+        .map(String::from);
+
+    Some(Question::new(
+        cell(1).to_string(),
+        cell(2).to_string(),
+        ty,
+        answer_time,
+        explanation,
+    ))
+}
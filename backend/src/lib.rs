@@ -0,0 +1,48 @@
+//! The Quizler server, split from the binary so the decoding of client
+//! messages can be driven by the fuzz targets in `fuzz`
+
+pub mod analytics;
+pub mod api_keys;
+pub mod appearance;
+pub mod audit;
+pub mod auth;
+pub mod bundle;
+pub mod checkpoint;
+pub mod clock;
+pub mod cluster;
+pub mod config;
+pub mod convert;
+pub mod decode;
+pub mod error;
+pub mod events;
+pub mod game;
+pub mod games;
+pub mod grading;
+pub mod images;
+pub mod import;
+pub mod lifecycle;
+#[cfg(feature = "loadtest")]
+pub mod loadtest;
+pub mod logging;
+#[cfg(feature = "lti")]
+pub mod lti;
+pub mod matching;
+pub mod metrics;
+#[cfg(feature = "email")]
+pub mod notify;
+#[cfg(feature = "oauth")]
+pub mod oauth;
+#[cfg(feature = "pdf")]
+pub mod pdf;
+pub mod proxy;
+pub mod rate_limit;
+pub mod replay;
+pub mod routes;
+pub mod scoring;
+pub mod session;
+pub mod storage;
+pub mod telemetry;
+#[cfg(feature = "tls")]
+pub mod tls;
+pub mod token;
+pub mod username;
@@ -0,0 +1,900 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use tracing::error;
+
+use crate::{
+    game::{
+        AnswerResult, GameConfig, GameMode, GameState, GameTiming, PlayerAnswer, PointModifier,
+        Question,
+    },
+    grading::Grade,
+    scoring::{buzzer_score, double_score, lose_wager, win_wager},
+    session::SessionId,
+};
+
+/// Time the game waits after starting before the first question
+const START_TIME: Duration = Duration::from_secs(5);
+
+/// The longest the host is given to grade open ended submissions
+const GRADING_TIME: Duration = Duration::from_secs(300);
+
+/// Step of the question lifecycle, each step runs either straight
+/// after the previous step or once the wait scheduled for it ends
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Step {
+    /// Begin the question at the index
+    Begin(usize),
+    /// Count down before showing the current question
+    Countdown,
+    /// Show the current question and wait for the players to be ready
+    Show,
+    /// Give the players time to read the current question
+    Read,
+    /// Open the current question for answers
+    Open,
+    /// Send the host the open ended submissions to grade
+    Grade,
+    /// Mark the answers to the current question
+    Mark,
+}
+
+/// What follows a step once it has run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Next {
+    /// The step runs straight away
+    Now(Step),
+    /// A step was scheduled to run once the wait ends
+    Wait,
+    /// There are no more questions and the game is finished
+    Finish,
+}
+
+/// Facts about the players that decide how the lifecycle continues,
+/// provided by the game after running each step
+pub struct Progress {
+    /// The largest answer time multiplier of the players
+    pub multiplier: f32,
+    /// The number of open ended submissions waiting to be graded
+    pub submissions: usize,
+    /// Whether the game should finish instead of moving on to the
+    /// next question
+    pub stop: bool,
+}
+
+/// Step that was paused along with the time remaining on its wait
+pub struct PausedStep {
+    /// The step to run once resumed
+    pub step: Step,
+    /// The time remaining on the displayed timer
+    pub remaining: Duration,
+    /// The time after the displayed timer before the step runs
+    pub grace: Duration,
+}
+
+/// Timer shown to the clients for the current wait
+struct Timer {
+    /// The time the timer started
+    started: Instant,
+    /// The time shown to the clients
+    duration: Duration,
+    /// Extra time after the shown duration before the step runs so
+    /// that players with extended time can finish
+    grace: Duration,
+}
+
+/// State machine for the question lifecycle of a game. The core knows
+/// nothing of sessions or actors, it's driven by the steps and times
+/// the game passes in so the flow of a game can be checked without
+/// any connections or timers
+pub struct GameCore {
+    /// The state of the game
+    state: GameState,
+    /// The index of the current question
+    question_index: usize,
+    /// The number of questions that have been marked
+    questions_marked: usize,
+    /// The timer for the current wait
+    timer: Timer,
+    /// The step that runs once the current wait ends
+    scheduled: Option<Step>,
+    /// The step that was paused by the host
+    paused: Option<PausedStep>,
+}
+
+impl GameCore {
+    /// Creates the core for a game in the lobby
+    ///
+    /// `now` The current time
+    pub fn new(now: Instant) -> Self {
+        Self::restore(GameState::Lobby, 0, 0, now)
+    }
+
+    /// Creates the core for a game restored part way through, nothing
+    /// is scheduled until the paused step is restored
+    ///
+    /// `state`            The state of the game
+    /// `question_index`   The index of the current question
+    /// `questions_marked` The number of questions that have been marked
+    /// `now`              The current time
+    pub fn restore(
+        state: GameState,
+        question_index: usize,
+        questions_marked: usize,
+        now: Instant,
+    ) -> Self {
+        Self {
+            state,
+            question_index,
+            questions_marked,
+            timer: Timer {
+                started: now,
+                duration: Duration::ZERO,
+                grace: Duration::ZERO,
+            },
+            scheduled: None,
+            paused: None,
+        }
+    }
+
+    pub fn state(&self) -> GameState {
+        self.state
+    }
+
+    pub fn set_state(&mut self, state: GameState) {
+        self.state = state;
+    }
+
+    pub fn question_index(&self) -> usize {
+        self.question_index
+    }
+
+    pub fn questions_marked(&self) -> usize {
+        self.questions_marked
+    }
+
+    /// Whether a step is waiting to run
+    pub fn is_waiting(&self) -> bool {
+        self.scheduled.is_some()
+    }
+
+    /// The step paused by the host if there is one
+    pub fn paused(&self) -> Option<&PausedStep> {
+        self.paused.as_ref()
+    }
+
+    /// Returns the current question
+    ///
+    /// `config` The config of the game
+    pub fn question<'a>(&self, config: &'a GameConfig) -> &'a Question {
+        match config.questions.get(self.question_index) {
+            Some(value) => value,
+            None => {
+                panic!("Attempted to access a question at an index that does not exist");
+            }
+        }
+    }
+
+    /// Schedules the first question to begin after the start time
+    ///
+    /// `now` The current time
+    pub fn start(&mut self, now: Instant) {
+        self.schedule(Step::Begin(0), START_TIME, Duration::ZERO, now);
+    }
+
+    /// Schedules the step to run once the duration plus the grace time
+    /// has passed, replacing any step already waiting
+    ///
+    /// `step`     The step to run
+    /// `duration` The time shown to the clients
+    /// `grace`    The extra time after the shown time
+    /// `now`      The current time
+    pub fn schedule(&mut self, step: Step, duration: Duration, grace: Duration, now: Instant) {
+        self.timer = Timer {
+            started: now,
+            duration,
+            grace,
+        };
+        self.scheduled = Some(step);
+    }
+
+    /// Takes the scheduled step if its wait has ended
+    ///
+    /// `now` The current time
+    pub fn due(&mut self, now: Instant) -> Option<Step> {
+        let end = self.timer.started + self.timer.duration + self.timer.grace;
+        if now < end {
+            return None;
+        }
+        self.scheduled.take()
+    }
+
+    /// Takes the scheduled step without waiting for its wait to end
+    pub fn skip(&mut self) -> Option<Step> {
+        self.scheduled.take()
+    }
+
+    /// Cancels the scheduled and paused steps
+    pub fn cancel(&mut self) {
+        self.scheduled = None;
+        self.paused = None;
+    }
+
+    /// Pauses the scheduled step keeping its remaining time, returns
+    /// whether there was a step to pause
+    ///
+    /// `now` The current time
+    pub fn pause(&mut self, now: Instant) -> bool {
+        let Some(step) = self.scheduled.take() else {
+            return false;
+        };
+        let (remaining, grace) = self.remaining(now);
+        self.paused = Some(PausedStep {
+            step,
+            remaining,
+            grace,
+        });
+        true
+    }
+
+    /// Schedules the paused step again with its remaining time, returns
+    /// whether there was a paused step
+    ///
+    /// `now` The current time
+    pub fn resume(&mut self, now: Instant) -> bool {
+        let Some(paused) = self.paused.take() else {
+            return false;
+        };
+        self.schedule(paused.step, paused.remaining, paused.grace, now);
+        true
+    }
+
+    /// Restores the step that continues the game from its current state
+    /// as paused with the provided remaining time, returns false when
+    /// nothing follows the current state
+    ///
+    /// `config`    The config of the game
+    /// `remaining` The time remaining on the displayed timer
+    /// `grace`     The time after the displayed timer before the step runs
+    pub fn restore_paused(
+        &mut self,
+        config: &GameConfig,
+        remaining: Duration,
+        grace: Duration,
+    ) -> bool {
+        let step = match self.state {
+            GameState::Lobby | GameState::Finished => return false,
+            GameState::Starting => Step::Begin(0),
+            GameState::AwaitingWagers => Step::Countdown,
+            GameState::Countdown => Step::Show,
+            GameState::AwaitingReady => Step::Read,
+            GameState::Reading => Step::Open,
+            GameState::AwaitingAnswers if self.question(config).is_open_ended() => Step::Grade,
+            GameState::AwaitingAnswers | GameState::Grading => Step::Mark,
+            GameState::Marked => Step::Begin(self.question_index + 1),
+        };
+        self.paused = Some(PausedStep {
+            step,
+            remaining,
+            grace,
+        });
+        true
+    }
+
+    /// Returns the time remaining on the displayed timer along with
+    /// the remaining grace time after it
+    ///
+    /// `now` The current time
+    pub fn remaining(&self, now: Instant) -> (Duration, Duration) {
+        let elapsed = self.elapsed(now);
+        let remaining = self.timer.duration.saturating_sub(elapsed);
+        let total_remaining = (self.timer.duration + self.timer.grace).saturating_sub(elapsed);
+        (remaining, total_remaining - remaining)
+    }
+
+    /// Returns the total time of the current wait including the grace
+    pub fn wait_time(&self) -> Duration {
+        self.timer.duration + self.timer.grace
+    }
+
+    /// Returns the time since the timer started
+    ///
+    /// `now` The current time
+    pub fn elapsed(&self, now: Instant) -> Duration {
+        now.saturating_duration_since(self.timer.started)
+    }
+
+    /// Returns the time shown to the clients
+    pub fn total(&self) -> Duration {
+        self.timer.duration
+    }
+
+    /// Returns the total and elapsed milliseconds to sync the timers
+    /// of the clients with, the elapsed time stops at the total
+    ///
+    /// `now` The current time
+    pub fn time_sync(&self, now: Instant) -> (u64, u64) {
+        let total = self.timer.duration.as_millis() as u64;
+        let elapsed = self.elapsed(now).as_millis() as u64;
+        (total, elapsed.min(total))
+    }
+
+    /// Whether an answer from a player with the time multiplier would
+    /// arrive after the time the player was given, an answer arriving
+    /// exactly at the deadline is still in time
+    ///
+    /// `multiplier` The answer time multiplier of the player
+    /// `now`        The current time
+    pub fn is_late(&self, multiplier: f32, now: Instant) -> bool {
+        self.elapsed(now) > self.timer.duration.mul_f32(multiplier)
+    }
+
+    /// Enters the step updating the question and state, returns the
+    /// state the game moved to if the step changes it
+    ///
+    /// `config` The config of the game
+    /// `step`   The step being run
+    pub fn enter(&mut self, config: &GameConfig, step: Step) -> Option<GameState> {
+        let state = match step {
+            Step::Begin(index) => {
+                self.question_index = index;
+                // Wagers are placed before the question is shown
+                if self.question(config).point_modifier() == PointModifier::Wager {
+                    GameState::AwaitingWagers
+                } else {
+                    return None;
+                }
+            }
+            Step::Countdown if self.timing(config).countdown_time == 0 => return None,
+            Step::Countdown => GameState::Countdown,
+            Step::Show => GameState::AwaitingReady,
+            Step::Read if self.timing(config).reading_time == 0 => return None,
+            Step::Read => GameState::Reading,
+            Step::Open => GameState::AwaitingAnswers,
+            Step::Grade => GameState::Grading,
+            Step::Mark => {
+                self.questions_marked = self.question_index + 1;
+                GameState::Marked
+            }
+        };
+        self.state = state;
+        Some(state)
+    }
+
+    /// Decides what follows the step once the game has run it and
+    /// schedules the next step when it should wait
+    ///
+    /// `config`   The config of the game
+    /// `step`     The step that was run
+    /// `progress` The progress of the players
+    /// `now`      The current time
+    pub fn next(
+        &mut self,
+        config: &GameConfig,
+        step: Step,
+        progress: &Progress,
+        now: Instant,
+    ) -> Next {
+        let timing = self.timing(config);
+        let millis = Duration::from_millis;
+
+        let (next, duration, grace) = match step {
+            Step::Begin(_) if matches!(self.state, GameState::AwaitingWagers) => {
+                (Step::Countdown, millis(timing.wait_time), Duration::ZERO)
+            }
+            Step::Begin(_) => return Next::Now(Step::Countdown),
+            Step::Countdown if timing.countdown_time == 0 => return Next::Now(Step::Show),
+            Step::Countdown => (Step::Show, millis(timing.countdown_time), Duration::ZERO),
+            // Players that aren't ready by the end of the wait time are left behind
+            Step::Show => (Step::Read, millis(timing.wait_time), Duration::ZERO),
+            Step::Read if timing.reading_time == 0 => return Next::Now(Step::Open),
+            Step::Read => (Step::Open, millis(timing.reading_time), Duration::ZERO),
+            Step::Open => {
+                let question = self.question(config);
+                let answer_time = question.answer_time();
+
+                // Wait long enough for the player with the most extended time
+                let grace = answer_time.mul_f32(progress.multiplier.max(1.0)) - answer_time;
+
+                // Open ended answers must be graded by the host before marking
+                let next = if question.is_open_ended() {
+                    Step::Grade
+                } else {
+                    Step::Mark
+                };
+                (next, answer_time, grace)
+            }
+            Step::Grade if progress.submissions == 0 => return Next::Now(Step::Mark),
+            Step::Grade => (Step::Mark, GRADING_TIME, Duration::ZERO),
+            Step::Mark => {
+                let index = self.question_index + 1;
+                if index >= config.questions.len() || progress.stop {
+                    return Next::Finish;
+                }
+                (Step::Begin(index), millis(timing.wait_time), Duration::ZERO)
+            }
+        };
+
+        self.schedule(next, duration, grace, now);
+        Next::Wait
+    }
+
+    /// Resolves the timing for the current question
+    ///
+    /// `config` The config of the game
+    fn timing(&self, config: &GameConfig) -> GameTiming {
+        self.question(config).timing(&config.timing)
+    }
+}
+
+/// Scores the answers to a question apart from the sessions so the
+/// scoring rules can be checked on their own
+pub struct Marking<'a> {
+    /// The config of the game
+    config: &'a GameConfig,
+    /// The question being marked
+    question: &'a Question,
+    /// The grades the host gave to the open ended submissions
+    grades: &'a HashMap<SessionId, Grade>,
+    /// The players that answered correctly in the order their answers
+    /// arrived, only collected in buzzer mode
+    buzz_order: Vec<SessionId>,
+}
+
+impl<'a> Marking<'a> {
+    /// Creates the marking for the question
+    ///
+    /// `config`   The config of the game
+    /// `index`    The index of the question being marked
+    /// `grades`   The grades the host gave
+    /// `answers`  The answer of each player to the question
+    pub fn new<'b>(
+        config: &'a GameConfig,
+        index: usize,
+        grades: &'a HashMap<SessionId, Grade>,
+        answers: impl Iterator<Item = (SessionId, Option<&'b PlayerAnswer>)>,
+    ) -> Self {
+        let question = &config.questions[index];
+
+        let buzz_order = match config.basic.mode {
+            GameMode::Buzzer { .. } => {
+                let mut correct: Vec<(usize, SessionId)> = answers
+                    .filter_map(|(id, answer)| {
+                        let answer = answer?;
+                        let result = question.ty().mark(&answer.answer, 0)?;
+                        result.is_correct().then_some((answer.arrival, id))
+                    })
+                    .collect();
+                correct.sort_by_key(|(arrival, _)| *arrival);
+                correct.into_iter().map(|(_, id)| id).collect()
+            }
+            _ => Vec::new(),
+        };
+
+        Self {
+            config,
+            question,
+            grades,
+            buzz_order,
+        }
+    }
+
+    /// Marks the answer of a player updating its score and streak,
+    /// returns `None` when the player didn't answer or the answer
+    /// couldn't be marked
+    ///
+    /// `id`         The ID of the player
+    /// `answer`     The answer of the player
+    /// `multiplier` The answer time multiplier of the player
+    /// `wager`      The amount the player wagered on the question
+    /// `score`      The total score of the player
+    /// `streak`     The streak of the player
+    pub fn mark(
+        &self,
+        id: SessionId,
+        answer: Option<&PlayerAnswer>,
+        multiplier: f32,
+        wager: Option<u32>,
+        score: &mut u32,
+        streak: &mut u32,
+    ) -> Option<AnswerResult> {
+        let question = self.question;
+        let scoring = &self.config.basic.scoring;
+
+        let Some(answer) = answer else {
+            // Player did not answer the question, polls have no
+            // correct answer so they don't break streaks
+            if !question.ty().is_poll() {
+                *streak = 0;
+            }
+            if let Some(wager) = wager {
+                *score = lose_wager(*score, wager);
+            }
+            return None;
+        };
+
+        // Time the answer arrived at the game rather than any client time,
+        // scaled so extended time players are scored on the same curve
+        let timing = question.timing(&self.config.timing);
+        let elapsed = answer.elapsed.div_f32(multiplier);
+        let is_bonus = elapsed.as_millis() as u64 <= timing.bonus_score_time;
+
+        // Flagged answers are scored as if they arrived at the last moment
+        let nullified = answer.flagged
+            && self
+                .config
+                .anti_cheat
+                .as_ref()
+                .is_some_and(|anti_cheat| anti_cheat.nullify_speed_bonus);
+        let (elapsed, is_bonus) = if nullified {
+            (question.answer_time(), false)
+        } else {
+            (elapsed, is_bonus)
+        };
+
+        let base_score = scoring.score(
+            elapsed,
+            question.answer_time(),
+            question.multiplier(),
+            is_bonus,
+        );
+
+        let result = if question.is_open_ended() {
+            // Ungraded submissions are treated as incorrect
+            self.grades
+                .get(&id)
+                .map_or(AnswerResult::Incorrect, |grade| grade.result(base_score))
+        } else {
+            match question.ty().mark(&answer.answer, base_score) {
+                Some(result) => result,
+                None => {
+                    error!("Mis matched question and answer types don't know how to mark.");
+                    return None;
+                }
+            }
+        };
+
+        // Later correct answers score less in buzzer mode
+        let result = match self.config.basic.mode {
+            GameMode::Buzzer { decay } if result.is_correct() => {
+                let position = self
+                    .buzz_order
+                    .iter()
+                    .position(|value| *value == id)
+                    .unwrap_or_default();
+                result.map_score(|score| buzzer_score(score, decay, position))
+            }
+            _ => result,
+        };
+
+        // Apply the streak bonus to fully correct answers
+        let result = match result {
+            AnswerResult::Participated(_) => result,
+            result if result.is_correct() => {
+                *streak += 1;
+                result.map_score(|score| scoring.apply_streak(score, *streak))
+            }
+            result => {
+                *streak = 0;
+                result
+            }
+        };
+
+        // Keep the answer within the most a question can score
+        let max_score = scoring.max_score(question.multiplier());
+        let result = result.map_score(|score| score.min(max_score));
+
+        let result = match question.point_modifier() {
+            PointModifier::Normal => result,
+            PointModifier::Double => result.map_score(double_score),
+            PointModifier::Wager => {
+                let wager = wager.unwrap_or(0);
+                if result.is_correct() {
+                    result.map_score(|score| win_wager(score, wager))
+                } else {
+                    *score = lose_wager(*score, wager);
+                    result
+                }
+            }
+        };
+
+        *score = score.saturating_add(result.score());
+        Some(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::*;
+
+    /// Creates a config with the provided timing and questions
+    fn config(timing: Value, questions: Vec<Value>) -> GameConfig {
+        serde_json::from_value(json!({
+            "basic": { "name": "Quiz", "text": "" },
+            "timing": timing,
+            "questions": questions,
+        }))
+        .unwrap()
+    }
+
+    /// Timing with every wait enabled
+    fn timing() -> Value {
+        json!({
+            "wait_time": 2000,
+            "bonus_score_time": 1000,
+            "countdown_time": 3000,
+            "reading_time": 1000,
+        })
+    }
+
+    /// Question of the provided type with ten seconds to answer
+    fn question(ty: Value) -> Value {
+        json!({ "title": "Question", "text": "", "ty": ty, "answer_time": 10000 })
+    }
+
+    fn single() -> Value {
+        question(json!({ "Single": { "answers": [0], "values": ["A", "B"] } }))
+    }
+
+    fn progress() -> Progress {
+        Progress {
+            multiplier: 1.0,
+            submissions: 0,
+            stop: false,
+        }
+    }
+
+    /// Runs the step the way the game does, returning what follows it
+    fn run(core: &mut GameCore, config: &GameConfig, step: Step, now: Instant) -> Next {
+        run_with(core, config, step, &progress(), now)
+    }
+
+    fn run_with(
+        core: &mut GameCore,
+        config: &GameConfig,
+        step: Step,
+        progress: &Progress,
+        now: Instant,
+    ) -> Next {
+        core.enter(config, step);
+        core.next(config, step, progress, now)
+    }
+
+    #[test]
+    fn questions_run_through_every_step() {
+        let config = config(timing(), vec![single(), single()]);
+        let now = Instant::now();
+        let mut core = GameCore::new(now);
+
+        core.start(now);
+        assert_eq!(core.due(now + Duration::from_millis(4999)), None);
+        assert_eq!(core.due(now + START_TIME), Some(Step::Begin(0)));
+
+        assert_eq!(
+            run(&mut core, &config, Step::Begin(0), now),
+            Next::Now(Step::Countdown)
+        );
+        assert_eq!(core.question_index(), 0);
+
+        assert_eq!(run(&mut core, &config, Step::Countdown, now), Next::Wait);
+        assert!(matches!(core.state(), GameState::Countdown));
+        assert_eq!(
+            core.due(now + Duration::from_millis(3000)),
+            Some(Step::Show)
+        );
+
+        assert_eq!(run(&mut core, &config, Step::Show, now), Next::Wait);
+        assert!(matches!(core.state(), GameState::AwaitingReady));
+        assert_eq!(
+            core.due(now + Duration::from_millis(2000)),
+            Some(Step::Read)
+        );
+
+        assert_eq!(run(&mut core, &config, Step::Read, now), Next::Wait);
+        assert!(matches!(core.state(), GameState::Reading));
+        assert_eq!(
+            core.due(now + Duration::from_millis(1000)),
+            Some(Step::Open)
+        );
+
+        assert_eq!(run(&mut core, &config, Step::Open, now), Next::Wait);
+        assert!(matches!(core.state(), GameState::AwaitingAnswers));
+        assert_eq!(
+            core.due(now + Duration::from_millis(10000)),
+            Some(Step::Mark)
+        );
+
+        assert_eq!(run(&mut core, &config, Step::Mark, now), Next::Wait);
+        assert!(matches!(core.state(), GameState::Marked));
+        assert_eq!(core.questions_marked(), 1);
+        assert_eq!(
+            core.due(now + Duration::from_millis(2000)),
+            Some(Step::Begin(1))
+        );
+
+        run(&mut core, &config, Step::Begin(1), now);
+        assert_eq!(core.question_index(), 1);
+        assert_eq!(run(&mut core, &config, Step::Mark, now), Next::Finish);
+        assert_eq!(core.questions_marked(), 2);
+    }
+
+    #[test]
+    fn extended_time_delays_marking_by_grace() {
+        let config = config(timing(), vec![single()]);
+        let now = Instant::now();
+        let mut core = GameCore::new(now);
+        core.enter(&config, Step::Begin(0));
+
+        let progress = Progress {
+            multiplier: 1.5,
+            ..progress()
+        };
+        assert_eq!(
+            run_with(&mut core, &config, Step::Open, &progress, now),
+            Next::Wait
+        );
+        assert_eq!(core.total(), Duration::from_millis(10000));
+        assert_eq!(core.wait_time(), Duration::from_millis(15000));
+        assert_eq!(core.due(now + Duration::from_millis(10000)), None);
+        assert_eq!(
+            core.due(now + Duration::from_millis(15000)),
+            Some(Step::Mark)
+        );
+    }
+
+    #[test]
+    fn zero_countdown_and_reading_are_skipped() {
+        let timing = json!({
+            "wait_time": 2000,
+            "bonus_score_time": 1000,
+            "countdown_time": 0,
+            "reading_time": 0,
+        });
+        let config = config(timing, vec![single()]);
+        let now = Instant::now();
+        let mut core = GameCore::new(now);
+
+        run(&mut core, &config, Step::Begin(0), now);
+        assert!(core.enter(&config, Step::Countdown).is_none());
+        assert_eq!(
+            core.next(&config, Step::Countdown, &progress(), now),
+            Next::Now(Step::Show)
+        );
+        assert!(!core.is_waiting());
+
+        assert_eq!(run(&mut core, &config, Step::Show, now), Next::Wait);
+        assert_eq!(
+            core.due(now + Duration::from_millis(2000)),
+            Some(Step::Read)
+        );
+
+        assert!(core.enter(&config, Step::Read).is_none());
+        assert!(matches!(core.state(), GameState::AwaitingReady));
+        assert_eq!(
+            core.next(&config, Step::Read, &progress(), now),
+            Next::Now(Step::Open)
+        );
+    }
+
+    #[test]
+    fn question_timing_overrides_the_game_timing() {
+        let mut overridden = single();
+        overridden["timing"] = json!({ "countdown_time": 0 });
+        let config = config(timing(), vec![overridden]);
+        let now = Instant::now();
+        let mut core = GameCore::new(now);
+
+        run(&mut core, &config, Step::Begin(0), now);
+        assert_eq!(
+            run(&mut core, &config, Step::Countdown, now),
+            Next::Now(Step::Show)
+        );
+    }
+
+    #[test]
+    fn open_ended_without_submissions_is_marked_straight_away() {
+        let config = config(timing(), vec![question(json!({ "OpenEnded": {} }))]);
+        let now = Instant::now();
+        let mut core = GameCore::new(now);
+        core.enter(&config, Step::Begin(0));
+
+        assert_eq!(run(&mut core, &config, Step::Open, now), Next::Wait);
+        assert_eq!(
+            core.due(now + Duration::from_millis(10000)),
+            Some(Step::Grade)
+        );
+
+        assert_eq!(
+            run(&mut core, &config, Step::Grade, now),
+            Next::Now(Step::Mark)
+        );
+        assert!(matches!(core.state(), GameState::Grading));
+        assert!(!core.is_waiting());
+    }
+
+    #[test]
+    fn open_ended_submissions_wait_for_grading() {
+        let config = config(timing(), vec![question(json!({ "OpenEnded": {} }))]);
+        let now = Instant::now();
+        let mut core = GameCore::new(now);
+        core.enter(&config, Step::Begin(0));
+
+        let progress = Progress {
+            submissions: 2,
+            ..progress()
+        };
+        assert_eq!(
+            run_with(&mut core, &config, Step::Grade, &progress, now),
+            Next::Wait
+        );
+        assert_eq!(core.total(), GRADING_TIME);
+        assert_eq!(core.due(now + GRADING_TIME), Some(Step::Mark));
+    }
+
+    #[test]
+    fn wager_questions_wait_for_wagers() {
+        let mut wager = single();
+        wager["point_modifier"] = json!("Wager");
+        let config = config(timing(), vec![wager]);
+        let now = Instant::now();
+        let mut core = GameCore::new(now);
+
+        assert!(matches!(
+            core.enter(&config, Step::Begin(0)),
+            Some(GameState::AwaitingWagers)
+        ));
+        assert_eq!(
+            core.next(&config, Step::Begin(0), &progress(), now),
+            Next::Wait
+        );
+        assert_eq!(
+            core.due(now + Duration::from_millis(2000)),
+            Some(Step::Countdown)
+        );
+    }
+
+    #[test]
+    fn unanswered_wager_is_lost() {
+        let mut wager = single();
+        wager["point_modifier"] = json!("Wager");
+        let config = config(timing(), vec![wager]);
+        let grades = HashMap::new();
+        let marking = Marking::new(&config, 0, &grades, std::iter::empty());
+
+        let (mut score, mut streak) = (1000, 3);
+        assert!(marking
+            .mark(1, None, 1.0, Some(300), &mut score, &mut streak)
+            .is_none());
+        assert_eq!(score, 700);
+        assert_eq!(streak, 0);
+
+        // Wagers larger than the score leave the player at zero
+        assert!(marking
+            .mark(1, None, 1.0, Some(5000), &mut score, &mut streak)
+            .is_none());
+        assert_eq!(score, 0);
+    }
+
+    #[test]
+    fn stop_finishes_before_the_last_question() {
+        let config = config(timing(), vec![single(), single(), single()]);
+        let now = Instant::now();
+        let mut core = GameCore::new(now);
+        core.enter(&config, Step::Begin(0));
+
+        let progress = Progress {
+            stop: true,
+            ..progress()
+        };
+        assert_eq!(
+            run_with(&mut core, &config, Step::Mark, &progress, now),
+            Next::Finish
+        );
+        assert_eq!(core.questions_marked(), 1);
+        assert!(!core.is_waiting());
+    }
+}
@@ -0,0 +1,464 @@
+use std::{
+    collections::HashMap,
+    io,
+    rc::Rc,
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+use actix_web::rt;
+use awc::ws::{Frame, Message};
+use futures_util::{SinkExt, StreamExt};
+use rand_chacha::ChaCha8Rng;
+use rand_core::{OsRng, RngCore, SeedableRng};
+use serde_json::{json, Value};
+
+use crate::game::{shuffle, QuestionAnswer};
+
+/// Options for a load test run, parsed from the arguments after the
+/// `loadtest` subcommand
+///
+/// ```text
+/// quizler loadtest --token ABCDE --clients 500 --latency normal:800,300
+/// ```
+struct Options {
+    /// The websocket URL of the server
+    url: String,
+    /// The token of the game the bots join
+    token: String,
+    /// The number of bots to connect
+    clients: usize,
+    /// The time the connections are spread over
+    ramp: Duration,
+    /// The distribution of the time bots take to answer
+    latency: Latency,
+    /// The maximum time the test runs for
+    duration: Duration,
+    /// The PIN for games that require one
+    pin: Option<String>,
+}
+
+impl Options {
+    /// Parses the options from the command line arguments
+    ///
+    /// `args` The arguments after the subcommand
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut options = Self {
+            url: "ws://127.0.0.1/ws".to_string(),
+            token: String::new(),
+            clients: 100,
+            ramp: Duration::from_secs(5),
+            latency: Latency::Uniform {
+                min: 500.0,
+                max: 5000.0,
+            },
+            duration: Duration::from_secs(600),
+            pin: None,
+        };
+
+        while let Some(arg) = args.next() {
+            let value = args
+                .next()
+                .ok_or_else(|| format!("Missing value for {}", arg))?;
+            match arg.as_str() {
+                "--url" => options.url = value,
+                "--token" => options.token = value,
+                "--clients" => options.clients = parse_value(&arg, &value)?,
+                "--ramp" => options.ramp = Duration::from_secs(parse_value(&arg, &value)?),
+                "--latency" => options.latency = value.parse()?,
+                "--duration" => options.duration = Duration::from_secs(parse_value(&arg, &value)?),
+                "--pin" => options.pin = Some(value),
+                _ => return Err(format!("Unknown option {}", arg)),
+            }
+        }
+
+        if options.token.is_empty() {
+            return Err("--token is required".to_string());
+        }
+        Ok(options)
+    }
+}
+
+/// Parses the value of an option
+///
+/// `arg`   The option name used in the error
+/// `value` The value to parse
+fn parse_value<T: FromStr>(arg: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("Invalid value for {}: {}", arg, value))
+}
+
+/// Distribution of the time in milliseconds that bots wait after the
+/// answers open before answering
+enum Latency {
+    /// Always the same time, `fixed:MS`
+    Fixed(f64),
+    /// Evenly spread between two times, `uniform:MIN-MAX`
+    Uniform { min: f64, max: f64 },
+    /// Normally distributed around the mean, `normal:MEAN,STD_DEV`
+    Normal { mean: f64, std_dev: f64 },
+    /// Exponentially distributed with the mean, `exp:MEAN`
+    Exponential { mean: f64 },
+}
+
+impl FromStr for Latency {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid latency distribution: {}", s);
+        let (kind, params) = s.split_once(':').ok_or_else(invalid)?;
+        let number = |value: &str| value.trim().parse::<f64>().map_err(|_| invalid());
+
+        let latency = match kind {
+            "fixed" => Self::Fixed(number(params)?),
+            "uniform" => {
+                let (min, max) = params.split_once('-').ok_or_else(invalid)?;
+                Self::Uniform {
+                    min: number(min)?,
+                    max: number(max)?,
+                }
+            }
+            "normal" => {
+                let (mean, std_dev) = params.split_once(',').ok_or_else(invalid)?;
+                Self::Normal {
+                    mean: number(mean)?,
+                    std_dev: number(std_dev)?,
+                }
+            }
+            "exp" => Self::Exponential {
+                mean: number(params)?,
+            },
+            _ => return Err(invalid()),
+        };
+        Ok(latency)
+    }
+}
+
+impl Latency {
+    /// Samples a delay from the distribution
+    ///
+    /// `rng` The random number generator
+    fn sample(&self, rng: &mut impl RngCore) -> Duration {
+        let millis = match *self {
+            Self::Fixed(value) => value,
+            Self::Uniform { min, max } => min + (max - min) * unit(rng),
+            Self::Normal { mean, std_dev } => {
+                // Box-Muller transform of two uniform samples
+                let radius = (-2.0 * (1.0 - unit(rng)).ln()).sqrt();
+                let angle = std::f64::consts::TAU * unit(rng);
+                mean + std_dev * radius * angle.cos()
+            }
+            Self::Exponential { mean } => -mean * (1.0 - unit(rng)).ln(),
+        };
+        Duration::from_secs_f64(millis.max(0.0) / 1000.0)
+    }
+}
+
+/// Samples a uniform value in the range `[0, 1)`
+///
+/// `rng` The random number generator
+fn unit(rng: &mut impl RngCore) -> f64 {
+    (rng.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Measurements made by a single bot
+#[derive(Default)]
+struct BotReport {
+    /// The time from opening the connection to joining the game
+    joined: Option<Duration>,
+    /// The number of messages received
+    messages: u64,
+    /// The number of bytes received
+    bytes: u64,
+    /// The number of answers sent
+    answers: u64,
+    /// The errors sent by the server
+    errors: u64,
+    /// The time each question opened for answering
+    opened: HashMap<usize, Instant>,
+    /// Why the bot stopped early
+    failure: Option<String>,
+}
+
+/// Runs the load test connecting the bots to the game and printing
+/// the report once the game finishes or the test duration ends
+///
+/// `args` The arguments after the subcommand
+pub async fn run(args: impl Iterator<Item = String>) -> io::Result<()> {
+    let options = Options::parse(args).map_err(io::Error::other)?;
+    let options = Rc::new(options);
+    println!(
+        "Connecting {} bots to {} on {}",
+        options.clients, options.token, options.url
+    );
+
+    let start = Instant::now();
+    let spacing = options.ramp / options.clients.max(1) as u32;
+    let mut handles = Vec::with_capacity(options.clients);
+    for index in 0..options.clients {
+        let options = options.clone();
+        let delay = spacing * index as u32;
+        handles.push(rt::spawn(async move {
+            rt::time::sleep(delay).await;
+            let mut report = BotReport::default();
+            let bot = run_bot(&options, index, &mut report);
+            if rt::time::timeout(options.duration.saturating_sub(delay), bot)
+                .await
+                .is_err()
+            {
+                report
+                    .failure
+                    .get_or_insert_with(|| "timed out".to_string());
+            }
+            report
+        }));
+    }
+
+    let mut reports = Vec::with_capacity(handles.len());
+    for handle in handles {
+        if let Ok(report) = handle.await {
+            reports.push(report);
+        }
+    }
+
+    print_report(&reports, start.elapsed());
+    Ok(())
+}
+
+/// Connects a single bot to the game and answers each question with
+/// a random answer after a delay from the latency distribution until
+/// the game finishes or the connection is closed
+///
+/// `options` The load test options
+/// `index`   The index of the bot used in its name
+/// `report`  The report the measurements are written to
+async fn run_bot(options: &Options, index: usize, report: &mut BotReport) {
+    let mut rng = ChaCha8Rng::seed_from_u64(OsRng.next_u64());
+    let connecting = Instant::now();
+
+    let mut socket = match awc::Client::new().ws(options.url.as_str()).connect().await {
+        Ok((_, socket)) => socket,
+        Err(err) => {
+            report.failure = Some(format!("connect failed: {}", err));
+            return;
+        }
+    };
+
+    let join = json!({
+        "ty": "TryConnect",
+        "token": options.token,
+        "username": format!("Bot {}", index + 1),
+        "pin": options.pin,
+    });
+    if socket
+        .send(Message::Text(join.to_string().into()))
+        .await
+        .is_err()
+    {
+        report.failure = Some("failed to send join".to_string());
+        return;
+    }
+
+    // The question currently shown and the number of questions seen
+    let mut question: Option<Value> = None;
+    let mut questions = 0;
+
+    while let Some(frame) = socket.next().await {
+        let text = match frame {
+            Ok(Frame::Text(bytes)) => bytes,
+            Ok(Frame::Ping(payload)) => {
+                let _ = socket.send(Message::Pong(payload)).await;
+                continue;
+            }
+            Ok(Frame::Close(_)) => break,
+            Ok(_) => continue,
+            Err(err) => {
+                report.failure = Some(format!("protocol error: {}", err));
+                break;
+            }
+        };
+        report.messages += 1;
+        report.bytes += text.len() as u64;
+
+        let Ok(message) = serde_json::from_slice::<Value>(&text) else {
+            continue;
+        };
+        // The question type of questions is written after the message
+        // type under the same key, so any object type is a question
+        let ty = match &message["ty"] {
+            Value::String(value) => value.as_str(),
+            Value::Object(_) => {
+                questions += 1;
+                question = Some(message["ty"].clone());
+                continue;
+            }
+            _ => continue,
+        };
+
+        match ty {
+            "Connected" => report.joined = Some(connecting.elapsed()),
+            "Error" => report.errors += 1,
+            "GameState" if message.get("Finished").is_some() => break,
+            "BeginQuestion" => {
+                report.opened.insert(questions, Instant::now());
+                let Some(answer) = question.as_ref().and_then(|ty| random_answer(ty, &mut rng))
+                else {
+                    continue;
+                };
+
+                rt::time::sleep(options.latency.sample(&mut rng)).await;
+                let answer = Message::Text(answer.to_string().into());
+                if socket.send(answer).await.is_err() {
+                    report.failure = Some("failed to send answer".to_string());
+                    break;
+                }
+                report.answers += 1;
+            }
+            _ => {}
+        }
+    }
+
+    if report.joined.is_none() {
+        report
+            .failure
+            .get_or_insert_with(|| "never joined".to_string());
+    }
+    let _ = socket.send(Message::Close(None)).await;
+}
+
+/// Creates a random answer message for the question type, returns
+/// `None` for types that aren't answered
+///
+/// `ty`  The question type from the question message
+/// `rng` The random number generator
+fn random_answer(ty: &Value, rng: &mut impl RngCore) -> Option<Value> {
+    let (kind, content) = ty.as_object()?.iter().next()?;
+    let count = content["values"].as_array().map_or(0, Vec::len).max(1);
+
+    let answer = match kind.as_str() {
+        "Single" | "Poll" => QuestionAnswer::Single {
+            answer: random_index(rng, count),
+        },
+        "MultiSelect" => QuestionAnswer::MultiSelect {
+            answers: vec![random_index(rng, count)],
+        },
+        "TrueFalse" => QuestionAnswer::TrueFalse {
+            answer: rng.next_u64().is_multiple_of(2),
+        },
+        "TextEntry" | "OpenEnded" => QuestionAnswer::TextEntry {
+            answer: "bot".to_string(),
+        },
+        "Numeric" => QuestionAnswer::Numeric {
+            answer: unit(rng) * 100.0,
+        },
+        "ClickableImage" | "Hotspot" => QuestionAnswer::ClickableImage {
+            answer: (unit(rng) as f32, unit(rng) as f32),
+        },
+        "Ordering" => {
+            let mut order: Vec<usize> = (0..count).collect();
+            shuffle(rng, &mut order);
+            QuestionAnswer::Ordering { order }
+        }
+        _ => return None,
+    };
+
+    let mut message = serde_json::to_value(answer).ok()?;
+    message
+        .as_object_mut()?
+        .insert("ty".to_string(), json!("Answer"));
+    Some(message)
+}
+
+/// Picks a random index below the count
+///
+/// `rng`   The random number generator
+/// `count` The number of values to pick from
+fn random_index(rng: &mut impl RngCore, count: usize) -> usize {
+    (rng.next_u64() % count as u64) as usize
+}
+
+/// Prints the throughput and latency percentiles measured by the bots
+///
+/// `reports` The reports of each bot
+/// `elapsed` The time the test ran for
+fn print_report(reports: &[BotReport], elapsed: Duration) {
+    let joined: Vec<Duration> = reports.iter().filter_map(|report| report.joined).collect();
+    let messages: u64 = reports.iter().map(|report| report.messages).sum();
+    let bytes: u64 = reports.iter().map(|report| report.bytes).sum();
+    let answers: u64 = reports.iter().map(|report| report.answers).sum();
+    let errors: u64 = reports.iter().map(|report| report.errors).sum();
+    let secs = elapsed.as_secs_f64().max(f64::EPSILON);
+
+    // How long after the first bot each bot saw each question open,
+    // this is the time taken to broadcast to every connection
+    let mut first: HashMap<usize, Instant> = HashMap::new();
+    for (question, at) in reports.iter().flat_map(|report| &report.opened) {
+        first
+            .entry(*question)
+            .and_modify(|value| *value = (*value).min(*at))
+            .or_insert(*at);
+    }
+    let spread: Vec<Duration> = reports
+        .iter()
+        .flat_map(|report| &report.opened)
+        .map(|(question, at)| at.duration_since(first[question]))
+        .collect();
+
+    let mut failures: HashMap<&str, usize> = HashMap::new();
+    for failure in reports
+        .iter()
+        .filter_map(|report| report.failure.as_deref())
+    {
+        *failures.entry(failure).or_default() += 1;
+    }
+
+    println!();
+    println!("Ran for {:.1}s", secs);
+    println!("Bots joined:       {}/{}", joined.len(), reports.len());
+    println!(
+        "Messages received: {} ({:.0}/s)",
+        messages,
+        messages as f64 / secs
+    );
+    println!(
+        "Bytes received:    {} ({:.1} KiB/s)",
+        bytes,
+        bytes as f64 / secs / 1024.0
+    );
+    println!(
+        "Answers sent:      {} ({:.1}/s)",
+        answers,
+        answers as f64 / secs
+    );
+    println!("Errors received:   {}", errors);
+    print_percentiles("Join latency", joined);
+    print_percentiles("Broadcast spread", spread);
+    for (failure, count) in failures {
+        println!("Failed ({}): {}", failure, count);
+    }
+}
+
+/// Prints the percentiles of the provided durations
+///
+/// `name`   The name of the measurement
+/// `values` The measured durations
+fn print_percentiles(name: &str, mut values: Vec<Duration>) {
+    if values.is_empty() {
+        println!("{}: no samples", name);
+        return;
+    }
+    values.sort_unstable();
+    let percentile = |p: f64| {
+        let index = ((values.len() - 1) as f64 * p).round() as usize;
+        values[index].as_secs_f64() * 1000.0
+    };
+    println!(
+        "{}: p50 {:.1}ms  p90 {:.1}ms  p99 {:.1}ms  max {:.1}ms",
+        name,
+        percentile(0.5),
+        percentile(0.9),
+        percentile(0.99),
+        percentile(1.0)
+    );
+}
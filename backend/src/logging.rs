@@ -0,0 +1,29 @@
+use tracing_subscriber::{fmt, layer::SubscriberExt, util::SubscriberInitExt, EnvFilter, Layer};
+
+use crate::config;
+
+/// Initializes the logger writing structured JSON lines unless plain
+/// text output has been requested, the levels are filtered using the
+/// RUST_LOG environment variable defaulting to info
+pub fn init() {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+
+    let output = match config::from_env_string(config::LOG_FORMAT).as_str() {
+        "text" => fmt::layer().boxed(),
+        _ => fmt::layer()
+            .json()
+            .with_current_span(true)
+            .with_span_list(false)
+            .boxed(),
+    };
+
+    let subscriber = tracing_subscriber::registry().with(filter).with(output);
+
+    // Spans are exported to the collector when one is configured
+    #[cfg(feature = "otel")]
+    let subscriber = subscriber.with(crate::telemetry::layer());
+
+    if let Err(err) = subscriber.try_init() {
+        eprintln!("Failed to initialize logger: {}", err);
+    }
+}
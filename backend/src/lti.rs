@@ -0,0 +1,488 @@
+use std::{
+    collections::HashMap,
+    sync::{Mutex, OnceLock},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use jsonwebtoken::{
+    decode, decode_header, encode, jwk::JwkSet, Algorithm, DecodingKey, EncodingKey, Header,
+    Validation,
+};
+use rand_core::{OsRng, RngCore};
+use reqwest::{Client, Url};
+use serde::{Deserialize, Serialize};
+use tracing::{error, warn};
+use uuid::Uuid;
+
+use crate::{
+    config,
+    storage::{GetLtiSubjects, Storage},
+};
+
+/// How long a launch has to be completed after the login initiation
+const STATE_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+/// How long the client assertions sent to the platform are valid for
+const ASSERTION_TTL: u64 = 5 * 60;
+
+/// The only launch message type games can be launched from
+const RESOURCE_LINK_REQUEST: &str = "LtiResourceLinkRequest";
+
+/// Role given to instructors by the platform
+const INSTRUCTOR_ROLE: &str = "http://purl.imsglobal.org/vocab/lis/v2/membership#Instructor";
+
+/// Scope for posting scores to line items
+const SCORE_SCOPE: &str = "https://purl.imsglobal.org/spec/lti-ags/scope/score";
+
+/// Scope for creating line items
+const LINEITEM_SCOPE: &str = "https://purl.imsglobal.org/spec/lti-ags/scope/lineitem";
+
+/// Errors that can occur while launching or posting scores
+#[derive(Debug)]
+pub enum LtiError {
+    /// No platform has been configured
+    NotConfigured,
+    /// The state didn't match a pending launch
+    InvalidState,
+    /// The launch token was invalid or not from the platform
+    InvalidToken,
+    /// The launch didn't grant access to the gradebook
+    NoGradebook,
+    /// A request to the platform failed
+    Platform(reqwest::Error),
+    /// The client assertion couldn't be signed
+    Signing(jsonwebtoken::errors::Error),
+}
+
+impl From<reqwest::Error> for LtiError {
+    fn from(value: reqwest::Error) -> Self {
+        Self::Platform(value)
+    }
+}
+
+/// Gradebook of the resource link a game was launched from that the
+/// scores of the players are posted back to
+#[derive(Clone, Serialize, Deserialize)]
+pub struct LtiContext {
+    /// The ID of the resource link within the platform
+    pub resource_link: String,
+    /// The URL of the line items container for creating a line item
+    #[serde(default)]
+    pub lineitems: Option<String>,
+    /// The URL of the line item for the resource link
+    #[serde(default)]
+    pub lineitem: Option<String>,
+    /// The scopes the platform granted for the gradebook
+    #[serde(default)]
+    pub scope: Vec<String>,
+}
+
+/// Verified launch from the platform
+pub struct Launch {
+    /// The ID of the user within the platform
+    pub subject: String,
+    /// The display name of the user if the platform shared it
+    pub name: Option<String>,
+    /// Whether the user is an instructor in the launch context
+    pub instructor: bool,
+    /// The gradebook of the resource link if the platform shared it
+    pub context: Option<LtiContext>,
+}
+
+/// Parameters the platform initiates a login with
+#[derive(Deserialize)]
+pub struct LoginParams {
+    /// The issuer of the platform
+    pub iss: String,
+    pub login_hint: String,
+    /// The URL the platform launches to after logging in
+    pub target_link_uri: String,
+    #[serde(default)]
+    pub lti_message_hint: Option<String>,
+    #[serde(default)]
+    pub client_id: Option<String>,
+}
+
+/// Claims of the id token the platform launches with
+#[derive(Deserialize)]
+struct LaunchClaims {
+    sub: String,
+    nonce: String,
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(rename = "https://purl.imsglobal.org/spec/lti/claim/message_type")]
+    message_type: String,
+    #[serde(default, rename = "https://purl.imsglobal.org/spec/lti/claim/roles")]
+    roles: Vec<String>,
+    #[serde(rename = "https://purl.imsglobal.org/spec/lti/claim/resource_link")]
+    resource_link: ResourceLinkClaim,
+    #[serde(
+        default,
+        rename = "https://purl.imsglobal.org/spec/lti-ags/claim/endpoint"
+    )]
+    endpoint: Option<EndpointClaim>,
+}
+
+#[derive(Deserialize)]
+struct ResourceLinkClaim {
+    id: String,
+}
+
+/// Assignment and grade services the platform granted the launch
+#[derive(Deserialize)]
+struct EndpointClaim {
+    #[serde(default)]
+    scope: Vec<String>,
+    #[serde(default)]
+    lineitems: Option<String>,
+    #[serde(default)]
+    lineitem: Option<String>,
+}
+
+/// Claims of the client assertion used to request an access token
+#[derive(Serialize)]
+struct AssertionClaims<'a> {
+    iss: &'a str,
+    sub: &'a str,
+    aud: &'a str,
+    iat: u64,
+    exp: u64,
+    jti: String,
+}
+
+/// Response from the platform token endpoint
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+}
+
+/// Line item created in the platform gradebook
+#[derive(Deserialize)]
+struct LineItem {
+    id: String,
+}
+
+/// Configured platform details
+struct Platform {
+    issuer: String,
+    client_id: String,
+    auth_url: String,
+    jwks_url: String,
+    token_url: String,
+    private_key: String,
+    key_id: String,
+}
+
+/// Returns the configured platform if one is configured
+fn platform() -> Option<Platform> {
+    let issuer = config::from_env_string(config::LTI_ISSUER);
+    if issuer.is_empty() {
+        return None;
+    }
+
+    Some(Platform {
+        issuer,
+        client_id: config::from_env_string(config::LTI_CLIENT_ID),
+        auth_url: config::from_env_string(config::LTI_AUTH_URL),
+        jwks_url: config::from_env_string(config::LTI_JWKS_URL),
+        token_url: config::from_env_string(config::LTI_TOKEN_URL),
+        private_key: config::from_env_string(config::LTI_PRIVATE_KEY),
+        key_id: config::from_env_string(config::LTI_KEY_ID),
+    })
+}
+
+/// Returns the nonces of the pending launches keyed by their state
+fn pending() -> &'static Mutex<HashMap<String, (String, Instant)>> {
+    static PENDING: OnceLock<Mutex<HashMap<String, (String, Instant)>>> = OnceLock::new();
+    PENDING.get_or_init(Default::default)
+}
+
+/// Creates a random hex string for states and nonces
+fn random_hex() -> String {
+    let mut bytes = [0u8; 16];
+    OsRng.fill_bytes(&mut bytes);
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Returns the current time as unix seconds
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|value| value.as_secs())
+        .unwrap_or_default()
+}
+
+/// Creates the URL to send the user to for authenticating with the
+/// platform in response to a login initiation, the state and nonce
+/// are recorded so that the launch can be checked against them
+///
+/// `params` The login initiation parameters
+pub fn login_url(params: &LoginParams) -> Result<Url, LtiError> {
+    let platform = platform().ok_or(LtiError::NotConfigured)?;
+    if params.iss != platform.issuer
+        || params
+            .client_id
+            .as_ref()
+            .is_some_and(|client_id| *client_id != platform.client_id)
+    {
+        return Err(LtiError::NotConfigured);
+    }
+
+    let state = random_hex();
+    let nonce = random_hex();
+    if let Ok(mut pending) = pending().lock() {
+        pending.retain(|_, (_, created)| created.elapsed() < STATE_TIMEOUT);
+        pending.insert(state.clone(), (nonce.clone(), Instant::now()));
+    }
+
+    let mut query = vec![
+        ("scope", "openid"),
+        ("response_type", "id_token"),
+        ("response_mode", "form_post"),
+        ("prompt", "none"),
+        ("client_id", platform.client_id.as_str()),
+        ("redirect_uri", params.target_link_uri.as_str()),
+        ("login_hint", params.login_hint.as_str()),
+        ("state", state.as_str()),
+        ("nonce", nonce.as_str()),
+    ];
+    if let Some(hint) = &params.lti_message_hint {
+        query.push(("lti_message_hint", hint.as_str()));
+    }
+
+    Url::parse_with_params(&platform.auth_url, &query).map_err(|err| {
+        error!("Failed to create LTI login URL: {:?}", err);
+        LtiError::NotConfigured
+    })
+}
+
+/// Verifies the id token the platform launched with against the keys
+/// of the platform and the nonce of the pending launch
+///
+/// `id_token` The id token from the platform
+/// `state`    The state from the login URL
+pub async fn verify_launch(id_token: &str, state: &str) -> Result<Launch, LtiError> {
+    let platform = platform().ok_or(LtiError::NotConfigured)?;
+
+    let nonce = pending()
+        .lock()
+        .ok()
+        .and_then(|mut pending| pending.remove(state))
+        .filter(|(_, created)| created.elapsed() < STATE_TIMEOUT)
+        .map(|(nonce, _)| nonce)
+        .ok_or(LtiError::InvalidState)?;
+
+    let header = decode_header(id_token).map_err(|_| LtiError::InvalidToken)?;
+    let keys: JwkSet = Client::new()
+        .get(&platform.jwks_url)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    let key = match &header.kid {
+        Some(kid) => keys.find(kid),
+        None => keys.keys.first(),
+    }
+    .ok_or(LtiError::InvalidToken)?;
+    let key = DecodingKey::from_jwk(key).map_err(|_| LtiError::InvalidToken)?;
+
+    let mut validation = Validation::new(Algorithm::RS256);
+    validation.set_audience(&[&platform.client_id]);
+    validation.set_issuer(&[&platform.issuer]);
+    let claims = decode::<LaunchClaims>(id_token, &key, &validation)
+        .map_err(|_| LtiError::InvalidToken)?
+        .claims;
+
+    if claims.nonce != nonce || claims.message_type != RESOURCE_LINK_REQUEST {
+        return Err(LtiError::InvalidToken);
+    }
+
+    let resource_link = claims.resource_link.id;
+    Ok(Launch {
+        subject: claims.sub,
+        name: claims.name,
+        instructor: claims.roles.iter().any(|role| role == INSTRUCTOR_ROLE),
+        context: claims.endpoint.map(|endpoint| LtiContext {
+            resource_link,
+            lineitems: endpoint.lineitems,
+            lineitem: endpoint.lineitem,
+            scope: endpoint.scope,
+        }),
+    })
+}
+
+/// Requests an access token from the platform for the provided
+/// scopes using a client assertion signed with the tool key
+///
+/// `client`   The HTTP client
+/// `platform` The configured platform
+/// `scopes`   The scopes to request
+async fn access_token(
+    client: &Client,
+    platform: &Platform,
+    scopes: &[&str],
+) -> Result<String, LtiError> {
+    let now = unix_now();
+    let claims = AssertionClaims {
+        iss: &platform.client_id,
+        sub: &platform.client_id,
+        aud: &platform.token_url,
+        iat: now,
+        exp: now + ASSERTION_TTL,
+        jti: random_hex(),
+    };
+    let mut header = Header::new(Algorithm::RS256);
+    header.kid = Some(platform.key_id.clone());
+
+    let pem = tokio::fs::read(&platform.private_key)
+        .await
+        .map_err(|err| {
+            error!("Failed to read LTI private key: {:?}", err);
+            LtiError::NotConfigured
+        })?;
+    let key = EncodingKey::from_rsa_pem(&pem).map_err(LtiError::Signing)?;
+    let assertion = encode(&header, &claims, &key).map_err(LtiError::Signing)?;
+
+    let token: TokenResponse = client
+        .post(&platform.token_url)
+        .form(&[
+            ("grant_type", "client_credentials"),
+            (
+                "client_assertion_type",
+                "urn:ietf:params:oauth:client-assertion-type:jwt-bearer",
+            ),
+            ("client_assertion", assertion.as_str()),
+            ("scope", scopes.join(" ").as_str()),
+        ])
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    Ok(token.access_token)
+}
+
+/// Creates the URL for the scores of the line item, the scores
+/// endpoint is the line item path followed by `/scores`
+///
+/// `lineitem` The URL of the line item
+fn scores_url(lineitem: &str) -> Option<Url> {
+    let mut url = Url::parse(lineitem).ok()?;
+    url.path_segments_mut().ok()?.pop_if_empty().push("scores");
+    Some(url)
+}
+
+/// Posts the scores of the players to the gradebook of the context
+/// creating a line item for the game when the resource link doesn't
+/// have one. Scores are the number of correct answers out of the
+/// number of questions that had a correct answer
+///
+/// `context` The gradebook to post to
+/// `label`   The label for a created line item
+/// `maximum` The maximum score
+/// `scores`  The subject of each player and their score
+async fn post_scores(
+    context: LtiContext,
+    label: String,
+    maximum: u32,
+    scores: Vec<(String, u32)>,
+) -> Result<(), LtiError> {
+    let platform = platform().ok_or(LtiError::NotConfigured)?;
+    if !context.scope.iter().any(|scope| scope == SCORE_SCOPE) {
+        return Err(LtiError::NoGradebook);
+    }
+
+    let client = Client::new();
+    let lineitem = match context.lineitem {
+        Some(value) => value,
+        None => {
+            let lineitems = context.lineitems.ok_or(LtiError::NoGradebook)?;
+            if !context.scope.iter().any(|scope| scope == LINEITEM_SCOPE) {
+                return Err(LtiError::NoGradebook);
+            }
+            let token = access_token(&client, &platform, &[LINEITEM_SCOPE]).await?;
+            let created: LineItem = client
+                .post(lineitems)
+                .bearer_auth(token)
+                .header("Content-Type", "application/vnd.ims.lis.v2.lineitem+json")
+                .json(&serde_json::json!({
+                    "label": label,
+                    "scoreMaximum": maximum,
+                    "resourceLinkId": context.resource_link,
+                }))
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await?;
+            created.id
+        }
+    };
+    let url = scores_url(&lineitem).ok_or(LtiError::NoGradebook)?;
+
+    let token = access_token(&client, &platform, &[SCORE_SCOPE]).await?;
+    let timestamp = chrono::Utc::now().to_rfc3339();
+    for (user, score) in scores {
+        let result = client
+            .post(url.clone())
+            .bearer_auth(&token)
+            .header("Content-Type", "application/vnd.ims.lis.v1.score+json")
+            .json(&serde_json::json!({
+                "userId": user,
+                "scoreGiven": score,
+                "scoreMaximum": maximum,
+                "activityProgress": "Completed",
+                "gradingProgress": "FullyGraded",
+                "timestamp": timestamp,
+            }))
+            .send()
+            .await
+            .and_then(|response| response.error_for_status());
+        // A single rejected score shouldn't stop the others being posted
+        if let Err(err) = result {
+            warn!("Failed to post LTI score for {}: {:?}", user, err);
+        }
+    }
+    Ok(())
+}
+
+/// Posts the scores of the players that launched from the platform
+/// back to the gradebook in the background, players without a
+/// platform account are skipped
+///
+/// `context` The gradebook of the game
+/// `label`   The label for a created line item
+/// `maximum` The maximum score
+/// `scores`  The profile of each player and their score
+pub fn submit_scores(context: LtiContext, label: String, maximum: u32, scores: Vec<(Uuid, u32)>) {
+    if scores.is_empty() || maximum == 0 {
+        return;
+    }
+
+    tokio::spawn(async move {
+        let Some(storage) = Storage::addr() else {
+            return;
+        };
+        let profiles = scores.iter().map(|(profile, _)| *profile).collect();
+        let subjects = match storage.send(GetLtiSubjects { profiles }).await {
+            Ok(value) => value,
+            Err(err) => {
+                error!("Failed to send LTI subjects request: {:?}", err);
+                return;
+            }
+        };
+
+        let scores = scores
+            .into_iter()
+            .filter_map(|(profile, score)| Some((subjects.get(&profile)?.clone(), score)))
+            .collect::<Vec<_>>();
+        if scores.is_empty() {
+            return;
+        }
+
+        if let Err(err) = post_scores(context, label, maximum, scores).await {
+            error!("Failed to post LTI scores: {:?}", err);
+        }
+    });
+}
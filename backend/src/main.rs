@@ -1,26 +1,206 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use actix::SystemService;
 use actix_web::{App, HttpServer};
 use dotenvy::dotenv;
-use log::info;
-
-mod env;
-mod error;
-mod game;
-mod games;
-mod routes;
-mod session;
+#[cfg(feature = "redis")]
+use quizler::cluster;
+#[cfg(any(feature = "nats", feature = "kafka"))]
+use quizler::events;
+#[cfg(feature = "loadtest")]
+use quizler::loadtest;
+#[cfg(feature = "email")]
+use quizler::notify;
+#[cfg(feature = "otel")]
+use quizler::telemetry;
+#[cfg(feature = "tls")]
+use quizler::tls;
+use quizler::{
+    api_keys, checkpoint, config,
+    games::{Games, GamesRequest, GamesResponse},
+    logging, routes, session, storage, username,
+};
+use tracing::{error, info};
 
-#[tokio::main]
+#[actix_web::main]
 async fn main() -> std::io::Result<()> {
     // Load environment variables
     dotenv().ok();
 
+    // Load the config file, the logger is configured by it so the
+    // errors are reported once the logger is running
+    let errors = config::load();
+
     // Initialize logger
-    env_logger::init();
+    logging::init();
+
+    // Run the load test against another server instead of serving
+    #[cfg(feature = "loadtest")]
+    if std::env::args().nth(1).as_deref() == Some("loadtest") {
+        return loadtest::run(std::env::args().skip(2)).await;
+    }
+
+    if !errors.is_empty() {
+        for err in &errors {
+            error!("Invalid config: {}", err);
+        }
+        return Err(std::io::Error::other(format!(
+            "{} invalid config values",
+            errors.len()
+        )));
+    }
+
+    // Open the storage database
+    let storage_path = config::from_env_string(config::STORAGE_PATH);
+    if let Err(err) = storage::Storage::start(storage_path) {
+        error!("Failed to open storage database: {:?}", err);
+        return Err(std::io::Error::other(err));
+    }
+
+    // Load the username profanity list
+    username::load_profanity(config::from_env_string(config::PROFANITY_LIST));
+
+    // Load the keys for programmatic quiz creation
+    api_keys::load_api_keys(config::from_env_string(config::API_KEYS));
+
+    // Start publishing game events to the configured sink
+    #[cfg(any(feature = "nats", feature = "kafka"))]
+    events::start();
+
+    // Start the mailer for emailing results to hosts
+    #[cfg(feature = "email")]
+    notify::start();
+
+    // Start sharing the games with the other nodes
+    #[cfg(feature = "redis")]
+    cluster::start();
+
+    // Restore the games that were running when the server last stopped
+    checkpoint::start();
+    if checkpoint::interval().is_some() {
+        let checkpoints = checkpoint::load_all();
+        if !checkpoints.is_empty() {
+            info!("Restoring {} games from checkpoints", checkpoints.len());
+            Games::from_registry().do_send(GamesRequest::Restore { checkpoints });
+        }
+    }
+
+    let mut server =
+        HttpServer::new(|| App::new().wrap(routes::cors()).configure(routes::configure))
+            .disable_signals();
+
+    // Serve HTTPS directly when a certificate has been configured
+    #[cfg(feature = "tls")]
+    let tls = tls::load()?;
+
+    for addr in config::bind_addresses() {
+        #[cfg(feature = "tls")]
+        if let Some(tls) = &tls {
+            info!("Starting Quizler with TLS on {}", addr);
+            server = server.bind_rustls_021(&addr, tls.clone())?;
+            continue;
+        }
+
+        info!("Starting Quizler on {}", addr);
+        server = server.bind(&addr)?;
+    }
+
+    #[cfg(unix)]
+    {
+        let path = config::from_env_string(config::UNIX_SOCKET);
+        if !path.is_empty() {
+            remove_stale_socket(&path);
+            info!("Starting Quizler on unix socket {}", path);
+            server = server.bind_uds(&path)?;
+        }
+    }
 
-    let port = env::from_env(env::PORT);
-    info!("Starting Quizler on port {}", port);
-    HttpServer::new(|| App::new().configure(routes::configure))
-        .bind(("0.0.0.0", port))?
-        .run()
+    let server = server.run();
+
+    // Drain the running games before stopping the server on shutdown
+    let handle = server.handle();
+    actix_web::rt::spawn(async move {
+        shutdown_signal().await;
+        drain_games().await;
+        handle.stop(true).await;
+    });
+
+    let result = server.await;
+
+    // Export any remaining spans before exiting
+    #[cfg(feature = "otel")]
+    telemetry::shutdown();
+
+    result
+}
+
+/// Removes the socket file left behind at the provided path by a
+/// previous run, other kinds of files are left for binding to fail
+///
+/// `path` The path of the unix socket
+#[cfg(unix)]
+fn remove_stale_socket(path: &str) {
+    use std::os::unix::fs::FileTypeExt;
+
+    let is_socket = std::fs::metadata(path).is_ok_and(|meta| meta.file_type().is_socket());
+    if is_socket {
+        if let Err(err) = std::fs::remove_file(path) {
+            error!("Failed to remove stale unix socket {}: {:?}", path, err);
+        }
+    }
+}
+
+/// Waits for a SIGINT or SIGTERM signal
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let mut terminate = match signal(SignalKind::terminate()) {
+            Ok(value) => value,
+            Err(err) => {
+                error!("Failed to listen for terminate signal: {:?}", err);
+                let _ = tokio::signal::ctrl_c().await;
+                return;
+            }
+        };
+
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => {}
+            _ = terminate.recv() => {}
+        }
+    }
+
+    #[cfg(not(unix))]
+    let _ = tokio::signal::ctrl_c().await;
+}
+
+/// Informs all the games of the shutdown and waits for the shutdown
+/// grace period so the in progress questions can finish
+async fn drain_games() {
+    let grace = Duration::from_secs(config::from_env(config::SHUTDOWN_GRACE));
+    let deadline = (SystemTime::now() + grace)
+        .duration_since(UNIX_EPOCH)
+        .map(|value| value.as_millis() as u64)
+        .unwrap_or_default();
+
+    info!("Shutting down, draining games for {}s", grace.as_secs());
+
+    match Games::from_registry()
+        .send(GamesRequest::Shutdown { deadline })
         .await
+    {
+        Ok(Ok(GamesResponse::ShuttingDown { games: 0 })) => {}
+        Ok(_) => tokio::time::sleep(grace).await,
+        Err(err) => error!("Failed to inform games of shutdown: {:?}", err),
+    }
+
+    // Let the remaining clients know whether to reconnect once the
+    // server is back, checkpointed games are restored after restarting
+    let reason = if checkpoint::interval().is_some() {
+        session::DisconnectReason::ServerRestarting
+    } else {
+        session::DisconnectReason::ServerShutdown
+    };
+    session::close_all(reason);
 }
@@ -0,0 +1,239 @@
+use serde::{Deserialize, Serialize};
+use unicode_normalization::UnicodeNormalization;
+
+/// Options controlling how text answers are compared against
+/// the accepted answers
+#[derive(Clone, Serialize, Deserialize)]
+pub struct TextMatchOptions {
+    /// Whether differences in letter case are ignored
+    #[serde(default = "default_true")]
+    pub ignore_case: bool,
+    /// Whether leading, trailing and repeated whitespace is ignored
+    #[serde(default = "default_true")]
+    pub trim: bool,
+    /// The maximum number of single character edits an answer can be
+    /// away from an accepted answer while still being accepted
+    #[serde(default)]
+    pub tolerance: usize,
+}
+
+impl Default for TextMatchOptions {
+    fn default() -> Self {
+        Self {
+            ignore_case: true,
+            trim: true,
+            tolerance: 0,
+        }
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// The result of matching a text answer
+pub enum TextMatch {
+    /// The answer matched an accepted answer exactly
+    Exact,
+    /// The answer was within the tolerance of an accepted answer
+    /// with the provided edit distance
+    Close(usize),
+    /// The answer didn't match any accepted answer
+    None,
+}
+
+/// Normalizes the provided text for comparison using the provided
+/// options. Text is always NFKC normalized so that equivalent unicode
+/// sequences (e.g. composed and decomposed accents) compare equal
+///
+/// `value`   The text to normalize
+/// `options` The matching options
+pub fn normalize(value: &str, options: &TextMatchOptions) -> String {
+    let value: String = value.nfkc().collect();
+    let value = if options.trim {
+        value.split_whitespace().collect::<Vec<_>>().join(" ")
+    } else {
+        value
+    };
+    if options.ignore_case {
+        value.to_lowercase()
+    } else {
+        value
+    }
+}
+
+/// Calculates the levenshtein edit distance between the chars
+/// of the two provided strings
+///
+/// `a` The first string
+/// `b` The second string
+pub fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+
+    for (i, ca) in a.chars().enumerate() {
+        current[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let cost = if ca == *cb { 0 } else { 1 };
+            current[j + 1] = (previous[j + 1] + 1)
+                .min(current[j] + 1)
+                .min(previous[j] + cost);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+
+    previous[b.len()]
+}
+
+/// Matches the provided answer against the accepted answers returning
+/// the closest match
+///
+/// `answer`   The submitted answer
+/// `accepted` The accepted answers
+/// `options`  The matching options
+pub fn match_text(answer: &str, accepted: &[String], options: &TextMatchOptions) -> TextMatch {
+    let answer = normalize(answer, options);
+    let mut closest: Option<usize> = None;
+
+    for value in accepted {
+        let value = normalize(value, options);
+        if value == answer {
+            return TextMatch::Exact;
+        }
+
+        let distance = levenshtein(&answer, &value);
+        if distance <= options.tolerance && closest.is_none_or(|closest| distance < closest) {
+            closest = Some(distance);
+        }
+    }
+
+    match closest {
+        Some(distance) => TextMatch::Close(distance),
+        None => TextMatch::None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Accepted answers used by the matching tests
+    fn accepted(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    #[test]
+    fn composed_and_decomposed_forms_are_equal() {
+        let options = TextMatchOptions::default();
+        let composed = "caf\u{e9}";
+        let decomposed = "cafe\u{301}";
+        assert_eq!(
+            normalize(composed, &options),
+            normalize(decomposed, &options)
+        );
+        assert!(matches!(
+            match_text(decomposed, &accepted(&[composed]), &options),
+            TextMatch::Exact
+        ));
+    }
+
+    #[test]
+    fn case_is_folded_when_ignored() {
+        let options = TextMatchOptions::default();
+        assert_eq!(normalize("\u{c9}COLE Paris", &options), "\u{e9}cole paris");
+        assert!(matches!(
+            match_text("PARIS", &accepted(&["paris"]), &options),
+            TextMatch::Exact
+        ));
+    }
+
+    #[test]
+    fn case_is_kept_when_not_ignored() {
+        let options = TextMatchOptions {
+            ignore_case: false,
+            ..TextMatchOptions::default()
+        };
+        assert!(matches!(
+            match_text("PARIS", &accepted(&["paris"]), &options),
+            TextMatch::None
+        ));
+    }
+
+    #[test]
+    fn accents_are_significant() {
+        let options = TextMatchOptions::default();
+        assert!(matches!(
+            match_text("cafe", &accepted(&["caf\u{e9}"]), &options),
+            TextMatch::None
+        ));
+
+        // A missing accent is a single edit away
+        let options = TextMatchOptions {
+            tolerance: 1,
+            ..TextMatchOptions::default()
+        };
+        assert!(matches!(
+            match_text("cafe", &accepted(&["caf\u{e9}"]), &options),
+            TextMatch::Close(1)
+        ));
+    }
+
+    #[test]
+    fn full_width_chars_are_normalized() {
+        let options = TextMatchOptions::default();
+        assert_eq!(
+            normalize("\u{ff21}\u{ff22}\u{ff23}\u{ff11}", &options),
+            "abc1"
+        );
+        assert!(matches!(
+            match_text(
+                "\u{ff30}\u{ff41}\u{ff52}\u{ff49}\u{ff53}",
+                &accepted(&["Paris"]),
+                &options
+            ),
+            TextMatch::Exact
+        ));
+    }
+
+    #[test]
+    fn whitespace_is_collapsed_when_trimmed() {
+        let options = TextMatchOptions::default();
+        assert_eq!(normalize("  New \t York\n ", &options), "new york");
+
+        // Full-width spaces are normalized to regular spaces first
+        assert_eq!(normalize("New\u{3000}York", &options), "new york");
+    }
+
+    #[test]
+    fn whitespace_is_kept_when_not_trimmed() {
+        let options = TextMatchOptions {
+            trim: false,
+            ..TextMatchOptions::default()
+        };
+        assert_eq!(normalize(" New  York ", &options), " new  york ");
+    }
+
+    #[test]
+    fn closest_answer_within_tolerance_is_chosen() {
+        let options = TextMatchOptions {
+            tolerance: 2,
+            ..TextMatchOptions::default()
+        };
+        assert!(matches!(
+            match_text("colour", &accepted(&["colourful", "color"]), &options),
+            TextMatch::Close(1)
+        ));
+        assert!(matches!(
+            match_text("grey", &accepted(&["purple"]), &options),
+            TextMatch::None
+        ));
+    }
+
+    #[test]
+    fn levenshtein_counts_chars() {
+        assert_eq!(levenshtein("", "abc"), 3);
+        assert_eq!(levenshtein("kitten", "sitting"), 3);
+        assert_eq!(levenshtein("\u{e9}t\u{e9}", "ete"), 2);
+    }
+}
@@ -0,0 +1,134 @@
+use std::sync::OnceLock;
+
+use prometheus::{
+    Encoder, Histogram, HistogramOpts, IntCounter, IntCounterVec, IntGauge, Opts, Registry,
+    TextEncoder,
+};
+use tracing::error;
+
+/// Collection of the metrics recorded by the server
+pub struct Metrics {
+    /// Registry the metrics are registered with
+    registry: Registry,
+    /// The number of game actors currently running
+    pub active_games: IntGauge,
+    /// The number of websocket sessions currently connected
+    pub connected_sessions: IntGauge,
+    /// The total number of client messages recieved
+    pub messages_received: IntCounter,
+    /// The time in seconds players took to answer questions
+    pub answer_latency: Histogram,
+    /// The time in seconds from games being created to finishing
+    pub game_duration: Histogram,
+    /// The total number of abandoned games that were stopped
+    pub games_reaped: IntCounter,
+    /// The total number of games that were stopped after panicking
+    pub games_crashed: IntCounter,
+    /// The total number of quizzes created with each API key
+    pub api_key_uses: IntCounterVec,
+    /// The total number of game events dropped because the
+    /// event sink had fallen behind
+    pub events_dropped: IntCounter,
+}
+
+/// The global metrics instance
+static METRICS: OnceLock<Metrics> = OnceLock::new();
+
+/// Returns the global metrics instance creating it if it
+/// hasn't been created yet
+pub fn metrics() -> &'static Metrics {
+    METRICS.get_or_init(Metrics::new)
+}
+
+impl Metrics {
+    fn new() -> Self {
+        let registry = Registry::new_custom(Some("quizler".to_string()), None)
+            .expect("Failed to create metrics registry");
+
+        let active_games = IntGauge::new("active_games", "Number of running games")
+            .expect("Failed to create active games metric");
+        let connected_sessions =
+            IntGauge::new("connected_sessions", "Number of connected sessions")
+                .expect("Failed to create connected sessions metric");
+        let messages_received = IntCounter::new(
+            "messages_received_total",
+            "Total number of client messages recieved",
+        )
+        .expect("Failed to create messages received metric");
+        let answer_latency = Histogram::with_opts(
+            HistogramOpts::new(
+                "answer_latency_seconds",
+                "Time taken by players to answer questions",
+            )
+            .buckets(vec![0.5, 1.0, 2.0, 5.0, 10.0, 20.0, 30.0, 60.0]),
+        )
+        .expect("Failed to create answer latency metric");
+        let game_duration = Histogram::with_opts(
+            HistogramOpts::new("game_duration_seconds", "Duration of finished games")
+                .buckets(vec![60.0, 300.0, 600.0, 1200.0, 1800.0, 3600.0, 7200.0]),
+        )
+        .expect("Failed to create game duration metric");
+        let games_reaped = IntCounter::new(
+            "games_reaped_total",
+            "Total number of abandoned games that were stopped",
+        )
+        .expect("Failed to create games reaped metric");
+        let games_crashed = IntCounter::new(
+            "games_crashed_total",
+            "Total number of games that were stopped after panicking",
+        )
+        .expect("Failed to create games crashed metric");
+        let api_key_uses = IntCounterVec::new(
+            Opts::new(
+                "api_key_uses_total",
+                "Total number of quizzes created with each API key",
+            ),
+            &["key"],
+        )
+        .expect("Failed to create API key uses metric");
+        let events_dropped = IntCounter::new(
+            "events_dropped_total",
+            "Total number of game events dropped by the event sink",
+        )
+        .expect("Failed to create events dropped metric");
+
+        let collectors: Vec<Box<dyn prometheus::core::Collector>> = vec![
+            Box::new(active_games.clone()),
+            Box::new(connected_sessions.clone()),
+            Box::new(messages_received.clone()),
+            Box::new(answer_latency.clone()),
+            Box::new(game_duration.clone()),
+            Box::new(games_reaped.clone()),
+            Box::new(games_crashed.clone()),
+            Box::new(api_key_uses.clone()),
+            Box::new(events_dropped.clone()),
+        ];
+        for collector in collectors {
+            registry
+                .register(collector)
+                .expect("Failed to register metric");
+        }
+
+        Self {
+            registry,
+            active_games,
+            connected_sessions,
+            messages_received,
+            answer_latency,
+            game_duration,
+            games_reaped,
+            games_crashed,
+            api_key_uses,
+            events_dropped,
+        }
+    }
+
+    /// Encodes all the metrics in the prometheus text format
+    pub fn encode(&self) -> String {
+        let mut buffer = Vec::new();
+        if let Err(err) = TextEncoder::new().encode(&self.registry.gather(), &mut buffer) {
+            error!("Failed to encode metrics: {:?}", err);
+        }
+        String::from_utf8(buffer).unwrap_or_default()
+    }
+}
@@ -0,0 +1,217 @@
+use std::sync::OnceLock;
+
+use lettre::{transport::smtp::authentication::Credentials, AsyncSmtpTransport, Tokio1Executor};
+use tokio::sync::mpsc;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use crate::{config, storage::GameResults};
+
+/// The number of results emails that can be waiting to be sent
+/// before new emails are dropped
+const BUFFER_SIZE: usize = 256;
+
+/// Results of a finished game to email to the user that hosted it
+pub struct ResultsEmail {
+    /// The ID of the user to email
+    pub owner: Uuid,
+    /// The results of the game
+    pub results: GameResults,
+}
+
+/// Sender for the email channel when SMTP is configured
+static SENDER: OnceLock<mpsc::Sender<ResultsEmail>> = OnceLock::new();
+
+/// Queues an email of the results created by the provided function
+/// to be sent to the host, the results are only created when SMTP
+/// is configured and the email is dropped when the mailer has fallen
+/// behind so that games are never slowed down by sending
+///
+/// `owner`   The ID of the user that hosted the game
+/// `results` Function creating the results of the game
+pub fn results_finished(owner: Uuid, results: impl FnOnce() -> GameResults) {
+    let Some(sender) = SENDER.get() else {
+        return;
+    };
+
+    let email = ResultsEmail {
+        owner,
+        results: results(),
+    };
+    if sender.try_send(email).is_err() {
+        warn!("Dropped results email for user {}", owner);
+    }
+}
+
+/// Starts the mailer for the SMTP server configured in the
+/// environment, nothing is started when no SMTP host is set
+pub fn start() {
+    let host = config::from_env_string(config::SMTP_HOST);
+    if host.is_empty() {
+        return;
+    }
+
+    let builder = match AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&host) {
+        Ok(value) => value,
+        Err(err) => {
+            error!("Invalid SMTP host {}: {:?}", host, err);
+            return;
+        }
+    };
+    let mut builder = builder.port(config::from_env(config::SMTP_PORT));
+    let username = config::from_env_string(config::SMTP_USERNAME);
+    if !username.is_empty() {
+        let password = config::from_env_string(config::SMTP_PASSWORD);
+        builder = builder.credentials(Credentials::new(username, password));
+    }
+    let from = match config::from_env_string(config::SMTP_FROM).parse() {
+        Ok(value) => value,
+        Err(err) => {
+            error!("Invalid SMTP from address: {:?}", err);
+            return;
+        }
+    };
+
+    let (sender, receiver) = mpsc::channel(BUFFER_SIZE);
+    drop(tokio::spawn(mailer::run(builder.build(), from, receiver)));
+
+    info!("Emailing results through {}", host);
+    let _ = SENDER.set(sender);
+}
+
+/// Mailer that sends the queued results emails
+mod mailer {
+    use lettre::{
+        message::{header::ContentType, Attachment, Mailbox, MultiPart, SinglePart},
+        AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+    };
+    use tokio::sync::mpsc;
+    use tracing::error;
+
+    use super::ResultsEmail;
+    use crate::{
+        analytics,
+        storage::{GameResults, GetUser, Storage},
+    };
+
+    /// Number of players listed in the summary of the email
+    const LISTED_PLAYERS: usize = 10;
+
+    /// Sends each of the queued emails to the email address of the
+    /// user that hosted the game, users without an email are skipped
+    ///
+    /// `transport` The SMTP transport
+    /// `from`      The address emails are sent from
+    /// `receiver`  The email channel
+    pub async fn run(
+        transport: AsyncSmtpTransport<Tokio1Executor>,
+        from: Mailbox,
+        mut receiver: mpsc::Receiver<ResultsEmail>,
+    ) {
+        while let Some(ResultsEmail { owner, results }) = receiver.recv().await {
+            let Some(storage) = Storage::addr() else {
+                continue;
+            };
+            let email = match storage.send(GetUser { id: owner }).await {
+                Ok(Some(user)) => match user.email {
+                    Some(value) => value,
+                    None => continue,
+                },
+                Ok(None) => continue,
+                Err(err) => {
+                    error!("Failed to send user request: {:?}", err);
+                    continue;
+                }
+            };
+            let to = match email.parse() {
+                Ok(value) => value,
+                Err(err) => {
+                    error!("Invalid email address for user {}: {:?}", owner, err);
+                    continue;
+                }
+            };
+
+            let message = match build(from.clone(), to, results) {
+                Ok(value) => value,
+                Err(err) => {
+                    error!("Failed to build results email: {:?}", err);
+                    continue;
+                }
+            };
+            if let Err(err) = transport.send(message).await {
+                error!("Failed to send results email: {:?}", err);
+            }
+        }
+    }
+
+    /// Builds the email with a summary of the results in the body
+    /// and the full results attached as CSV
+    ///
+    /// `from`    The address the email is sent from
+    /// `to`      The address the email is sent to
+    /// `results` The results of the game
+    fn build(
+        from: Mailbox,
+        to: Mailbox,
+        results: GameResults,
+    ) -> Result<Message, lettre::error::Error> {
+        let subject = format!("Results for {}", results.name);
+        let body = summary(&results);
+        let filename = format!("{}.csv", results.id);
+        let csv: String = results.into_csv_rows().collect();
+        let csv_type = ContentType::parse("text/csv").unwrap_or(ContentType::TEXT_PLAIN);
+
+        Message::builder()
+            .from(from)
+            .to(to)
+            .subject(subject)
+            .multipart(
+                MultiPart::mixed()
+                    .singlepart(SinglePart::plain(body))
+                    .singlepart(Attachment::new(filename).body(csv, csv_type)),
+            )
+    }
+
+    /// Creates the plain text summary of the results listing the
+    /// top players and how well each question was answered
+    ///
+    /// `results` The results of the game
+    fn summary(results: &GameResults) -> String {
+        let mut body = format!(
+            "{} finished with {} players and {} questions.\n\nTop players:\n",
+            results.name,
+            results.players.len(),
+            results.questions.len()
+        );
+
+        let mut players: Vec<_> = results.players.iter().collect();
+        players.sort_by_key(|player| std::cmp::Reverse(player.score));
+        for (index, player) in players.iter().take(LISTED_PLAYERS).enumerate() {
+            body.push_str(&format!(
+                "{}. {} - {}\n",
+                index + 1,
+                player.name,
+                player.score
+            ));
+        }
+
+        body.push_str("\nQuestions:\n");
+        let analytics = analytics::analyze(results);
+        for (index, question) in analytics.questions.iter().enumerate() {
+            let correct = match question.percent_correct {
+                Some(value) => format!("{:.0}% correct", value),
+                None if question.answered == 0 => "not answered".to_string(),
+                None => "poll".to_string(),
+            };
+            body.push_str(&format!(
+                "{}. {} - {}\n",
+                index + 1,
+                question.title,
+                correct
+            ));
+        }
+
+        body.push_str("\nThe full results are attached.\n");
+        body
+    }
+}
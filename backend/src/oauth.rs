@@ -0,0 +1,199 @@
+use std::{
+    collections::HashMap,
+    sync::{Mutex, OnceLock},
+    time::{Duration, Instant},
+};
+
+use rand_core::{OsRng, RngCore};
+use reqwest::{Client, Url};
+use serde::Deserialize;
+use tracing::error;
+
+use crate::config;
+
+/// OAuth2 providers hosts can log in with
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Provider {
+    Google,
+    Microsoft,
+}
+
+/// How long a login attempt has to be completed
+const STATE_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+/// Errors that can occur while logging in
+#[derive(Debug)]
+pub enum OAuthError {
+    /// The provider doesn't have a client configured
+    NotConfigured,
+    /// The state didn't match a pending login attempt
+    InvalidState,
+    /// The request to the provider failed
+    Provider(reqwest::Error),
+}
+
+impl From<reqwest::Error> for OAuthError {
+    fn from(value: reqwest::Error) -> Self {
+        Self::Provider(value)
+    }
+}
+
+/// Details of the account returned by the provider
+#[derive(Deserialize)]
+pub struct UserInfo {
+    /// The ID of the account within the provider
+    pub sub: String,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub email: Option<String>,
+}
+
+/// Response from the provider token endpoint
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+}
+
+impl Provider {
+    /// The name the provider is stored under
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Google => "google",
+            Self::Microsoft => "microsoft",
+        }
+    }
+
+    fn authorize_url(&self) -> &'static str {
+        match self {
+            Self::Google => "https://accounts.google.com/o/oauth2/v2/auth",
+            Self::Microsoft => "https://login.microsoftonline.com/common/oauth2/v2.0/authorize",
+        }
+    }
+
+    fn token_url(&self) -> &'static str {
+        match self {
+            Self::Google => "https://oauth2.googleapis.com/token",
+            Self::Microsoft => "https://login.microsoftonline.com/common/oauth2/v2.0/token",
+        }
+    }
+
+    fn userinfo_url(&self) -> &'static str {
+        match self {
+            Self::Google => "https://openidconnect.googleapis.com/v1/userinfo",
+            Self::Microsoft => "https://graph.microsoft.com/oidc/userinfo",
+        }
+    }
+
+    /// Returns the client ID and secret for the provider if configured
+    fn client(&self) -> Option<(String, String)> {
+        let (id, secret) = match self {
+            Self::Google => (config::GOOGLE_CLIENT_ID, config::GOOGLE_CLIENT_SECRET),
+            Self::Microsoft => (config::MICROSOFT_CLIENT_ID, config::MICROSOFT_CLIENT_SECRET),
+        };
+        let id = config::from_env_string(id);
+        let secret = config::from_env_string(secret);
+        if id.is_empty() || secret.is_empty() {
+            return None;
+        }
+        Some((id, secret))
+    }
+
+    /// The URL the provider redirects back to after logging in
+    fn redirect_url(&self) -> String {
+        format!(
+            "{}/api/auth/{}/callback",
+            config::from_env_string(config::SERVER_URL).trim_end_matches('/'),
+            self.name()
+        )
+    }
+}
+
+/// Returns the states of the pending login attempts
+fn pending() -> &'static Mutex<HashMap<String, Instant>> {
+    static PENDING: OnceLock<Mutex<HashMap<String, Instant>>> = OnceLock::new();
+    PENDING.get_or_init(Default::default)
+}
+
+/// Creates the URL to send the host to for logging in with the
+/// provider, the state is recorded so that the callback can be
+/// checked against it
+///
+/// `provider` The provider to log in with
+pub fn login_url(provider: Provider) -> Result<Url, OAuthError> {
+    let (client_id, _) = provider.client().ok_or(OAuthError::NotConfigured)?;
+
+    let mut bytes = [0u8; 16];
+    OsRng.fill_bytes(&mut bytes);
+    let state: String = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+
+    if let Ok(mut pending) = pending().lock() {
+        pending.retain(|_, created| created.elapsed() < STATE_TIMEOUT);
+        pending.insert(state.clone(), Instant::now());
+    }
+
+    Url::parse_with_params(
+        provider.authorize_url(),
+        &[
+            ("client_id", client_id.as_str()),
+            ("redirect_uri", provider.redirect_url().as_str()),
+            ("response_type", "code"),
+            ("scope", "openid email profile"),
+            ("state", state.as_str()),
+        ],
+    )
+    .map_err(|err| {
+        error!("Failed to create login URL: {:?}", err);
+        OAuthError::NotConfigured
+    })
+}
+
+/// Completes a login by exchanging the code from the provider for an
+/// access token and loading the account details with it
+///
+/// `provider` The provider logged in with
+/// `code`     The authorization code from the provider
+/// `state`    The state from the login URL
+pub async fn complete_login(
+    provider: Provider,
+    code: &str,
+    state: &str,
+) -> Result<UserInfo, OAuthError> {
+    let (client_id, client_secret) = provider.client().ok_or(OAuthError::NotConfigured)?;
+
+    let valid = pending()
+        .lock()
+        .ok()
+        .and_then(|mut pending| pending.remove(state))
+        .is_some_and(|created| created.elapsed() < STATE_TIMEOUT);
+    if !valid {
+        return Err(OAuthError::InvalidState);
+    }
+
+    let client = Client::new();
+    let token: TokenResponse = client
+        .post(provider.token_url())
+        .form(&[
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", provider.redirect_url().as_str()),
+            ("client_id", client_id.as_str()),
+            ("client_secret", client_secret.as_str()),
+        ])
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+
+    let info = client
+        .get(provider.userinfo_url())
+        .bearer_auth(token.access_token)
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    Ok(info)
+}
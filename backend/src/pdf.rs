@@ -0,0 +1,185 @@
+use printpdf::{BuiltinFont, Error, IndirectFontRef, Mm, PdfDocument, PdfLayerReference};
+
+use crate::storage::{GameResults, PlayerResults};
+
+/// Width of an A4 page in millimeters
+const A4_WIDTH: f32 = 210.0;
+/// Height of an A4 page in millimeters
+const A4_HEIGHT: f32 = 297.0;
+/// Margin around the content of a page in millimeters
+const MARGIN: f32 = 20.0;
+/// Height of each player row in the results report in millimeters
+const ROW_HEIGHT: f32 = 7.0;
+
+/// Renders a completion certificate for the provided player on a
+/// single landscape A4 page
+///
+/// `results` The results of the game
+/// `player`  The player the certificate is for
+pub fn certificate(results: &GameResults, player: &PlayerResults) -> Result<Vec<u8>, Error> {
+    let (doc, page, layer) = PdfDocument::new(
+        format!("{} - {}", results.name, player.name),
+        Mm(A4_HEIGHT),
+        Mm(A4_WIDTH),
+        "Certificate",
+    );
+    let bold = doc.add_builtin_font(BuiltinFont::HelveticaBold)?;
+    let regular = doc.add_builtin_font(BuiltinFont::Helvetica)?;
+    let layer = doc.get_page(page).get_layer(layer);
+
+    let rank = rank(results, player.score);
+    let lines = [
+        ("Certificate of Completion", 32.0, &bold),
+        ("This certifies that", 16.0, &regular),
+        (player.name.as_str(), 36.0, &bold),
+        ("completed", 16.0, &regular),
+        (results.name.as_str(), 24.0, &bold),
+    ];
+
+    let mut y = A4_WIDTH - MARGIN - 20.0;
+    for (text, size, font) in lines {
+        layer.use_text(text, size, Mm(MARGIN + 10.0), Mm(y), font);
+        y -= 20.0;
+    }
+
+    let details = [
+        format!(
+            "Score: {}  |  Rank {} of {}",
+            player.score,
+            rank,
+            results.players.len()
+        ),
+        format!(
+            "Correct answers: {} of {}",
+            correct_count(player),
+            results.questions.len()
+        ),
+        format!("Date: {}", format_date(results.finished_at)),
+    ];
+    for text in details {
+        layer.use_text(text, 14.0, Mm(MARGIN + 10.0), Mm(y), &regular);
+        y -= 9.0;
+    }
+
+    doc.save_to_bytes()
+}
+
+/// Renders a report of the results for every player ranked by score,
+/// players that don't fit on the first page continue onto new pages
+///
+/// `results` The results of the game
+pub fn report(results: &GameResults) -> Result<Vec<u8>, Error> {
+    let (doc, page, layer) =
+        PdfDocument::new(results.name.clone(), Mm(A4_WIDTH), Mm(A4_HEIGHT), "Report");
+    let bold = doc.add_builtin_font(BuiltinFont::HelveticaBold)?;
+    let regular = doc.add_builtin_font(BuiltinFont::Helvetica)?;
+    let mut layer = doc.get_page(page).get_layer(layer);
+
+    let mut y = A4_HEIGHT - MARGIN;
+    layer.use_text(&results.name, 24.0, Mm(MARGIN), Mm(y), &bold);
+    y -= 10.0;
+    layer.use_text(
+        format!(
+            "{} players  |  {} questions  |  {}",
+            results.players.len(),
+            results.questions.len(),
+            format_date(results.finished_at)
+        ),
+        12.0,
+        Mm(MARGIN),
+        Mm(y),
+        &regular,
+    );
+    y -= 15.0;
+    report_header(&layer, &bold, y);
+    y -= ROW_HEIGHT;
+
+    let mut players: Vec<&PlayerResults> = results.players.iter().collect();
+    players.sort_by(|a, b| b.score.cmp(&a.score));
+
+    for player in players {
+        if y < MARGIN {
+            let (page, next) = doc.add_page(Mm(A4_WIDTH), Mm(A4_HEIGHT), "Report");
+            layer = doc.get_page(page).get_layer(next);
+            y = A4_HEIGHT - MARGIN;
+            report_header(&layer, &bold, y);
+            y -= ROW_HEIGHT;
+        }
+
+        let columns = [
+            rank(results, player.score).to_string(),
+            player.name.clone(),
+            player.score.to_string(),
+            format!("{}/{}", correct_count(player), results.questions.len()),
+        ];
+        for (text, x) in columns.iter().zip(COLUMNS) {
+            layer.use_text(text, 11.0, Mm(x), Mm(y), &regular);
+        }
+        y -= ROW_HEIGHT;
+    }
+
+    doc.save_to_bytes()
+}
+
+/// Horizontal position in millimeters of each column of the report
+const COLUMNS: [f32; 4] = [MARGIN, MARGIN + 15.0, MARGIN + 110.0, MARGIN + 140.0];
+
+/// Writes the header row of the report table
+///
+/// `layer` The layer to write to
+/// `font`  The font for the header
+/// `y`     The vertical position of the row
+fn report_header(layer: &PdfLayerReference, font: &IndirectFontRef, y: f32) {
+    let headers = ["Rank", "Name", "Score", "Correct"];
+    for (text, x) in headers.iter().zip(COLUMNS) {
+        layer.use_text(*text, 11.0, Mm(x), Mm(y), font);
+    }
+}
+
+/// Ranks the score against the scores of the other players,
+/// tied players share a rank
+///
+/// `results` The results of the game
+/// `score`   The score to rank
+fn rank(results: &GameResults, score: u32) -> usize {
+    results
+        .players
+        .iter()
+        .filter(|player| player.score > score)
+        .count()
+        + 1
+}
+
+/// Counts the answers of the player that were correct
+///
+/// `player` The player
+fn correct_count(player: &PlayerResults) -> usize {
+    player
+        .answers
+        .iter()
+        .flatten()
+        .filter(|answer| answer.result.is_correct())
+        .count()
+}
+
+/// Formats the unix timestamp as a `YYYY-MM-DD` UTC date
+///
+/// `secs` The unix timestamp in seconds
+fn format_date(secs: u64) -> String {
+    // Converts days since the unix epoch to a civil date
+    let days = (secs / 86_400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
@@ -0,0 +1,170 @@
+use std::{net::IpAddr, sync::OnceLock};
+
+use actix_web::http::header::{HeaderMap, FORWARDED};
+
+use crate::config;
+
+/// Name of the header listing the addresses a request was forwarded for
+const X_FORWARDED_FOR: &str = "x-forwarded-for";
+
+/// Value in the trusted proxies trusting connections over the unix socket
+pub const UNIX_PROXY: &str = "unix";
+
+/// Range of IP addresses in CIDR notation (e.g. 10.0.0.0/8)
+pub struct Cidr {
+    /// The network address
+    addr: IpAddr,
+    /// The number of leading bits that must match
+    prefix: u32,
+}
+
+impl Cidr {
+    /// Parses a CIDR range, a single address is treated as a
+    /// range containing only that address
+    ///
+    /// `value` The value to parse
+    pub fn parse(value: &str) -> Option<Self> {
+        let (addr, prefix) = match value.split_once('/') {
+            Some((addr, prefix)) => (addr.parse::<IpAddr>().ok()?, Some(prefix.parse().ok()?)),
+            None => (value.parse::<IpAddr>().ok()?, None),
+        };
+        let max = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix = prefix.unwrap_or(max);
+        if prefix > max {
+            return None;
+        }
+        Some(Self { addr, prefix })
+    }
+
+    /// Checks whether the range contains the provided address
+    ///
+    /// `ip` The address to check
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match (self.addr, ip.to_canonical()) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix).unwrap_or(0);
+                u32::from(net) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix).unwrap_or(0);
+                u128::from(net) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+/// The proxies trusted to report the address of the client
+struct TrustedProxies {
+    /// The trusted address ranges
+    ranges: Vec<Cidr>,
+    /// Whether connections over the unix socket are trusted
+    unix: bool,
+}
+
+impl TrustedProxies {
+    fn from_config() -> Self {
+        let value = config::from_env_string(config::TRUSTED_PROXIES);
+        let mut ranges = Vec::new();
+        let mut unix = false;
+        for entry in value
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+        {
+            if entry == UNIX_PROXY {
+                unix = true;
+            } else if let Some(range) = Cidr::parse(entry) {
+                ranges.push(range);
+            }
+        }
+        Self { ranges, unix }
+    }
+
+    /// Checks whether the provided peer is trusted, a missing
+    /// address is a connection over the unix socket
+    ///
+    /// `ip` The address of the peer
+    fn is_trusted(&self, ip: Option<IpAddr>) -> bool {
+        match ip {
+            Some(ip) => self.ranges.iter().any(|range| range.contains(ip)),
+            None => self.unix,
+        }
+    }
+}
+
+/// Returns the trusted proxies loading them on first use
+fn trusted() -> &'static TrustedProxies {
+    static TRUSTED: OnceLock<TrustedProxies> = OnceLock::new();
+    TRUSTED.get_or_init(TrustedProxies::from_config)
+}
+
+/// Finds the address of the client making the request, when the peer is
+/// a trusted proxy the forwarding headers are walked from the closest hop
+/// until an address that isn't a trusted proxy is found
+///
+/// `peer`    The address of the directly connected peer
+/// `headers` The request headers
+pub fn client_ip(peer: Option<IpAddr>, headers: &HeaderMap) -> Option<IpAddr> {
+    let trusted = trusted();
+    if !trusted.is_trusted(peer) {
+        return peer;
+    }
+
+    let mut client = peer;
+    for hop in forwarded_for(headers).into_iter().rev() {
+        // Obfuscated or invalid hops can't be looked past
+        let Some(ip) = hop else {
+            break;
+        };
+        client = Some(ip);
+        if !trusted.is_trusted(client) {
+            break;
+        }
+    }
+    client
+}
+
+/// Collects the addresses from the forwarding headers in the order the
+/// hops were added, the standard Forwarded header is preferred over
+/// X-Forwarded-For. Hops that aren't addresses are None
+///
+/// `headers` The request headers
+fn forwarded_for(headers: &HeaderMap) -> Vec<Option<IpAddr>> {
+    let forwarded: Vec<Option<IpAddr>> = headers
+        .get_all(FORWARDED)
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(|element| {
+            element.split(';').find_map(|pair| {
+                let (name, value) = pair.trim().split_once('=')?;
+                name.eq_ignore_ascii_case("for")
+                    .then(|| parse_node(value.trim_matches('"')))
+            })
+        })
+        .collect();
+    if !forwarded.is_empty() {
+        return forwarded;
+    }
+
+    headers
+        .get_all(X_FORWARDED_FOR)
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|value| parse_node(value.trim()))
+        .collect()
+}
+
+/// Parses the address from a forwarded node which may include a
+/// port and have IPv6 addresses within brackets
+///
+/// `value` The node value
+fn parse_node(value: &str) -> Option<IpAddr> {
+    if let Ok(ip) = value.parse() {
+        return Some(ip);
+    }
+    if let Some(rest) = value.strip_prefix('[') {
+        return rest.split_once(']')?.0.parse().ok();
+    }
+    value.rsplit_once(':')?.0.parse().ok()
+}
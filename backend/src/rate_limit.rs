@@ -0,0 +1,230 @@
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    time::{Duration, Instant},
+};
+
+/// Fixed window rate limiter keyed by IP address
+pub struct RateLimiter {
+    /// The maximum number of hits allowed within a window
+    limit: u32,
+    /// The length of each window
+    window: Duration,
+    /// The start of the current window and the hits within it for each address
+    entries: HashMap<IpAddr, (Instant, u32)>,
+}
+
+impl RateLimiter {
+    /// The number of entries after which expired entries are pruned
+    const PRUNE_THRESHOLD: usize = 1024;
+
+    /// Creates a new rate limiter
+    ///
+    /// `limit`  The maximum number of hits allowed within a window
+    /// `window` The length of each window
+    pub fn new(limit: u32, window: Duration) -> Self {
+        Self {
+            limit,
+            window,
+            entries: HashMap::new(),
+        }
+    }
+
+    /// Records a hit for the provided address returning whether the
+    /// address is still within the limit
+    ///
+    /// `ip` The address to record the hit for
+    pub fn hit(&mut self, ip: IpAddr) -> bool {
+        if self.entries.len() > Self::PRUNE_THRESHOLD {
+            let window = self.window;
+            self.entries
+                .retain(|_, (start, _)| start.elapsed() < window);
+        }
+
+        let now = Instant::now();
+        let entry = self.entries.entry(ip).or_insert((now, 0));
+        if entry.0.elapsed() >= self.window {
+            *entry = (now, 0);
+        }
+        entry.1 = entry.1.saturating_add(1);
+        entry.1 <= self.limit
+    }
+
+    /// Checks whether the provided address has reached the limit
+    /// without recording a hit
+    ///
+    /// `ip` The address to check
+    pub fn is_limited(&self, ip: IpAddr) -> bool {
+        self.entries
+            .get(&ip)
+            .is_some_and(|(start, hits)| start.elapsed() < self.window && *hits >= self.limit)
+    }
+}
+
+/// Fixed window counter for limiting a single client
+pub struct WindowCounter {
+    /// The start of the current window
+    start: Instant,
+    /// The number of hits within the current window
+    hits: u32,
+}
+
+impl WindowCounter {
+    pub fn new() -> Self {
+        Self {
+            start: Instant::now(),
+            hits: 0,
+        }
+    }
+
+    /// Records a hit returning whether the hits are still within the limit
+    ///
+    /// `limit`  The maximum number of hits allowed within a window
+    /// `window` The length of each window
+    pub fn hit(&mut self, limit: u32, window: Duration) -> bool {
+        if self.start.elapsed() >= window {
+            self.start = Instant::now();
+            self.hits = 0;
+        }
+        self.hits = self.hits.saturating_add(1);
+        self.hits <= limit
+    }
+}
+
+impl Default for WindowCounter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Token bucket allowing bursts of hits while limiting the sustained rate
+pub struct TokenBucket {
+    /// The number of tokens added each second
+    rate: f64,
+    /// The maximum number of tokens the bucket can hold
+    burst: f64,
+    /// The number of tokens in the bucket
+    tokens: f64,
+    /// The last time tokens were added
+    last: Instant,
+}
+
+impl TokenBucket {
+    /// Creates a new full token bucket
+    ///
+    /// `rate`  The number of tokens added each second
+    /// `burst` The maximum number of tokens the bucket can hold
+    pub fn new(rate: u32, burst: u32) -> Self {
+        Self {
+            rate: rate as f64,
+            burst: burst as f64,
+            tokens: burst as f64,
+            last: Instant::now(),
+        }
+    }
+
+    /// Takes a token from the bucket returning whether one was available
+    pub fn take(&mut self) -> bool {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last).as_secs_f64();
+        self.last = now;
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.burst);
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Action to take for a message checked by the flood guard
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum FloodAction {
+    /// The message is within the limit
+    Allow,
+    /// The message should be dropped without a response
+    Drop,
+    /// The message should be dropped and the client warned
+    Warn,
+    /// The client should be muted and the message dropped
+    Mute,
+    /// The client should be disconnected
+    Disconnect,
+}
+
+/// Guard against a single client flooding the server with messages,
+/// clients that keep exceeding the limit are warned, then muted for
+/// a while and then disconnected
+pub struct FloodGuard {
+    /// Bucket limiting the rate of messages
+    bucket: TokenBucket,
+    /// The number of times the client has exceeded the limit
+    strikes: u32,
+    /// The last time the client was given a strike
+    last_strike: Option<Instant>,
+    /// The time the client is muted until
+    muted_until: Option<Instant>,
+}
+
+impl FloodGuard {
+    /// The minimum time between strikes, messages over the limit within
+    /// this time of the last strike are dropped without another strike
+    const STRIKE_COOLDOWN: Duration = Duration::from_secs(1);
+    /// The time without a strike after which the strikes are forgiven
+    const STRIKE_RESET: Duration = Duration::from_secs(60);
+    /// The strike on which the client is muted
+    const MUTE_STRIKE: u32 = 3;
+    /// The strike on which the client is disconnected
+    const DISCONNECT_STRIKE: u32 = 4;
+    /// The time the client is muted for
+    const MUTE_DURATION: Duration = Duration::from_secs(10);
+
+    /// Creates a new flood guard
+    ///
+    /// `rate`  The number of messages allowed each second
+    /// `burst` The number of messages allowed in a burst
+    pub fn new(rate: u32, burst: u32) -> Self {
+        Self {
+            bucket: TokenBucket::new(rate, burst),
+            strikes: 0,
+            last_strike: None,
+            muted_until: None,
+        }
+    }
+
+    /// Checks a message from the client returning the action to take
+    pub fn check(&mut self) -> FloodAction {
+        let now = Instant::now();
+        if self.muted_until.is_some_and(|until| now < until) {
+            return FloodAction::Drop;
+        }
+
+        if self.bucket.take() {
+            return FloodAction::Allow;
+        }
+
+        if let Some(last) = self.last_strike {
+            let since = now.duration_since(last);
+            if since < Self::STRIKE_COOLDOWN {
+                return FloodAction::Drop;
+            }
+            if since >= Self::STRIKE_RESET {
+                self.strikes = 0;
+            }
+        }
+
+        self.strikes += 1;
+        self.last_strike = Some(now);
+
+        match self.strikes {
+            strikes if strikes >= Self::DISCONNECT_STRIKE => FloodAction::Disconnect,
+            Self::MUTE_STRIKE => {
+                self.muted_until = Some(now + Self::MUTE_DURATION);
+                FloodAction::Mute
+            }
+            _ => FloodAction::Warn,
+        }
+    }
+}
@@ -0,0 +1,204 @@
+use std::{
+    collections::HashMap,
+    io::{self, ErrorKind},
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::{
+    config,
+    game::{GameId, GameState, QuestionAnswer},
+    session::SessionId,
+};
+
+/// Version of the on-disk replay format, replays written with a
+/// different version are rejected when loaded
+pub const REPLAY_VERSION: u32 = 1;
+
+/// Maximum number of events recorded for a single game, events past
+/// this are dropped so a long running game can't use unbounded memory
+const MAX_EVENTS: usize = 100_000;
+
+/// Timeline of the events of a finished game that the host
+/// can step through afterwards
+#[derive(Serialize, Deserialize)]
+pub struct Replay {
+    /// The version of the replay format
+    pub version: u32,
+    /// The ID of the game, this is the same as the ID of its results
+    pub game: Uuid,
+    /// The token the game was played under
+    pub token: String,
+    /// The ID of the quiz the game was created from
+    #[serde(default, deserialize_with = "crate::storage::deserialize_quiz")]
+    pub quiz: Option<GameId>,
+    /// The ID of the user that hosted the game if the host was logged in
+    #[serde(default)]
+    pub owner: Option<Uuid>,
+    /// The name of the game
+    pub name: String,
+    /// Unix timestamp in seconds that the game was created
+    pub started_at: u64,
+    /// Whether events were dropped after reaching the event limit
+    #[serde(default)]
+    pub truncated: bool,
+    /// The recorded events in the order they happened
+    pub events: Vec<ReplayEvent>,
+}
+
+/// Event within a replay
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ReplayEvent {
+    /// Milliseconds since the game was created that the event happened
+    pub at: u64,
+    /// The event itself
+    #[serde(flatten)]
+    pub kind: ReplayEventKind,
+}
+
+/// The kinds of events recorded in a replay
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(tag = "ty")]
+pub enum ReplayEventKind {
+    /// A player joined the game
+    PlayerJoined { id: SessionId, name: String },
+    /// A player left or was removed from the game
+    PlayerLeft { id: SessionId },
+    /// The game moved to a new state
+    StateChanged { state: GameState },
+    /// The question at the index was shown
+    Question { index: usize, title: String },
+    /// A player submitted an answer, `elapsed` is the milliseconds
+    /// since answering opened
+    Answer {
+        id: SessionId,
+        index: usize,
+        answer: QuestionAnswer,
+        elapsed: u64,
+    },
+    /// The scores of the players that changed
+    Scores { scores: HashMap<SessionId, u32> },
+}
+
+/// Records the events of a game as they happen
+pub struct ReplayRecorder {
+    /// The time recording started
+    start: Instant,
+    /// The events recorded so far
+    events: Vec<ReplayEvent>,
+    /// Whether events have been dropped
+    truncated: bool,
+}
+
+impl Default for ReplayRecorder {
+    fn default() -> Self {
+        Self {
+            start: Instant::now(),
+            events: Vec::new(),
+            truncated: false,
+        }
+    }
+}
+
+impl ReplayRecorder {
+    /// Continues recording the events of a restored game
+    ///
+    /// `events`    The events recorded before the game was restored
+    /// `truncated` Whether events had been dropped
+    /// `elapsed`   The time since the game was created
+    pub fn resume(events: Vec<ReplayEvent>, truncated: bool, elapsed: Duration) -> Self {
+        Self {
+            start: Instant::now()
+                .checked_sub(elapsed)
+                .unwrap_or_else(Instant::now),
+            events,
+            truncated,
+        }
+    }
+
+    /// Records the provided event at the current time
+    ///
+    /// `kind` The event to record
+    pub fn record(&mut self, kind: ReplayEventKind) {
+        if self.events.len() >= MAX_EVENTS {
+            self.truncated = true;
+            return;
+        }
+
+        self.events.push(ReplayEvent {
+            at: self.start.elapsed().as_millis() as u64,
+            kind,
+        });
+    }
+
+    /// Returns a copy of the recorded events along with whether any
+    /// events were dropped
+    pub fn events(&self) -> (Vec<ReplayEvent>, bool) {
+        (self.events.clone(), self.truncated)
+    }
+
+    /// Takes the recorded events returning them along with whether
+    /// any events were dropped
+    pub fn take(&mut self) -> (Vec<ReplayEvent>, bool) {
+        (
+            std::mem::take(&mut self.events),
+            std::mem::take(&mut self.truncated),
+        )
+    }
+}
+
+/// Returns the path the replay for the provided game is stored at
+///
+/// `game` The ID of the game
+fn replay_path(game: Uuid) -> PathBuf {
+    PathBuf::from(config::from_env_string(config::REPLAY_DIR)).join(format!("{}.json", game))
+}
+
+/// Writes the provided replay to disk
+///
+/// `replay` The replay to write
+pub async fn save(replay: &Replay) -> io::Result<()> {
+    let path = replay_path(replay.game);
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    let bytes = serde_json::to_vec(replay)?;
+    tokio::fs::write(path, bytes).await
+}
+
+/// Only the version of a replay used to check the version
+/// before parsing the rest of the replay
+#[derive(Deserialize)]
+struct ReplayHeader {
+    version: u32,
+}
+
+/// Loads the replay for the provided game from disk, replays
+/// from a different version of the format are not loaded
+///
+/// `game` The ID of the game
+pub async fn load(game: Uuid) -> io::Result<Option<Replay>> {
+    let bytes = match tokio::fs::read(replay_path(game)).await {
+        Ok(value) => value,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err),
+    };
+
+    let header: ReplayHeader = serde_json::from_slice(&bytes)?;
+    if header.version != REPLAY_VERSION {
+        warn!(
+            "Replay for game {} has unsupported version {}",
+            game, header.version
+        );
+        return Err(io::Error::new(
+            ErrorKind::InvalidData,
+            "unsupported replay version",
+        ));
+    }
+
+    Ok(Some(serde_json::from_slice(&bytes)?))
+}
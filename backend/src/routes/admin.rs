@@ -0,0 +1,168 @@
+use actix_web::{
+    delete, get, post, put,
+    web::{self, Json, ServiceConfig},
+    HttpResponse,
+};
+use futures_util::future::join_all;
+use serde::{Deserialize, Serialize};
+use tracing::{error, info};
+
+use crate::{
+    error::{FieldError, FieldErrorReason, ValidationErrors},
+    game::{Game, GameRequest, GameResponse},
+    games::registry,
+    routes::Admin,
+    session::{self, ServerMessage},
+};
+
+/// The maximum length of an announcement
+const MAX_ANNOUNCEMENT_LENGTH: usize = 500;
+
+/// Configuration function for configuring the admin routes
+pub fn configure(cfg: &mut ServiceConfig) {
+    cfg.service(list_games)
+        .service(get_game)
+        .service(close_game)
+        .service(announce)
+        .service(get_maintenance)
+        .service(set_maintenance);
+}
+
+/// Request to force close a game
+#[derive(Deserialize)]
+struct CloseRequest {
+    /// The reason shown to the sessions in the game
+    #[serde(default)]
+    reason: Option<String>,
+}
+
+/// Request to broadcast an announcement
+#[derive(Deserialize)]
+struct AnnounceRequest {
+    /// The text of the announcement
+    text: String,
+}
+
+/// Maintenance mode state
+#[derive(Serialize, Deserialize)]
+struct Maintenance {
+    /// Whether maintenance mode is enabled
+    enabled: bool,
+}
+
+/// Response for a broadcast announcement
+#[derive(Serialize)]
+struct AnnounceResponse {
+    /// The number of sessions the announcement was sent to
+    sessions: usize,
+}
+
+/// Sends the provided request to the game producing the error
+/// response on failure
+///
+/// `game` The game to send to
+/// `msg`  The request to send
+async fn send(game: actix::Addr<Game>, msg: GameRequest) -> Result<GameResponse, HttpResponse> {
+    match game.send(msg).await {
+        Ok(Ok(value)) => Ok(value),
+        Ok(Err(err)) => Err(HttpResponse::Conflict().json(err)),
+        // Games stop between being found and handling the request
+        Err(_) => Err(HttpResponse::NotFound().finish()),
+    }
+}
+
+/// Route for listing the running games
+#[get("/api/admin/games")]
+async fn list_games(_admin: Admin) -> HttpResponse {
+    let games = join_all(
+        registry()
+            .all()
+            .into_iter()
+            .map(|game| send(game, GameRequest::Inspect)),
+    )
+    .await;
+
+    let games: Vec<_> = games
+        .into_iter()
+        .filter_map(|res| match res {
+            Ok(GameResponse::Details(details)) => Some(details.game),
+            _ => None,
+        })
+        .collect();
+
+    HttpResponse::Ok().json(games)
+}
+
+/// Route for inspecting the game with the provided token
+#[get("/api/admin/games/{token}")]
+async fn get_game(_admin: Admin, token: web::Path<String>) -> HttpResponse {
+    let Some(game) = registry().get(&token) else {
+        return HttpResponse::NotFound().finish();
+    };
+
+    match send(game, GameRequest::Inspect).await {
+        Ok(GameResponse::Details(details)) => HttpResponse::Ok().json(details),
+        Ok(_) => {
+            error!("Unexpected game response to inspect request");
+            HttpResponse::InternalServerError().finish()
+        }
+        Err(response) => response,
+    }
+}
+
+/// Route for force closing the game with the provided token
+#[delete("/api/admin/games/{token}")]
+async fn close_game(
+    _admin: Admin,
+    token: web::Path<String>,
+    body: Option<Json<CloseRequest>>,
+) -> HttpResponse {
+    let Some(game) = registry().get(&token) else {
+        return HttpResponse::NotFound().finish();
+    };
+
+    let reason = body
+        .and_then(|body| body.into_inner().reason)
+        .unwrap_or_else(|| "Game was closed by the server".to_string());
+
+    match send(game, GameRequest::Close { reason }).await {
+        Ok(_) => HttpResponse::NoContent().finish(),
+        Err(response) => response,
+    }
+}
+
+/// Route for broadcasting an announcement to every connected session
+#[post("/api/admin/announce")]
+async fn announce(_admin: Admin, Json(req): Json<AnnounceRequest>) -> HttpResponse {
+    let text = req.text.trim();
+    if text.is_empty() || text.chars().count() > MAX_ANNOUNCEMENT_LENGTH {
+        let errors = vec![FieldError::new("text", FieldErrorReason::InvalidValue)];
+        return HttpResponse::BadRequest().json(ValidationErrors { errors });
+    }
+
+    info!("Broadcasting announcement: {}", text);
+    let sessions = session::broadcast(ServerMessage::Announcement {
+        text: text.to_string(),
+    });
+    HttpResponse::Ok().json(AnnounceResponse { sessions })
+}
+
+/// Route for checking whether maintenance mode is enabled
+#[get("/api/admin/maintenance")]
+async fn get_maintenance(_admin: Admin) -> HttpResponse {
+    HttpResponse::Ok().json(Maintenance {
+        enabled: registry().is_maintenance(),
+    })
+}
+
+/// Route for enabling or disabling maintenance mode, while enabled
+/// running games continue but new games and players are refused
+#[put("/api/admin/maintenance")]
+async fn set_maintenance(_admin: Admin, Json(req): Json<Maintenance>) -> HttpResponse {
+    info!(
+        "Maintenance mode {}",
+        if req.enabled { "enabled" } else { "disabled" }
+    );
+    registry().set_maintenance(req.enabled);
+    HttpResponse::Ok().json(req)
+}
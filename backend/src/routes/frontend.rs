@@ -0,0 +1,65 @@
+use actix_web::{
+    get,
+    http::header::{CacheControl, CacheDirective},
+    web::{self, ServiceConfig},
+    HttpResponse,
+};
+use rust_embed::RustEmbed;
+
+/// The frontend files built with the static adapter, embedded
+/// into the binary when building with the embed feature
+#[derive(RustEmbed)]
+#[folder = "../frontend/build"]
+struct Assets;
+
+/// The page served for paths that don't match a file so that the
+/// frontend router can handle them
+const FALLBACK: &str = "index.html";
+
+/// Prefix of the files that are named by their content hash
+const IMMUTABLE_PREFIX: &str = "_app/immutable/";
+
+/// First path segments of the server routes that shouldn't fall
+/// back to the frontend
+const SERVER_SEGMENTS: &[&str] = &["api", "ws", "health", "metrics"];
+
+/// Configuration function for configuring the frontend routes, must
+/// be configured last as it matches every path
+pub fn configure(cfg: &mut ServiceConfig) {
+    cfg.service(get_file);
+}
+
+/// Route serving the embedded frontend files falling back to
+/// the index page for the frontend routes
+#[get("/{path:.*}")]
+async fn get_file(path: web::Path<String>) -> HttpResponse {
+    let path = path.into_inner();
+    let segment = path.split('/').next().unwrap_or_default();
+    if SERVER_SEGMENTS.contains(&segment) {
+        return HttpResponse::NotFound().finish();
+    }
+
+    let (path, file) = match Assets::get(&path) {
+        Some(file) => (path.as_str(), file),
+        None => match Assets::get(FALLBACK) {
+            Some(file) => (FALLBACK, file),
+            None => return HttpResponse::NotFound().finish(),
+        },
+    };
+
+    // Hashed files never change but pages must be checked for updates
+    let cache = if path.starts_with(IMMUTABLE_PREFIX) {
+        CacheControl(vec![
+            CacheDirective::Public,
+            CacheDirective::MaxAge(31_536_000),
+            CacheDirective::Extension("immutable".to_string(), None),
+        ])
+    } else {
+        CacheControl(vec![CacheDirective::NoCache])
+    };
+
+    HttpResponse::Ok()
+        .content_type(file.metadata.mimetype())
+        .insert_header(cache)
+        .body(file.data.into_owned())
+}
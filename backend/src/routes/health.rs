@@ -0,0 +1,135 @@
+use std::time::Duration;
+
+use actix::{System, SystemService};
+use actix_web::{get, web::ServiceConfig, HttpResponse};
+use serde::Serialize;
+use tokio::time::timeout;
+
+use crate::{
+    games::{registry, Games, GamesRequest},
+    storage::{Ping, Storage},
+};
+
+/// The time the services are given to respond to readiness checks
+const CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Configuration function for configuring the health routes
+pub fn configure(cfg: &mut ServiceConfig) {
+    cfg.service(health).service(live).service(ready);
+}
+
+/// Status of the server reported by the health endpoint
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case")]
+enum ServerStatus {
+    /// The server is accepting new games and players
+    Ok,
+    /// The server is in maintenance mode
+    Maintenance,
+    /// The server is shutting down
+    ShuttingDown,
+}
+
+/// Response from the health endpoint
+#[derive(Serialize)]
+struct HealthResponse {
+    /// The status of the server
+    status: ServerStatus,
+    /// The number of running games
+    games: usize,
+}
+
+/// Result of an individual readiness check
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case")]
+enum CheckStatus {
+    /// The check passed
+    Ok,
+    /// The service didn't respond in time
+    Unresponsive,
+    /// The service isn't running
+    Unavailable,
+}
+
+/// Response from the readiness endpoint
+#[derive(Serialize)]
+struct ReadyResponse {
+    /// Whether the server is ready to accept traffic
+    ready: bool,
+    /// Whether the server is shutting down
+    shutting_down: bool,
+    /// Status of the games service
+    games: CheckStatus,
+    /// Status of the storage database
+    storage: CheckStatus,
+}
+
+/// Route reporting the status of the server
+#[get("/health")]
+async fn health() -> HttpResponse {
+    let registry = registry();
+    let status = if registry.is_shutting_down() {
+        ServerStatus::ShuttingDown
+    } else if registry.is_maintenance() {
+        ServerStatus::Maintenance
+    } else {
+        ServerStatus::Ok
+    };
+
+    HttpResponse::Ok().json(HealthResponse {
+        status,
+        games: registry.count(),
+    })
+}
+
+/// Liveness probe route, succeeds while the actor system is running
+#[get("/health/live")]
+async fn live() -> HttpResponse {
+    if System::try_current().is_some() {
+        HttpResponse::Ok().finish()
+    } else {
+        HttpResponse::ServiceUnavailable().finish()
+    }
+}
+
+/// Readiness probe route, succeeds when the games service and the
+/// storage database respond and the server isn't shutting down
+#[get("/health/ready")]
+async fn ready() -> HttpResponse {
+    let shutting_down = registry().is_shutting_down();
+
+    let games = match timeout(
+        CHECK_TIMEOUT,
+        Games::from_registry().send(GamesRequest::Ping),
+    )
+    .await
+    {
+        Ok(Ok(_)) => CheckStatus::Ok,
+        Ok(Err(_)) => CheckStatus::Unavailable,
+        Err(_) => CheckStatus::Unresponsive,
+    };
+
+    let storage = match Storage::addr() {
+        Some(storage) => match timeout(CHECK_TIMEOUT, storage.send(Ping)).await {
+            Ok(Ok(true)) => CheckStatus::Ok,
+            Ok(_) => CheckStatus::Unavailable,
+            Err(_) => CheckStatus::Unresponsive,
+        },
+        None => CheckStatus::Unavailable,
+    };
+
+    let ready =
+        !shutting_down && matches!(games, CheckStatus::Ok) && matches!(storage, CheckStatus::Ok);
+
+    let mut response = if ready {
+        HttpResponse::Ok()
+    } else {
+        HttpResponse::ServiceUnavailable()
+    };
+    response.json(ReadyResponse {
+        ready,
+        shutting_down,
+        games,
+        storage,
+    })
+}
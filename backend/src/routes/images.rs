@@ -0,0 +1,148 @@
+use std::path::Path;
+
+use actix_multipart::{Field, Multipart};
+use actix_web::{
+    get,
+    middleware::from_fn,
+    post,
+    web::{self, ServiceConfig},
+    HttpResponse,
+};
+use futures_util::StreamExt;
+use serde::Serialize;
+use tokio::{fs::File, io::AsyncWriteExt};
+use tracing::error;
+use uuid::Uuid;
+
+use crate::{
+    config,
+    error::ImageError,
+    game::GameId,
+    images::{game_dir, image_path, ImageFormat},
+};
+
+/// Configuration function for configuring the image routes
+pub fn configure(cfg: &mut ServiceConfig) {
+    cfg.service(upload_images).service(get_image);
+}
+
+/// Response for successfully uploaded images
+#[derive(Serialize)]
+struct UploadedImages {
+    /// The IDs of the uploaded images in the order they were uploaded
+    ids: Vec<Uuid>,
+}
+
+/// Response for a failed image upload
+#[derive(Serialize)]
+struct UploadFailed {
+    error: ImageError,
+}
+
+/// Route for uploading images for a game as multipart form data,
+/// each image is streamed to storage as it is recieved
+#[post("/api/image/{game}", wrap = "from_fn(super::limit_creation)")]
+async fn upload_images(game: web::Path<GameId>, mut payload: Multipart) -> HttpResponse {
+    let dir = game_dir(game.into_inner());
+    if let Err(err) = tokio::fs::create_dir_all(&dir).await {
+        error!("Failed to create image directory: {:?}", err);
+        return HttpResponse::InternalServerError().finish();
+    }
+
+    let max_size = config::from_env(config::MAX_IMAGE_SIZE);
+    let mut ids = Vec::new();
+
+    while let Some(field) = payload.next().await {
+        let result = match field {
+            Ok(field) => store_image(&dir, field, max_size).await,
+            Err(_) => Err(ImageError::Upload),
+        };
+
+        match result {
+            Ok(id) => ids.push(id),
+            Err(error) => return HttpResponse::BadRequest().json(UploadFailed { error }),
+        }
+    }
+
+    HttpResponse::Created().json(UploadedImages { ids })
+}
+
+/// Stores the image from the provided field in the directory under
+/// a new ID removing the partial file if anything fails
+///
+/// `dir`      The directory to store the image in
+/// `field`    The field containing the image
+/// `max_size` The maximum allowed image size
+async fn store_image(dir: &Path, mut field: Field, max_size: usize) -> Result<Uuid, ImageError> {
+    let id = Uuid::new_v4();
+    let path = dir.join(id.to_string());
+
+    let mut file = File::create(&path).await.map_err(|_| ImageError::Storage)?;
+
+    let result = write_image(&mut file, &mut field, max_size).await;
+    if result.is_err() {
+        drop(file);
+        let _ = tokio::fs::remove_file(&path).await;
+    }
+
+    result.map(|_| id)
+}
+
+/// Writes the chunks of the field to the file checking the size
+/// limit and the magic bytes of the image as they arrive
+///
+/// `file`     The file to write to
+/// `field`    The field to read from
+/// `max_size` The maximum allowed image size
+async fn write_image(
+    file: &mut File,
+    field: &mut Field,
+    max_size: usize,
+) -> Result<(), ImageError> {
+    let mut header: Vec<u8> = Vec::with_capacity(ImageFormat::MAGIC_LENGTH);
+    let mut size = 0;
+
+    while let Some(chunk) = field.next().await {
+        let chunk = chunk.map_err(|_| ImageError::Upload)?;
+
+        size += chunk.len();
+        if size > max_size {
+            return Err(ImageError::TooLarge);
+        }
+
+        // Check the format once enough of the header is available
+        if header.len() < ImageFormat::MAGIC_LENGTH {
+            let needed = (ImageFormat::MAGIC_LENGTH - header.len()).min(chunk.len());
+            header.extend_from_slice(&chunk[..needed]);
+            if header.len() == ImageFormat::MAGIC_LENGTH && ImageFormat::detect(&header).is_none() {
+                return Err(ImageError::UnsupportedFormat);
+            }
+        }
+
+        file.write_all(&chunk)
+            .await
+            .map_err(|_| ImageError::Storage)?;
+    }
+
+    // Images smaller than the magic length still need to be checked
+    if ImageFormat::detect(&header).is_none() {
+        return Err(ImageError::UnsupportedFormat);
+    }
+
+    file.flush().await.map_err(|_| ImageError::Storage)
+}
+
+/// Route for retrieving a previously uploaded image
+#[get("/api/image/{game}/{id}")]
+async fn get_image(path: web::Path<(GameId, Uuid)>) -> HttpResponse {
+    let (game, id) = path.into_inner();
+    let bytes = match tokio::fs::read(image_path(game, &id)).await {
+        Ok(value) => value,
+        Err(_) => return HttpResponse::NotFound().finish(),
+    };
+
+    match ImageFormat::detect(&bytes) {
+        Some(format) => HttpResponse::Ok().content_type(format.mime()).body(bytes),
+        None => HttpResponse::NotFound().finish(),
+    }
+}
@@ -0,0 +1,171 @@
+use actix::{Handler, Message};
+use actix_web::{
+    delete, get,
+    middleware::from_fn,
+    post, put,
+    web::{self, Payload, ServiceConfig},
+    HttpResponse,
+};
+use serde::Serialize;
+use tracing::error;
+use uuid::Uuid;
+
+use crate::{
+    error::ValidationErrors,
+    routes::HostIdentity,
+    storage::{DeleteLibraryQuiz, GetLibraryQuiz, ListLibraryQuizzes, SaveLibraryQuiz, Storage},
+};
+
+/// Configuration function for configuring the library routes
+pub fn configure(cfg: &mut ServiceConfig) {
+    cfg.service(list_quizzes)
+        .service(create_quiz)
+        .service(get_quiz)
+        .service(update_quiz)
+        .service(delete_quiz)
+        .service(launch_quiz);
+}
+
+/// Response for a quiz saved to the library
+#[derive(Serialize)]
+struct LibraryQuizCreated {
+    /// The ID of the quiz within the library
+    id: Uuid,
+}
+
+/// Sends the provided message to storage, producing the error
+/// response on failure
+///
+/// `msg` The message to send
+async fn send<M>(msg: M) -> Result<M::Result, HttpResponse>
+where
+    M: Message + Send + 'static,
+    M::Result: Send,
+    Storage: Handler<M>,
+{
+    let storage = Storage::addr().ok_or_else(|| HttpResponse::ServiceUnavailable().finish())?;
+
+    storage.send(msg).await.map_err(|err| {
+        error!("Failed to send library request: {:?}", err);
+        HttpResponse::InternalServerError().finish()
+    })
+}
+
+/// Route for listing the quizzes in the library
+#[get("/api/library")]
+async fn list_quizzes(host: HostIdentity) -> HttpResponse {
+    match send(ListLibraryQuizzes {
+        owner: host.owner(),
+    })
+    .await
+    {
+        Ok(entries) => HttpResponse::Ok().json(entries),
+        Err(response) => response,
+    }
+}
+
+/// Route for saving a quiz bundle to the library, responds with
+/// the ID of the stored quiz or a list of the invalid fields
+#[post("/api/library")]
+async fn create_quiz(host: HostIdentity, payload: Payload) -> HttpResponse {
+    let bundle = match super::quiz::read_bundle(payload).await {
+        Ok(value) => value,
+        Err(response) => return response,
+    };
+    if let Err(errors) = bundle.validate() {
+        return HttpResponse::BadRequest().json(ValidationErrors { errors });
+    }
+
+    let id = Uuid::new_v4();
+    match send(SaveLibraryQuiz {
+        id,
+        bundle,
+        create: true,
+        owner: host.owner(),
+    })
+    .await
+    {
+        Ok(true) => HttpResponse::Created().json(LibraryQuizCreated { id }),
+        Ok(false) => HttpResponse::InternalServerError().finish(),
+        Err(response) => response,
+    }
+}
+
+/// Route for retrieving a quiz bundle from the library
+#[get("/api/library/{id}")]
+async fn get_quiz(host: HostIdentity, id: web::Path<Uuid>) -> HttpResponse {
+    match send(GetLibraryQuiz {
+        id: id.into_inner(),
+        owner: host.owner(),
+    })
+    .await
+    {
+        Ok(Some(bundle)) => HttpResponse::Ok().json(bundle),
+        Ok(None) => HttpResponse::NotFound().finish(),
+        Err(response) => response,
+    }
+}
+
+/// Route for replacing a quiz in the library with the provided bundle
+#[put("/api/library/{id}")]
+async fn update_quiz(host: HostIdentity, id: web::Path<Uuid>, payload: Payload) -> HttpResponse {
+    let bundle = match super::quiz::read_bundle(payload).await {
+        Ok(value) => value,
+        Err(response) => return response,
+    };
+    if let Err(errors) = bundle.validate() {
+        return HttpResponse::BadRequest().json(ValidationErrors { errors });
+    }
+
+    match send(SaveLibraryQuiz {
+        id: id.into_inner(),
+        bundle,
+        create: false,
+        owner: host.owner(),
+    })
+    .await
+    {
+        Ok(true) => HttpResponse::NoContent().finish(),
+        Ok(false) => HttpResponse::NotFound().finish(),
+        Err(response) => response,
+    }
+}
+
+/// Route for removing a quiz from the library
+#[delete("/api/library/{id}")]
+async fn delete_quiz(host: HostIdentity, id: web::Path<Uuid>) -> HttpResponse {
+    match send(DeleteLibraryQuiz {
+        id: id.into_inner(),
+        owner: host.owner(),
+    })
+    .await
+    {
+        Ok(true) => HttpResponse::NoContent().finish(),
+        Ok(false) => HttpResponse::NotFound().finish(),
+        Err(response) => response,
+    }
+}
+
+/// Route for creating a game from a quiz in the library, responds
+/// with the ID for the host to connect with
+#[post("/api/library/{id}/launch", wrap = "from_fn(super::limit_creation)")]
+async fn launch_quiz(host: HostIdentity, id: web::Path<Uuid>) -> HttpResponse {
+    let mut bundle = match send(GetLibraryQuiz {
+        id: id.into_inner(),
+        owner: host.owner(),
+    })
+    .await
+    {
+        Ok(Some(value)) => value,
+        Ok(None) => return HttpResponse::NotFound().finish(),
+        Err(response) => return response,
+    };
+
+    // Results of the game are kept in the history of the host
+    bundle.quiz.owner = host.owner();
+
+    match bundle.validate() {
+        Ok(images) => super::quiz::create(bundle.quiz, images).await,
+        Err(errors) => HttpResponse::BadRequest().json(ValidationErrors { errors }),
+    }
+}
@@ -0,0 +1,120 @@
+use actix_web::{get, web::ServiceConfig, HttpResponse};
+use tracing::error;
+
+use crate::{
+    routes::HostIdentity,
+    storage::{GetUser, Storage},
+};
+
+/// Configuration function for configuring the login routes, logging
+/// in with providers is only available with the oauth feature
+pub fn configure(cfg: &mut ServiceConfig) {
+    cfg.service(current_user);
+
+    #[cfg(feature = "oauth")]
+    cfg.configure(oauth::configure);
+}
+
+/// Route for retrieving the logged in host
+#[get("/api/auth/me")]
+async fn current_user(host: HostIdentity) -> HttpResponse {
+    let (Some(id), Some(storage)) = (host.owner(), Storage::addr()) else {
+        return HttpResponse::NotFound().finish();
+    };
+
+    match storage.send(GetUser { id }).await {
+        Ok(Some(user)) => HttpResponse::Ok().json(user),
+        Ok(None) => HttpResponse::NotFound().finish(),
+        Err(err) => {
+            error!("Failed to send user request: {:?}", err);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+/// Routes for logging in with OAuth providers
+#[cfg(feature = "oauth")]
+mod oauth {
+    use actix_web::{
+        get,
+        http::header,
+        web::{self, Query, ServiceConfig},
+        HttpResponse,
+    };
+    use serde::Deserialize;
+    use tracing::{error, warn};
+
+    use crate::{
+        auth::issue_user_token,
+        config,
+        oauth::{complete_login, login_url, OAuthError, Provider},
+        storage::{Storage, UpsertUser},
+    };
+
+    pub fn configure(cfg: &mut ServiceConfig) {
+        cfg.service(login).service(callback);
+    }
+
+    /// Route sending the host to the provider to log in
+    #[get("/api/auth/{provider}/login")]
+    async fn login(provider: web::Path<Provider>) -> HttpResponse {
+        match login_url(provider.into_inner()) {
+            Ok(url) => HttpResponse::Found()
+                .insert_header((header::LOCATION, url.as_str()))
+                .finish(),
+            Err(_) => HttpResponse::NotFound().finish(),
+        }
+    }
+
+    /// Query parameters the provider redirects back with
+    #[derive(Deserialize)]
+    struct CallbackQuery {
+        code: String,
+        state: String,
+    }
+
+    /// Route the provider redirects back to after logging in, the host is
+    /// sent back to the frontend with the token in the URL fragment
+    #[get("/api/auth/{provider}/callback")]
+    async fn callback(provider: web::Path<Provider>, query: Query<CallbackQuery>) -> HttpResponse {
+        let provider = provider.into_inner();
+        let info = match complete_login(provider, &query.code, &query.state).await {
+            Ok(value) => value,
+            Err(OAuthError::Provider(err)) => {
+                warn!("Failed to log in with {}: {:?}", provider.name(), err);
+                return HttpResponse::BadGateway().finish();
+            }
+            Err(_) => return HttpResponse::BadRequest().finish(),
+        };
+
+        let Some(storage) = Storage::addr() else {
+            return HttpResponse::ServiceUnavailable().finish();
+        };
+
+        let user = match storage
+            .send(UpsertUser {
+                provider: provider.name().to_string(),
+                name: info.name.or_else(|| info.email.clone()).unwrap_or_default(),
+                subject: info.sub,
+                email: info.email,
+            })
+            .await
+        {
+            Ok(Some(value)) => value,
+            Ok(None) => return HttpResponse::InternalServerError().finish(),
+            Err(err) => {
+                error!("Failed to send user request: {:?}", err);
+                return HttpResponse::InternalServerError().finish();
+            }
+        };
+
+        let location = format!(
+            "{}/login#token={}",
+            config::from_env_string(config::PUBLIC_URL).trim_end_matches('/'),
+            issue_user_token(user.id)
+        );
+        HttpResponse::Found()
+            .insert_header((header::LOCATION, location))
+            .finish()
+    }
+}
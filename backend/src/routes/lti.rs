@@ -0,0 +1,122 @@
+use actix_web::{
+    cookie::{time, Cookie, SameSite},
+    get,
+    http::header,
+    post,
+    web::{Form, Query, ServiceConfig},
+    HttpRequest, HttpResponse,
+};
+use serde::Deserialize;
+use tracing::{error, warn};
+
+use crate::{
+    auth, config,
+    lti::{login_url, verify_launch, LoginParams, LtiContext, LtiError},
+    routes::profile::profile_cookie,
+    storage::{LinkLtiUser, Storage},
+};
+
+/// Name of the cookie identifying the gradebook an instructor
+/// launched from
+pub const LTI_COOKIE: &str = "quizler_lti";
+
+/// Configuration function for configuring the LTI routes
+pub fn configure(cfg: &mut ServiceConfig) {
+    cfg.service(login_get).service(login_post).service(launch);
+}
+
+/// Returns the gradebook from the LTI cookie of an instructor
+///
+/// `req` The request
+pub fn cookie_context(req: &HttpRequest) -> Option<LtiContext> {
+    req.cookie(LTI_COOKIE)
+        .and_then(|cookie| auth::verify_lti_context_token(cookie.value()))
+}
+
+/// Sends the user to the platform to authenticate for the launch
+///
+/// `params` The login initiation parameters
+fn login(params: &LoginParams) -> HttpResponse {
+    match login_url(params) {
+        Ok(url) => HttpResponse::Found()
+            .insert_header((header::LOCATION, url.as_str()))
+            .finish(),
+        Err(_) => HttpResponse::BadRequest().finish(),
+    }
+}
+
+/// Route for the platform initiating a login with query parameters
+#[get("/api/lti/login")]
+async fn login_get(params: Query<LoginParams>) -> HttpResponse {
+    login(&params)
+}
+
+/// Route for the platform initiating a login with a form post
+#[post("/api/lti/login")]
+async fn login_post(params: Form<LoginParams>) -> HttpResponse {
+    login(&params)
+}
+
+/// Form the platform posts the launch with
+#[derive(Deserialize)]
+struct LaunchForm {
+    id_token: String,
+    state: String,
+}
+
+/// Route the platform launches to after authenticating, instructors
+/// are given a cookie so the games they create post scores back to
+/// the gradebook and learners are linked to a profile so their scores
+/// can be matched to their LMS account
+#[post("/api/lti/launch")]
+async fn launch(req: HttpRequest, form: Form<LaunchForm>) -> HttpResponse {
+    let launch = match verify_launch(&form.id_token, &form.state).await {
+        Ok(value) => value,
+        Err(LtiError::Platform(err)) => {
+            warn!("Failed to verify LTI launch: {:?}", err);
+            return HttpResponse::BadGateway().finish();
+        }
+        Err(_) => return HttpResponse::Unauthorized().finish(),
+    };
+
+    let location = config::from_env_string(config::PUBLIC_URL);
+    let location = format!("{}/", location.trim_end_matches('/'));
+    let mut response = HttpResponse::Found();
+    response.insert_header((header::LOCATION, location));
+
+    if launch.instructor {
+        if let Some(context) = launch.context {
+            let ttl = config::from_env(config::HOST_TOKEN_TTL);
+            let cookie = Cookie::build(LTI_COOKIE, auth::issue_lti_context_token(context))
+                .path("/")
+                .http_only(true)
+                .same_site(SameSite::Lax)
+                .secure(req.connection_info().scheme() == "https")
+                .max_age(time::Duration::seconds(ttl.min(i64::MAX as u64) as i64))
+                .finish();
+            response.cookie(cookie);
+        }
+        return response.finish();
+    }
+
+    let Some(storage) = Storage::addr() else {
+        return HttpResponse::ServiceUnavailable().finish();
+    };
+    let profile = match storage
+        .send(LinkLtiUser {
+            subject: launch.subject,
+            name: launch.name.unwrap_or_default(),
+        })
+        .await
+    {
+        Ok(Some(value)) => value,
+        Ok(None) => return HttpResponse::InternalServerError().finish(),
+        Err(err) => {
+            error!("Failed to send LTI user request: {:?}", err);
+            return HttpResponse::InternalServerError().finish();
+        }
+    };
+
+    response.cookie(profile_cookie(&req, profile));
+    response.finish()
+}
@@ -0,0 +1,17 @@
+use actix_web::{get, web::ServiceConfig, HttpResponse};
+use prometheus::TEXT_FORMAT;
+
+use crate::metrics::metrics;
+
+/// Configuration function for configuring the metrics routes
+pub fn configure(cfg: &mut ServiceConfig) {
+    cfg.service(get_metrics);
+}
+
+/// Route for scraping the server metrics in the prometheus format
+#[get("/metrics")]
+async fn get_metrics() -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type(TEXT_FORMAT)
+        .body(metrics().encode())
+}
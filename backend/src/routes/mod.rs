@@ -1,5 +1,310 @@
-use actix_web::web::ServiceConfig;
+use std::{
+    net::IpAddr,
+    sync::{Mutex, OnceLock},
+    time::Duration,
+};
+
+use actix_cors::Cors;
+use actix_web::{
+    body::BoxBody,
+    dev::{ServiceRequest, ServiceResponse},
+    error::ErrorUnauthorized,
+    get,
+    http::header::{self, HeaderMap},
+    middleware::Next,
+    web::{Payload, Query, ServiceConfig},
+    FromRequest, HttpRequest, HttpResponse,
+};
+use actix_web_actors::ws;
+use futures_util::future::{ready, Ready};
+use serde::Deserialize;
+use tracing::info_span;
+use uuid::Uuid;
+
+use crate::{
+    api_keys::find_api_key,
+    auth, config,
+    game::GameId,
+    metrics::metrics,
+    proxy,
+    rate_limit::RateLimiter,
+    session::{Encoding, Session},
+};
+
+mod admin;
+#[cfg(feature = "embed")]
+mod frontend;
+mod health;
+mod images;
+mod library;
+mod login;
+#[cfg(feature = "lti")]
+mod lti;
+mod metrics;
+mod profile;
+mod qr;
+mod quiz;
+mod replay;
+mod results;
 
 /// Configuration function for configuring
 /// all the routes
-pub fn configure(cfg: &mut ServiceConfig) {}
+pub fn configure(cfg: &mut ServiceConfig) {
+    cfg.service(connect)
+        .configure(health::configure)
+        .configure(quiz::configure)
+        .configure(images::configure)
+        .configure(results::configure)
+        .configure(replay::configure)
+        .configure(library::configure)
+        .configure(login::configure)
+        .configure(profile::configure)
+        .configure(admin::configure)
+        .configure(metrics::configure)
+        .configure(qr::configure);
+
+    #[cfg(feature = "lti")]
+    cfg.configure(lti::configure);
+
+    // The frontend matches every path so is configured last
+    #[cfg(feature = "embed")]
+    cfg.configure(frontend::configure);
+}
+
+/// Returns the origins allowed to make cross site requests
+fn allowed_origins() -> &'static [String] {
+    static ORIGINS: OnceLock<Vec<String>> = OnceLock::new();
+    ORIGINS.get_or_init(config::allowed_origins)
+}
+
+/// Checks whether the provided origin is allowed
+///
+/// `origin` The value of the origin header
+fn is_allowed_origin(origin: &str) -> bool {
+    allowed_origins()
+        .iter()
+        .any(|allowed| allowed == "*" || allowed.eq_ignore_ascii_case(origin))
+}
+
+/// Creates the CORS middleware allowing the configured origins
+/// to use the API
+pub fn cors() -> Cors {
+    Cors::default()
+        .allowed_origin_fn(|origin, _req| origin.to_str().is_ok_and(is_allowed_origin))
+        .allow_any_method()
+        .allow_any_header()
+        .max_age(3600)
+}
+
+/// Checks the origin of the websocket upgrade request, browsers always
+/// send the origin so requests from other sites are rejected unless the
+/// origin is allowed. Clients that aren't browsers don't send an origin
+///
+/// `req` The upgrade request
+fn is_allowed_upgrade(req: &HttpRequest) -> bool {
+    let Some(origin) = req.headers().get(header::ORIGIN) else {
+        return true;
+    };
+    let Ok(origin) = origin.to_str() else {
+        return false;
+    };
+
+    // Connections from the same site as the server are allowed
+    let same_site = origin
+        .split_once("://")
+        .is_some_and(|(_, host)| host.eq_ignore_ascii_case(req.connection_info().host()));
+
+    same_site || is_allowed_origin(origin)
+}
+
+/// Query parameters for the websocket connection
+#[derive(Deserialize)]
+struct ConnectQuery {
+    /// The encoding server messages should be sent with
+    #[serde(default)]
+    encoding: Encoding,
+    /// The protocol version the client speaks, clients from before
+    /// versioning don't send one and speak the first version
+    #[serde(default = "default_protocol_version")]
+    version: u32,
+}
+
+/// The protocol version of clients that don't send a version
+fn default_protocol_version() -> u32 {
+    1
+}
+
+/// Route for upgrading the connection to a websocket and starting
+/// a new session for it
+#[get("/ws")]
+async fn connect(
+    req: HttpRequest,
+    query: Query<ConnectQuery>,
+    stream: Payload,
+) -> actix_web::Result<HttpResponse> {
+    if !is_allowed_upgrade(&req) {
+        return Ok(HttpResponse::Forbidden().finish());
+    }
+
+    let ip = client_ip(&req);
+    let profile = profile::cookie_profile(&req);
+    let _span = info_span!("upgrade", ip = ?ip).entered();
+    ws::WsResponseBuilder::new(
+        Session::new(ip, query.encoding, query.version, profile),
+        &req,
+        stream,
+    )
+    .frame_size(config::from_env(config::MAX_MESSAGE_SIZE))
+    .start()
+}
+
+/// Returns the address of the client making the request
+///
+/// `req` The request
+fn client_ip(req: &HttpRequest) -> Option<IpAddr> {
+    proxy::client_ip(req.peer_addr().map(|addr| addr.ip()), req.headers())
+}
+
+/// Middleware limiting the number of requests each IP address can
+/// make to expensive routes such as quiz creation and image uploads
+async fn limit_creation(
+    req: ServiceRequest,
+    next: Next<BoxBody>,
+) -> actix_web::Result<ServiceResponse<BoxBody>> {
+    static LIMITER: OnceLock<Mutex<RateLimiter>> = OnceLock::new();
+
+    if let Some(ip) = client_ip(req.request()) {
+        let limiter = LIMITER.get_or_init(|| {
+            let limit = config::from_env(config::CREATE_LIMIT);
+            let window = Duration::from_secs(config::from_env(config::RATE_LIMIT_WINDOW));
+            Mutex::new(RateLimiter::new(limit, window))
+        });
+
+        let allowed = match limiter.lock() {
+            Ok(mut limiter) => limiter.hit(ip),
+            Err(_) => true,
+        };
+
+        if !allowed {
+            return Ok(req.into_response(HttpResponse::TooManyRequests().finish()));
+        }
+    }
+
+    next.call(req).await
+}
+
+/// Middleware for routes creating quizzes which accepts an API key as a
+/// bearer token, keys are limited by their own rate limit and requests
+/// without a key fall back to the limit for their IP address
+async fn limit_api_creation(
+    req: ServiceRequest,
+    next: Next<BoxBody>,
+) -> actix_web::Result<ServiceResponse<BoxBody>> {
+    let Some(value) = bearer_token(req.headers()) else {
+        return limit_creation(req, next).await;
+    };
+
+    let Some(key) = find_api_key(value) else {
+        return Ok(req.into_response(HttpResponse::Unauthorized().finish()));
+    };
+
+    if !key.hit() {
+        return Ok(req.into_response(HttpResponse::TooManyRequests().finish()));
+    }
+
+    metrics().api_key_uses.with_label_values(&[&key.name]).inc();
+
+    next.call(req).await
+}
+
+/// Host authenticated through the bearer token in the authorization
+/// header, either with the host key or a token from logging in
+pub enum HostIdentity {
+    /// Authenticated with the host key, has access to everything
+    Admin,
+    /// Authenticated as a logged in user
+    User(Uuid),
+}
+
+impl HostIdentity {
+    /// Finds the identity from the bearer token in the headers
+    ///
+    /// `headers` The request headers
+    pub fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        let token = bearer_token(headers)?;
+        if auth::is_host_key(token) {
+            Some(Self::Admin)
+        } else {
+            auth::verify_user_token(token).map(Self::User)
+        }
+    }
+
+    /// Returns the user that owns the things this host creates and
+    /// limits the things it can access, None for the admin
+    pub fn owner(&self) -> Option<Uuid> {
+        match self {
+            Self::Admin => None,
+            Self::User(id) => Some(*id),
+        }
+    }
+}
+
+impl FromRequest for HostIdentity {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut actix_web::dev::Payload) -> Self::Future {
+        ready(
+            Self::from_headers(req.headers())
+                .ok_or_else(|| ErrorUnauthorized("Invalid host credentials")),
+        )
+    }
+}
+
+/// Extractor for routes that can only be used by the admin which
+/// requires the host key as the bearer token
+pub struct Admin;
+
+impl FromRequest for Admin {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut actix_web::dev::Payload) -> Self::Future {
+        ready(match HostIdentity::from_headers(req.headers()) {
+            Some(HostIdentity::Admin) => Ok(Self),
+            _ => Err(ErrorUnauthorized("Invalid admin credentials")),
+        })
+    }
+}
+
+/// Returns the bearer token from the authorization header
+///
+/// `headers` The request headers
+fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+}
+
+/// Checks that the request has a bearer host token granting
+/// control of the provided quiz
+///
+/// `req`  The request
+/// `quiz` The ID of the quiz
+fn is_quiz_host(req: &HttpRequest, quiz: GameId) -> bool {
+    bearer_token(req.headers()).is_some_and(|token| auth::verify_host_token(token, quiz))
+}
+
+/// Checks that the request may view what a finished game left behind,
+/// the request must provide the host token for the quiz the game was
+/// created from or be from the logged in host that owns the game
+///
+/// `req`   The request
+/// `quiz`  The ID of the quiz the game was created from
+/// `owner` The ID of the user that hosted the game
+fn is_game_host(req: &HttpRequest, quiz: Option<GameId>, owner: Option<Uuid>) -> bool {
+    quiz.is_some_and(|quiz| is_quiz_host(req, quiz))
+        || HostIdentity::from_headers(req.headers())
+            .is_some_and(|host| host.owner().is_none() || host.owner() == owner)
+}
@@ -0,0 +1,155 @@
+use actix::{Handler, Message};
+use actix_web::{
+    cookie::{time, Cookie, SameSite},
+    delete, get, put,
+    web::{Json, ServiceConfig},
+    HttpRequest, HttpResponse,
+};
+use serde::Deserialize;
+use tracing::error;
+use uuid::Uuid;
+
+use crate::{
+    appearance::is_valid_avatar,
+    auth, config,
+    routes::HostIdentity,
+    storage::{DeleteProfile, GetProfile, Storage, UpsertProfile},
+    username,
+};
+
+/// Name of the cookie identifying the profile of a returning player
+pub const PROFILE_COOKIE: &str = "quizler_profile";
+
+/// Configuration function for configuring the profile routes
+pub fn configure(cfg: &mut ServiceConfig) {
+    cfg.service(get_profile)
+        .service(update_profile)
+        .service(delete_profile);
+}
+
+/// Returns the ID of the profile from the profile cookie
+///
+/// `req` The request
+pub fn cookie_profile(req: &HttpRequest) -> Option<Uuid> {
+    req.cookie(PROFILE_COOKIE)
+        .and_then(|cookie| auth::verify_profile_token(cookie.value()))
+}
+
+/// Returns the ID of the profile for the request, logged in users
+/// use their account and other players use the profile cookie
+///
+/// `req` The request
+fn request_profile(req: &HttpRequest) -> Option<Uuid> {
+    match HostIdentity::from_headers(req.headers()) {
+        Some(HostIdentity::User(id)) => Some(id),
+        _ => cookie_profile(req),
+    }
+}
+
+/// Creates the cookie identifying the provided profile
+///
+/// `req` The request the cookie is for
+/// `id`  The ID of the profile
+pub(super) fn profile_cookie(req: &HttpRequest, id: Uuid) -> Cookie<'static> {
+    let ttl = config::from_env(config::PROFILE_TOKEN_TTL);
+    Cookie::build(PROFILE_COOKIE, auth::issue_profile_token(id))
+        .path("/")
+        .http_only(true)
+        .same_site(SameSite::Lax)
+        .secure(req.connection_info().scheme() == "https")
+        .max_age(time::Duration::seconds(ttl.min(i64::MAX as u64) as i64))
+        .finish()
+}
+
+/// Sends the provided message to storage, producing the error
+/// response on failure
+///
+/// `msg` The message to send
+async fn send<M>(msg: M) -> Result<M::Result, HttpResponse>
+where
+    M: Message + Send + 'static,
+    M::Result: Send,
+    Storage: Handler<M>,
+{
+    let storage = Storage::addr().ok_or_else(|| HttpResponse::ServiceUnavailable().finish())?;
+
+    storage.send(msg).await.map_err(|err| {
+        error!("Failed to send profile request: {:?}", err);
+        HttpResponse::InternalServerError().finish()
+    })
+}
+
+/// Route for retrieving the profile of the returning player
+#[get("/api/profile")]
+async fn get_profile(req: HttpRequest) -> HttpResponse {
+    let Some(id) = request_profile(&req) else {
+        return HttpResponse::NotFound().finish();
+    };
+
+    match send(GetProfile { id }).await {
+        Ok(Some(profile)) => HttpResponse::Ok().json(profile),
+        Ok(None) => HttpResponse::NotFound().finish(),
+        Err(response) => response,
+    }
+}
+
+/// Request to create or update a profile
+#[derive(Deserialize)]
+struct ProfileUpdate {
+    /// The display name to keep between games
+    name: String,
+    /// The chosen avatar
+    #[serde(default)]
+    avatar: Option<String>,
+}
+
+/// Route for opting in to a profile or updating the existing one,
+/// new profiles are given a cookie identifying them
+#[put("/api/profile")]
+async fn update_profile(req: HttpRequest, body: Json<ProfileUpdate>) -> HttpResponse {
+    let ProfileUpdate { name, avatar } = body.into_inner();
+    let name = match username::validate(&name) {
+        Ok(value) => value,
+        Err(err) => return HttpResponse::BadRequest().json(err),
+    };
+    if avatar
+        .as_deref()
+        .is_some_and(|avatar| !is_valid_avatar(avatar))
+    {
+        return HttpResponse::BadRequest().finish();
+    }
+
+    let id = request_profile(&req).unwrap_or_else(Uuid::new_v4);
+    match send(UpsertProfile { id, name, avatar }).await {
+        // The cookie is refreshed so that it doesn't expire while in use
+        Ok(Some(profile)) => HttpResponse::Ok()
+            .cookie(profile_cookie(&req, id))
+            .json(profile),
+        Ok(None) => HttpResponse::InternalServerError().finish(),
+        Err(response) => response,
+    }
+}
+
+/// Route for deleting the profile of the returning player and
+/// forgetting the profile cookie
+#[delete("/api/profile")]
+async fn delete_profile(req: HttpRequest) -> HttpResponse {
+    let Some(id) = request_profile(&req) else {
+        return HttpResponse::NotFound().finish();
+    };
+
+    match send(DeleteProfile { id }).await {
+        Ok(true) => {
+            let mut response = HttpResponse::NoContent().finish();
+            let mut cookie = Cookie::named(PROFILE_COOKIE);
+            cookie.set_path("/");
+            cookie.make_removal();
+            if let Err(err) = response.add_cookie(&cookie) {
+                error!("Failed to remove profile cookie: {:?}", err);
+            }
+            response
+        }
+        Ok(false) => HttpResponse::NotFound().finish(),
+        Err(response) => response,
+    }
+}
@@ -0,0 +1,83 @@
+use std::io::Cursor;
+
+use actix_web::{
+    get,
+    web::{self, ServiceConfig},
+    HttpResponse,
+};
+use image::{ImageOutputFormat, Luma};
+use qrcode::{render::svg, QrCode};
+use serde::Deserialize;
+use tracing::error;
+
+use crate::{config, token::normalize_vanity};
+
+/// Configuration function for configuring the QR code routes
+pub fn configure(cfg: &mut ServiceConfig) {
+    cfg.service(get_qr);
+}
+
+/// The minimum width and height in pixels of rendered codes
+const QR_SIZE: u32 = 256;
+
+/// Image formats QR codes can be rendered as
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum QrFormat {
+    #[default]
+    Svg,
+    Png,
+}
+
+/// Query parameters for the QR code route
+#[derive(Deserialize)]
+struct QrQuery {
+    /// The format to render the code as
+    #[serde(default)]
+    format: QrFormat,
+}
+
+/// Route for rendering a QR code linking to the join page for the
+/// game with the provided token. The token isn't checked against the
+/// running games so that this can't be used to guess tokens
+#[get("/api/qr/{token}")]
+async fn get_qr(token: web::Path<String>, query: web::Query<QrQuery>) -> HttpResponse {
+    let token = match normalize_vanity(&token) {
+        Some(value) => value,
+        None => return HttpResponse::BadRequest().finish(),
+    };
+
+    let base_url = config::from_env_string(config::PUBLIC_URL);
+    let url = format!("{}/?token={}", base_url.trim_end_matches('/'), token);
+
+    let code = match QrCode::new(url.as_bytes()) {
+        Ok(value) => value,
+        Err(err) => {
+            error!("Failed to create QR code: {:?}", err);
+            return HttpResponse::InternalServerError().finish();
+        }
+    };
+
+    match query.format {
+        QrFormat::Svg => {
+            let image = code
+                .render::<svg::Color>()
+                .min_dimensions(QR_SIZE, QR_SIZE)
+                .build();
+            HttpResponse::Ok().content_type("image/svg+xml").body(image)
+        }
+        QrFormat::Png => {
+            let image = code
+                .render::<Luma<u8>>()
+                .min_dimensions(QR_SIZE, QR_SIZE)
+                .build();
+
+            let mut bytes = Vec::new();
+            if let Err(err) = image.write_to(&mut Cursor::new(&mut bytes), ImageOutputFormat::Png) {
+                error!("Failed to encode QR code: {:?}", err);
+                return HttpResponse::InternalServerError().finish();
+            }
+            HttpResponse::Ok().content_type("image/png").body(bytes)
+        }
+    }
+}
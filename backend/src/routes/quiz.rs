@@ -0,0 +1,222 @@
+use actix::SystemService;
+use actix_web::{
+    get,
+    http::header::{ContentDisposition, DispositionParam, DispositionType},
+    middleware::from_fn,
+    post,
+    web::{self, Bytes, BytesMut, Json, Payload, Query, ServiceConfig},
+    HttpRequest, HttpResponse,
+};
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use tracing::error;
+
+use crate::{
+    auth::issue_host_token,
+    bundle::{self, DecodedImage, QuizBundle},
+    config,
+    convert::{convert, ConvertFormat},
+    error::{FieldError, FieldErrorReason, ValidationErrors},
+    game::{GameConfig, GameId, Question},
+    games::{Games, GamesRequest, GamesResponse},
+    import::{import, ImportFormat},
+};
+
+/// Configuration function for configuring the quiz routes
+pub fn configure(cfg: &mut ServiceConfig) {
+    cfg.service(create_quiz)
+        .service(import_quiz)
+        .service(convert_quiz)
+        .service(export_quiz)
+        .service(import_bundle);
+}
+
+/// Response for a successfully created quiz
+#[derive(Serialize)]
+struct QuizCreated {
+    /// The ID the host uses to connect to the game
+    id: GameId,
+    /// Signed token the host provides when connecting and with
+    /// privileged actions
+    host_token: String,
+}
+
+/// Route for creating a new quiz from the provided quiz definition,
+/// responds with the ID for the host to connect with or a list of
+/// the fields that failed validation. Quizzes created by instructors
+/// launched from an LMS post their scores back to its gradebook
+#[post("/api/quiz", wrap = "from_fn(super::limit_api_creation)")]
+async fn create_quiz(req: HttpRequest, Json(config): Json<GameConfig>) -> HttpResponse {
+    create(with_lti_context(&req, config), Vec::new()).await
+}
+
+/// Attaches the gradebook from the LTI cookie of the instructor
+/// creating the quiz
+///
+/// `req`    The request
+/// `config` The quiz definition
+#[cfg(feature = "lti")]
+fn with_lti_context(req: &HttpRequest, mut config: GameConfig) -> GameConfig {
+    config.lti = super::lti::cookie_context(req);
+    config
+}
+
+/// Quizzes can't be launched from an LMS without the lti feature
+///
+/// `req`    The request
+/// `config` The quiz definition
+#[cfg(not(feature = "lti"))]
+fn with_lti_context(_req: &HttpRequest, config: GameConfig) -> GameConfig {
+    config
+}
+
+/// Validates and creates the quiz storing its definition along with
+/// the provided images under the new quiz ID
+///
+/// `config` The quiz definition
+/// `images` The images to store for the quiz
+pub async fn create(config: GameConfig, images: Vec<DecodedImage>) -> HttpResponse {
+    let errors = config.validate();
+    if !errors.is_empty() {
+        return HttpResponse::BadRequest().json(ValidationErrors { errors });
+    }
+
+    let definition = match serde_json::to_vec(&config) {
+        Ok(value) => value,
+        Err(err) => {
+            error!("Failed to serialize quiz definition: {:?}", err);
+            return HttpResponse::InternalServerError().finish();
+        }
+    };
+
+    match Games::from_registry()
+        .send(GamesRequest::PreInitGame {
+            config: Box::new(config),
+        })
+        .await
+    {
+        Ok(Ok(GamesResponse::PreInitComplete { id })) => {
+            if let Err(err) = bundle::store(id, &definition, &images).await {
+                error!("Failed to store quiz {}: {:?}", id, err);
+                if !images.is_empty() {
+                    return HttpResponse::InternalServerError().finish();
+                }
+            }
+            HttpResponse::Created().json(QuizCreated {
+                id,
+                host_token: issue_host_token(id),
+            })
+        }
+        Ok(Err(err)) => HttpResponse::ServiceUnavailable().json(err),
+        Ok(_) => {
+            error!("Unexpected games response to quiz creation");
+            HttpResponse::InternalServerError().finish()
+        }
+        Err(err) => {
+            error!("Failed to send quiz creation request: {:?}", err);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+/// Query parameters for importing questions
+#[derive(Deserialize)]
+struct ImportQuery {
+    /// The format of the uploaded file
+    #[serde(default)]
+    format: ImportFormat,
+}
+
+/// Response for successfully imported questions
+#[derive(Serialize)]
+struct ImportedQuestions {
+    questions: Vec<Question>,
+}
+
+/// Route for converting a CSV or XLSX file into questions, responds
+/// with the converted questions or a list of the rows that were invalid
+#[post("/api/quiz/import", wrap = "from_fn(super::limit_creation)")]
+async fn import_quiz(query: Query<ImportQuery>, body: Bytes) -> HttpResponse {
+    match import(query.format, &body) {
+        Ok(questions) => HttpResponse::Ok().json(ImportedQuestions { questions }),
+        Err(errors) => HttpResponse::BadRequest().json(ValidationErrors { errors }),
+    }
+}
+
+/// Query parameters for converting a quiz from another platform
+#[derive(Deserialize)]
+struct ConvertQuery {
+    /// The format of the uploaded export
+    format: ConvertFormat,
+}
+
+/// Route for converting a quiz exported from another platform into
+/// a quiz, responds with the converted quiz or a list of the invalid
+/// questions
+#[post("/api/quiz/convert", wrap = "from_fn(super::limit_creation)")]
+async fn convert_quiz(query: Query<ConvertQuery>, body: Bytes) -> HttpResponse {
+    match convert(query.format, &body) {
+        Ok(quiz) => HttpResponse::Ok().json(quiz),
+        Err(errors) => HttpResponse::BadRequest().json(ValidationErrors { errors }),
+    }
+}
+
+/// Route for exporting a quiz as a portable bundle containing the quiz
+/// definition and the images it uses, requires the host token
+#[get("/api/quiz/{id}/export")]
+async fn export_quiz(req: HttpRequest, id: web::Path<GameId>) -> HttpResponse {
+    let id = id.into_inner();
+    if !super::is_quiz_host(&req, id) {
+        return HttpResponse::Unauthorized().finish();
+    }
+    match bundle::export(id).await {
+        Ok(Some(bundle)) => HttpResponse::Ok()
+            .insert_header(ContentDisposition {
+                disposition: DispositionType::Attachment,
+                parameters: vec![DispositionParam::Filename(format!("quiz-{}.json", id))],
+            })
+            .json(bundle),
+        Ok(None) => HttpResponse::NotFound().finish(),
+        Err(err) => {
+            error!("Failed to export quiz {}: {:?}", id, err);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+/// Route for creating a quiz from a previously exported bundle, the
+/// bundled images are stored under their original IDs so the questions
+/// don't need to be changed
+#[post("/api/quiz/bundle", wrap = "from_fn(super::limit_api_creation)")]
+async fn import_bundle(payload: Payload) -> HttpResponse {
+    let bundle = match read_bundle(payload).await {
+        Ok(value) => value,
+        Err(response) => return response,
+    };
+
+    match bundle::decode_images(&bundle.images) {
+        Ok(images) => create(bundle.quiz, images).await,
+        Err(errors) => HttpResponse::BadRequest().json(ValidationErrors { errors }),
+    }
+}
+
+/// Reads a quiz bundle from the request body, bundles embed their
+/// images so they are allowed to be larger than other JSON bodies
+///
+/// `payload` The request body
+pub async fn read_bundle(mut payload: Payload) -> Result<QuizBundle, HttpResponse> {
+    let max_size = config::from_env(config::MAX_BUNDLE_SIZE);
+    let mut body = BytesMut::new();
+    while let Some(chunk) = payload.next().await {
+        let chunk = chunk.map_err(|_| HttpResponse::BadRequest().finish())?;
+        if body.len() + chunk.len() > max_size {
+            return Err(HttpResponse::PayloadTooLarge().finish());
+        }
+        body.extend_from_slice(&chunk);
+    }
+
+    serde_json::from_slice(&body).map_err(|_| {
+        let errors = vec![FieldError::new("bundle", FieldErrorReason::InvalidValue)];
+        HttpResponse::BadRequest().json(ValidationErrors { errors })
+    })
+}
@@ -0,0 +1,34 @@
+use actix_web::{
+    get,
+    web::{self, ServiceConfig},
+    HttpRequest, HttpResponse,
+};
+use tracing::error;
+use uuid::Uuid;
+
+use crate::replay;
+
+/// Configuration function for configuring the replay routes
+pub fn configure(cfg: &mut ServiceConfig) {
+    cfg.service(get_replay);
+}
+
+/// Route for retrieving the replay of a finished game so that the
+/// host can step through the events of the game
+#[get("/api/replay/{id}")]
+async fn get_replay(req: HttpRequest, id: web::Path<Uuid>) -> HttpResponse {
+    let replay = match replay::load(id.into_inner()).await {
+        Ok(Some(value)) => value,
+        Ok(None) => return HttpResponse::NotFound().finish(),
+        Err(err) => {
+            error!("Failed to load replay: {}", err);
+            return HttpResponse::InternalServerError().finish();
+        }
+    };
+
+    if !super::is_game_host(&req, replay.quiz, replay.owner) {
+        return HttpResponse::Unauthorized().finish();
+    }
+
+    HttpResponse::Ok().json(replay)
+}
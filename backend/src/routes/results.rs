@@ -0,0 +1,172 @@
+use std::convert::Infallible;
+
+use actix_web::{
+    get,
+    http::header::{ContentDisposition, DispositionParam, DispositionType},
+    web::{self, Bytes, ServiceConfig},
+    HttpRequest, HttpResponse,
+};
+use futures_util::stream;
+use tracing::error;
+use uuid::Uuid;
+
+use crate::{
+    analytics,
+    routes::HostIdentity,
+    storage::{GameResults, GetResults, ListResults, Storage},
+};
+
+/// Configuration function for configuring the results routes
+pub fn configure(cfg: &mut ServiceConfig) {
+    cfg.service(list_results)
+        .service(get_results)
+        .service(get_results_csv)
+        .service(get_results_analytics);
+
+    #[cfg(feature = "pdf")]
+    cfg.service(get_results_report)
+        .service(get_results_certificate);
+}
+
+/// Loads the results with the provided ID from storage, producing
+/// the error response on failure. The request must provide the host
+/// token for the quiz the results are from or be from the logged in
+/// host that owns the results
+///
+/// `req` The request
+/// `id`  The ID of the results
+async fn load_results(req: &HttpRequest, id: Uuid) -> Result<GameResults, HttpResponse> {
+    let storage = Storage::addr().ok_or_else(|| HttpResponse::ServiceUnavailable().finish())?;
+
+    match storage.send(GetResults { id }).await {
+        Ok(Some(results)) => {
+            if !super::is_game_host(req, results.quiz, results.owner) {
+                return Err(HttpResponse::Unauthorized().finish());
+            }
+            Ok(results)
+        }
+        Ok(None) => Err(HttpResponse::NotFound().finish()),
+        Err(err) => {
+            error!("Failed to send results request: {:?}", err);
+            Err(HttpResponse::InternalServerError().finish())
+        }
+    }
+}
+
+/// Route for listing the results of the games hosted by the logged in host
+#[get("/api/results")]
+async fn list_results(host: HostIdentity) -> HttpResponse {
+    let Some(owner) = host.owner() else {
+        return HttpResponse::BadRequest().finish();
+    };
+    let Some(storage) = Storage::addr() else {
+        return HttpResponse::ServiceUnavailable().finish();
+    };
+
+    match storage.send(ListResults { owner }).await {
+        Ok(entries) => HttpResponse::Ok().json(entries),
+        Err(err) => {
+            error!("Failed to send results request: {:?}", err);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+/// Route for retrieving the results of a finished game
+#[get("/api/results/{id}")]
+async fn get_results(req: HttpRequest, id: web::Path<Uuid>) -> HttpResponse {
+    match load_results(&req, id.into_inner()).await {
+        Ok(results) => HttpResponse::Ok().json(results),
+        Err(response) => response,
+    }
+}
+
+/// Route for retrieving the item statistics for each question of a
+/// finished game
+#[get("/api/results/{id}/analytics")]
+async fn get_results_analytics(req: HttpRequest, id: web::Path<Uuid>) -> HttpResponse {
+    match load_results(&req, id.into_inner()).await {
+        Ok(results) => HttpResponse::Ok().json(analytics::analyze(&results)),
+        Err(response) => response,
+    }
+}
+
+/// Creates the response for a rendered PDF document
+///
+/// `rendered` The result of rendering the document
+/// `filename` The name the document is downloaded as
+#[cfg(feature = "pdf")]
+fn pdf_response(rendered: Result<Vec<u8>, printpdf::Error>, filename: String) -> HttpResponse {
+    match rendered {
+        Ok(bytes) => HttpResponse::Ok()
+            .content_type("application/pdf")
+            .insert_header(ContentDisposition {
+                disposition: DispositionType::Attachment,
+                parameters: vec![DispositionParam::Filename(filename)],
+            })
+            .body(bytes),
+        Err(err) => {
+            error!("Failed to render PDF: {:?}", err);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+/// Route for retrieving a printable PDF report of the results of
+/// a finished game
+#[cfg(feature = "pdf")]
+#[get("/api/results/{id}/report")]
+async fn get_results_report(req: HttpRequest, id: web::Path<Uuid>) -> HttpResponse {
+    let results = match load_results(&req, id.into_inner()).await {
+        Ok(value) => value,
+        Err(response) => return response,
+    };
+
+    let filename = format!("{}.pdf", results.id);
+    pdf_response(crate::pdf::report(&results), filename)
+}
+
+/// Route for retrieving the PDF completion certificate for
+/// a player of a finished game
+#[cfg(feature = "pdf")]
+#[get("/api/results/{id}/certificates/{player}")]
+async fn get_results_certificate(
+    req: HttpRequest,
+    path: web::Path<(Uuid, crate::session::SessionId)>,
+) -> HttpResponse {
+    let (id, player) = path.into_inner();
+    let results = match load_results(&req, id).await {
+        Ok(value) => value,
+        Err(response) => return response,
+    };
+    let Some(player) = results.players.iter().find(|value| value.id == player) else {
+        return HttpResponse::NotFound().finish();
+    };
+
+    let filename = format!("{}-{}.pdf", results.id, player.id);
+    pdf_response(crate::pdf::certificate(&results, player), filename)
+}
+
+/// Route for retrieving the results of a finished game as CSV with a
+/// row for each player, rows are streamed as they are encoded
+#[get("/api/results/{id}/csv")]
+async fn get_results_csv(req: HttpRequest, id: web::Path<Uuid>) -> HttpResponse {
+    let results = match load_results(&req, id.into_inner()).await {
+        Ok(value) => value,
+        Err(response) => return response,
+    };
+
+    let filename = format!("{}.csv", results.id);
+
+    HttpResponse::Ok()
+        .content_type("text/csv")
+        .insert_header(ContentDisposition {
+            disposition: DispositionType::Attachment,
+            parameters: vec![DispositionParam::Filename(filename)],
+        })
+        .streaming(stream::iter(
+            results
+                .into_csv_rows()
+                .map(|row| Ok::<_, Infallible>(Bytes::from(row))),
+        ))
+}
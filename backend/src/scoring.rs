@@ -0,0 +1,311 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+/// Model describing how correct answers are scored based on
+/// how quickly they were answered.
+///
+/// Every score calculated here holds to the following invariants:
+///
+/// - Scores never go below zero, losses such as lost wagers saturate
+///   at zero rather than wrapping
+/// - A faster correct answer never scores less than a slower correct
+///   answer to the same question with the same streak and modifiers
+/// - The score for a single question never exceeds the bound from
+///   [`ScoringModel::max_score`] along with the point modifier of the
+///   question
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScoringModel {
+    /// Score awarded for an instant correct answer
+    pub base_score: u32,
+    /// Minimum score awarded for a correct answer at the last moment
+    pub min_score: u32,
+    /// The amount awarded if answered within the bonus time
+    pub bonus_score: u32,
+    /// How the score decays from the base score to the minimum
+    pub curve: DecayCurve,
+    /// Extra portion of the score awarded for each consecutive correct
+    /// answer after the first (e.g. 0.1 for 10% per answer)
+    #[serde(default)]
+    pub streak_bonus: f32,
+    /// The streak length after which the streak bonus stops growing
+    #[serde(default)]
+    pub max_streak: u32,
+}
+
+impl Default for ScoringModel {
+    fn default() -> Self {
+        Self {
+            base_score: 1000,
+            min_score: 100,
+            bonus_score: 0,
+            curve: DecayCurve::Linear,
+            streak_bonus: 0.0,
+            max_streak: 0,
+        }
+    }
+}
+
+/// Curves for how the score decays over the answer time
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(tag = "ty")]
+pub enum DecayCurve {
+    /// Score decays at a constant rate
+    Linear,
+    /// Score decays quickly at first then slows down, larger
+    /// rates decay faster
+    Exponential { rate: f32 },
+}
+
+impl ScoringModel {
+    /// Whether the model can be scored with, the minimum score can't be
+    /// above the base score and the curve and streak bonus can't be
+    /// negative since either would break the invariants
+    pub fn is_valid(&self) -> bool {
+        let valid_curve = match self.curve {
+            DecayCurve::Linear => true,
+            DecayCurve::Exponential { rate } => rate.is_finite() && rate >= 0.0,
+        };
+        let valid_streak = self.streak_bonus.is_finite() && self.streak_bonus >= 0.0;
+        self.min_score <= self.base_score && valid_curve && valid_streak
+    }
+
+    /// Calculates the score for a correct answer, the score only ever
+    /// shrinks as the elapsed time grows
+    ///
+    /// `elapsed`     The time taken to answer
+    /// `answer_time` The total time given to answer
+    /// `multiplier`  The question score multiplier
+    /// `is_bonus`    Whether the answer was within the bonus time
+    pub fn score(
+        &self,
+        elapsed: Duration,
+        answer_time: Duration,
+        multiplier: f32,
+        is_bonus: bool,
+    ) -> u32 {
+        // Progress through the answer time from 0 to 1
+        let progress = if answer_time.is_zero() {
+            1.0
+        } else {
+            (elapsed.as_secs_f32() / answer_time.as_secs_f32()).min(1.0)
+        };
+
+        // Portion of the score range remaining from 1 to 0
+        let remaining = match self.curve {
+            DecayCurve::Linear => 1.0 - progress,
+            DecayCurve::Exponential { rate } => {
+                // Normalized so the curve still reaches zero at the end
+                let end = (-rate).exp();
+                if end >= 1.0 {
+                    1.0 - progress
+                } else {
+                    ((-rate * progress).exp() - end) / (1.0 - end)
+                }
+            }
+        };
+
+        let range = self.base_score.saturating_sub(self.min_score) as f32;
+        let mut score = self
+            .min_score
+            .saturating_add((range * remaining.clamp(0.0, 1.0)) as u32);
+        if is_bonus {
+            score = score.saturating_add(self.bonus_score);
+        }
+
+        scale(score, multiplier.max(0.0))
+    }
+
+    /// Applies the streak bonus to the provided score
+    ///
+    /// `score`  The score for the answer
+    /// `streak` The current streak including the answer
+    pub fn apply_streak(&self, score: u32, streak: u32) -> u32 {
+        let steps = streak.saturating_sub(1).min(self.max_streak);
+        scale(score, self.streak_factor(steps))
+    }
+
+    /// Returns the most a single correct answer can score on a question
+    /// with the multiplier before the point modifier of the question is
+    /// applied, which is an instant answer within the bonus time on the
+    /// longest streak
+    ///
+    /// `multiplier` The question score multiplier
+    pub fn max_score(&self, multiplier: f32) -> u32 {
+        let best = self.base_score.max(self.min_score);
+        let best = scale(best.saturating_add(self.bonus_score), multiplier.max(0.0));
+        scale(best, self.streak_factor(self.max_streak))
+    }
+
+    /// Returns the factor the score is scaled by after the provided
+    /// number of consecutive correct answers beyond the first
+    ///
+    /// `steps` The number of streak steps
+    fn streak_factor(&self, steps: u32) -> f32 {
+        1.0 + self.streak_bonus.max(0.0) * steps as f32
+    }
+}
+
+/// Scales the score of a correct answer in buzzer mode by its position
+/// in the order the correct answers arrived, each position scores the
+/// decay fraction of the one before so later answers never score more
+///
+/// `score`    The score for the answer
+/// `decay`    The fraction kept by each later position
+/// `position` The position of the answer with the first being zero
+pub fn buzzer_score(score: u32, decay: f32, position: usize) -> u32 {
+    let position = i32::try_from(position).unwrap_or(i32::MAX);
+    scale(score, decay.clamp(0.0, 1.0).powi(position))
+}
+
+/// Doubles the score for a double points question
+///
+/// `score` The score for the answer
+pub fn double_score(score: u32) -> u32 {
+    score.saturating_mul(2)
+}
+
+/// Adds the wager won by a correct answer to the score for the answer
+///
+/// `score` The score for the answer
+/// `wager` The amount wagered
+pub fn win_wager(score: u32, wager: u32) -> u32 {
+    score.saturating_add(wager)
+}
+
+/// Removes the wager lost by an incorrect or missing answer from the
+/// total score of the player, the total never drops below zero
+///
+/// `total` The total score of the player
+/// `wager` The amount wagered
+pub fn lose_wager(total: u32, wager: u32) -> u32 {
+    total.saturating_sub(wager)
+}
+
+/// Scales the score by a factor rounding to the nearest point, the
+/// result saturates rather than overflowing
+///
+/// `score`  The score to scale
+/// `factor` The non negative factor to scale by
+fn scale(score: u32, factor: f32) -> u32 {
+    // Float to int casts saturate and turn NaN into zero
+    ((score as f32) * factor).round() as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+
+    /// Valid scoring models, the base scores stay below 2^24 so the
+    /// score range is exact as a float
+    fn model() -> impl Strategy<Value = ScoringModel> {
+        let curve = prop_oneof![
+            Just(DecayCurve::Linear),
+            (0.0f32..20.0).prop_map(|rate| DecayCurve::Exponential { rate }),
+        ];
+        (
+            0u32..=1_000_000,
+            0u32..=10_000,
+            curve,
+            0.0f32..1.0,
+            0u32..20,
+        )
+            .prop_flat_map(
+                |(base_score, bonus_score, curve, streak_bonus, max_streak)| {
+                    (0..=base_score).prop_map(move |min_score| ScoringModel {
+                        base_score,
+                        min_score,
+                        bonus_score,
+                        curve,
+                        streak_bonus,
+                        max_streak,
+                    })
+                },
+            )
+    }
+
+    proptest! {
+        #[test]
+        fn generated_models_are_valid(model in model()) {
+            prop_assert!(model.is_valid());
+        }
+
+        #[test]
+        fn lost_wagers_never_go_below_zero(total: u32, wager: u32) {
+            let remaining = lose_wager(total, wager);
+            prop_assert!(remaining <= total);
+            prop_assert_eq!(remaining, total.saturating_sub(wager));
+        }
+
+        #[test]
+        fn faster_answers_never_score_less(
+            model in model(),
+            answer_time in 0u64..120_000,
+            first in 0u64..150_000,
+            second in 0u64..150_000,
+            multiplier in 0.0f32..10.0,
+            is_bonus: bool,
+            streak in 1u32..50,
+        ) {
+            let answer_time = Duration::from_millis(answer_time);
+            let faster = Duration::from_millis(first.min(second));
+            let slower = Duration::from_millis(first.max(second));
+
+            let faster = model.score(faster, answer_time, multiplier, is_bonus);
+            let slower = model.score(slower, answer_time, multiplier, is_bonus);
+            prop_assert!(faster >= slower);
+            prop_assert!(model.apply_streak(faster, streak) >= model.apply_streak(slower, streak));
+        }
+
+        #[test]
+        fn scores_never_exceed_the_max_score(
+            model in model(),
+            answer_time in 0u64..120_000,
+            elapsed in 0u64..150_000,
+            multiplier in 0.0f32..10.0,
+            is_bonus: bool,
+            streak in 1u32..50,
+        ) {
+            let score = model.score(
+                Duration::from_millis(elapsed),
+                Duration::from_millis(answer_time),
+                multiplier,
+                is_bonus,
+            );
+            let score = model.apply_streak(score, streak);
+            prop_assert!(score <= model.max_score(multiplier));
+        }
+
+        #[test]
+        fn point_modifiers_bound_the_score(
+            model in model(),
+            elapsed in 0u64..150_000,
+            multiplier in 0.0f32..10.0,
+            streak in 1u32..50,
+            wager: u32,
+        ) {
+            let max_score = model.max_score(multiplier);
+            let score = model.score(Duration::from_millis(elapsed), Duration::from_secs(30), multiplier, true);
+            let score = model.apply_streak(score, streak).min(max_score);
+
+            prop_assert!(double_score(score) <= double_score(max_score));
+            prop_assert!(win_wager(score, wager) <= win_wager(max_score, wager));
+        }
+
+        #[test]
+        fn later_buzzes_never_score_more(
+            score in 0u32..=1_000_000,
+            decay in 0.0f32..=1.0,
+            first in 0usize..100,
+            second in 0usize..100,
+        ) {
+            let earlier = buzzer_score(score, decay, first.min(second));
+            let later = buzzer_score(score, decay, first.max(second));
+            prop_assert!(earlier >= later);
+            prop_assert!(earlier <= score);
+            prop_assert_eq!(buzzer_score(score, decay, 0), score);
+        }
+    }
+}
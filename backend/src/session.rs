@@ -1,33 +1,160 @@
-use std::collections::HashMap;
+use std::{
+    cell::{Cell, RefCell},
+    collections::{HashMap, VecDeque},
+    net::IpAddr,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc, OnceLock,
+    },
+    time::{Duration, Instant},
+};
 
 use actix::{
     dev::MessageResponse, Actor, ActorContext, Addr, AsyncContext, Handler, Message, StreamHandler,
+    SystemService,
 };
+use actix_web::web::Bytes;
 use actix_web_actors::ws;
-use log::{error, info};
-use serde::{ser::SerializeStruct, Deserialize, Serialize};
+use bytestring::ByteString;
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use tracing::{error, field, info, info_span, Span};
+use uuid::Uuid;
 
 use crate::{
+    appearance::Appearance,
+    auth::verify_host_token,
+    cluster::directory,
+    config,
+    decode::decode_client_message,
     error::ServerError,
     game::{
-        AnswerResult, BasicConfig, Game, GameId, GameState, GameTiming, Question, QuestionAnswer,
+        AnswerResult, BasicConfig, Game, GameId, GameRequest, GameState, GameSummary, GameTiming,
+        HostAction, PlayerDetails, PlayerQuestion, QuestionAnswer, QuestionStats, Reaction,
+        ResumeToken, RevealedAnswer, StateSnapshot, QUESTION_TYPES,
     },
+    games::{registry, Games, GamesRequest},
+    metrics::metrics,
+    rate_limit::{FloodAction, FloodGuard, WindowCounter},
+    telemetry::traced,
 };
 
 pub struct Session {
     /// Unique ID of the session
     id: SessionId,
+    /// The ID the session is stored under in the connected sessions,
+    /// unlike the session ID this doesn't change when joining a game
+    key: u32,
     /// Address to the current game if apart of one
     game: Option<SessionGame>,
+    /// The last time a heartbeat was recieved from the client
+    heartbeat: Instant,
+    /// The IP address the session connected from
+    ip: Option<IpAddr>,
+    /// The encoding used for messages sent to the client
+    encoding: Encoding,
+    /// The protocol version the client speaks
+    version: u32,
+    /// The profile of the returning player from the profile cookie
+    profile: Option<Uuid>,
+    /// Counter limiting how often reactions are forwarded to the game
+    reaction_limit: WindowCounter,
+    /// Guard limiting the rate of messages from the client
+    flood_guard: FloodGuard,
+    /// Tracking of the bytes written that the client hasn't received
+    outbound: Outbound,
+    /// Smoothed round trip time to the client measured using pings
+    rtt: Option<Duration>,
+    /// Span of the request that upgraded the connection, the spans
+    /// of the session link back to it
+    upgrade: Span,
+}
+
+/// The current version of the websocket protocol
+pub const PROTOCOL_VERSION: u32 = 1;
+/// The oldest version of the websocket protocol still supported
+pub const MIN_PROTOCOL_VERSION: u32 = 1;
+
+/// The names of the supported encodings
+const ENCODINGS: &[&str] = &["json", "msgpack"];
+
+/// Features of the protocol supported by the server, advertised to
+/// clients when they connect
+#[derive(Serialize, Clone)]
+pub struct Features {
+    /// The question types that can be asked
+    pub question_types: &'static [&'static str],
+    /// The encodings messages can be sent with
+    pub encodings: &'static [&'static str],
+}
+
+/// Encodings that server messages can be written with, negotiated
+/// when the client connects
+#[derive(Clone, Copy, Default, Deserialize)]
+pub enum Encoding {
+    /// JSON encoded text frames
+    #[default]
+    #[serde(rename = "json")]
+    Json,
+    /// MessagePack encoded binary frames
+    #[serde(rename = "msgpack")]
+    MessagePack,
 }
 
 pub struct SessionGame {
-    id: GameId,
-    addr: Addr<Game>,
+    /// The token of the game
+    pub token: String,
+    /// The ID of the quiz the game was created from
+    pub quiz: GameId,
+    /// Address to the game
+    pub addr: Addr<Game>,
 }
 
 pub type SessionId = u32;
 
+/// Counter for producing unique session IDs
+static SESSION_ID: AtomicU32 = AtomicU32::new(1);
+
+/// The currently connected sessions
+static SESSIONS: OnceLock<DashMap<u32, Addr<Session>>> = OnceLock::new();
+
+/// Returns the currently connected sessions
+fn sessions() -> &'static DashMap<u32, Addr<Session>> {
+    SESSIONS.get_or_init(DashMap::new)
+}
+
+/// Reserves the session IDs up to and including the provided ID so
+/// that new sessions don't reuse the IDs of restored players
+///
+/// `id` The largest ID in use
+pub fn reserve_ids(id: SessionId) {
+    SESSION_ID.fetch_max(id.saturating_add(1), Ordering::AcqRel);
+}
+
+/// Sends the provided message to every connected session returning
+/// the number of sessions it was sent to
+///
+/// `message` The message to send
+pub fn broadcast(message: ServerMessage) -> usize {
+    let message = SharedMessage::new(message);
+    let sessions = sessions();
+    for session in sessions.iter() {
+        session.do_send(SessionRequest::Shared(message.clone()));
+    }
+    sessions.len()
+}
+
+/// Interval between each heartbeat ping sent to the client
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// The weight of each new round trip time measurement
+const RTT_WEIGHT: f32 = 0.125;
+
+/// The number of reactions a session can send within the reaction window
+const REACTION_LIMIT: u32 = 10;
+/// The window reactions are limited within
+const REACTION_WINDOW: Duration = Duration::from_secs(5);
+
 /// Messages recieved from the client
 #[derive(Deserialize)]
 #[serde(tag = "ty")]
@@ -38,21 +165,115 @@ pub enum ClientMessage {
         token: String,
         // The username to try and connect with
         username: String,
+        // The PIN for games that require one to join
+        #[serde(default)]
+        pin: Option<String>,
+        // The avatar and color to join with
+        #[serde(default)]
+        appearance: Appearance,
+    },
+    /// Message to watch the game with the provided token without playing
+    Spectate {
+        // The game token to spectate
+        token: String,
     },
+    /// Message to connect as the host of a created quiz
+    HostConnect {
+        // The ID returned when creating the quiz
+        id: GameId,
+        // The signed host token returned when creating the quiz
+        host_token: String,
+    },
+    /// Message to reconnect to a game that the client was previously
+    /// connected to using the resume token it was issued
+    Reconnect {
+        // The resume token from the connected message
+        token: ResumeToken,
+    },
+    /// Message to change the avatar and color of the player
+    SetAppearance(Appearance),
+    /// Message requesting the current state of the game, used by
+    /// clients that have fallen out of sync to recover
+    Resync,
     /// Message indicating the client is ready to play
     Ready,
+    /// Message to wager an amount of score on the next question
+    Wager { amount: u32 },
+    /// Message to join the team with the provided index
+    JoinTeam { team: usize },
+    /// Message to send a chat message to the game
+    Chat { text: String },
+    /// Message to react live during the game
+    React { reaction: Reaction },
     /// Message to start the game
-    Start,
+    Start {
+        /// Whether to start even if not all players are ready
+        #[serde(default)]
+        force: bool,
+        /// The signed host token
+        host_token: String,
+    },
     /// Message to cancel starting the game
-    Cancel,
+    Cancel {
+        /// The signed host token
+        host_token: String,
+    },
     /// Message to answer the question
     Answer(QuestionAnswer),
+    /// Message from the host to control the game
+    HostAction {
+        action: HostAction,
+        /// The signed host token
+        host_token: String,
+    },
+}
+
+impl ServerMessage {
+    /// Checks whether the message can be held back for clients that
+    /// are behind, only the latest of these messages matters
+    fn is_low_priority(&self) -> bool {
+        matches!(self, Self::Reactions { .. } | Self::AnswerProgress { .. })
+    }
+}
+
+impl ClientMessage {
+    /// Returns the name of the message type used in logs
+    fn name(&self) -> &'static str {
+        match self {
+            Self::TryConnect { .. } => "TryConnect",
+            Self::Spectate { .. } => "Spectate",
+            Self::HostConnect { .. } => "HostConnect",
+            Self::Reconnect { .. } => "Reconnect",
+            Self::SetAppearance(_) => "SetAppearance",
+            Self::Resync => "Resync",
+            Self::Ready => "Ready",
+            Self::Wager { .. } => "Wager",
+            Self::JoinTeam { .. } => "JoinTeam",
+            Self::Chat { .. } => "Chat",
+            Self::React { .. } => "React",
+            Self::Start { .. } => "Start",
+            Self::Cancel { .. } => "Cancel",
+            Self::Answer(_) => "Answer",
+            Self::HostAction { .. } => "HostAction",
+        }
+    }
 }
 
 /// Messages sent by the server
 #[derive(Serialize, Clone)]
 #[serde(tag = "ty")]
 pub enum ServerMessage {
+    /// Message sent when the connection opens advertising the
+    /// protocol versions and features the server supports
+    Hello {
+        /// The current protocol version
+        version: u32,
+        /// The oldest supported protocol version
+        min_version: u32,
+        /// The supported features
+        features: Features,
+    },
+
     /// Message indicating a complete successful connection
     Connected {
         /// The session ID
@@ -60,13 +281,43 @@ pub enum ServerMessage {
         /// The joined game token
         token: String,
         /// Basic game config information
-        basic: BasicConfig,
+        basic: Box<BasicConfig>,
+        /// Timing data for different game events
+        timing: GameTiming,
+        /// Token used to resume the session after losing the connection
+        resume: ResumeToken,
+        /// The name of the player which differs from the requested
+        /// name when the name was generated, None for the host
+        #[serde(skip_serializing_if = "Option::is_none")]
+        name: Option<String>,
+    },
+    /// Message indicating a successful connection as a spectator
+    Spectating {
+        /// The session ID
+        id: u32,
+        /// The joined game token
+        token: String,
+        /// Basic game config information
+        basic: Box<BasicConfig>,
         /// Timing data for different game events
         timing: GameTiming,
     },
     /// Message providing information about another player in
     /// the game
-    OtherPlayer { id: SessionId, name: String },
+    OtherPlayer {
+        id: SessionId,
+        name: String,
+        /// The index of the team the player is in
+        team: Option<usize>,
+        /// The avatar and color chosen by the player
+        appearance: Appearance,
+    },
+
+    /// Message informing everyone that a player changed their avatar or color
+    PlayerAppearance {
+        id: SessionId,
+        appearance: Appearance,
+    },
 
     /// Message indicating the current state of the game
     GameState(GameState),
@@ -80,21 +331,220 @@ pub enum ServerMessage {
     },
 
     /// Question data for the next question
-    Question(Question),
+    Question(PlayerQuestion),
 
     /// Result message for showing the results of a player
-    AnswerResult(AnswerResult),
+    AnswerResult {
+        /// The marked result of the answer
+        result: AnswerResult,
+        /// The number of consecutive correct answers
+        streak: u32,
+    },
+
+    /// The correct answer to the question that was just marked
+    /// along with the explanation for the answer
+    QuestionReveal {
+        correct: RevealedAnswer,
+        explanation: Option<String>,
+    },
 
     /// Message to begin the question displaying the answers
     /// at the bottom for the user to choose
     BeginQuestion,
 
-    /// Update for the player scores
-    ScoreUpdate { scores: HashMap<SessionId, u32> },
+    /// Full scores of every player, sent when joining or resynchronizing
+    ScoreUpdate {
+        scores: HashMap<SessionId, u32>,
+        streaks: HashMap<SessionId, u32>,
+        /// Combined score of each team when in team mode
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        teams: Vec<u32>,
+        /// The rank of the recipient in anonymous games where
+        /// the scores only contain the score of the recipient
+        #[serde(skip_serializing_if = "Option::is_none")]
+        rank: Option<u32>,
+    },
+
+    /// Scores and streaks that changed since the last update, players
+    /// missing from the last update have no score
+    ScoreDelta {
+        #[serde(skip_serializing_if = "HashMap::is_empty")]
+        scores: HashMap<SessionId, u32>,
+        #[serde(skip_serializing_if = "HashMap::is_empty")]
+        streaks: HashMap<SessionId, u32>,
+        /// Combined score of each team when the team scores changed
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        teams: Vec<u32>,
+    },
+
+    /// Final summary of the game sent when the game finishes
+    GameSummary(GameSummary),
+
+    /// Snapshot of the game sent to players joining a game in progress
+    /// and to sessions requesting a resync
+    StateSnapshot(Box<StateSnapshot>),
+
+    /// Host only message with the answer breakdown for the
+    /// question that was just marked
+    QuestionStats(QuestionStats),
+
+    /// Chat message sent by a player or the host
+    Chat {
+        id: SessionId,
+        name: String,
+        text: String,
+    },
+
+    /// Host only message with the anonymized submissions to an open
+    /// ended question, grades refer to the index of the submission
+    Submissions { submissions: Vec<String> },
+
+    /// The number of responses to each value of a poll sent
+    /// to everyone once the poll ends
+    PollResults { counts: Vec<usize> },
+
+    /// Message indicating which player answered first in buzzer mode
+    Buzzed { id: SessionId },
+
+    /// Message indicating a player was eliminated, eliminated
+    /// players continue to receive the game as spectators
+    Eliminated { id: SessionId },
+
+    /// Message asking a player to wager on the next question before
+    /// it is shown, the wager can be up to the player score
+    WagerOpen { max: u32 },
+
+    /// Host only message with the number of connected players
+    /// that have answered the current question
+    AnswerProgress { answered: usize, total: usize },
+
+    /// Host only message with the details of every player
+    PlayerList { players: Vec<PlayerDetails> },
+
+    /// Host only message indicating a player answered the question at
+    /// the index sooner than a person could, `elapsed` is the time in
+    /// milliseconds the player had after the question reached them
+    AnswerFlagged {
+        id: SessionId,
+        index: usize,
+        elapsed: u64,
+    },
+
+    /// Batch of the reactions sent since the last batch
+    Reactions { counts: HashMap<Reaction, u32> },
+
+    /// Message informing a player it has been given extended time,
+    /// the shown timers should be multiplied by the multiplier
+    TimeExtended { multiplier: f32 },
+
+    /// Message informing a player whether the host has muted it
+    Muted { muted: bool },
+
+    /// Message indicating a player was placed into a team
+    TeamChanged { id: SessionId, team: Option<usize> },
+
+    /// Message indicating how many of the connected players are ready
+    ReadyState { ready: usize, total: usize },
+
+    /// Message indicating a player was removed from the game
+    PlayerRemoved { id: SessionId },
+
+    /// Message indicating the host paused the game timer
+    Paused,
+
+    /// Message indicating the host resumed the game timer
+    Resumed,
+
+    /// Message informing the host of the ID to retrieve the
+    /// game results with
+    ResultsSaved { id: Uuid },
+
+    /// Message informing a player it has been promoted to host
+    /// after the previous host didn't return
+    HostPromoted {
+        /// Token for reclaiming the game as the new host
+        resume: ResumeToken,
+        /// Signed token the new host provides with host actions
+        host_token: String,
+    },
+
+    /// Message indicating the host of the game has changed
+    HostChanged { id: SessionId },
+
+    /// Message informing a player that it is waiting for the
+    /// host to approve its join request
+    JoinPending { token: String },
+
+    /// Message informing the host of a player asking to join
+    JoinRequest { id: SessionId, name: String },
+
+    /// Message informing the host that a player waiting for
+    /// approval left before being approved
+    JoinRequestCancelled { id: SessionId },
+
+    /// Announcement from the server admins sent to every session
+    Announcement { text: String },
+
+    /// Message indicating the server is shutting down, the current
+    /// question will finish but the game will end afterwards
+    ServerShutdown {
+        /// Unix timestamp in milliseconds the server will stop at
+        deadline: u64,
+    },
 }
 
 impl Actor for Session {
     type Context = ws::WebsocketContext<Session>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        metrics().connected_sessions.inc();
+        sessions().insert(self.key, ctx.address());
+
+        self.write_message(
+            ctx,
+            ServerMessage::Hello {
+                version: PROTOCOL_VERSION,
+                min_version: MIN_PROTOCOL_VERSION,
+                features: Features {
+                    question_types: QUESTION_TYPES,
+                    encodings: ENCODINGS,
+                },
+            },
+        );
+
+        if !(MIN_PROTOCOL_VERSION..=PROTOCOL_VERSION).contains(&self.version) {
+            info!(
+                "Session {} uses unsupported protocol {}",
+                self.id, self.version
+            );
+            self.write_message(ctx, ServerError::UnsupportedProtocol);
+            self.disconnect(ctx, DisconnectReason::UnsupportedProtocol);
+            return;
+        }
+
+        let timeout = Duration::from_secs(config::from_env(config::HEARTBEAT_TIMEOUT));
+        ctx.run_interval(HEARTBEAT_INTERVAL, move |actor, ctx| {
+            if actor.heartbeat.elapsed() > timeout {
+                info!("Session {} timed out waiting for heartbeat", actor.id);
+                actor.disconnect(ctx, DisconnectReason::IdleTimeout);
+                return;
+            }
+            actor.outbound.ping(ctx);
+        });
+    }
+
+    fn stopped(&mut self, ctx: &mut Self::Context) {
+        metrics().connected_sessions.dec();
+        sessions().remove(&self.key);
+
+        // Inform the game of the disconnect so the slot can be held
+        if let Some(game) = self.game.take() {
+            game.addr.do_send(GameRequest::Disconnect {
+                id: self.id,
+                addr: ctx.address(),
+            });
+        }
+    }
 }
 
 type SessionContext = ws::WebsocketContext<Session>;
@@ -102,56 +552,794 @@ type SessionContext = ws::WebsocketContext<Session>;
 #[derive(Message)]
 #[rtype(result = "SessionResponse")]
 pub enum SessionRequest {
+    /// Request informing the session that it has joined a game
+    /// under the provided session ID
+    Joined { id: SessionId, game: SessionGame },
     /// Request to send a message to the session client
     Message(ServerMessage),
+    /// Request to send a message shared with other sessions
+    Shared(SharedMessage),
     /// Request to send an error to the session client
     Error(ServerError),
+    /// Request informing the session it was removed from its game
+    /// and should be closed with the provided reason
+    Removed { reason: DisconnectReason },
+}
+
+/// Reasons the server closes a session, each is sent to the client as
+/// a close code in the range reserved for applications so that clients
+/// can show why they were disconnected and decide whether to reconnect
+#[derive(Clone)]
+pub enum DisconnectReason {
+    /// The host removed the player from the game
+    Kicked,
+    /// The host banned the player from the game
+    Banned,
+    /// The game filled up before the player could join
+    GameFull,
+    /// The host denied the join request
+    JoinDenied,
+    /// The game finished and was stopped
+    GameEnded,
+    /// The game was stopped after being abandoned
+    GameAbandoned,
+    /// The game was closed by the server admins
+    GameClosed(String),
+    /// The server is shutting down
+    ServerShutdown,
+    /// The client sent a message that broke the protocol
+    ProtocolViolation,
+    /// The client stopped responding to heartbeats
+    IdleTimeout,
+    /// The client sent too many messages
+    RateLimited,
+    /// The client fell too far behind receiving messages
+    SlowClient,
+    /// The client speaks a protocol version that isn't supported
+    UnsupportedProtocol,
+    /// The server is restarting and the game will be restored, the
+    /// client should reconnect with its resume token
+    ServerRestarting,
+    /// The game stopped unexpectedly
+    GameCrashed,
+    /// The player connected to the game from another session
+    ConnectionReplaced,
+}
+
+impl DisconnectReason {
+    /// The maximum length of a close reason description in bytes
+    const MAX_DESCRIPTION: usize = 123;
+
+    /// Returns the close code sent for the reason
+    pub fn code(&self) -> u16 {
+        match self {
+            Self::Kicked => 4000,
+            Self::Banned => 4001,
+            Self::GameFull => 4002,
+            Self::JoinDenied => 4003,
+            Self::GameEnded => 4004,
+            Self::GameAbandoned => 4005,
+            Self::GameClosed(_) => 4006,
+            Self::ServerShutdown => 4007,
+            Self::ProtocolViolation => 4008,
+            Self::IdleTimeout => 4009,
+            Self::RateLimited => 4010,
+            Self::SlowClient => 4011,
+            Self::UnsupportedProtocol => 4012,
+            Self::ServerRestarting => 4013,
+            Self::GameCrashed => 4014,
+            Self::ConnectionReplaced => 4015,
+        }
+    }
+
+    /// Returns the human readable description of the reason
+    pub fn description(&self) -> &str {
+        match self {
+            Self::Kicked => "Kicked from the game",
+            Self::Banned => "Banned from the game",
+            Self::GameFull => "The game is full",
+            Self::JoinDenied => "Join request denied",
+            Self::GameEnded => "The game has ended",
+            Self::GameAbandoned => "Game was abandoned",
+            Self::GameClosed(reason) => reason,
+            Self::ServerShutdown => "The server is shutting down",
+            Self::ProtocolViolation => "Invalid message sent",
+            Self::IdleTimeout => "Connection timed out",
+            Self::RateLimited => "Too many messages sent",
+            Self::SlowClient => "Connection too slow",
+            Self::UnsupportedProtocol => "Client version not supported",
+            Self::ServerRestarting => "The server is restarting",
+            Self::GameCrashed => "The game stopped unexpectedly",
+            Self::ConnectionReplaced => "Connected from another session",
+        }
+    }
+
+    /// Creates the websocket close reason
+    fn close_reason(&self) -> ws::CloseReason {
+        let mut description = self.description();
+        if description.len() > Self::MAX_DESCRIPTION {
+            let mut end = Self::MAX_DESCRIPTION;
+            while !description.is_char_boundary(end) {
+                end -= 1;
+            }
+            description = &description[..end];
+        }
+
+        ws::CloseReason {
+            code: ws::CloseCode::Other(self.code()),
+            description: Some(description.to_string()),
+        }
+    }
+}
+
+/// Closes every connected session with the provided reason
+///
+/// `reason` The reason for closing the sessions
+pub fn close_all(reason: DisconnectReason) {
+    for session in sessions().iter() {
+        session.do_send(SessionRequest::Removed {
+            reason: reason.clone(),
+        });
+    }
 }
 
 pub enum SessionResponse {
     None,
 }
 
+/// Message waiting to be written to a session
+enum Pending {
+    Message(ServerMessage),
+    Shared(SharedMessage),
+}
+
+impl Pending {
+    /// Returns the underlying server message
+    fn message(&self) -> &ServerMessage {
+        match self {
+            Self::Message(message) => message,
+            Self::Shared(message) => &message.0.message,
+        }
+    }
+}
+
+/// Tracks the bytes written to the client that it hasn't received yet.
+/// Pings are sent with a sequence number and as messages arrive in order,
+/// the pong for a ping confirms every byte written before the ping
+struct Outbound {
+    /// The maximum number of unconfirmed bytes before the
+    /// client is disconnected
+    max: u64,
+    /// The total number of bytes written
+    written: Cell<u64>,
+    /// The number of bytes the client has confirmed receiving
+    acked: Cell<u64>,
+    /// The number of bytes written when the last ping was sent
+    ping_written: Cell<u64>,
+    /// The sequence number for the next ping
+    next_ping: Cell<u64>,
+    /// The sequence number of each unanswered ping along with the
+    /// number of bytes written and the time when it was sent
+    pings: RefCell<VecDeque<(u64, u64, Instant)>>,
+    /// Low priority messages held back while the client is behind,
+    /// only the latest message of each kind is kept
+    held: Vec<Pending>,
+}
+
+impl Outbound {
+    /// Creates tracking for a new session
+    ///
+    /// `max` The maximum number of unconfirmed bytes
+    fn new(max: u64) -> Self {
+        Self {
+            max,
+            written: Cell::new(0),
+            acked: Cell::new(0),
+            ping_written: Cell::new(0),
+            next_ping: Cell::new(0),
+            pings: RefCell::new(VecDeque::new()),
+            held: Vec::new(),
+        }
+    }
+
+    /// The number of bytes written between pings while writing
+    const PING_BYTES: u64 = 32 * 1024;
+    /// The number of unconfirmed bytes after which low priority
+    /// messages are held back
+    const HOLD_BYTES: u64 = 64 * 1024;
+    /// The maximum number of unanswered pings that are tracked
+    const MAX_PINGS: usize = 64;
+
+    /// Returns the number of bytes the client hasn't confirmed
+    fn unconfirmed(&self) -> u64 {
+        self.written.get().saturating_sub(self.acked.get())
+    }
+
+    /// Checks whether low priority messages should be held back
+    fn is_behind(&self) -> bool {
+        self.unconfirmed() > Self::HOLD_BYTES
+    }
+
+    /// Checks whether the client has fallen too far behind
+    fn is_overflowing(&self) -> bool {
+        self.unconfirmed() > self.max
+    }
+
+    /// Sends a ping tracking the bytes written before it
+    ///
+    /// `ctx` The session context
+    fn ping(&self, ctx: &mut SessionContext) {
+        let seq = self.next_ping.get();
+        self.next_ping.set(seq + 1);
+
+        let written = self.written.get();
+        self.ping_written.set(written);
+
+        let mut pings = self.pings.borrow_mut();
+        if pings.len() >= Self::MAX_PINGS {
+            pings.pop_front();
+        }
+        pings.push_back((seq, written, Instant::now()));
+        ctx.ping(&seq.to_be_bytes());
+    }
+
+    /// Records bytes written to the client sending a ping once
+    /// enough has been written since the last one
+    ///
+    /// `ctx`    The session context
+    /// `length` The number of bytes written
+    fn record(&self, ctx: &mut SessionContext, length: usize) {
+        let written = self.written.get() + length as u64;
+        self.written.set(written);
+        if written - self.ping_written.get() >= Self::PING_BYTES {
+            self.ping(ctx);
+        }
+    }
+
+    /// Handles a pong from the client confirming the bytes written
+    /// before the matching ping, returns the round trip time of the
+    /// ping if it was still tracked
+    ///
+    /// `payload` The pong payload
+    fn pong(&self, payload: &[u8]) -> Option<Duration> {
+        let seq = <[u8; 8]>::try_from(payload).map(u64::from_be_bytes).ok()?;
+
+        let mut rtt = None;
+        let mut pings = self.pings.borrow_mut();
+        while let Some(&(ping_seq, written, sent)) = pings.front() {
+            if ping_seq > seq {
+                break;
+            }
+            pings.pop_front();
+            self.acked.set(written);
+            if ping_seq == seq {
+                rtt = Some(sent.elapsed());
+            }
+        }
+        rtt
+    }
+
+    /// Holds back the low priority message replacing any held
+    /// message of the same kind
+    ///
+    /// `message` The message to hold
+    fn hold(&mut self, message: Pending) {
+        let kind = std::mem::discriminant(message.message());
+        self.held
+            .retain(|held| std::mem::discriminant(held.message()) != kind);
+        self.held.push(message);
+    }
+}
+
+/// Server message that is broadcast to many sessions, the message is
+/// encoded at most once for each encoding and the encoded frames are
+/// shared between all the sessions
+#[derive(Clone)]
+pub struct SharedMessage(Arc<SharedMessageData>);
+
+struct SharedMessageData {
+    /// The underlying message
+    message: ServerMessage,
+    /// The message encoded as JSON
+    json: OnceLock<Option<ByteString>>,
+    /// The message encoded as MessagePack
+    msgpack: OnceLock<Option<Bytes>>,
+}
+
+impl SharedMessage {
+    pub fn new(message: ServerMessage) -> Self {
+        Self(Arc::new(SharedMessageData {
+            message,
+            json: OnceLock::new(),
+            msgpack: OnceLock::new(),
+        }))
+    }
+
+    /// Returns the JSON encoded message encoding it on first use
+    fn json(&self) -> Option<&ByteString> {
+        self.0
+            .json
+            .get_or_init(|| encode_json(&self.0.message))
+            .as_ref()
+    }
+
+    /// Returns the MessagePack encoded message encoding it on first use
+    fn msgpack(&self) -> Option<&Bytes> {
+        self.0
+            .msgpack
+            .get_or_init(|| encode_msgpack(&self.0.message))
+            .as_ref()
+    }
+}
+
+/// Encodes the provided message as JSON text
+///
+/// `msg` The message to encode
+fn encode_json<M: Serialize>(msg: &M) -> Option<ByteString> {
+    match serde_json::to_string(msg) {
+        Ok(value) => Some(value.into()),
+        Err(err) => {
+            error!("Failed to encode server message as JSON: {:?}", err);
+            None
+        }
+    }
+}
+
+/// Encodes the provided message as MessagePack bytes
+///
+/// `msg` The message to encode
+fn encode_msgpack<M: Serialize>(msg: &M) -> Option<Bytes> {
+    // Named encoding keeps the field names for the tagged messages
+    match rmp_serde::to_vec_named(msg) {
+        Ok(value) => Some(value.into()),
+        Err(err) => {
+            error!("Failed to encode server message as MessagePack: {:?}", err);
+            None
+        }
+    }
+}
+
 impl Session {
-    /// Writes a server message by encoding it to json and then sending it
-    /// as a text message through the web socket context
+    /// Creates a new session with a unique session ID, the current
+    /// span is kept as the span of the upgrade request
+    ///
+    /// `ip`       The IP address the session connected from
+    /// `encoding` The encoding to write messages with
+    /// `version`  The protocol version the client speaks
+    /// `profile`  The profile of the returning player if they have one
+    pub fn new(
+        ip: Option<IpAddr>,
+        encoding: Encoding,
+        version: u32,
+        profile: Option<Uuid>,
+    ) -> Self {
+        let id = SESSION_ID.fetch_add(1, Ordering::AcqRel);
+        Self {
+            id,
+            key: id,
+            game: None,
+            heartbeat: Instant::now(),
+            ip,
+            encoding,
+            version,
+            profile,
+            reaction_limit: WindowCounter::new(),
+            flood_guard: FloodGuard::new(
+                config::from_env(config::MESSAGE_RATE),
+                config::from_env(config::MESSAGE_BURST),
+            ),
+            upgrade: Span::current(),
+            outbound: Outbound::new(config::from_env(config::MAX_OUTBOUND_BUFFER) as u64),
+            rtt: None,
+        }
+    }
+
+    /// Writes a server message by encoding it with the session encoding
+    /// and then sending it through the web socket context. JSON is sent as
+    /// text messages and MessagePack as binary messages
     ///
     /// `ctx` The context to write to
     /// `msg` The message to write
-    fn write_message<M: Serialize>(ctx: &mut SessionContext, msg: M) {
-        // Serialize the message
-        let value = match serde_json::to_string(&msg) {
-            Ok(value) => value,
-            Err(err) => {
-                error!("Failed to encode server message as JSON: {:?}", err);
-                return;
-            }
+    fn write_message<M: Serialize>(&self, ctx: &mut SessionContext, msg: M) {
+        let length = match self.encoding {
+            Encoding::Json => encode_json(&msg).map(|value| {
+                let length = value.len();
+                ctx.text(value);
+                length
+            }),
+            Encoding::MessagePack => encode_msgpack(&msg).map(|value| {
+                let length = value.len();
+                ctx.binary(value);
+                length
+            }),
+        };
+        if let Some(length) = length {
+            self.record_written(ctx, length);
+        }
+    }
+
+    /// Writes a shared server message using the encoded form of the
+    /// message for the session encoding
+    ///
+    /// `ctx` The context to write to
+    /// `msg` The shared message to write
+    fn write_shared(&self, ctx: &mut SessionContext, msg: &SharedMessage) {
+        let length = match self.encoding {
+            Encoding::Json => msg.json().map(|value| {
+                ctx.text(value.clone());
+                value.len()
+            }),
+            Encoding::MessagePack => msg.msgpack().map(|value| {
+                ctx.binary(value.clone());
+                value.len()
+            }),
         };
+        if let Some(length) = length {
+            self.record_written(ctx, length);
+        }
+    }
+
+    /// Records bytes written to the client disconnecting clients that
+    /// have fallen too far behind so they can't keep growing the buffer
+    ///
+    /// `ctx`    The session context
+    /// `length` The number of bytes written
+    fn record_written(&self, ctx: &mut SessionContext, length: usize) {
+        self.outbound.record(ctx, length);
+        if self.outbound.is_overflowing() {
+            info!(
+                "Session {} disconnected for falling too far behind",
+                self.id
+            );
+            self.disconnect(ctx, DisconnectReason::SlowClient);
+        }
+    }
+
+    /// Updates the smoothed round trip time with a new measurement
+    /// and informs the game so it can compensate for the latency
+    ///
+    /// `sample` The measured round trip time
+    fn update_rtt(&mut self, sample: Duration) {
+        // Exponentially weighted like TCP so a single slow pong
+        // doesn't swing the estimate
+        let rtt = match self.rtt {
+            Some(rtt) => rtt.mul_f32(1.0 - RTT_WEIGHT) + sample.mul_f32(RTT_WEIGHT),
+            None => sample,
+        };
+        self.rtt = Some(rtt);
+
+        if let Some(game) = &self.game {
+            game.addr
+                .do_send(traced(GameRequest::Latency { id: self.id, rtt }));
+        }
+    }
+
+    /// Writes the held back low priority messages once the client has
+    /// caught up
+    ///
+    /// `ctx` The session context
+    fn flush_held(&mut self, ctx: &mut SessionContext) {
+        if self.outbound.is_behind() {
+            return;
+        }
+        for pending in std::mem::take(&mut self.outbound.held) {
+            match pending {
+                Pending::Message(message) => self.write_message(ctx, message),
+                Pending::Shared(message) => self.write_shared(ctx, &message),
+            }
+        }
+    }
+
+    /// Closes the connection sending the close code for the reason
+    ///
+    /// `ctx`    The session context
+    /// `reason` The reason for closing
+    fn disconnect(&self, ctx: &mut SessionContext, reason: DisconnectReason) {
+        ctx.close(Some(reason.close_reason()));
+        ctx.stop();
+    }
+
+    /// Checks the rate of messages from the client taking the action
+    /// for clients exceeding the limit, returns whether the message
+    /// should be handled
+    ///
+    /// `ctx` The session context
+    fn check_flood(&mut self, ctx: &mut SessionContext) -> bool {
+        match self.flood_guard.check() {
+            FloodAction::Allow => return true,
+            FloodAction::Drop => {}
+            FloodAction::Warn => self.write_message(ctx, ServerError::RateLimited),
+            FloodAction::Mute => {
+                info!("Session {} muted for sending too many messages", self.id);
+                self.write_message(ctx, ServerError::RateLimited);
+            }
+            FloodAction::Disconnect => {
+                info!(
+                    "Session {} disconnected for sending too many messages",
+                    self.id
+                );
+                self.disconnect(ctx, DisconnectReason::RateLimited);
+            }
+        }
+        false
+    }
 
-        // Write the text frame
-        ctx.text(value);
+    /// Creates the span for logs about the session, the message
+    /// field is recorded once the message type is known
+    fn span(&self) -> Span {
+        let span = info_span!(
+            "session",
+            session_id = self.id,
+            game_id = field::Empty,
+            message = field::Empty,
+        );
+        if let Some(game) = &self.game {
+            span.record("game_id", game.token.as_str());
+        }
+        span.follows_from(&self.upgrade);
+        span
     }
 
     /// Handles a recieved client message
     fn handle_message(&mut self, message: ClientMessage, ctx: &mut SessionContext) {
+        // Connection messages are only valid outside of a game and
+        // every other message is only valid inside of one
+        let is_connect = matches!(
+            message,
+            ClientMessage::TryConnect { .. }
+                | ClientMessage::Spectate { .. }
+                | ClientMessage::HostConnect { .. }
+                | ClientMessage::Reconnect { .. }
+        );
+        if is_connect == self.game.is_some() {
+            self.write_message(ctx, ServerError::InvalidStateForAction);
+            return;
+        }
+
         match message {
-            ClientMessage::TryConnect { token, username } => {
-                Self::try_connect(ctx, token, username);
+            ClientMessage::TryConnect {
+                token,
+                username,
+                pin,
+                appearance,
+            } => {
+                self.try_connect(ctx, token, username, pin, appearance);
+            }
+            ClientMessage::Spectate { token } => {
+                self.spectate(ctx, token);
+            }
+            ClientMessage::HostConnect { id, host_token } => {
+                if !verify_host_token(&host_token, id) {
+                    self.write_message(ctx, ServerError::InvalidHostToken);
+                    return;
+                }
+                Self::send_games(
+                    ctx,
+                    GamesRequest::HostConnect {
+                        id,
+                        sess_id: self.id,
+                        addr: ctx.address(),
+                    },
+                );
+            }
+            ClientMessage::Reconnect { token } => {
+                self.reconnect(ctx, token);
+            }
+            ClientMessage::Answer(answer) => {
+                self.send_game(
+                    ctx,
+                    GameRequest::Answer {
+                        id: self.id,
+                        answer,
+                    },
+                );
+            }
+            ClientMessage::HostAction { action, host_token } => {
+                if !self.is_host_token(ctx, &host_token) {
+                    return;
+                }
+                self.send_game(
+                    ctx,
+                    GameRequest::HostAction {
+                        id: self.id,
+                        action,
+                    },
+                );
+            }
+            ClientMessage::React { reaction } => {
+                // Excess reactions are dropped here so that floods never
+                // reach the game mailbox and delay answers
+                if !self.reaction_limit.hit(REACTION_LIMIT, REACTION_WINDOW) {
+                    return;
+                }
+                if let Some(game) = &self.game {
+                    game.addr.do_send(traced(GameRequest::React { reaction }));
+                }
+            }
+            ClientMessage::Chat { text } => {
+                self.send_game(ctx, GameRequest::Chat { id: self.id, text });
+            }
+            ClientMessage::JoinTeam { team } => {
+                self.send_game(ctx, GameRequest::JoinTeam { id: self.id, team });
+            }
+            ClientMessage::SetAppearance(appearance) => {
+                self.send_game(
+                    ctx,
+                    GameRequest::SetAppearance {
+                        id: self.id,
+                        appearance,
+                    },
+                );
+            }
+            ClientMessage::Resync => {
+                self.send_game(ctx, GameRequest::Resync { id: self.id });
+            }
+            ClientMessage::Ready => {
+                self.send_game(ctx, GameRequest::Ready { id: self.id });
+            }
+            ClientMessage::Wager { amount } => {
+                self.send_game(
+                    ctx,
+                    GameRequest::Wager {
+                        id: self.id,
+                        amount,
+                    },
+                );
+            }
+            ClientMessage::Start { force, host_token } => {
+                if !self.is_host_token(ctx, &host_token) {
+                    return;
+                }
+                self.send_game(ctx, GameRequest::Start { id: self.id, force });
+            }
+            ClientMessage::Cancel { host_token } => {
+                if !self.is_host_token(ctx, &host_token) {
+                    return;
+                }
+                self.send_game(ctx, GameRequest::Cancel { id: self.id });
             }
-            ClientMessage::Ready => todo!(),
-            _ => todo!(),
         }
     }
 
+    /// Checks that the host token grants control of the quiz the
+    /// current game was created from, writing an error to the client
+    /// when it doesn't
+    ///
+    /// `ctx`        The session context
+    /// `host_token` The host token provided with the message
+    fn is_host_token(&self, ctx: &mut SessionContext, host_token: &str) -> bool {
+        let valid = self
+            .game
+            .as_ref()
+            .is_some_and(|game| verify_host_token(host_token, game.quiz));
+        if !valid {
+            self.write_message(ctx, ServerError::InvalidHostToken);
+        }
+        valid
+    }
+
+    /// Sends the provided request to the game this session is apart of
+    /// forwarding any errors back to the session client
+    ///
+    /// `ctx` The session context
+    /// `msg` The request to send
+    fn send_game(&self, ctx: &mut SessionContext, msg: GameRequest) {
+        let game = match &self.game {
+            Some(value) => value.addr.clone(),
+            None => {
+                self.write_message(ctx, ServerError::UnknownPlayer);
+                return;
+            }
+        };
+        let addr = ctx.address();
+        let msg = traced(msg);
+        tokio::spawn(async move {
+            match game.send(msg).await {
+                Ok(Ok(_)) => {}
+                Ok(Err(err)) => addr.do_send(SessionRequest::Error(err)),
+                Err(err) => error!("Failed to send game request: {:?}", err),
+            }
+        });
+    }
+
     /// Attempts to connect this session to a game with the provided token
     /// using the provided username
     ///
-    /// `ctx`      The session context
-    /// `token`    The game token
-    /// `username` The username to use
-    fn try_connect(ctx: &mut SessionContext, token: String, username: String) {
+    /// `ctx`        The session context
+    /// `token`      The game token
+    /// `username`   The username to use
+    /// `pin`        The join PIN if provided
+    /// `appearance` The avatar and color to join with
+    fn try_connect(
+        &self,
+        ctx: &mut SessionContext,
+        token: String,
+        username: String,
+        pin: Option<String>,
+        appearance: Appearance,
+    ) {
+        let addr = ctx.address();
+        let msg = GameRequest::TryConnect {
+            id: self.id,
+            name: username,
+            addr: addr.clone(),
+            ip: self.ip,
+            pin,
+            profile: self.profile,
+            appearance,
+        };
+        Self::find_game(token, self.ip, msg, addr);
+    }
+
+    /// Attempts to connect this session to a game with the provided
+    /// token as a spectator
+    ///
+    /// `ctx`   The session context
+    /// `token` The game token
+    fn spectate(&self, ctx: &mut SessionContext, token: String) {
+        let addr = ctx.address();
+        let msg = GameRequest::Spectate {
+            id: self.id,
+            addr: addr.clone(),
+        };
+        Self::find_game(token, self.ip, msg, addr);
+    }
+
+    /// Finds the game with the provided token and sends it the connection
+    /// request, the lookup may need to ask the other nodes so it happens
+    /// off the session
+    ///
+    /// `token` The game token
+    /// `ip`    The address of the session
+    /// `msg`   The connection request
+    /// `addr`  The address of the connecting session
+    fn find_game(token: String, ip: Option<IpAddr>, msg: GameRequest, addr: Addr<Session>) {
+        tokio::spawn(async move {
+            match registry().find(&token, ip).await {
+                Ok(game) => Games::connect(game, msg, addr),
+                Err(err) => addr.do_send(SessionRequest::Error(err)),
+            }
+        });
+    }
+
+    /// Attempts to reconnect this session to the game that the
+    /// provided resume token was issued by
+    ///
+    /// `ctx`   The session context
+    /// `token` The resume token
+    fn reconnect(&self, ctx: &mut SessionContext, token: ResumeToken) {
         let addr = ctx.address();
-        tokio::spawn(async move {});
+        let Some(game) = registry().get(&token.game) else {
+            tokio::spawn(async move {
+                let error = match directory().locate(token.game).await {
+                    Some(node) => ServerError::WrongNode(node),
+                    None => ServerError::InvalidToken,
+                };
+                addr.do_send(SessionRequest::Error(error));
+            });
+            return;
+        };
+        let msg = GameRequest::Reconnect {
+            key: token.key,
+            addr: addr.clone(),
+        };
+        Games::connect(game, msg, addr);
+    }
+
+    /// Sends the provided request to the games store forwarding
+    /// any errors back to the session client
+    ///
+    /// `ctx` The session context
+    /// `msg` The request to send
+    fn send_games(ctx: &mut SessionContext, msg: GamesRequest) {
+        let addr = ctx.address();
+        tokio::spawn(async move {
+            match Games::from_registry().send(msg).await {
+                Ok(Ok(_)) => {}
+                Ok(Err(err)) => addr.do_send(SessionRequest::Error(err)),
+                Err(err) => error!("Failed to send games request: {:?}", err),
+            }
+        });
     }
 }
 
@@ -159,12 +1347,35 @@ impl Handler<SessionRequest> for Session {
     type Result = SessionResponse;
 
     fn handle(&mut self, msg: SessionRequest, ctx: &mut Self::Context) -> Self::Result {
+        let span = self.span();
+        let _enter = span.enter();
+
         match msg {
+            SessionRequest::Joined { id, game } => {
+                self.id = id;
+                self.game = Some(game);
+            }
             SessionRequest::Message(message) => {
-                Self::write_message(ctx, message);
+                if message.is_low_priority() && self.outbound.is_behind() {
+                    self.outbound.hold(Pending::Message(message));
+                } else {
+                    self.write_message(ctx, message);
+                }
+            }
+            SessionRequest::Shared(message) => {
+                if message.0.message.is_low_priority() && self.outbound.is_behind() {
+                    self.outbound.hold(Pending::Shared(message));
+                } else {
+                    self.write_shared(ctx, &message);
+                }
             }
             SessionRequest::Error(error) => {
-                Self::write_message(ctx, error);
+                self.write_message(ctx, error);
+            }
+            SessionRequest::Removed { reason } => {
+                // Clear the game so the removal isn't treated as a disconnect
+                self.game = None;
+                self.disconnect(ctx, reason);
             }
         }
         SessionResponse::None
@@ -173,22 +1384,52 @@ impl Handler<SessionRequest> for Session {
 
 impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for Session {
     fn handle(&mut self, item: Result<ws::Message, ws::ProtocolError>, ctx: &mut Self::Context) {
+        let span = self.span();
+        let _enter = span.enter();
+
         // Handle protocol errors
         let message = match item {
             Ok(message) => message,
+            // Frames over the size limit can't be recovered from
+            Err(ws::ProtocolError::Overflow) => {
+                info!("Session sent a message over the size limit");
+                self.write_message(ctx, ServerError::MalformedMessage);
+                self.disconnect(ctx, DisconnectReason::ProtocolViolation);
+                return;
+            }
             Err(err) => {
                 error!("Got error while recieving websocket messages: {:?}", err);
                 return;
             }
         };
 
-        // Only expect text messages
-        let text = match message {
-            ws::Message::Text(value) => value,
-            ws::Message::Pong(ping) => {
+        // Any message from the client counts as a heartbeat
+        self.heartbeat = Instant::now();
+
+        // Messages are limited before decoding to keep floods cheap
+        if matches!(message, ws::Message::Text(_) | ws::Message::Binary(_))
+            && !self.check_flood(ctx)
+        {
+            return;
+        }
+
+        // Decode the recieved client message, the encoding is detected
+        // from the message regardless of the negotiated encoding
+        let decode = info_span!("decode").entered();
+        let value = match message {
+            ws::Message::Text(text) => decode_client_message(text.as_bytes()),
+            ws::Message::Binary(bytes) => decode_client_message(&bytes),
+            ws::Message::Ping(ping) => {
                 ctx.pong(&ping);
                 return;
             }
+            ws::Message::Pong(payload) => {
+                if let Some(rtt) = self.outbound.pong(&payload) {
+                    self.update_rtt(rtt);
+                }
+                self.flush_held(ctx);
+                return;
+            }
             ws::Message::Close(reason) => {
                 info!("Session connection closed: {:?}", reason);
                 ctx.stop();
@@ -197,17 +1438,20 @@ impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for Session {
             _ => return,
         };
 
-        // Decode the recieved client message
-        let value = match serde_json::from_str::<ClientMessage>(&*text) {
+        drop(decode);
+
+        let value = match value {
             Ok(value) => value,
             Err(err) => {
-                error!("Unable to decode client message: {:?}", err);
-                Self::write_message(ctx, ServerError::MalformedMessage);
+                error!("Unable to decode client message: {}", err);
+                self.write_message(ctx, ServerError::MalformedMessage);
                 return;
             }
         };
 
         // Handle the client message
+        span.record("message", value.name());
+        metrics().messages_received.inc();
         self.handle_message(value, ctx);
     }
 }
@@ -0,0 +1,971 @@
+use std::{
+    sync::OnceLock,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use actix::{Actor, Addr, Handler, Message, SyncArbiter, SyncContext};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{de::IgnoredAny, Deserialize, Serialize};
+use tracing::error;
+use uuid::Uuid;
+
+use crate::{
+    audit::AuditEntry,
+    bundle::QuizBundle,
+    game::{AnswerResult, GameId, QuestionAnswer},
+    session::SessionId,
+};
+
+/// Actor for the embedded SQLite storage, runs on its own thread
+/// as the database calls are blocking
+pub struct Storage {
+    /// Connection to the database
+    conn: Connection,
+}
+
+/// Address of the started storage actor
+static STORAGE: OnceLock<Addr<Storage>> = OnceLock::new();
+
+/// Schema for the storage tables
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS results (
+    id TEXT PRIMARY KEY NOT NULL,
+    token TEXT NOT NULL,
+    finished_at INTEGER NOT NULL,
+    data TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS library (
+    id TEXT PRIMARY KEY NOT NULL,
+    name TEXT NOT NULL,
+    questions INTEGER NOT NULL,
+    created_at INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL,
+    data TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS users (
+    id TEXT PRIMARY KEY NOT NULL,
+    provider TEXT NOT NULL,
+    subject TEXT NOT NULL,
+    name TEXT NOT NULL,
+    email TEXT,
+    created_at INTEGER NOT NULL,
+    UNIQUE (provider, subject)
+);
+CREATE TABLE IF NOT EXISTS profiles (
+    id TEXT PRIMARY KEY NOT NULL,
+    name TEXT NOT NULL,
+    avatar TEXT,
+    games_played INTEGER NOT NULL DEFAULT 0,
+    answers_total INTEGER NOT NULL DEFAULT 0,
+    answers_correct INTEGER NOT NULL DEFAULT 0,
+    rank_total INTEGER NOT NULL DEFAULT 0,
+    created_at INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS lti_users (
+    profile TEXT PRIMARY KEY NOT NULL,
+    subject TEXT NOT NULL UNIQUE
+);
+";
+
+/// Migrations applied to databases created before columns were added,
+/// the index of each migration is tracked using the user version
+const MIGRATIONS: &[&str] = &["
+ALTER TABLE library ADD COLUMN owner TEXT;
+ALTER TABLE results ADD COLUMN owner TEXT;
+"];
+
+/// Quiz ID as stored by any version of the server
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StoredQuiz {
+    Id(GameId),
+    /// Quiz IDs used to be counted from zero on each start so
+    /// the same ID was given to unrelated quizzes
+    Counted(IgnoredAny),
+}
+
+/// Deserializes a stored quiz ID, counted IDs from older versions are
+/// dropped since a host token for a new quiz could share the ID
+///
+/// `deserializer` The deserializer to read from
+pub fn deserialize_quiz<'de, D>(deserializer: D) -> Result<Option<GameId>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let quiz = Option::<StoredQuiz>::deserialize(deserializer)?;
+    Ok(match quiz {
+        Some(StoredQuiz::Id(id)) => Some(id),
+        Some(StoredQuiz::Counted(_)) | None => None,
+    })
+}
+
+/// Returns the current time as unix seconds
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|value| value.as_secs())
+        .unwrap_or_default()
+}
+
+/// Applies the migrations that haven't been applied to the database
+///
+/// `conn` The database connection
+fn migrate(conn: &Connection) -> rusqlite::Result<()> {
+    let version: usize = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        conn.execute_batch(migration)?;
+        conn.pragma_update(None, "user_version", index + 1)?;
+    }
+    Ok(())
+}
+
+impl Storage {
+    /// Opens the database at the provided path creating any missing
+    /// tables and starts the storage actor
+    ///
+    /// `path` The path to the database file
+    pub fn start(path: String) -> rusqlite::Result<()> {
+        // Open once up front so failures are reported on startup
+        let conn = Connection::open(&path)?;
+        conn.execute_batch(SCHEMA)?;
+        migrate(&conn)?;
+        drop(conn);
+
+        let addr = SyncArbiter::start(1, move || Storage {
+            conn: Connection::open(&path).expect("Failed to open storage database"),
+        });
+        let _ = STORAGE.set(addr);
+        Ok(())
+    }
+
+    /// Returns the address of the storage actor if it has been started
+    pub fn addr() -> Option<&'static Addr<Storage>> {
+        STORAGE.get()
+    }
+}
+
+impl Actor for Storage {
+    type Context = SyncContext<Self>;
+}
+
+/// Summary of a finished game
+#[derive(Serialize, Deserialize)]
+pub struct GameResults {
+    /// Unique ID of the results
+    pub id: Uuid,
+    /// The token the game was played under
+    pub token: String,
+    /// The ID of the user that hosted the game if the host was logged in
+    #[serde(default)]
+    pub owner: Option<Uuid>,
+    /// The ID of the quiz the game was created from, host tokens for
+    /// this quiz grant access to the results
+    #[serde(default, deserialize_with = "deserialize_quiz")]
+    pub quiz: Option<GameId>,
+    /// The name of the game
+    pub name: String,
+    /// Unix timestamp in seconds that the game was created
+    pub started_at: u64,
+    /// Unix timestamp in seconds that the game finished
+    pub finished_at: u64,
+    /// The questions that were asked
+    pub questions: Vec<QuestionResults>,
+    /// The results for each player
+    pub players: Vec<PlayerResults>,
+    /// The host actions and suspicious events recorded during the game
+    #[serde(default)]
+    pub audit: Vec<AuditEntry>,
+    /// Whether entries were dropped from the audit log
+    #[serde(default)]
+    pub audit_truncated: bool,
+}
+
+impl GameResults {
+    /// Converts the results into CSV rows, a header row followed by a
+    /// row for each player
+    pub fn into_csv_rows(self) -> impl Iterator<Item = String> {
+        let count = self.questions.len();
+
+        let mut header = vec!["Name".to_string()];
+        for index in 1..=count {
+            header.push(format!("Q{} Result", index));
+            header.push(format!("Q{} Time (ms)", index));
+        }
+        header.push("Score".to_string());
+        let header = csv_row(header);
+
+        std::iter::once(header).chain(
+            self.players
+                .into_iter()
+                .map(move |player| player_row(player, count)),
+        )
+    }
+}
+
+/// Creates the CSV row for the provided player
+///
+/// `player` The player results
+/// `count`  The number of questions
+fn player_row(player: PlayerResults, count: usize) -> String {
+    let mut fields = vec![player.name];
+    for index in 0..count {
+        match player.answers.get(index).and_then(Option::as_ref) {
+            Some(answer) => {
+                let result = match answer.result {
+                    AnswerResult::Correct(_) => "Correct",
+                    AnswerResult::Close(_) => "Close",
+                    AnswerResult::Partial(_) => "Partial",
+                    AnswerResult::Incorrect => "Incorrect",
+                    AnswerResult::Participated(_) => "Participated",
+                };
+                fields.push(result.to_string());
+                fields.push(answer.elapsed.to_string());
+            }
+            None => {
+                fields.push(String::new());
+                fields.push(String::new());
+            }
+        }
+    }
+    fields.push(player.score.to_string());
+    csv_row(fields)
+}
+
+/// Joins the fields into a CSV row quoting any fields that
+/// contain special characters
+///
+/// `fields` The fields of the row
+fn csv_row(fields: Vec<String>) -> String {
+    let mut row = fields
+        .into_iter()
+        .map(|field| {
+            if field.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", field.replace('"', "\"\""))
+            } else {
+                field
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(",");
+    row.push_str("\r\n");
+    row
+}
+
+/// Details of a question within the results
+#[derive(Serialize, Deserialize)]
+pub struct QuestionResults {
+    /// The title of the question
+    pub title: String,
+    /// The time in milliseconds given to answer the question
+    pub answer_time: u64,
+    /// Whether the question was a poll without a correct answer
+    #[serde(default)]
+    pub poll: bool,
+}
+
+/// Results for a specific player
+#[derive(Serialize, Deserialize)]
+pub struct PlayerResults {
+    /// The session ID of the player
+    pub id: SessionId,
+    /// The player name
+    pub name: String,
+    /// The final score of the player
+    pub score: u32,
+    /// The index of the team the player was in
+    #[serde(default)]
+    pub team: Option<usize>,
+    /// The answer for each question if the player answered
+    pub answers: Vec<Option<AnswerRecord>>,
+}
+
+/// Record of an answer to a question
+#[derive(Serialize, Deserialize)]
+pub struct AnswerRecord {
+    /// The marked result
+    pub result: AnswerResult,
+    /// The time in milliseconds taken to answer
+    pub elapsed: u64,
+    /// The submitted answer in the original answer order, missing
+    /// from results saved before answers were recorded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub answer: Option<QuestionAnswer>,
+}
+
+/// Message to save the results of a finished game
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct SaveResults(pub GameResults);
+
+/// Message to load the results of a finished game
+#[derive(Message)]
+#[rtype(result = "Option<GameResults>")]
+pub struct GetResults {
+    pub id: Uuid,
+}
+
+/// Message checking that the database can be queried
+#[derive(Message)]
+#[rtype(result = "bool")]
+pub struct Ping;
+
+impl Handler<Ping> for Storage {
+    type Result = bool;
+
+    fn handle(&mut self, _msg: Ping, _ctx: &mut Self::Context) -> Self::Result {
+        self.conn.query_row("SELECT 1", [], |_| Ok(())).is_ok()
+    }
+}
+
+impl Handler<SaveResults> for Storage {
+    type Result = ();
+
+    fn handle(&mut self, msg: SaveResults, _ctx: &mut Self::Context) -> Self::Result {
+        let results = msg.0;
+        let data = match serde_json::to_string(&results) {
+            Ok(value) => value,
+            Err(err) => {
+                error!("Failed to encode game results: {:?}", err);
+                return;
+            }
+        };
+
+        if let Err(err) = self.conn.execute(
+            "INSERT OR REPLACE INTO results (id, token, finished_at, data, owner) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                results.id.to_string(),
+                results.token,
+                results.finished_at,
+                data,
+                results.owner.map(|owner| owner.to_string())
+            ],
+        ) {
+            error!("Failed to save game results: {:?}", err);
+        }
+    }
+}
+
+impl Handler<GetResults> for Storage {
+    type Result = Option<GameResults>;
+
+    fn handle(&mut self, msg: GetResults, _ctx: &mut Self::Context) -> Self::Result {
+        let data: String = match self
+            .conn
+            .query_row(
+                "SELECT data FROM results WHERE id = ?1",
+                params![msg.id.to_string()],
+                |row| row.get(0),
+            )
+            .optional()
+        {
+            Ok(value) => value?,
+            Err(err) => {
+                error!("Failed to load game results: {:?}", err);
+                return None;
+            }
+        };
+
+        match serde_json::from_str(&data) {
+            Ok(value) => Some(value),
+            Err(err) => {
+                error!("Failed to decode stored game results: {:?}", err);
+                None
+            }
+        }
+    }
+}
+
+/// Summary of a quiz stored in the library
+#[derive(Serialize)]
+pub struct LibraryEntry {
+    /// Unique ID of the quiz
+    pub id: Uuid,
+    /// The name of the quiz
+    pub name: String,
+    /// The number of questions in the quiz
+    pub questions: usize,
+    /// Unix timestamp in seconds that the quiz was created
+    pub created_at: u64,
+    /// Unix timestamp in seconds that the quiz was last updated
+    pub updated_at: u64,
+}
+
+/// Message to store a quiz in the library, replacing the existing
+/// quiz when `create` is false. Responds with whether the quiz was
+/// stored, updates fail when the quiz doesn't exist or belongs to
+/// another owner
+#[derive(Message)]
+#[rtype(result = "bool")]
+pub struct SaveLibraryQuiz {
+    pub id: Uuid,
+    pub bundle: QuizBundle,
+    pub create: bool,
+    /// The user the quiz belongs to, None for quizzes managed
+    /// with the host key which can access every quiz
+    pub owner: Option<Uuid>,
+}
+
+/// Message to list the summaries of the quizzes in the library
+/// that belong to the owner
+#[derive(Message)]
+#[rtype(result = "Vec<LibraryEntry>")]
+pub struct ListLibraryQuizzes {
+    pub owner: Option<Uuid>,
+}
+
+/// Message to load a quiz from the library
+#[derive(Message)]
+#[rtype(result = "Option<QuizBundle>")]
+pub struct GetLibraryQuiz {
+    pub id: Uuid,
+    pub owner: Option<Uuid>,
+}
+
+/// Message to delete a quiz from the library, responds with
+/// whether the quiz existed
+#[derive(Message)]
+#[rtype(result = "bool")]
+pub struct DeleteLibraryQuiz {
+    pub id: Uuid,
+    pub owner: Option<Uuid>,
+}
+
+impl Handler<SaveLibraryQuiz> for Storage {
+    type Result = bool;
+
+    fn handle(&mut self, msg: SaveLibraryQuiz, _ctx: &mut Self::Context) -> Self::Result {
+        let data = match serde_json::to_string(&msg.bundle) {
+            Ok(value) => value,
+            Err(err) => {
+                error!("Failed to encode library quiz: {:?}", err);
+                return false;
+            }
+        };
+
+        let now = unix_now();
+        let quiz = &msg.bundle.quiz;
+        let owner = msg.owner.map(|owner| owner.to_string());
+
+        let result = if msg.create {
+            self.conn.execute(
+                "INSERT INTO library (id, name, questions, created_at, updated_at, data, owner) VALUES (?1, ?2, ?3, ?4, ?4, ?5, ?6)",
+                params![msg.id.to_string(), quiz.basic.name, quiz.questions.len(), now, data, owner],
+            )
+        } else {
+            self.conn.execute(
+                "UPDATE library SET name = ?2, questions = ?3, updated_at = ?4, data = ?5 WHERE id = ?1 AND (?6 IS NULL OR owner = ?6)",
+                params![msg.id.to_string(), quiz.basic.name, quiz.questions.len(), now, data, owner],
+            )
+        };
+
+        match result {
+            Ok(changed) => changed > 0,
+            Err(err) => {
+                error!("Failed to save library quiz: {:?}", err);
+                false
+            }
+        }
+    }
+}
+
+impl Handler<ListLibraryQuizzes> for Storage {
+    type Result = Vec<LibraryEntry>;
+
+    fn handle(&mut self, msg: ListLibraryQuizzes, _ctx: &mut Self::Context) -> Self::Result {
+        let owner = msg.owner.map(|owner| owner.to_string());
+        let result = self
+            .conn
+            .prepare(
+                "SELECT id, name, questions, created_at, updated_at FROM library WHERE ?1 IS NULL OR owner = ?1 ORDER BY updated_at DESC",
+            )
+            .and_then(|mut stmt| {
+                let rows = stmt.query_map(params![owner], |row| {
+                    let id: String = row.get(0)?;
+                    Ok(LibraryEntry {
+                        id: Uuid::parse_str(&id).unwrap_or_default(),
+                        name: row.get(1)?,
+                        questions: row.get(2)?,
+                        created_at: row.get(3)?,
+                        updated_at: row.get(4)?,
+                    })
+                })?;
+                rows.collect::<rusqlite::Result<Vec<LibraryEntry>>>()
+            });
+
+        match result {
+            Ok(value) => value,
+            Err(err) => {
+                error!("Failed to list library quizzes: {:?}", err);
+                Vec::new()
+            }
+        }
+    }
+}
+
+impl Handler<GetLibraryQuiz> for Storage {
+    type Result = Option<QuizBundle>;
+
+    fn handle(&mut self, msg: GetLibraryQuiz, _ctx: &mut Self::Context) -> Self::Result {
+        let data: String = match self
+            .conn
+            .query_row(
+                "SELECT data FROM library WHERE id = ?1 AND (?2 IS NULL OR owner = ?2)",
+                params![msg.id.to_string(), msg.owner.map(|owner| owner.to_string())],
+                |row| row.get(0),
+            )
+            .optional()
+        {
+            Ok(value) => value?,
+            Err(err) => {
+                error!("Failed to load library quiz: {:?}", err);
+                return None;
+            }
+        };
+
+        match serde_json::from_str(&data) {
+            Ok(value) => Some(value),
+            Err(err) => {
+                error!("Failed to decode stored library quiz: {:?}", err);
+                None
+            }
+        }
+    }
+}
+
+impl Handler<DeleteLibraryQuiz> for Storage {
+    type Result = bool;
+
+    fn handle(&mut self, msg: DeleteLibraryQuiz, _ctx: &mut Self::Context) -> Self::Result {
+        match self.conn.execute(
+            "DELETE FROM library WHERE id = ?1 AND (?2 IS NULL OR owner = ?2)",
+            params![msg.id.to_string(), msg.owner.map(|owner| owner.to_string())],
+        ) {
+            Ok(changed) => changed > 0,
+            Err(err) => {
+                error!("Failed to delete library quiz: {:?}", err);
+                false
+            }
+        }
+    }
+}
+
+/// Account of a host that logged in through an OAuth provider
+#[derive(Serialize)]
+pub struct User {
+    /// Unique ID of the user
+    pub id: Uuid,
+    /// The provider the user logged in with
+    pub provider: String,
+    /// The display name of the user
+    pub name: String,
+    /// The email address of the user if the provider shared it
+    pub email: Option<String>,
+    /// Unix timestamp in seconds the user first logged in
+    pub created_at: u64,
+}
+
+/// Message to find the user for the provider account creating the
+/// user if this is the first time they have logged in, the name and
+/// email are updated to the latest from the provider
+#[cfg(feature = "oauth")]
+#[derive(Message)]
+#[rtype(result = "Option<User>")]
+pub struct UpsertUser {
+    /// The provider the user logged in with
+    pub provider: String,
+    /// The ID of the account within the provider
+    pub subject: String,
+    pub name: String,
+    pub email: Option<String>,
+}
+
+/// Message to load a user
+#[derive(Message)]
+#[rtype(result = "Option<User>")]
+pub struct GetUser {
+    pub id: Uuid,
+}
+
+/// Summary of the results of a finished game
+#[derive(Serialize)]
+pub struct ResultsEntry {
+    /// Unique ID of the results
+    pub id: Uuid,
+    /// The token the game was played under
+    pub token: String,
+    /// Unix timestamp in seconds that the game finished
+    pub finished_at: u64,
+}
+
+/// Message to list the results of the games hosted by a user
+#[derive(Message)]
+#[rtype(result = "Vec<ResultsEntry>")]
+pub struct ListResults {
+    pub owner: Uuid,
+}
+
+impl Storage {
+    /// Loads the user matching the provided condition
+    ///
+    /// `condition` The SQL condition for the user
+    /// `params`    The parameters for the condition
+    fn find_user(&self, condition: &str, params: impl rusqlite::Params) -> Option<User> {
+        let result = self
+            .conn
+            .query_row(
+                &format!(
+                    "SELECT id, provider, name, email, created_at FROM users WHERE {}",
+                    condition
+                ),
+                params,
+                |row| {
+                    let id: String = row.get(0)?;
+                    Ok(User {
+                        id: Uuid::parse_str(&id).unwrap_or_default(),
+                        provider: row.get(1)?,
+                        name: row.get(2)?,
+                        email: row.get(3)?,
+                        created_at: row.get(4)?,
+                    })
+                },
+            )
+            .optional();
+
+        match result {
+            Ok(value) => value,
+            Err(err) => {
+                error!("Failed to load user: {:?}", err);
+                None
+            }
+        }
+    }
+}
+
+#[cfg(feature = "oauth")]
+impl Handler<UpsertUser> for Storage {
+    type Result = Option<User>;
+
+    fn handle(&mut self, msg: UpsertUser, _ctx: &mut Self::Context) -> Self::Result {
+        if let Err(err) = self.conn.execute(
+            "INSERT INTO users (id, provider, subject, name, email, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+             ON CONFLICT (provider, subject) DO UPDATE SET name = excluded.name, email = excluded.email",
+            params![
+                Uuid::new_v4().to_string(),
+                msg.provider,
+                msg.subject,
+                msg.name,
+                msg.email,
+                unix_now()
+            ],
+        ) {
+            error!("Failed to save user: {:?}", err);
+            return None;
+        }
+
+        self.find_user(
+            "provider = ?1 AND subject = ?2",
+            params![msg.provider, msg.subject],
+        )
+    }
+}
+
+impl Handler<GetUser> for Storage {
+    type Result = Option<User>;
+
+    fn handle(&mut self, msg: GetUser, _ctx: &mut Self::Context) -> Self::Result {
+        self.find_user("id = ?1", params![msg.id.to_string()])
+    }
+}
+
+impl Handler<ListResults> for Storage {
+    type Result = Vec<ResultsEntry>;
+
+    fn handle(&mut self, msg: ListResults, _ctx: &mut Self::Context) -> Self::Result {
+        let result = self
+            .conn
+            .prepare(
+                "SELECT id, token, finished_at FROM results WHERE owner = ?1 ORDER BY finished_at DESC",
+            )
+            .and_then(|mut stmt| {
+                let rows = stmt.query_map(params![msg.owner.to_string()], |row| {
+                    let id: String = row.get(0)?;
+                    Ok(ResultsEntry {
+                        id: Uuid::parse_str(&id).unwrap_or_default(),
+                        token: row.get(1)?,
+                        finished_at: row.get(2)?,
+                    })
+                })?;
+                rows.collect::<rusqlite::Result<Vec<ResultsEntry>>>()
+            });
+
+        match result {
+            Ok(value) => value,
+            Err(err) => {
+                error!("Failed to list results: {:?}", err);
+                Vec::new()
+            }
+        }
+    }
+}
+
+/// Profile of a returning player that opted in to keeping their
+/// name, avatar and stats between games
+#[derive(Serialize)]
+pub struct Profile {
+    /// Unique ID of the profile, the user ID for logged in users
+    pub id: Uuid,
+    /// The display name of the player
+    pub name: String,
+    /// The avatar chosen by the player
+    pub avatar: Option<String>,
+    /// The number of finished games the player played
+    pub games_played: u64,
+    /// The fraction of the answered questions answered correctly
+    pub accuracy: f32,
+    /// The average final rank of the player, 1 being first
+    pub average_rank: f32,
+    /// Unix timestamp in seconds the profile was created
+    pub created_at: u64,
+}
+
+/// Message to create or update the name and avatar of a profile
+#[derive(Message)]
+#[rtype(result = "Option<Profile>")]
+pub struct UpsertProfile {
+    pub id: Uuid,
+    pub name: String,
+    pub avatar: Option<String>,
+}
+
+/// Message to load a profile
+#[derive(Message)]
+#[rtype(result = "Option<Profile>")]
+pub struct GetProfile {
+    pub id: Uuid,
+}
+
+/// Message to delete a profile, responds with whether it existed
+#[derive(Message)]
+#[rtype(result = "bool")]
+pub struct DeleteProfile {
+    pub id: Uuid,
+}
+
+/// Stats of a player with a profile from a finished game
+pub struct ProfileStats {
+    /// The ID of the profile
+    pub id: Uuid,
+    /// The number of questions the player answered
+    pub answered: u32,
+    /// The number of questions the player answered correctly
+    pub correct: u32,
+    /// The final rank of the player, 1 being first
+    pub rank: u32,
+}
+
+/// Message to add the stats from a finished game to the profiles
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct RecordProfileStats(pub Vec<ProfileStats>);
+
+impl Storage {
+    /// Loads the profile with the provided ID
+    ///
+    /// `id` The ID of the profile
+    fn find_profile(&self, id: Uuid) -> Option<Profile> {
+        let result = self
+            .conn
+            .query_row(
+                "SELECT name, avatar, games_played, answers_total, answers_correct, rank_total, created_at FROM profiles WHERE id = ?1",
+                params![id.to_string()],
+                |row| {
+                    let games_played: u64 = row.get(2)?;
+                    let answers_total: u64 = row.get(3)?;
+                    let answers_correct: u64 = row.get(4)?;
+                    let rank_total: u64 = row.get(5)?;
+                    Ok(Profile {
+                        id,
+                        name: row.get(0)?,
+                        avatar: row.get(1)?,
+                        games_played,
+                        accuracy: if answers_total > 0 {
+                            answers_correct as f32 / answers_total as f32
+                        } else {
+                            0.0
+                        },
+                        average_rank: if games_played > 0 {
+                            rank_total as f32 / games_played as f32
+                        } else {
+                            0.0
+                        },
+                        created_at: row.get(6)?,
+                    })
+                },
+            )
+            .optional();
+
+        match result {
+            Ok(value) => value,
+            Err(err) => {
+                error!("Failed to load profile: {:?}", err);
+                None
+            }
+        }
+    }
+}
+
+impl Handler<UpsertProfile> for Storage {
+    type Result = Option<Profile>;
+
+    fn handle(&mut self, msg: UpsertProfile, _ctx: &mut Self::Context) -> Self::Result {
+        if let Err(err) = self.conn.execute(
+            "INSERT INTO profiles (id, name, avatar, created_at) VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT (id) DO UPDATE SET name = excluded.name, avatar = excluded.avatar",
+            params![msg.id.to_string(), msg.name, msg.avatar, unix_now()],
+        ) {
+            error!("Failed to save profile: {:?}", err);
+            return None;
+        }
+
+        self.find_profile(msg.id)
+    }
+}
+
+impl Handler<GetProfile> for Storage {
+    type Result = Option<Profile>;
+
+    fn handle(&mut self, msg: GetProfile, _ctx: &mut Self::Context) -> Self::Result {
+        self.find_profile(msg.id)
+    }
+}
+
+impl Handler<DeleteProfile> for Storage {
+    type Result = bool;
+
+    fn handle(&mut self, msg: DeleteProfile, _ctx: &mut Self::Context) -> Self::Result {
+        // The LMS account is linked to a new profile on the next launch
+        if let Err(err) = self.conn.execute(
+            "DELETE FROM lti_users WHERE profile = ?1",
+            params![msg.id.to_string()],
+        ) {
+            error!("Failed to unlink LTI user: {:?}", err);
+        }
+
+        match self.conn.execute(
+            "DELETE FROM profiles WHERE id = ?1",
+            params![msg.id.to_string()],
+        ) {
+            Ok(count) => count > 0,
+            Err(err) => {
+                error!("Failed to delete profile: {:?}", err);
+                false
+            }
+        }
+    }
+}
+
+impl Handler<RecordProfileStats> for Storage {
+    type Result = ();
+
+    fn handle(&mut self, msg: RecordProfileStats, _ctx: &mut Self::Context) -> Self::Result {
+        // Players without a stored profile are ignored
+        for stats in msg.0 {
+            if let Err(err) = self.conn.execute(
+                "UPDATE profiles SET games_played = games_played + 1, answers_total = answers_total + ?2,
+                 answers_correct = answers_correct + ?3, rank_total = rank_total + ?4 WHERE id = ?1",
+                params![stats.id.to_string(), stats.answered, stats.correct, stats.rank],
+            ) {
+                error!("Failed to record profile stats: {:?}", err);
+            }
+        }
+    }
+}
+
+/// Message to find the profile linked to the LMS account creating the
+/// profile and link when this is the first launch of the account
+#[cfg(feature = "lti")]
+#[derive(Message)]
+#[rtype(result = "Option<Uuid>")]
+pub struct LinkLtiUser {
+    /// The ID of the user within the LMS
+    pub subject: String,
+    /// The name for a created profile
+    pub name: String,
+}
+
+/// Message to find the LMS accounts linked to the provided profiles
+#[cfg(feature = "lti")]
+#[derive(Message)]
+#[rtype(result = "std::collections::HashMap<Uuid, String>")]
+pub struct GetLtiSubjects {
+    pub profiles: Vec<Uuid>,
+}
+
+#[cfg(feature = "lti")]
+impl Handler<LinkLtiUser> for Storage {
+    type Result = Option<Uuid>;
+
+    fn handle(&mut self, msg: LinkLtiUser, _ctx: &mut Self::Context) -> Self::Result {
+        let existing = self
+            .conn
+            .query_row(
+                "SELECT profile FROM lti_users WHERE subject = ?1",
+                params![msg.subject],
+                |row| row.get::<_, String>(0),
+            )
+            .optional();
+        match existing {
+            Ok(Some(profile)) => return Uuid::parse_str(&profile).ok(),
+            Ok(None) => {}
+            Err(err) => {
+                error!("Failed to load LTI user: {:?}", err);
+                return None;
+            }
+        }
+
+        let id = Uuid::new_v4();
+        let result = self.conn.execute(
+            "INSERT INTO profiles (id, name, created_at) VALUES (?1, ?2, ?3)",
+            params![id.to_string(), msg.name, unix_now()],
+        );
+        let result = result.and_then(|_| {
+            self.conn.execute(
+                "INSERT INTO lti_users (profile, subject) VALUES (?1, ?2)",
+                params![id.to_string(), msg.subject],
+            )
+        });
+        if let Err(err) = result {
+            error!("Failed to link LTI user: {:?}", err);
+            return None;
+        }
+
+        Some(id)
+    }
+}
+
+#[cfg(feature = "lti")]
+impl Handler<GetLtiSubjects> for Storage {
+    type Result = actix::MessageResult<GetLtiSubjects>;
+
+    fn handle(&mut self, msg: GetLtiSubjects, _ctx: &mut Self::Context) -> Self::Result {
+        let mut subjects = std::collections::HashMap::new();
+        for profile in msg.profiles {
+            let subject = self
+                .conn
+                .query_row(
+                    "SELECT subject FROM lti_users WHERE profile = ?1",
+                    params![profile.to_string()],
+                    |row| row.get::<_, String>(0),
+                )
+                .optional();
+            match subject {
+                Ok(Some(subject)) => {
+                    subjects.insert(profile, subject);
+                }
+                Ok(None) => {}
+                Err(err) => error!("Failed to load LTI user: {:?}", err),
+            }
+        }
+        actix::MessageResult(subjects)
+    }
+}
@@ -0,0 +1,76 @@
+use actix::Message;
+use tracing::Span;
+
+/// Message wrapped with the span it was sent from so that the span
+/// created by the recieving actor continues the same trace
+pub struct Traced<M> {
+    /// The wrapped message
+    pub msg: M,
+    /// The span the message was sent from
+    pub span: Span,
+}
+
+impl<M: Message> Message for Traced<M> {
+    type Result = M::Result;
+}
+
+/// Wraps the provided message with the current span
+///
+/// `msg` The message to wrap
+pub fn traced<M>(msg: M) -> Traced<M> {
+    Traced {
+        msg,
+        span: Span::current(),
+    }
+}
+
+/// Creates the layer exporting spans to the OpenTelemetry collector
+/// configured through the environment, None when no collector has
+/// been configured or the exporter couldn't be created
+#[cfg(feature = "otel")]
+pub fn layer<S>() -> Option<impl tracing_subscriber::Layer<S>>
+where
+    S: tracing::Subscriber + for<'span> tracing_subscriber::registry::LookupSpan<'span>,
+{
+    use opentelemetry::KeyValue;
+    use opentelemetry_otlp::WithExportConfig;
+    use opentelemetry_sdk::{runtime, trace, Resource};
+
+    use crate::config;
+
+    let endpoint = config::from_env_string(config::OTLP_ENDPOINT);
+    if endpoint.is_empty() {
+        return None;
+    }
+
+    let service_name = config::from_env_string(config::OTLP_SERVICE_NAME);
+    let tracer = opentelemetry_otlp::new_pipeline()
+        .tracing()
+        .with_exporter(
+            opentelemetry_otlp::new_exporter()
+                .tonic()
+                .with_endpoint(endpoint),
+        )
+        .with_trace_config(
+            trace::config().with_resource(Resource::new(vec![KeyValue::new(
+                "service.name",
+                service_name,
+            )])),
+        )
+        .install_batch(runtime::Tokio);
+
+    match tracer {
+        Ok(tracer) => Some(tracing_opentelemetry::layer().with_tracer(tracer)),
+        Err(err) => {
+            // The logger isn't running yet so this can't be logged
+            eprintln!("Failed to create OpenTelemetry exporter: {}", err);
+            None
+        }
+    }
+}
+
+/// Flushes the spans that haven't been exported yet
+#[cfg(feature = "otel")]
+pub fn shutdown() {
+    opentelemetry::global::shutdown_tracer_provider();
+}
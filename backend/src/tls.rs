@@ -0,0 +1,46 @@
+use std::{
+    fs::File,
+    io::{self, BufReader},
+};
+
+use rustls::{Certificate, PrivateKey, ServerConfig};
+use rustls_pemfile::Item;
+
+use crate::config;
+
+/// Loads the TLS config from the certificate and key files set in the
+/// config, None when TLS hasn't been configured
+pub fn load() -> io::Result<Option<ServerConfig>> {
+    let cert_path = config::from_env_string(config::TLS_CERT);
+    let key_path = config::from_env_string(config::TLS_KEY);
+    if cert_path.is_empty() || key_path.is_empty() {
+        return Ok(None);
+    }
+
+    let certs: Vec<Certificate> =
+        rustls_pemfile::certs(&mut BufReader::new(File::open(&cert_path)?))?
+            .into_iter()
+            .map(Certificate)
+            .collect();
+    if certs.is_empty() {
+        return Err(io::Error::other(format!(
+            "No certificates found in {}",
+            cert_path
+        )));
+    }
+
+    let key = rustls_pemfile::read_all(&mut BufReader::new(File::open(&key_path)?))?
+        .into_iter()
+        .find_map(|item| match item {
+            Item::PKCS8Key(key) | Item::RSAKey(key) | Item::ECKey(key) => Some(PrivateKey(key)),
+            _ => None,
+        })
+        .ok_or_else(|| io::Error::other(format!("No private key found in {}", key_path)))?;
+
+    ServerConfig::builder()
+        .with_safe_defaults()
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .map(Some)
+        .map_err(io::Error::other)
+}
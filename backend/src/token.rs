@@ -0,0 +1,84 @@
+use std::ops::RangeInclusive;
+
+use rand_core::{OsRng, RngCore};
+
+use crate::config;
+
+/// Available chars to create tokens from, ambiguous chars (0/O
+/// and 1/I) are excluded so tokens are easy to read out
+const CHARSET: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+/// The number of bits needed to index the charset
+const CHARSET_BITS: u32 = 5;
+
+/// The number of attempts at generating a free token before
+/// moving on to longer tokens
+const MAX_ATTEMPTS: usize = 32;
+
+/// The minimum length of generated tokens
+const MIN_LENGTH: usize = 4;
+
+/// The allowed lengths of host supplied vanity tokens
+const VANITY_LENGTH: RangeInclusive<usize> = 4..=12;
+
+/// Generator for the tokens players use to join games
+#[derive(Clone)]
+pub struct TokenGenerator {
+    /// The length of generated tokens
+    length: usize,
+}
+
+impl TokenGenerator {
+    /// Creates a new token generator using the token length
+    /// from the environment
+    pub fn from_env() -> Self {
+        let length: usize = config::from_env(config::TOKEN_LENGTH);
+        Self {
+            length: length.max(MIN_LENGTH),
+        }
+    }
+
+    /// Generates a random token that isn't already in use. When too
+    /// many attempts collide the token length is increased
+    ///
+    /// `exists` Function checking whether a token is already in use
+    pub fn generate(&self, exists: impl Fn(&str) -> bool) -> String {
+        let mut length = self.length;
+        loop {
+            for _ in 0..MAX_ATTEMPTS {
+                let token = random_token(length);
+                if !exists(&token) {
+                    return token;
+                }
+            }
+
+            // The token space is crowded so move to longer tokens
+            length += 1;
+        }
+    }
+}
+
+/// Generates a random token of the provided length from the charset
+///
+/// `length` The length of the token
+fn random_token(length: usize) -> String {
+    let mut rand = OsRng;
+    (0..length)
+        .map(|_| {
+            // The charset is a power of two so every value is a valid index
+            let index = rand.next_u32() >> (32 - CHARSET_BITS);
+            char::from(CHARSET[index as usize])
+        })
+        .collect()
+}
+
+/// Validates and normalizes a host supplied vanity token returning
+/// None if the token isn't allowed. Vanity tokens are uppercase
+/// letters and digits
+///
+/// `value` The vanity token
+pub fn normalize_vanity(value: &str) -> Option<String> {
+    let value = value.trim().to_ascii_uppercase();
+    let valid = VANITY_LENGTH.contains(&value.len())
+        && value.bytes().all(|value| value.is_ascii_alphanumeric());
+    valid.then_some(value)
+}
@@ -0,0 +1,147 @@
+use std::{fs, path::Path, sync::OnceLock};
+
+use rand_core::RngCore;
+use tracing::{info, warn};
+use unicode_normalization::UnicodeNormalization;
+
+use crate::error::{ServerError, UsernameError};
+
+/// The minimum number of chars allowed in a username
+const MIN_LENGTH: usize = 1;
+/// The maximum number of chars allowed in a username
+const MAX_LENGTH: usize = 24;
+
+/// Adjectives that generated names start with
+const ADJECTIVES: &[&str] = &[
+    "Brave", "Bright", "Calm", "Clever", "Cosmic", "Curious", "Daring", "Eager", "Fancy", "Fuzzy",
+    "Gentle", "Happy", "Jolly", "Lucky", "Mighty", "Nimble", "Plucky", "Quick", "Quiet", "Shiny",
+    "Silly", "Sneaky", "Sunny", "Swift", "Witty", "Zesty",
+];
+
+/// Animals that generated names end with
+const ANIMALS: &[&str] = &[
+    "Badger", "Beaver", "Bison", "Cheetah", "Dolphin", "Falcon", "Ferret", "Gecko", "Hedgehog",
+    "Koala", "Lemur", "Llama", "Lynx", "Moose", "Narwhal", "Otter", "Panda", "Parrot", "Penguin",
+    "Puffin", "Rabbit", "Raccoon", "Squirrel", "Tiger", "Walrus", "Wombat",
+];
+
+/// Words that aren't allowed to appear within usernames
+static PROFANITY: OnceLock<Vec<String>> = OnceLock::new();
+
+/// Loads the profanity list from the file at the provided path. The
+/// file contains one word per line, blank lines and lines starting
+/// with # are ignored. A missing file results in an empty list
+///
+/// `path` The path to the profanity list
+pub fn load_profanity(path: impl AsRef<Path>) {
+    let path = path.as_ref();
+    let words: Vec<String> = match fs::read_to_string(path) {
+        Ok(value) => value
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| strip(&line.nfkc().collect::<String>()))
+            .filter(|word| !word.is_empty())
+            .collect(),
+        Err(err) => {
+            warn!(
+                "Unable to load profanity list from {}: {}",
+                path.display(),
+                err
+            );
+            Vec::new()
+        }
+    };
+    info!("Loaded {} profanity list entries", words.len());
+    let _ = PROFANITY.set(words);
+}
+
+/// Validates the provided username returning the normalized form
+/// of the username that should be used. Usernames are NFKC normalized
+/// with whitespace trimmed and collapsed
+///
+/// `name` The username to validate
+pub fn validate(name: &str) -> Result<String, ServerError> {
+    let name: String = name.nfkc().collect();
+    let name = name.split_whitespace().collect::<Vec<_>>().join(" ");
+
+    let length = name.chars().count();
+    if !(MIN_LENGTH..=MAX_LENGTH).contains(&length) {
+        return Err(ServerError::InvalidUsername(UsernameError::Length));
+    }
+
+    if !name.chars().all(is_allowed_char) {
+        return Err(ServerError::InvalidUsername(UsernameError::Charset));
+    }
+
+    if is_profane(&name) {
+        return Err(ServerError::InvalidUsername(UsernameError::Profanity));
+    }
+
+    Ok(name)
+}
+
+/// Generates a friendly name made of an adjective and an animal for
+/// players that joined without a name or aren't allowed to choose one
+///
+/// `rng` The random number generator to use
+pub fn generate(rng: &mut impl RngCore) -> String {
+    let adjective = ADJECTIVES[rng.next_u32() as usize % ADJECTIVES.len()];
+    let animal = ANIMALS[rng.next_u32() as usize % ANIMALS.len()];
+    format!("{} {}", adjective, animal)
+}
+
+/// Checks whether two usernames are considered the same. Names
+/// that only differ by letter case are treated as duplicates
+///
+/// `a` The first username
+/// `b` The second username
+pub fn is_same(a: &str, b: &str) -> bool {
+    a.to_lowercase() == b.to_lowercase()
+}
+
+/// Checks whether any of the words within the provided text are on
+/// the profanity list. Unlike usernames each word is compared as a
+/// whole so that longer words containing a listed word are allowed
+///
+/// `text` The text to check
+pub fn contains_profanity(text: &str) -> bool {
+    let Some(words) = PROFANITY.get() else {
+        return false;
+    };
+    text.split_whitespace()
+        .map(strip)
+        .any(|value| words.contains(&value))
+}
+
+/// Checks whether the provided char is allowed within usernames
+///
+/// `value` The char to check
+fn is_allowed_char(value: char) -> bool {
+    value.is_alphanumeric() || matches!(value, ' ' | '_' | '-' | '.' | '\'')
+}
+
+/// Checks whether the provided username contains any of the words
+/// from the profanity list. Separators are stripped before checking
+/// so that words can't be split up with spaces or punctuation
+///
+/// `name` The username to check
+fn is_profane(name: &str) -> bool {
+    let Some(words) = PROFANITY.get() else {
+        return false;
+    };
+    let name = strip(name);
+    words.iter().any(|word| name.contains(word.as_str()))
+}
+
+/// Strips all non alphanumeric chars from the provided value
+/// and converts it to lowercase
+///
+/// `value` The value to strip
+fn strip(value: &str) -> String {
+    value
+        .chars()
+        .filter(|value| value.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}